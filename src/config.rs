@@ -11,6 +11,77 @@ pub enum SyscallMode {
     Sandbox,
 }
 
+/// The emulated processor, selecting the architecture extensions the decoder accepts and the
+/// guest sees in its auxiliary vector, `uname` and /proc/cpuinfo
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum CpuModel {
+    /// ARMv6KZ with VFPv2 and without Thumb-2, as in the first Raspberry Pi
+    #[value(name = "arm1176")]
+    Arm1176,
+    /// The same extensions as the Cortex-A15, its big.LITTLE partner
+    #[value(name = "cortex-a7")]
+    CortexA7,
+    /// ARMv7-A with VFPv3 and NEON, without hardware divide
+    #[value(name = "cortex-a9")]
+    CortexA9,
+    /// ARMv7-A with VFPv4, NEON, hardware divide and LPAE
+    #[value(name = "cortex-a15")]
+    CortexA15,
+    /// ARMv8-A in AArch32 state, adding the crypto and CRC32 instructions
+    #[value(name = "cortex-a53-aarch32")]
+    CortexA53Aarch32,
+}
+
+impl CpuModel {
+    /// The major architecture version, which Linux reports in AT_PLATFORM as `v7l` and in the
+    /// `uname` machine as `armv7l`
+    pub fn architecture(self) -> u32 {
+        match self {
+            CpuModel::Arm1176 => 6,
+            CpuModel::CortexA7 | CpuModel::CortexA9 | CpuModel::CortexA15 => 7,
+            CpuModel::CortexA53Aarch32 => 8,
+        }
+    }
+
+    /// The Main ID Register: implementer, variant, architecture, primary part number and revision
+    pub fn midr(self) -> u32 {
+        match self {
+            // r0p7
+            CpuModel::Arm1176 => 0x410f_b767,
+            // r0p5
+            CpuModel::CortexA7 => 0x410f_c075,
+            // r3p0
+            CpuModel::CortexA9 => 0x413f_c090,
+            // r2p3
+            CpuModel::CortexA15 => 0x412f_c0f3,
+            // r0p4
+            CpuModel::CortexA53Aarch32 => 0x410f_d034,
+        }
+    }
+}
+
+/// Which counters the guest may read from user mode through CP15
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum Counters {
+    /// None of them, reading CNTVCT, CNTFRQ or PMCCNTR raises SIGILL
+    None,
+    /// The generic timer's CNTVCT and CNTFRQ, like Linux configures it
+    Timer,
+    /// The generic timer and the PMU cycle counter PMCCNTR, as if PMUSERENR enabled user access
+    All,
+}
+
+/// How the ARM code of a Linux process is executed
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum Engine {
+    /// Interpret every instruction
+    Interpreter,
+    /// Compile every block to x86-64 the first time it runs
+    Jit,
+    /// Interpret blocks until they ran often, then compile them to x86-64
+    Both,
+}
+
 #[derive(Debug, Clone, ValueEnum, PartialEq, PartialOrd)]
 pub enum Log {
     None,
@@ -34,6 +105,37 @@ pub struct Config {
     #[arg(short = 'C', long, value_enum, default_value_t = SyscallMode::Sandbox)]
     pub syscalls: SyscallMode,
 
+    /// Processor model to emulate
+    #[arg(long, value_enum, default_value_t = CpuModel::CortexA15)]
+    pub cpu: CpuModel,
+
+    /// Counters readable from user mode
+    #[arg(long, value_enum, default_value_t = Counters::Timer)]
+    pub counters: Counters,
+
+    /// Map the kernel user helpers, like __kuser_cmpxchg, in the vector page at 0xffff0000
+    #[arg(long)]
+    pub kuser_helpers: bool,
+
+    /// Run a bare-metal image instead of a Linux process: start in Supervisor mode, take
+    /// exceptions through the vector table and exit through semihosting
+    #[arg(long, conflicts_with = "kuser_helpers")]
+    pub bare_metal: bool,
+
+    /// Take exceptions through the vectors at 0xffff0000 instead of 0, as if SCTLR.V was set
+    #[arg(long, requires = "bare_metal")]
+    pub high_vectors: bool,
+
+    /// Fault on UNPREDICTABLE ARM and Thumb encodings, like writeback to a loaded base register,
+    /// instead of executing them as one of the behaviours ARMv8 permits. The fault exits with 126
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Execution engine. Compiled blocks fall back to the interpreter for the instructions the JIT
+    /// doesn't translate, logging instructions interprets all of them.
+    #[arg(long, value_enum, default_value_t = Engine::Interpreter)]
+    pub engine: Engine,
+
     /// Stack size for the emulated process (in bytes)
     #[arg(short, long, default_value_t = 1024 * 1024)]
     pub stack_size: usize,
@@ -0,0 +1,328 @@
+//! Shifter and flag computation shared by the data-processing instructions, see ARMv7 ARM A2.2
+//! "ARM core data types and arithmetic" and A8.4 "Shifts applied to a register".
+
+/// Negative condition flag, bit 31 of the CPSR
+pub const N: u32 = 1 << 31;
+/// Zero condition flag, bit 30 of the CPSR
+pub const Z: u32 = 1 << 30;
+/// Carry condition flag, bit 29 of the CPSR
+pub const C: u32 = 1 << 29;
+/// Overflow condition flag, bit 28 of the CPSR
+pub const V: u32 = 1 << 28;
+/// Sticky saturation flag, bit 27 of the CPSR
+pub const Q: u32 = 1 << 27;
+/// Greater than or equal flags of the parallel additions and subtractions, one per byte, bits
+/// 19..16 of the CPSR
+pub const GE: u32 = 0xf << 16;
+/// All four condition flags, also the layout of the FPSCR comparison flags
+pub const NZCV: u32 = N | Z | C | V;
+
+/// Shift type encoded in bits 6..5 of a register operand
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Shift {
+    #[default]
+    Lsl,
+    Lsr,
+    Asr,
+    Ror,
+    /// Only produced by `decode_imm_shift` for `ror #0`
+    Rrx,
+}
+
+impl From<u32> for Shift {
+    fn from(value: u32) -> Self {
+        match value & 0b11 {
+            0b00 => Self::Lsl,
+            0b01 => Self::Lsr,
+            0b10 => Self::Asr,
+            _ => Self::Ror,
+        }
+    }
+}
+
+/// DecodeImmShift: an immediate shift of 0 encodes a shift by 32 for LSR and ASR, and RRX for
+/// ROR.
+pub fn decode_imm_shift(shift: Shift, imm5: u32) -> (Shift, u32) {
+    match (shift, imm5) {
+        (Shift::Lsr, 0) => (Shift::Lsr, 32),
+        (Shift::Asr, 0) => (Shift::Asr, 32),
+        (Shift::Ror, 0) => (Shift::Rrx, 1),
+        (shift, amount) => (shift, amount),
+    }
+}
+
+/// Shift_C: shift `value` by `amount`, returning the result and the shifter carry out. A shift by
+/// zero passes `carry_in` through unchanged.
+pub fn shift_c(value: u32, shift: Shift, amount: u32, carry_in: bool) -> (u32, bool) {
+    if amount == 0 && shift != Shift::Rrx {
+        return (value, carry_in);
+    }
+
+    match shift {
+        Shift::Lsl => match amount {
+            1..=31 => (value << amount, (value >> (32 - amount)) & 1 == 1),
+            32 => (0, value & 1 == 1),
+            _ => (0, false),
+        },
+        Shift::Lsr => match amount {
+            1..=31 => (value >> amount, (value >> (amount - 1)) & 1 == 1),
+            32 => (0, value >> 31 == 1),
+            _ => (0, false),
+        },
+        Shift::Asr => {
+            let amount = amount.min(32);
+            let result = ((value as i32) >> (amount.min(31))) as u32;
+            (result, ((value as i32) >> (amount - 1)) & 1 == 1)
+        }
+        Shift::Ror => {
+            let result = value.rotate_right(amount % 32);
+            (result, result >> 31 == 1)
+        }
+        Shift::Rrx => ((value >> 1) | ((carry_in as u32) << 31), value & 1 == 1),
+    }
+}
+
+/// AddWithCarry: returns the result, the carry out and the signed overflow of `x + y + carry_in`.
+/// Subtraction is `add_with_carry(x, !y, true)`.
+pub fn add_with_carry(x: u32, y: u32, carry_in: bool) -> (u32, bool, bool) {
+    let unsigned = x as u64 + y as u64 + carry_in as u64;
+    let signed = x as i32 as i64 + y as i32 as i64 + carry_in as i64;
+    let result = unsigned as u32;
+    (result, unsigned >> 32 != 0, result as i32 as i64 != signed)
+}
+
+/// ARMExpandImm_C: the modified immediate of a data-processing instruction together with its
+/// carry out, which is only defined by the rotation if one is applied.
+pub fn expand_imm_c(imm12: u32, carry_in: bool) -> (u32, bool) {
+    let value = super::decoder::rotated_imm(imm12);
+    if imm12 >> 8 == 0 {
+        (value, carry_in)
+    } else {
+        (value, value >> 31 == 1)
+    }
+}
+
+/// ThumbExpandImm_C: the Thumb-2 modified immediate `i:imm3:imm8` either replicates imm8 across
+/// the word or rotates `1:imm7` by the top five bits, in which case bit 31 becomes the carry out.
+pub fn thumb_expand_imm_c(imm12: u32, carry_in: bool) -> (u32, bool) {
+    let imm8 = imm12 & 0xff;
+    if imm12 >> 10 == 0 {
+        let value = match (imm12 >> 8) & 0b11 {
+            0b00 => imm8,
+            0b01 => (imm8 << 16) | imm8,
+            0b10 => (imm8 << 24) | (imm8 << 8),
+            _ => imm8 * 0x0101_0101,
+        };
+        (value, carry_in)
+    } else {
+        let value = (0x80 | (imm12 & 0x7f)).rotate_right(imm12 >> 7);
+        (value, value >> 31 == 1)
+    }
+}
+
+/// SignedSatQ: clamp `value` to the range of an `n` bit signed integer, returning whether it
+/// saturated.
+pub fn signed_sat_q(value: i64, n: u32) -> (u32, bool) {
+    debug_assert!((1..=32).contains(&n));
+    let max = (1i64 << (n - 1)) - 1;
+    let min = -(1i64 << (n - 1));
+    (value.clamp(min, max) as u32, value < min || value > max)
+}
+
+/// UnsignedSatQ: clamp `value` to the range of an `n` bit unsigned integer, returning whether it
+/// saturated.
+pub fn unsigned_sat_q(value: i64, n: u32) -> (u32, bool) {
+    debug_assert!(n <= 31);
+    let max = (1i64 << n) - 1;
+    (value.clamp(0, max) as u32, value < 0 || value > max)
+}
+
+/// ConditionPassed: evaluate the 4-bit condition field against the NZCV flags in `cpsr`.
+///
+/// 0xF is the unconditional instruction space and never passes here.
+#[inline(always)]
+pub fn condition_passed(cond: u8, cpsr: u32) -> bool {
+    let n = cpsr & N != 0;
+    let z = cpsr & Z != 0;
+    let c = cpsr & C != 0;
+    let v = cpsr & V != 0;
+
+    let result = match cond >> 1 {
+        0b000 => z,                 // EQ / NE
+        0b001 => c,                 // CS / CC
+        0b010 => n,                 // MI / PL
+        0b011 => v,                 // VS / VC
+        0b100 => c && !z,           // HI / LS
+        0b101 => n == v,            // GE / LT
+        0b110 => n == v && !z,      // GT / LE
+        _ => return cond == 0b1110, // AL, NV
+    };
+
+    // odd conditions are the inverse of their even counterpart
+    if cond & 1 == 1 { !result } else { result }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        C, N, Shift, V, Z, add_with_carry, condition_passed, decode_imm_shift, expand_imm_c,
+        shift_c, signed_sat_q, thumb_expand_imm_c, unsigned_sat_q,
+    };
+
+    #[test]
+    fn imm_shift_of_zero_encodes_32_and_rrx() {
+        assert_eq!(decode_imm_shift(Shift::Lsl, 0), (Shift::Lsl, 0));
+        assert_eq!(decode_imm_shift(Shift::Lsr, 0), (Shift::Lsr, 32));
+        assert_eq!(decode_imm_shift(Shift::Asr, 0), (Shift::Asr, 32));
+        assert_eq!(decode_imm_shift(Shift::Ror, 0), (Shift::Rrx, 1));
+        assert_eq!(decode_imm_shift(Shift::Ror, 4), (Shift::Ror, 4));
+    }
+
+    #[test]
+    fn shifter_carry_out() {
+        assert_eq!(shift_c(0x8000_0001, Shift::Lsl, 1, false), (0x2, true));
+        assert_eq!(
+            shift_c(0x8000_0001, Shift::Lsr, 1, false),
+            (0x4000_0000, true)
+        );
+        assert_eq!(
+            shift_c(0x8000_0000, Shift::Asr, 4, false),
+            (0xf800_0000, false)
+        );
+        assert_eq!(
+            shift_c(0x8000_0000, Shift::Asr, 32, false),
+            (0xffff_ffff, true)
+        );
+        assert_eq!(
+            shift_c(0x0000_0001, Shift::Ror, 1, false),
+            (0x8000_0000, true)
+        );
+        assert_eq!(
+            shift_c(0x0000_0001, Shift::Rrx, 1, true),
+            (0x8000_0000, true)
+        );
+        assert_eq!(shift_c(0x1234, Shift::Lsl, 0, true), (0x1234, true));
+        assert_eq!(shift_c(0x1, Shift::Lsl, 32, false), (0, true));
+        assert_eq!(shift_c(0x1, Shift::Lsl, 33, true), (0, false));
+        assert_eq!(shift_c(0x8000_0000, Shift::Lsr, 32, false), (0, true));
+    }
+
+    #[test]
+    fn add_with_carry_flags() {
+        assert_eq!(add_with_carry(1, 2, false), (3, false, false));
+        assert_eq!(add_with_carry(u32::MAX, 1, false), (0, true, false));
+        assert_eq!(
+            add_with_carry(0x7fff_ffff, 1, false),
+            (0x8000_0000, false, true)
+        );
+        // 5 - 5: no borrow, carry set
+        assert_eq!(add_with_carry(5, !5, true), (0, true, false));
+        // 0 - 1: borrow, carry clear
+        assert_eq!(add_with_carry(0, !1, true), (u32::MAX, false, false));
+    }
+
+    #[test]
+    fn expanded_immediate_carry() {
+        assert_eq!(expand_imm_c(0x0ff, true), (0xff, true));
+        assert_eq!(expand_imm_c(0x4ff, false), (0xff00_0000, true));
+        assert_eq!(expand_imm_c(0xc01, true), (0x100, false));
+    }
+
+    #[test]
+    fn thumb_expanded_immediate() {
+        assert_eq!(thumb_expand_imm_c(0x0ab, true), (0xab, true));
+        assert_eq!(thumb_expand_imm_c(0x1ab, false), (0x00ab_00ab, false));
+        assert_eq!(thumb_expand_imm_c(0x2ab, false), (0xab00_ab00, false));
+        assert_eq!(thumb_expand_imm_c(0x3ab, false), (0xabab_abab, false));
+        // 0x400 rotates 1:0000000 right by 8
+        assert_eq!(thumb_expand_imm_c(0x400, false), (0x8000_0000, true));
+        // 0x47f rotates 1:1111111 right by 8
+        assert_eq!(thumb_expand_imm_c(0x47f, false), (0xff00_0000, true));
+        assert_eq!(thumb_expand_imm_c(0xfff, true), (0x1fe, false));
+    }
+
+    #[test]
+    fn saturation_clamps_and_reports() {
+        assert_eq!(signed_sat_q(100, 8), (100, false));
+        assert_eq!(signed_sat_q(200, 8), (127, true));
+        assert_eq!(signed_sat_q(-200, 8), (-128i32 as u32, true));
+        assert_eq!(signed_sat_q(i32::MIN as i64, 32), (0x8000_0000, false));
+        assert_eq!(unsigned_sat_q(-1, 8), (0, true));
+        assert_eq!(unsigned_sat_q(300, 8), (255, true));
+        assert_eq!(unsigned_sat_q(255, 8), (255, false));
+        assert_eq!(unsigned_sat_q(5, 0), (0, true));
+    }
+
+    #[test]
+    fn conditions_follow_flags() {
+        const EQ: u8 = 0x0;
+        const NE: u8 = 0x1;
+        const CS: u8 = 0x2;
+        const CC: u8 = 0x3;
+        const MI: u8 = 0x4;
+        const PL: u8 = 0x5;
+        const VS: u8 = 0x6;
+        const VC: u8 = 0x7;
+        const HI: u8 = 0x8;
+        const LS: u8 = 0x9;
+        const GE: u8 = 0xa;
+        const LT: u8 = 0xb;
+        const GT: u8 = 0xc;
+        const LE: u8 = 0xd;
+        const AL: u8 = 0xe;
+        const NV: u8 = 0xf;
+
+        assert!(condition_passed(EQ, Z));
+        assert!(!condition_passed(NE, Z));
+        assert!(condition_passed(CS, C));
+        assert!(condition_passed(CC, 0));
+        assert!(condition_passed(MI, N));
+        assert!(condition_passed(PL, 0));
+        assert!(condition_passed(VS, V));
+        assert!(condition_passed(VC, 0));
+        assert!(condition_passed(HI, C));
+        assert!(!condition_passed(HI, C | Z));
+        assert!(condition_passed(LS, Z));
+        assert!(condition_passed(LS, 0));
+        assert!(condition_passed(GE, N | V));
+        assert!(condition_passed(GE, 0));
+        assert!(condition_passed(LT, N));
+        assert!(condition_passed(LT, V));
+        assert!(condition_passed(GT, 0));
+        assert!(!condition_passed(GT, Z));
+        assert!(condition_passed(LE, Z));
+        assert!(condition_passed(LE, N));
+        assert!(condition_passed(AL, 0));
+        assert!(!condition_passed(NV, N | Z | C | V));
+    }
+
+    #[test]
+    fn compare_flags_drive_signed_and_unsigned_conditions() {
+        // flags of `cmp x, y`
+        let cmp = |x: u32, y: u32| {
+            let (result, carry, overflow) = add_with_carry(x, !y, true);
+            let mut cpsr = 0;
+            if result >> 31 == 1 {
+                cpsr |= N;
+            }
+            if result == 0 {
+                cpsr |= Z;
+            }
+            if carry {
+                cpsr |= C;
+            }
+            if overflow {
+                cpsr |= V;
+            }
+            cpsr
+        };
+
+        // -1 < 1 signed (LT), but 0xffffffff > 1 unsigned (HI)
+        assert!(condition_passed(0xb, cmp(u32::MAX, 1)));
+        assert!(condition_passed(0x8, cmp(u32::MAX, 1)));
+        // i32::MIN < 1 needs V to be taken into account
+        assert!(condition_passed(0xb, cmp(0x8000_0000, 1)));
+        assert!(condition_passed(0xc, cmp(5, 3)));
+        assert!(condition_passed(0xd, cmp(3, 3)));
+    }
+}
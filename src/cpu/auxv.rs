@@ -0,0 +1,82 @@
+//! The initial process stack the kernel hands to `_start`: argc, the NULL terminated argv and
+//! envp pointer arrays and the auxiliary vector, with the strings and bytes they point to above
+//! them, see the System V ABI "Process Initialization".
+
+use std::hash::{BuildHasher, Hasher};
+
+use crate::{config::CpuModel, cpu::features::Features, mem};
+
+/// end of the auxiliary vector
+const AT_NULL: u32 = 0;
+/// system page size
+const AT_PAGESZ: u32 = 6;
+/// string identifying the platform, used by the dynamic linker to pick libraries
+const AT_PLATFORM: u32 = 15;
+/// processor capabilities, see [`super::features::Features::hwcap`]
+const AT_HWCAP: u32 = 16;
+/// address of 16 random bytes, glibc seeds its stack protector and pointer guard from them
+const AT_RANDOM: u32 = 25;
+/// more processor capabilities, see [`super::features::Features::hwcap2`]
+const AT_HWCAP2: u32 = 26;
+
+const PAGE_SIZE: u32 = 4096;
+
+/// Build the initial stack below `top` for a process with the single argument `argv0` and an
+/// empty environment, reporting the platform and hardware capabilities of `model`. Returns the
+/// stack pointer, pointing at argc.
+pub fn initial_stack(
+    mem: &mut mem::Mem,
+    top: u32,
+    argv0: &[u8],
+    model: CpuModel,
+) -> Result<u32, &'static str> {
+    let features = Features::of(model);
+    let mut sp = top;
+    let mut push_bytes = |mem: &mut mem::Mem, bytes: &[u8]| -> Result<u32, &'static str> {
+        sp -= bytes.len() as u32;
+        for (i, byte) in bytes.iter().enumerate() {
+            mem.write_u8(sp + i as u32, *byte)?;
+        }
+        Ok(sp)
+    };
+
+    let argv0 = push_bytes(mem, &[argv0, b"\0"].concat())?;
+    let platform = format!("v{}l\0", model.architecture());
+    let platform = push_bytes(mem, platform.as_bytes())?;
+    let random = {
+        let state = std::collections::hash_map::RandomState::new();
+        let (mut a, mut b) = (state.build_hasher(), state.build_hasher());
+        a.write_u32(top);
+        b.write_u32(!top);
+        push_bytes(
+            mem,
+            &[a.finish().to_le_bytes(), b.finish().to_le_bytes()].concat(),
+        )?
+    };
+
+    let words = [
+        1,
+        argv0,
+        0,
+        // envp
+        0,
+        AT_HWCAP,
+        features.hwcap(),
+        AT_HWCAP2,
+        features.hwcap2(),
+        AT_PAGESZ,
+        PAGE_SIZE,
+        AT_PLATFORM,
+        platform,
+        AT_RANDOM,
+        random,
+        AT_NULL,
+        0,
+    ];
+    // the ABI wants sp doubleword aligned at process entry
+    let sp = (random - 4 * words.len() as u32) & !7;
+    for (i, word) in words.iter().enumerate() {
+        mem.write_u32(sp + 4 * i as u32, *word)?;
+    }
+    Ok(sp)
+}
@@ -0,0 +1,205 @@
+//! Basic blocks of decoded ARM instructions keyed by their guest address, so a hot loop is
+//! fetched and decoded once instead of on every pass. A block is straight-line code up to the
+//! first branch, it never crosses a page or the end of the program image.
+//!
+//! The pages holding blocks are marked in [`mem::Mem`], a write to them invalidates the blocks it
+//! overlaps before the next instruction runs, as does `cacheflush` for its range.
+
+use std::{
+    cell::{Cell, OnceCell},
+    collections::HashMap,
+    hash::{BuildHasherDefault, Hasher},
+    rc::Rc,
+};
+
+use crate::{
+    cpu::{
+        decoder::{self, Decoded, InstructionKind},
+        jit,
+    },
+    mem,
+};
+
+/// Instructions decoded into a block at most, so a long run of straight-line code that is left
+/// early doesn't cost much more than stepping through it
+const MAX_BLOCK_LEN: usize = 64;
+
+pub(super) struct Block {
+    /// the address past the last instruction
+    pub end: u32,
+    pub instrs: Box<[Decoded]>,
+    /// how often the block ran before it was compiled
+    pub runs: Cell<u32>,
+    /// the translation, None if the block couldn't be compiled
+    pub compiled: OnceCell<Option<jit::Entry>>,
+}
+
+impl Block {
+    /// Whether `kind` ends a block. Instructions that only sometimes write pc, like a
+    /// conditional branch or `ldr pc`, leave it as they execute.
+    pub fn ends_with(kind: InstructionKind) -> bool {
+        matches!(
+            kind,
+            InstructionKind::Branch
+                | InstructionKind::Bx
+                | InstructionKind::BlxReg
+                | InstructionKind::BlxImm
+                | InstructionKind::Svc
+                | InstructionKind::Rfe
+                | InstructionKind::Undefined
+                | InstructionKind::Bkpt
+                | InstructionKind::Unknown
+        )
+    }
+}
+
+/// Hashes a guest address by a multiplication, SipHash costs more than the lookup saves
+#[derive(Default)]
+struct AddrHasher(u64);
+
+impl Hasher for AddrHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0.rotate_left(8) ^ byte as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        }
+    }
+
+    fn write_u32(&mut self, addr: u32) {
+        let hash = (addr as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        self.0 = hash ^ (hash >> 32);
+    }
+}
+
+#[derive(Default)]
+pub(super) struct Blocks {
+    blocks: HashMap<u32, Rc<Block>, BuildHasherDefault<AddrHasher>>,
+    /// the start addresses of the blocks in each page, by page number
+    pages: HashMap<u32, Vec<u32>, BuildHasherDefault<AddrHasher>>,
+}
+
+impl Blocks {
+    #[inline(always)]
+    pub fn get(&self, start: u32) -> Option<Rc<Block>> {
+        self.blocks.get(&start).cloned()
+    }
+
+    /// Decode the block at `start`, stopping before `limit`. None if the word at `start` can't
+    /// be fetched.
+    pub fn build(&mut self, mem: &mut mem::Mem, start: u32, limit: u32) -> Option<Rc<Block>> {
+        let page = start / mem::CODE_PAGE_SIZE;
+        let limit = limit.min((page + 1).saturating_mul(mem::CODE_PAGE_SIZE));
+
+        let mut instrs = Vec::new();
+        let mut addr = start;
+        while addr < limit && instrs.len() < MAX_BLOCK_LEN {
+            let Some(word) = mem.read_u32(addr) else {
+                break;
+            };
+            let decoded = decoder::decode_word(word);
+            instrs.push(decoded);
+            addr += 4;
+            if Block::ends_with(decoded.kind) {
+                break;
+            }
+        }
+        if instrs.is_empty() {
+            return None;
+        }
+
+        mem.mark_code(start, addr - start);
+        let block = Rc::new(Block {
+            end: addr,
+            instrs: instrs.into_boxed_slice(),
+            runs: Cell::new(0),
+            compiled: OnceCell::new(),
+        });
+        self.blocks.insert(start, block.clone());
+        self.pages.entry(page).or_default().push(start);
+        Some(block)
+    }
+
+    /// Drop the blocks overlapping `start..end`, pages left without blocks are no longer
+    /// tracked
+    pub fn invalidate(&mut self, mem: &mut mem::Mem, start: u32, end: u32) {
+        if start >= end {
+            return;
+        }
+        let pages = start / mem::CODE_PAGE_SIZE..=(end - 1) / mem::CODE_PAGE_SIZE;
+        for page in pages {
+            let Some(starts) = self.pages.get_mut(&page) else {
+                continue;
+            };
+            starts.retain(|block_start| {
+                let overlaps = *block_start < end && self.blocks[block_start].end > start;
+                if overlaps {
+                    self.blocks.remove(block_start);
+                }
+                !overlaps
+            });
+            if starts.is_empty() {
+                self.pages.remove(&page);
+                mem.unmark_code(page * mem::CODE_PAGE_SIZE);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Blocks;
+    use crate::{cpu::decoder::InstructionKind, mem::Mem};
+
+    /// mov r0, #1, add r0, r0, #1 and b . at 0x1000, more movs up to the end of the page
+    fn mem() -> Mem {
+        let mut mem = Mem::with_size(0x4000);
+        mem.write_u32(0x1000, 0xe3a0_0001).unwrap();
+        mem.write_u32(0x1004, 0xe280_0001).unwrap();
+        mem.write_u32(0x1008, 0xeaff_fffe).unwrap();
+        for addr in (0x100c..0x2000).step_by(4) {
+            mem.write_u32(addr, 0xe3a0_0001).unwrap();
+        }
+        mem
+    }
+
+    #[test]
+    fn blocks_end_at_a_branch_the_limit_or_the_page() {
+        let mut mem = mem();
+        let mut blocks = Blocks::default();
+
+        let block = blocks.build(&mut mem, 0x1000, 0x4000).unwrap();
+        assert_eq!(block.end, 0x100c);
+        assert_eq!(block.instrs[2].kind, InstructionKind::Branch);
+        assert_eq!(blocks.build(&mut mem, 0x1004, 0x1008).unwrap().end, 0x1008);
+        assert_eq!(blocks.build(&mut mem, 0x1ff8, 0x4000).unwrap().end, 0x2000);
+        assert!(blocks.get(0x1000).is_some());
+        // the null page can't be fetched from
+        assert!(blocks.build(&mut mem, 0x0, 0x4000).is_none());
+    }
+
+    #[test]
+    fn writes_only_invalidate_the_blocks_they_overlap() {
+        let mut mem = mem();
+        let mut blocks = Blocks::default();
+        blocks.build(&mut mem, 0x1000, 0x4000);
+        blocks.build(&mut mem, 0x1004, 0x4000);
+        blocks.build(&mut mem, 0x1100, 0x4000);
+
+        blocks.invalidate(&mut mem, 0x1008, 0x100c);
+        assert!(blocks.get(0x1000).is_none());
+        assert!(blocks.get(0x1004).is_none());
+        assert!(blocks.get(0x1100).is_some());
+
+        // the page stays tracked until its last block is gone
+        mem.write_u32(0x1800, 0).unwrap();
+        assert!(mem.code_written());
+        mem.take_code_writes();
+        blocks.invalidate(&mut mem, 0x1000, 0x2000);
+        assert!(blocks.get(0x1100).is_none());
+        mem.write_u32(0x1800, 0).unwrap();
+        assert!(!mem.code_written());
+    }
+}
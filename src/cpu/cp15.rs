@@ -0,0 +1,220 @@
+//! The system control coprocessor CP15, see ARMv7 ARM B3.17 "Organization of the CP15 registers
+//! in a VMSA implementation". Linux grants PL0 access to the thread ID registers, the deprecated
+//! barrier operations, the generic timer's virtual count and, if enabled, the PMU cycle counter.
+//! Bare-metal images additionally reach SCTLR, the fault status and address registers and the
+//! virtual timer from the privileged modes. Every other CP15 register and every other
+//! coprocessor is undefined.
+
+use crate::{
+    config::Counters,
+    cpu::{
+        Cpu,
+        decoder::{Decoded, Operands},
+    },
+    err, util,
+};
+
+/// Frequency of the generic timer, reported by CNTFRQ
+const TIMER_HZ: u128 = 24_000_000;
+
+/// SCTLR out of reset on a Cortex-A, caches and the MMU are off
+const SCTLR_RESET: u32 = 0x00c5_0078;
+/// SCTLR.V, selects the high exception vectors at 0xffff0000
+pub const SCTLR_V: u32 = 1 << 13;
+
+/// short-descriptor fault status of a translation fault on a section
+const TRANSLATION_FAULT: u32 = 0b00101;
+/// short-descriptor fault status of a debug event, BKPT in a bare-metal image
+pub const DEBUG_EVENT: u32 = 0b00010;
+
+/// CNTV_CTL bits, the timer condition is met and not masked
+const CNTV_ENABLE: u32 = 1 << 0;
+const CNTV_IMASK: u32 = 1 << 1;
+const CNTV_ISTATUS: u32 = 1 << 2;
+
+/// The CP15 state
+pub struct Cp15 {
+    /// TPIDRURW, read-write thread ID
+    pub tpidrurw: u32,
+    /// TPIDRURO, read-only thread ID, set by the kernel through the set_tls private syscall
+    pub tpidruro: u32,
+    /// TPIDRPRW, the thread ID only the privileged modes see
+    tpidrprw: u32,
+    /// counters the guest may read
+    pub counters: Counters,
+    /// system control register, only V has an effect
+    pub sctlr: u32,
+    /// DFSR and DFAR, status and address of the last data abort
+    dfsr: u32,
+    dfar: u32,
+    /// IFSR and IFAR, status and address of the last prefetch abort
+    pub ifsr: u32,
+    ifar: u32,
+    /// CNTV_CTL, enable and mask of the virtual timer
+    cntv_ctl: u32,
+    /// CNTV_CVAL, the virtual count at which the timer fires
+    cntv_cval: u64,
+}
+
+impl Cp15 {
+    pub fn new(counters: Counters) -> Self {
+        Self {
+            tpidrurw: 0,
+            tpidruro: 0,
+            tpidrprw: 0,
+            counters,
+            sctlr: SCTLR_RESET,
+            dfsr: 0,
+            dfar: 0,
+            ifsr: 0,
+            ifar: 0,
+            cntv_ctl: 0,
+            cntv_cval: 0,
+        }
+    }
+
+    /// Record an abort on `addr` as a translation fault, a prefetch abort in IFSR and IFAR, a data
+    /// abort in DFSR and DFAR
+    pub fn abort(&mut self, addr: u32, prefetch: bool) {
+        if prefetch {
+            (self.ifsr, self.ifar) = (TRANSLATION_FAULT, addr);
+        } else {
+            (self.dfsr, self.dfar) = (TRANSLATION_FAULT, addr);
+        }
+    }
+
+    /// Whether the virtual timer is enabled, not masked and reached its compare value, which
+    /// raises an IRQ
+    pub fn timer_fired(&self) -> bool {
+        self.cntv_ctl & (CNTV_ENABLE | CNTV_IMASK) == CNTV_ENABLE
+            && self.virtual_count() >= self.cntv_cval
+    }
+
+    /// CNTVCT, the virtual count at [`TIMER_HZ`] since the emulator started
+    fn virtual_count(&self) -> u64 {
+        (util::since_start_ns() * TIMER_HZ / 1_000_000_000) as u64
+    }
+
+    /// PMCCNTR, cycles of a 1 GHz clock since the emulator started
+    fn cycle_count(&self) -> u32 {
+        util::since_start_ns() as u32
+    }
+}
+
+impl<'cpu, const PRINT_INSTR: bool> Cpu<'cpu, PRINT_INSTR> {
+    /// MRRC and MCRR, op is coproc:opc1:CRm
+    pub(super) fn coprocessor64(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            l: read,
+            rt2,
+            rt,
+            op,
+            ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+        if op >> 8 != 15 {
+            return self.undefined(raw);
+        }
+        if rt == 15 || rt2 == 15 || rt == rt2 {
+            return Err(err::Err::UnpredictableInstruction(raw));
+        }
+
+        let timer = self.cp15.counters != Counters::None;
+        let privileged = self.privileged();
+        let value = match (read, (op >> 4) & 0xf, op & 0xf) {
+            // CNTVCT
+            (true, 1, 14) if timer => self.cp15.virtual_count(),
+            // CNTV_CVAL
+            (true, 3, 14) if timer && privileged => self.cp15.cntv_cval,
+            (false, 3, 14) if timer && privileged => {
+                self.cp15.cntv_cval = ((self.r[rt2] as u64) << 32) | self.r[rt] as u64;
+                return Ok(());
+            }
+            _ => return self.undefined(raw),
+        };
+        self.r[rt] = value as u32;
+        self.r[rt2] = (value >> 32) as u32;
+        Ok(())
+    }
+
+    /// MRC and MCR, op is coproc:opc1:CRn:CRm:opc2
+    pub(super) fn coprocessor(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            l: read, rt, op, ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+        if op >> 14 != 15 {
+            return self.undefined(raw);
+        }
+        if rt == 15 {
+            return Err(err::Err::UnpredictableInstruction(raw));
+        }
+
+        let timer = self.cp15.counters != Counters::None;
+        let privileged = self.privileged();
+        // (opc1, CRn, CRm, opc2)
+        let register = (
+            (op >> 11) & 0b111,
+            (op >> 7) & 0xf,
+            (op >> 3) & 0xf,
+            op & 0b111,
+        );
+        match (read, register) {
+            // TPIDRURW
+            (true, (0, 13, 0, 2)) => self.r[rt] = self.cp15.tpidrurw,
+            (false, (0, 13, 0, 2)) => self.cp15.tpidrurw = self.r[rt],
+            // TPIDRURO
+            (true, (0, 13, 0, 3)) => self.r[rt] = self.cp15.tpidruro,
+            (false, (0, 13, 0, 3)) if privileged => self.cp15.tpidruro = self.r[rt],
+            // TPIDRPRW
+            (true, (0, 13, 0, 4)) if privileged => self.r[rt] = self.cp15.tpidrprw,
+            (false, (0, 13, 0, 4)) if privileged => self.cp15.tpidrprw = self.r[rt],
+            // SCTLR
+            (true, (0, 1, 0, 0)) if privileged => self.r[rt] = self.cp15.sctlr,
+            (false, (0, 1, 0, 0)) if privileged => self.cp15.sctlr = self.r[rt],
+            // DFSR, IFSR, DFAR and IFAR
+            (true, (0, 5, 0, 0)) if privileged => self.r[rt] = self.cp15.dfsr,
+            (false, (0, 5, 0, 0)) if privileged => self.cp15.dfsr = self.r[rt],
+            (true, (0, 5, 0, 1)) if privileged => self.r[rt] = self.cp15.ifsr,
+            (false, (0, 5, 0, 1)) if privileged => self.cp15.ifsr = self.r[rt],
+            (true, (0, 6, 0, 0)) if privileged => self.r[rt] = self.cp15.dfar,
+            (false, (0, 6, 0, 0)) if privileged => self.cp15.dfar = self.r[rt],
+            (true, (0, 6, 0, 2)) if privileged => self.r[rt] = self.cp15.ifar,
+            (false, (0, 6, 0, 2)) if privileged => self.cp15.ifar = self.r[rt],
+            // CP15ISB, CP15DSB and CP15DMB, a single guest thread observes its own accesses in
+            // program order
+            (false, (0, 7, 5, 4) | (0, 7, 10, 4) | (0, 7, 10, 5)) => {}
+            // CNTFRQ
+            (true, (0, 14, 0, 0)) if timer => self.r[rt] = TIMER_HZ as u32,
+            // CNTV_TVAL, the signed distance of the compare value from the count
+            (true, (0, 14, 3, 0)) if timer && privileged => {
+                let count = self.cp15.virtual_count();
+                self.r[rt] = self.cp15.cntv_cval.wrapping_sub(count) as u32;
+            }
+            (false, (0, 14, 3, 0)) if timer && privileged => {
+                let count = self.cp15.virtual_count();
+                self.cp15.cntv_cval = count.wrapping_add(self.r[rt] as i32 as u64);
+            }
+            // CNTV_CTL
+            (true, (0, 14, 3, 1)) if timer && privileged => {
+                let ctl = self.cp15.cntv_ctl;
+                let met =
+                    ctl & CNTV_ENABLE != 0 && self.cp15.virtual_count() >= self.cp15.cntv_cval;
+                self.r[rt] = if met { ctl | CNTV_ISTATUS } else { ctl };
+            }
+            (false, (0, 14, 3, 1)) if timer && privileged => {
+                self.cp15.cntv_ctl = self.r[rt] & (CNTV_ENABLE | CNTV_IMASK);
+            }
+            // PMUSERENR, EN in bit 0
+            (true, (0, 9, 14, 0)) => {
+                self.r[rt] = (self.cp15.counters == Counters::All) as u32;
+            }
+            // PMCCNTR
+            (true, (0, 9, 13, 0)) if self.cp15.counters == Counters::All => {
+                self.r[rt] = self.cp15.cycle_count();
+            }
+            _ => return self.undefined(raw),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,507 @@
+//! The ARMv8 AArch32 cryptographic extension, see ARMv8 ARM F6 "AES", "SHA1" and "SHA256"
+//! instructions, and the CRC32 instructions of the base instruction set. The AES and SHA
+//! instructions are Advanced SIMD encodings on the quadword registers and are routed here from
+//! [`super::neon`].
+//!
+//! A quadword holds the AES state in the byte order of FIPS 197: byte `r + 4 * c` of the register
+//! is row `r` of column `c`. The SHA instructions work on words, element 0 being the least
+//! significant one.
+
+use crate::{
+    cpu::{
+        Cpu,
+        decoder::{Decoded, Operands},
+        features::Features,
+        neon::elem,
+    },
+    err,
+};
+
+/// AES S-box, the multiplicative inverse in GF(2^8) followed by the affine transformation
+const SBOX: [u8; 256] = {
+    let mut sbox = [0; 256];
+    let mut x = 0;
+    while x < 256 {
+        // x^254 is the inverse of x, and 0 for 0
+        let mut inverse = 1;
+        let mut i = 0;
+        while i < 254 {
+            inverse = gf_mul(inverse, x as u8);
+            i += 1;
+        }
+        let b = inverse;
+        sbox[x] =
+            b ^ b.rotate_left(1) ^ b.rotate_left(2) ^ b.rotate_left(3) ^ b.rotate_left(4) ^ 0x63;
+        x += 1;
+    }
+    sbox
+};
+
+const INVERSE_SBOX: [u8; 256] = {
+    let mut inverse = [0; 256];
+    let mut x = 0;
+    while x < 256 {
+        inverse[SBOX[x] as usize] = x as u8;
+        x += 1;
+    }
+    inverse
+};
+
+/// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1
+const fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    while b != 0 {
+        if b & 1 == 1 {
+            product ^= a;
+        }
+        a = (a << 1) ^ if a & 0x80 != 0 { 0x1b } else { 0 };
+        b >>= 1;
+    }
+    product
+}
+
+fn bytes(state: u128) -> [u8; 16] {
+    state.to_le_bytes()
+}
+
+/// AESE without the round key: ShiftRows, then SubBytes
+fn aes_encrypt_round(state: u128) -> u128 {
+    let s = bytes(state);
+    u128::from_le_bytes(std::array::from_fn(|i| {
+        let (r, c) = (i % 4, i / 4);
+        SBOX[s[r + 4 * ((c + r) % 4)] as usize]
+    }))
+}
+
+/// AESD without the round key: InvShiftRows, then InvSubBytes
+fn aes_decrypt_round(state: u128) -> u128 {
+    let s = bytes(state);
+    u128::from_le_bytes(std::array::from_fn(|i| {
+        let (r, c) = (i % 4, i / 4);
+        INVERSE_SBOX[s[r + 4 * ((c + 4 - r) % 4)] as usize]
+    }))
+}
+
+/// MixColumns with the coefficients of the first row, `[2, 3, 1, 1]` for AESMC and
+/// `[14, 11, 13, 9]` for AESIMC
+fn aes_mix_columns(state: u128, coefficients: [u8; 4]) -> u128 {
+    let s = bytes(state);
+    u128::from_le_bytes(std::array::from_fn(|i| {
+        let (r, c) = (i % 4, i / 4);
+        (0..4).fold(0, |acc, k| {
+            acc ^ gf_mul(coefficients[(k + 4 - r) % 4], s[k + 4 * c])
+        })
+    }))
+}
+
+fn sha_choose(x: u32, y: u32, z: u32) -> u32 {
+    ((y ^ z) & x) ^ z
+}
+
+fn sha_majority(x: u32, y: u32, z: u32) -> u32 {
+    (x & y) | ((x | y) & z)
+}
+
+fn sha_parity(x: u32, y: u32, z: u32) -> u32 {
+    x ^ y ^ z
+}
+
+fn word(v: u128, e: usize) -> u32 {
+    elem(v, e, 32) as u32
+}
+
+fn words(w: [u32; 4]) -> u128 {
+    w.iter()
+        .rev()
+        .fold(0, |acc, &word| (acc << 32) | word as u128)
+}
+
+/// Four rounds of SHA-1 on the hash state `abcd` and `e` with the schedule words plus the round
+/// constant in `wk`, `f` being the choose, parity or majority function of the rounds
+fn sha1_hash(mut abcd: u128, mut e: u32, wk: u128, f: fn(u32, u32, u32) -> u32) -> u128 {
+    for i in 0..4 {
+        let t = f(word(abcd, 1), word(abcd, 2), word(abcd, 3));
+        e = e
+            .wrapping_add(word(abcd, 0).rotate_left(5))
+            .wrapping_add(t)
+            .wrapping_add(word(wk, i));
+        let b = word(abcd, 1).rotate_left(30);
+        let rotated = (abcd & !(0xffff_ffff << 32)) | ((b as u128) << 32);
+        // rotate e:abcd left by a word
+        (abcd, e) = ((rotated << 32) | e as u128, word(rotated, 3));
+    }
+    abcd
+}
+
+/// Four rounds of SHA-256 on the hash state halves `abcd` and `efgh`, returning the new
+/// `abcd` for SHA256H and the new `efgh` for SHA256H2
+fn sha256_hash(mut abcd: u128, mut efgh: u128, wk: u128, part1: bool) -> u128 {
+    for i in 0..4 {
+        let (a, e) = (word(abcd, 0), word(efgh, 0));
+        let choose = sha_choose(e, word(efgh, 1), word(efgh, 2));
+        let majority = sha_majority(a, word(abcd, 1), word(abcd, 2));
+        let sigma1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let sigma0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let t = word(efgh, 3)
+            .wrapping_add(sigma1)
+            .wrapping_add(choose)
+            .wrapping_add(word(wk, i));
+        let d = t.wrapping_add(word(abcd, 3));
+        let h = t.wrapping_add(sigma0).wrapping_add(majority);
+        // rotate efgh:abcd left by a word
+        (abcd, efgh) = ((abcd << 32) | h as u128, (efgh << 32) | d as u128);
+    }
+    if part1 { abcd } else { efgh }
+}
+
+/// SHA1SU1: the last step of the SHA-1 message schedule update
+fn sha1_schedule_1(x: u128, y: u128) -> u128 {
+    let t = x ^ (y >> 32);
+    let w: [u32; 4] = std::array::from_fn(|e| word(t, e).rotate_left(1));
+    words([w[0], w[1], w[2], w[3] ^ word(t, 0).rotate_left(2)])
+}
+
+/// SHA256SU0: the small sigma 0 part of the SHA-256 message schedule update
+fn sha256_schedule_0(x: u128, y: u128) -> u128 {
+    let t = (x >> 32) | ((word(y, 0) as u128) << 96);
+    words(std::array::from_fn(|e| {
+        let w = word(t, e);
+        (w.rotate_right(7) ^ w.rotate_right(18) ^ (w >> 3)).wrapping_add(word(x, e))
+    }))
+}
+
+/// SHA256SU1: the small sigma 1 part of the SHA-256 message schedule update, the last two
+/// words depending on the first two results
+fn sha256_schedule_1(x: u128, y: u128, z: u128) -> u128 {
+    let t0 = (y >> 32) | ((word(z, 0) as u128) << 96);
+    let mut w = [0; 4];
+    for e in 0..4 {
+        let prev = if e < 2 { word(z, e + 2) } else { w[e - 2] };
+        let sigma = prev.rotate_right(17) ^ prev.rotate_right(19) ^ (prev >> 10);
+        w[e] = sigma.wrapping_add(word(x, e)).wrapping_add(word(t0, e));
+    }
+    words(w)
+}
+
+/// Carry-less multiplication of two doublewords, VMULL.P64
+fn poly_mul_64(a: u64, b: u64) -> u128 {
+    (0..64)
+        .filter(|i| b >> i & 1 == 1)
+        .fold(0, |acc, i| acc ^ ((a as u128) << i))
+}
+
+/// The reflected CRC of `size` bytes of `value` with the reflected polynomial `poly`, without
+/// inverting the accumulator
+fn crc32(acc: u32, value: u32, size: u32, poly: u32) -> u32 {
+    let bits = 8 << size;
+    let data = if bits == 32 {
+        value
+    } else {
+        value & ((1 << bits) - 1)
+    };
+    (0..bits).fold(acc ^ data, |crc, _| {
+        (crc >> 1) ^ if crc & 1 == 1 { poly } else { 0 }
+    })
+}
+
+impl<'cpu, const PRINT_INSTR: bool> Cpu<'cpu, PRINT_INSTR> {
+    /// CRC32 and CRC32C (C in bit 9) of the low byte, halfword or word of Rm, selected by bits
+    /// 22..21, into the accumulator Rn. Undefined without [`Features::CRC32`].
+    pub(super) fn crc32(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        if !self.features.contains(Features::CRC32) {
+            return self.undefined(decoded.raw);
+        }
+
+        let Operands { op, rn, rd, rm, .. } = decoded.operands;
+        // op is bits 22..21:9
+        let size = op >> 1;
+        if size == 0b11 || rn == 15 || rd == 15 || rm == 15 {
+            return Err(err::Err::UnpredictableInstruction(decoded.raw));
+        }
+        // CRC-32 (0x04c11db7) and CRC-32C (0x1edc6f41), bit reversed
+        let poly = if op & 1 == 1 {
+            0x82f6_3b78
+        } else {
+            0xedb8_8320
+        };
+        self.r[rd] = crc32(self.r[rn], self.r[rm], size, poly);
+        Ok(())
+    }
+
+    /// The two register instructions of the Advanced SIMD miscellaneous space, A in bits 17..16
+    /// and B in bits 10..6: AESE, AESD, AESMC and AESIMC with size 00, SHA1H, SHA1SU1 and
+    /// SHA256SU0 with size 10. Undefined without [`Features::CRYPTO`].
+    pub(super) fn crypto_two_registers(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            vd: d,
+            vm: m,
+            op,
+            size,
+            ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+        if !self.features.contains(Features::CRYPTO) || (d | m) & 1 == 1 {
+            return self.undefined(raw);
+        }
+
+        let (x, y) = (self.q(d / 2), self.q(m / 2));
+        // op is A:B
+        let result = match (op >> 5, op & 0b1_1111, size) {
+            (0b00, 0b01100, 0b00) => aes_encrypt_round(x ^ y),
+            (0b00, 0b01101, 0b00) => aes_decrypt_round(x ^ y),
+            (0b00, 0b01110, 0b00) => aes_mix_columns(y, [2, 3, 1, 1]),
+            (0b00, 0b01111, 0b00) => aes_mix_columns(y, [14, 11, 13, 9]),
+            (0b01, 0b01011, 0b10) => word(y, 0).rotate_left(30) as u128,
+            (0b10, 0b01110, 0b10) => sha1_schedule_1(x, y),
+            (0b10, 0b01111, 0b10) => sha256_schedule_0(x, y),
+            _ => return self.undefined(raw),
+        };
+        self.set_q(d / 2, result);
+        Ok(())
+    }
+
+    /// The three register SHA instructions with bits 11..8 = 1100: SHA1C, SHA1P, SHA1M and
+    /// SHA1SU0 selected by size with U clear, SHA256H, SHA256H2 and SHA256SU1 with U set. Always
+    /// on Q registers, undefined without [`Features::CRYPTO`].
+    pub(super) fn crypto_three_registers(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            vd: d,
+            vn: n,
+            vm: m,
+            size,
+            u,
+            q,
+            ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+        if !self.features.contains(Features::CRYPTO) || !q || (d | n | m) & 1 == 1 {
+            return self.undefined(raw);
+        }
+
+        let (x, y, z) = (self.q(d / 2), self.q(n / 2), self.q(m / 2));
+        let result = match (u, size) {
+            (false, 0b00) => sha1_hash(x, word(y, 0), z, sha_choose),
+            (false, 0b01) => sha1_hash(x, word(y, 0), z, sha_parity),
+            (false, 0b10) => sha1_hash(x, word(y, 0), z, sha_majority),
+            // SHA1SU0
+            (false, _) => x ^ ((y << 64) | (x >> 64)) ^ z,
+            (true, 0b00) => sha256_hash(x, y, z, true),
+            (true, 0b01) => sha256_hash(y, x, z, false),
+            (true, 0b10) => sha256_schedule_1(x, y, z),
+            (true, _) => return self.undefined(raw),
+        };
+        self.set_q(d / 2, result);
+        Ok(())
+    }
+
+    /// VMULL.P64, the three registers of different lengths form with size 10. Undefined without
+    /// [`Features::CRYPTO`].
+    pub(super) fn crypto_vmull_p64(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            vd: d,
+            vn: n,
+            vm: m,
+            ..
+        } = decoded.operands;
+        if !self.features.contains(Features::CRYPTO) || d & 1 == 1 {
+            return self.undefined(decoded.raw);
+        }
+        self.set_q(d / 2, poly_mul_64(self.d[n], self.d[m]));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        aes_decrypt_round, aes_encrypt_round, aes_mix_columns, crc32, gf_mul, poly_mul_64,
+        sha_choose, sha_majority, sha_parity, sha1_hash, sha1_schedule_1, sha256_hash,
+        sha256_schedule_0, sha256_schedule_1, word, words,
+    };
+
+    /// the message words of the single padded block of "abc"
+    fn abc_block() -> [u128; 4] {
+        let mut w = [0; 16];
+        w[0] = 0x6162_6380;
+        w[15] = 24;
+        std::array::from_fn(|i| words([w[4 * i], w[4 * i + 1], w[4 * i + 2], w[4 * i + 3]]))
+    }
+
+    fn add(x: u128, y: u128) -> u128 {
+        words(std::array::from_fn(|e| word(x, e).wrapping_add(word(y, e))))
+    }
+
+    #[test]
+    fn aes_128_like_the_instructions() {
+        // FIPS 197 appendix C.1, the round keys expanded with the S-box of AESE on a zero state
+        // the bytes 00 01 02 .. 0f, byte 0 being the least significant
+        let mut key = 0x0f0e_0d0c_0b0a_0908_0706_0504_0302_0100;
+        let mut keys = vec![key];
+        let mut rcon = 1u8;
+        for _ in 0..10 {
+            let last = word(key, 3);
+            // ShiftRows has no effect on a word broadcast to all columns, leaving SubWord
+            let sub = word(aes_encrypt_round(words([last; 4])), 0);
+            let mut k: [u32; 4] = std::array::from_fn(|e| word(key, e));
+            k[0] ^= sub.rotate_right(8) ^ rcon as u32;
+            for e in 1..4 {
+                k[e] ^= k[e - 1];
+            }
+            key = words(k);
+            keys.push(key);
+            rcon = gf_mul(rcon, 2);
+        }
+
+        let plain = 0xffee_ddcc_bbaa_9988_7766_5544_3322_1100;
+        let mut state = plain;
+        for (round, key) in keys[..10].iter().enumerate() {
+            state = aes_encrypt_round(state ^ key);
+            if round < 9 {
+                state = aes_mix_columns(state, [2, 3, 1, 1]);
+            }
+        }
+        state ^= keys[10];
+        assert_eq!(state, 0x5ac5_b470_80b7_cdd8_3004_7b6a_d8e0_c469);
+
+        // the equivalent inverse cipher, with InvMixColumns applied to the middle round keys
+        state = aes_mix_columns(aes_decrypt_round(state ^ keys[10]), [14, 11, 13, 9]);
+        for key in keys[2..10].iter().rev() {
+            let key = aes_mix_columns(*key, [14, 11, 13, 9]);
+            state = aes_mix_columns(aes_decrypt_round(state ^ key), [14, 11, 13, 9]);
+        }
+        state = aes_decrypt_round(state ^ aes_mix_columns(keys[1], [14, 11, 13, 9]));
+        assert_eq!(state ^ keys[0], plain);
+    }
+
+    #[test]
+    fn sha1_of_abc() {
+        let h = [
+            0x6745_2301,
+            0xefcd_ab89,
+            0x98ba_dcfe,
+            0x1032_5476,
+            0xc3d2_e1f0,
+        ];
+        let k = [0x5a82_7999, 0x6ed9_eba1, 0x8f1b_bcdc, 0xca62_c1d6];
+        let mut w = abc_block();
+        let (mut abcd, mut e) = (words([h[0], h[1], h[2], h[3]]), h[4]);
+        for i in 0..20 {
+            let wk = add(w[i % 4], words([k[i / 5]; 4]));
+            let next_e = word(abcd, 0).rotate_left(30);
+            let f = match i / 5 {
+                0 => sha_choose,
+                2 => sha_majority,
+                _ => sha_parity,
+            };
+            abcd = sha1_hash(abcd, e, wk, f);
+            e = next_e;
+            if i < 16 {
+                let (w0, w1, w2, w3) = (w[i % 4], w[(i + 1) % 4], w[(i + 2) % 4], w[(i + 3) % 4]);
+                // SHA1SU0 followed by SHA1SU1
+                w[i % 4] = sha1_schedule_1(w0 ^ ((w1 << 64) | (w0 >> 64)) ^ w2, w3);
+            }
+        }
+        let digest: Vec<u32> = (0..4)
+            .map(|i| word(abcd, i).wrapping_add(h[i]))
+            .chain([e.wrapping_add(h[4])])
+            .collect();
+        assert_eq!(
+            digest,
+            [
+                0xa999_3e36,
+                0x4706_816a,
+                0xba3e_2571,
+                0x7850_c26c,
+                0x9cd0_d89d
+            ]
+        );
+    }
+
+    #[test]
+    fn sha256_of_abc() {
+        let h = [
+            0x6a09_e667,
+            0xbb67_ae85,
+            0x3c6e_f372,
+            0xa54f_f53a,
+            0x510e_527f,
+            0x9b05_688c,
+            0x1f83_d9ab,
+            0x5be0_cd19,
+        ];
+        // the first 32 bits of the fractional parts of the cube roots of the first 64 primes
+        let primes = (2u32..).filter(|n| (2..*n).all(|d| n % d != 0)).take(64);
+        let k: Vec<u32> = primes
+            .map(|p| ((p as f64).cbrt().fract() * 4294967296.0) as u32)
+            .collect();
+        assert_eq!((k[0], k[63]), (0x428a_2f98, 0xc671_78f2));
+
+        let mut w = abc_block();
+        let (mut abcd, mut efgh) = (
+            words([h[0], h[1], h[2], h[3]]),
+            words([h[4], h[5], h[6], h[7]]),
+        );
+        for i in 0..16 {
+            let wk = add(
+                w[i % 4],
+                words([k[4 * i], k[4 * i + 1], k[4 * i + 2], k[4 * i + 3]]),
+            );
+            if i < 12 {
+                let updated = sha256_schedule_0(w[i % 4], w[(i + 1) % 4]);
+                w[i % 4] = sha256_schedule_1(updated, w[(i + 2) % 4], w[(i + 3) % 4]);
+            }
+            let previous = abcd;
+            abcd = sha256_hash(abcd, efgh, wk, true);
+            efgh = sha256_hash(previous, efgh, wk, false);
+        }
+        let digest: Vec<u32> = (0..8)
+            .map(|i| {
+                let v = if i < 4 {
+                    word(abcd, i)
+                } else {
+                    word(efgh, i - 4)
+                };
+                v.wrapping_add(h[i])
+            })
+            .collect();
+        assert_eq!(
+            digest,
+            [
+                0xba78_16bf,
+                0x8f01_cfea,
+                0x4141_40de,
+                0x5dae_2223,
+                0xb003_61a3,
+                0x9617_7a9c,
+                0xb410_ff61,
+                0xf200_15ad
+            ]
+        );
+    }
+
+    #[test]
+    fn crc32_and_polynomial_multiply() {
+        let check = |poly| {
+            !b"123456789"
+                .iter()
+                .fold(!0, |acc, &byte| crc32(acc, byte as u32, 0, poly))
+        };
+        assert_eq!(check(0xedb8_8320), 0xcbf4_3926);
+        assert_eq!(check(0x82f6_3b78), 0xe306_9283);
+        // a word at a time gives the same result as its bytes
+        let word = u32::from_le_bytes(*b"1234");
+        let bytes = b"1234"
+            .iter()
+            .fold(!0, |acc, &byte| crc32(acc, byte as u32, 0, 0xedb8_8320));
+        assert_eq!(crc32(!0, word, 2, 0xedb8_8320), bytes);
+        // the upper bits of a byte or halfword operand are ignored
+        assert_eq!(
+            crc32(0, 0xffff_ff31, 0, 0xedb8_8320),
+            crc32(0, 0x31, 0, 0xedb8_8320)
+        );
+
+        assert_eq!(poly_mul_64(0b11, 0b11), 0b101);
+        assert_eq!(poly_mul_64(1 << 63, 1 << 63), 1 << 126);
+        assert_eq!(poly_mul_64(u64::MAX, 2), (u64::MAX as u128) << 1);
+    }
+}
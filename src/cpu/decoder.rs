@@ -1,19 +1,253 @@
+use std::sync::LazyLock;
+
+use table::DecodeTable;
+
+use crate::cpu::alu;
+
 #[derive(Debug, Copy, Clone)]
 pub struct Decoded {
     pub cond: u8,
     pub kind: InstructionKind,
     pub raw: u32,
+    /// the operand fields the matching rule names
+    pub operands: Operands,
+    /// the UNPREDICTABLE rule the instruction violates, checked once as it is decoded
+    pub unpredictable: Option<unpredictable::Rule>,
+}
+
+impl Decoded {
+    /// The instruction `raw` decodes to, checked against the UNPREDICTABLE rules
+    pub fn new(cond: u8, kind: InstructionKind, raw: u32, operands: Operands) -> Self {
+        let mut decoded = Decoded {
+            cond,
+            kind,
+            raw,
+            operands,
+            unpredictable: None,
+        };
+        decoded.unpredictable = unpredictable::check(&decoded);
+        decoded
+    }
+}
+
+/// Operand fields extracted by [`decode_word`], those the matching rule doesn't name are zero.
+/// The fields are named like in the ARMv7 ARM encoding diagrams.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Operands {
+    /// destination register, RdHi of the long multiplies
+    pub rd: usize,
+    /// first source register, or the base register of a load or store
+    pub rn: usize,
+    /// register operand, shifted by a data-processing or load/store instruction
+    pub rm: usize,
+    /// register holding the shift amount of a register shifted register operand
+    pub rs: usize,
+    /// accumulator of a multiply, RdLo of the long multiplies
+    pub ra: usize,
+    /// transferred register of a load or store
+    pub rt: usize,
+    /// second transferred register of MRRC, MCRR and the 64-bit VMOV
+    pub rt2: usize,
+    /// floating-point or Advanced SIMD destination register as D:Vd, see [`crate::cpu::vfp`] for
+    /// how a single precision register is numbered
+    pub vd: usize,
+    /// floating-point or Advanced SIMD first operand register as N:Vn
+    pub vn: usize,
+    /// floating-point or Advanced SIMD second operand register as M:Vm
+    pub vm: usize,
+    /// register list of LDM and STM, bit n selecting Rn
+    pub list: u32,
+    /// immediate, fields split across the word are concatenated in the order the rule names them
+    pub imm: u32,
+    /// opcode fields selecting the operation within a kind, concatenated like `imm`
+    pub op: u32,
+    /// Advanced SIMD element size, 8 << size bits, or sz selecting double precision
+    pub size: u32,
+    /// shift type of a register operand
+    pub shift: alu::Shift,
+    /// P, pre-indexed addressing
+    pub p: bool,
+    /// U, add the offset to the base
+    pub u: bool,
+    /// B, byte transfer of LDRB and STRB
+    pub b: bool,
+    /// W, write the address back to the base register
+    pub w: bool,
+    /// L, load, or link for a branch
+    pub l: bool,
+    /// S, set the flags, or the User mode registers of LDM and STM
+    pub s: bool,
+    /// Q, operate on the quadword Advanced SIMD registers
+    pub q: bool,
+}
+
+/// The operand a field of a rule extracts into.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+enum Field {
+    rd,
+    rn,
+    rm,
+    rs,
+    ra,
+    rt,
+    rt2,
+    vd,
+    vn,
+    vm,
+    list,
+    imm,
+    op,
+    size,
+    shift,
+    p,
+    u,
+    b,
+    w,
+    l,
+    s,
+    q,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum InstructionKind {
-    MovImm,
+    /// Data-processing with a modified immediate operand, `add r0, r1, #4`
+    DataProcessingImm,
+    /// Data-processing with a register operand shifted by an immediate, `add r0, r1, r2, lsl #2`
+    DataProcessingReg,
+    /// Data-processing with a register operand shifted by a register, `add r0, r1, r2, lsl r3`
+    DataProcessingRegShift,
+    /// MUL, MLA, MLS, UMAAL and the 64-bit UMULL, UMLAL, SMULL and SMLAL
+    Multiply,
+    /// SMLA<x><y>, SMLAW<y>, SMULW<y>, SMLAL<x><y> and SMUL<x><y>
+    HalfwordMultiply,
+    /// SMLAD, SMUAD, SMLSD, SMUSD, SMLALD, SMLSLD, SMMLA, SMMUL and SMMLS
+    MediaMultiply,
+    /// MOVW and MOVT with a 16-bit immediate split into imm4:imm12
+    MovImm16,
+    /// count leading zeros
+    Clz,
+    /// SXTB, SXTH, SXTB16, UXTB, UXTH, UXTB16 and their accumulating forms
+    Extend,
+    /// REV, REV16, REVSH and RBIT
+    Reverse,
+    /// SSAT and USAT
+    Saturate,
+    /// SADD16, UQSUB8, SHASX and the rest of the parallel additions and subtractions on the
+    /// halfwords or bytes of the core registers, the plain forms set the GE flags
+    ParallelAddSub,
+    /// SEL, picking the bytes of Rn or Rm by the GE flags
+    Select,
+    /// USAD8 and USADA8, the sum of the absolute byte differences
+    SumAbsDiff,
+    /// QADD, QSUB, QDADD and QDSUB, saturating to 32 bits and setting Q
+    SaturatingAddSub,
+    /// SBFX, UBFX, BFI and BFC
+    Bitfield,
+    /// SDIV and UDIV, only with [`crate::cpu::features::Features::IDIV`]
+    Divide,
+    /// LDREX, STREX and their byte, halfword and doubleword forms
+    Exclusive,
+    /// CRC32 and CRC32C of a byte, halfword or word, only with
+    /// [`crate::cpu::features::Features::CRC32`]
+    Crc32,
+    /// CLREX, unconditional
+    Clrex,
+    /// DMB, DSB and ISB, unconditional
+    Barrier,
+    /// NOP, YIELD, WFE, WFI and SEV
+    Hint,
+    /// MRS, reading the CPSR or with R set the SPSR
+    Mrs,
+    /// MSR with a register, writing the bytes of the CPSR or SPSR its mask selects
+    Msr,
+    /// MSR with a modified immediate, like [`InstructionKind::Msr`]
+    MsrImm,
+    /// CPSIE, CPSID and CPS changing the interrupt masks and the mode, unconditional
+    Cps,
+    /// SRS, storing LR and the SPSR to the stack of another mode, unconditional
+    Srs,
+    /// RFE, returning from an exception with pc and the CPSR loaded from memory, unconditional
+    Rfe,
+    /// SETEND, selecting the endianness of data accesses, unconditional
+    Setend,
+    /// PLD, PLDW and PLI, unconditional and executed as a NOP, like the unallocated memory hints
+    /// next to them
+    Preload,
+    /// VADD, VSUB, VMUL, VNMUL, VDIV, VMLA, VFMA and their variants, the three register
+    /// floating-point instructions
+    VfpDataProcessing,
+    /// VMOV (immediate and register), VABS, VNEG, VSQRT, VCMP and VCVT, the other floating-point
+    /// data-processing instructions
+    VfpOther,
+    /// VMOV between a core and a single precision register or half a double, VMRS and VMSR
+    VfpTransfer,
+    /// VMOV between two core registers and two singles or a double
+    VfpTransfer64,
+    /// VLDR, VSTR, VLDM and VSTM, including `vpush {d8-d15}` and `vpop {d8-d15}`
+    VfpLoadStore,
+    /// Advanced SIMD with three registers of the same length, unconditional: VADD, VMUL, VAND,
+    /// VSHL, VMAX and the rest of the integer and single precision element-wise operations
+    NeonThreeSame,
+    /// Advanced SIMD with three registers of different lengths, the long, wide and narrowing
+    /// VADD, VSUB, VMLA and VMUL forms, unconditional
+    NeonThreeDifferent,
+    /// Advanced SIMD with two registers and a scalar, VMLA, VMUL and their long and doubling
+    /// forms by an element, unconditional
+    NeonScalar,
+    /// Advanced SIMD with two registers and a shift amount, VSHR, VSHL, VSRI, VSLI, VSHRN, VSHLL,
+    /// VMOVL and VCVT between floating and fixed point, unconditional
+    NeonShift,
+    /// VMOV, VMVN, VORR and VBIC with a modified immediate, unconditional
+    NeonModifiedImm,
+    /// Advanced SIMD with two registers, VREV, VCNT, VZIP, VMOVN, VCVT and the rest of the
+    /// miscellaneous operations, unconditional
+    NeonMisc,
+    /// VEXT, unconditional
+    NeonVext,
+    /// VTBL and VTBX, unconditional
+    NeonVtbl,
+    /// VDUP of a scalar, unconditional
+    NeonVdupScalar,
+    /// VLD1-VLD4 and VST1-VST4 of multiple structures, unconditional
+    NeonLoadStoreMultiple,
+    /// VLD1-VLD4 and VST1-VST4 of a single lane, unconditional
+    NeonLoadStoreLane,
+    /// VLD1-VLD4 to all lanes, unconditional
+    NeonLoadAllLanes,
+    /// MRC and MCR to coprocessors other than 10 and 11, only the user-visible CP15 registers are
+    /// defined
+    Coprocessor,
+    /// MRRC and MCRR, the 64-bit transfers of [`InstructionKind::Coprocessor`]
+    Coprocessor64,
+    /// UDF, the permanently undefined encoding, and HLT, HVC and SMC, which are undefined in user
+    /// mode. Raises SIGILL whatever its condition.
+    Undefined,
+    /// BKPT, raises SIGTRAP, unconditional
+    Bkpt,
+    /// LDM/STM in all four addressing modes, including `push {r4, lr}` and `pop {r4, pc}`
+    BlockTransfer,
     /// Only encoding A1 is supported
     ///
     /// See https://support.arm.com/documentation/ddi0406/b/Application-Level-Architecture/Instruction-Details/Alphabetical-list-of-instructions/B?lang=en
     Branch,
     Svc,
-    LdrLiteral,
+    /// `bx Rm`, branch and switch instruction set according to bit 0 of Rm
+    Bx,
+    /// `blx Rm`, like [`InstructionKind::Bx`] but saves the return address to lr
+    BlxReg,
+    /// `blx label`, always switches to Thumb, only exists in the unconditional space
+    BlxImm,
+    /// LDR, STR, LDRB and STRB with a 12-bit immediate offset, `ldr r0, [r1, #-4]!`
+    LoadStoreImm,
+    /// LDRH, STRH, LDRSB, LDRSH, LDRD and STRD with an 8-bit immediate offset split into bits
+    /// 11..8 and 3..0, `ldrh r0, [r1, #2]`
+    ExtraLoadStoreImm,
+    /// LDRH, STRH, LDRSB, LDRSH, LDRD and STRD with a register offset, `ldrd r0, r1, [r2, r3]`
+    ExtraLoadStoreReg,
+    /// LDR, STR, LDRB and STRB with a register offset shifted by an immediate, `ldr r0, [r1, r2,
+    /// lsl #2]`
+    LoadStoreReg,
     Unknown,
 }
 
@@ -21,26 +255,42 @@ struct ArmRule {
     kind: InstructionKind,
     mask: u32,
     value: u32,
+    /// operand fields as inclusive bit ranges
+    fields: &'static [(Field, u8, u8)],
 }
 
-// Build a classification rule from fixed bit ranges. Each rule becomes a
-// single `(word & mask) == value` comparison.
+// Build a classification rule from fixed bit ranges and the operand fields it extracts. Each rule
+// becomes a single `(word & mask) == value` comparison.
 //
-// This macro does not decode instruction operands. `bits(hi..lo = value)` and
-// `bit(n = value)` are inclusive bit matches.
+// `bits(hi..lo = value)` and `bit(n = value)` are inclusive bit matches. The fields after `=>`
+// name the operands in [`Operands`] by their bit ranges or single bits, a field split across the
+// word lists its parts from the most significant one down.
 //
-// Example: `arm_rule!(Branch { bits(27..25 = 0b101) })`
-// means "classify as Branch when bits 27..25 equal 101".
+// Example: `arm_rule!(Branch { bits(27..25 = 0b101) } => l(24), imm(23..0))`
+// means "classify as Branch when bits 27..25 equal 101, with L in bit 24 and imm24 in bits
+// 23..0".
 macro_rules! arm_rule {
-    ($kind:ident { $($field:ident($($args:tt)*)),* $(,)? }) => {
+    ($kind:ident { $($field:ident($($args:tt)*)),* $(,)? }
+        $(=> $($operand:ident($($high:literal $(.. $low:literal)?),+)),+ $(,)?)?) => {
         ArmRule {
             kind: InstructionKind::$kind,
             mask: 0 $(| arm_mask!($field($($args)*)))*,
             value: 0 $(| arm_value!($field($($args)*)))*,
+            fields: &[$($($((Field::$operand, $high, field_low!($high $(.. $low)?)),)+)+)?],
         }
     };
 }
 
+// The low bit of an operand field, a single bit field is both its high and low bit.
+macro_rules! field_low {
+    ($bit:literal) => {
+        $bit
+    };
+    ($high:literal .. $low:literal) => {
+        $low
+    };
+}
+
 macro_rules! arm_mask {
     (bit($bit:literal = $value:expr)) => {
         1u32 << $bit
@@ -59,43 +309,493 @@ macro_rules! arm_value {
     };
 }
 
+/// timing the decode tables on the example programs
+#[cfg(test)]
+mod bench;
+/// lookup tables over the rules below, indexed by the primary opcode
+mod table;
+/// decoding Thumb and Thumb-2 instructions, shares the rule macros above
+pub mod thumb;
+/// the UNPREDICTABLE ARM encodings `--strict` rejects
+pub mod unpredictable;
+
+static DECODE_TABLE: LazyLock<DecodeTable> = LazyLock::new(|| DecodeTable::new(DECODE_RULES));
+static UNCONDITIONAL_TABLE: LazyLock<DecodeTable> =
+    LazyLock::new(|| DecodeTable::new(UNCONDITIONAL_RULES));
+
+// The unconditional space, cond = 1111, see ARMv7 ARM A5.7. Words not matched here are
+// UNDEFINED, they never fall through to DECODE_RULES.
+const UNCONDITIONAL_RULES: &[ArmRule] = &[
+    // BLX (immediate) reuses the branch encoding, H supplies bit 1 of the halfword aligned target
+    arm_rule!(BlxImm {
+        bits(27..25 = 0b101),
+    } => imm(23..0, 24)),
+    // CLREX and the barriers live on top of the PLD encoding
+    arm_rule!(Clrex {
+        bits(27..4 = 0x57ff01),
+    }),
+    arm_rule!(Barrier {
+        bits(27..4 = 0x57ff04),
+    } => imm(3..0)),
+    arm_rule!(Barrier {
+        bits(27..4 = 0x57ff05),
+    } => imm(3..0)),
+    arm_rule!(Barrier {
+        bits(27..4 = 0x57ff06),
+    } => imm(3..0)),
+    // CPS with bit 16 clear, SETEND sets it. imod and M select the change, the immediate is
+    // A:I:F followed by the mode.
+    arm_rule!(Cps {
+        bits(27..20 = 0b00010000),
+        bit(16 = 0),
+        bit(5 = 0),
+    } => op(19..17), imm(8..6, 4..0)),
+    arm_rule!(Setend {
+        bits(27..20 = 0b00010000),
+        bit(16 = 1),
+        bits(7..4 = 0b0000),
+    } => op(9)),
+    // the preload hints have bits 21..20 = 01, R in bit 22 tells PLD from PLDW and bit 24 PLI
+    // from PLD. The register forms set bit 25.
+    arm_rule!(Preload {
+        bits(27..25 = 0b010),
+        bits(21..20 = 0b01),
+    } => op(24, 22), u(23), rn(19..16), imm(11..0)),
+    arm_rule!(Preload {
+        bits(27..25 = 0b011),
+        bits(21..20 = 0b01),
+        bit(4 = 0),
+    } => op(24, 22), u(23), rn(19..16), imm(11..7), shift(6..5), rm(3..0)),
+    // SRS always stores to the SP of the mode in bits 4..0, RFE loads from Rn
+    arm_rule!(Srs {
+        bits(27..25 = 0b100),
+        bit(22 = 1),
+        bit(20 = 0),
+        bits(19..16 = 0b1101),
+        bits(15..5 = 0b00000101000),
+    } => p(24), u(23), w(21), imm(4..0)),
+    arm_rule!(Rfe {
+        bits(27..25 = 0b100),
+        bit(22 = 0),
+        bit(20 = 1),
+        bits(15..0 = 0x0a00),
+    } => p(24), u(23), w(21), rn(19..16)),
+    // Advanced SIMD takes the rest of the space below the PLD hints, split by U in bit 24, A in
+    // bits 23..19, B in bits 11..8 and C in bits 7..4, see A7.4 "Table A7-8". Bit 6 is Q unless
+    // the encoding uses it otherwise, op the bits selecting the operation.
+    arm_rule!(NeonThreeSame {
+        bits(27..25 = 0b001),
+        bit(23 = 0),
+    } => u(24), vd(22, 15..12), size(21..20), vn(7, 19..16), op(11..8, 4), q(6), vm(5, 3..0)),
+    // the modified immediate is i:imm3:imm4, op is cmode:op
+    arm_rule!(NeonModifiedImm {
+        bits(27..25 = 0b001),
+        bit(23 = 1),
+        bits(21..19 = 0b000),
+        bit(7 = 0),
+        bit(4 = 1),
+    } => imm(24, 18..16, 3..0), vd(22, 15..12), op(11..8, 5), q(6)),
+    // the shift amount and the element size are encoded in L:imm6
+    arm_rule!(NeonShift {
+        bits(27..25 = 0b001),
+        bit(23 = 1),
+        bit(4 = 1),
+    } => u(24), vd(22, 15..12), imm(7, 21..16), op(11..8), q(6), vm(5, 3..0)),
+    // A = 1x0xx and 1x10x, bit 6 tells the scalar forms from the three register ones
+    arm_rule!(NeonThreeDifferent {
+        bits(27..25 = 0b001),
+        bit(23 = 1),
+        bit(21 = 0),
+        bit(6 = 0),
+        bit(4 = 0),
+    } => u(24), vd(22, 15..12), size(21..20), vn(7, 19..16), op(11..8), vm(5, 3..0)),
+    arm_rule!(NeonThreeDifferent {
+        bits(27..25 = 0b001),
+        bit(23 = 1),
+        bits(21..20 = 0b10),
+        bit(6 = 0),
+        bit(4 = 0),
+    } => u(24), vd(22, 15..12), size(21..20), vn(7, 19..16), op(11..8), vm(5, 3..0)),
+    // the scalar is an element of Vm, see `Cpu::neon_scalar`. Bit 24 is Q for the operations
+    // keeping the element size, U for the long ones.
+    arm_rule!(NeonScalar {
+        bits(27..25 = 0b001),
+        bit(23 = 1),
+        bit(21 = 0),
+        bit(6 = 1),
+        bit(4 = 0),
+    } => q(24), u(24), vd(22, 15..12), size(21..20), vn(7, 19..16), op(11..8), vm(5, 3..0)),
+    arm_rule!(NeonScalar {
+        bits(27..25 = 0b001),
+        bit(23 = 1),
+        bits(21..20 = 0b10),
+        bit(6 = 1),
+        bit(4 = 0),
+    } => q(24), u(24), vd(22, 15..12), size(21..20), vn(7, 19..16), op(11..8), vm(5, 3..0)),
+    arm_rule!(NeonVext {
+        bits(27..23 = 0b00101),
+        bits(21..20 = 0b11),
+        bit(4 = 0),
+    } => vd(22, 15..12), vn(7, 19..16), imm(11..8), q(6), vm(5, 3..0)),
+    // op is A in bits 17..16 and B in bits 10..6
+    arm_rule!(NeonMisc {
+        bits(27..23 = 0b00111),
+        bits(21..20 = 0b11),
+        bit(11 = 0),
+        bit(4 = 0),
+    } => vd(22, 15..12), size(19..18), op(17..16, 10..6), q(6), vm(5, 3..0)),
+    // the table length minus one is in imm, op selects VTBX
+    arm_rule!(NeonVtbl {
+        bits(27..23 = 0b00111),
+        bits(21..20 = 0b11),
+        bits(11..10 = 0b10),
+        bit(4 = 0),
+    } => vd(22, 15..12), vn(7, 19..16), imm(9..8), op(6), vm(5, 3..0)),
+    arm_rule!(NeonVdupScalar {
+        bits(27..23 = 0b00111),
+        bits(21..20 = 0b11),
+        bits(11..7 = 0b11000),
+        bit(4 = 0),
+    } => vd(22, 15..12), imm(19..16), q(6), vm(5, 3..0)),
+    // op is type respectively the structure size minus one, imm align respectively index_align
+    // of the single lane and T:a of the all lanes forms
+    arm_rule!(NeonLoadStoreMultiple {
+        bits(27..23 = 0b01000),
+        bit(20 = 0),
+    } => vd(22, 15..12), l(21), rn(19..16), op(11..8), size(7..6), imm(5..4), rm(3..0)),
+    arm_rule!(NeonLoadAllLanes {
+        bits(27..23 = 0b01001),
+        bits(21..20 = 0b10),
+        bits(11..10 = 0b11),
+    } => vd(22, 15..12), l(21), rn(19..16), op(9..8), size(7..6), imm(5..4), rm(3..0)),
+    arm_rule!(NeonLoadStoreLane {
+        bits(27..23 = 0b01001),
+        bit(20 = 0),
+    } => vd(22, 15..12), l(21), rn(19..16), size(11..10), op(9..8), imm(7..4), rm(3..0)),
+    // ARMv8 puts VSEL, VMAXNM, VMINNM, VRINTA/N/P/M and VCVTA/N/P/M into the floating-point
+    // space, not modeled yet
+    arm_rule!(Unknown {
+        bits(27..24 = 0b1110),
+        bits(11..9 = 0b101),
+    }),
+];
+
+// Rules only overlap if the earlier one is a special case of the later one, matching a subset of
+// its words, so the first match is also the most specific one. See the
+// `rules_only_overlap_as_special_cases` test.
 const DECODE_RULES: &[ArmRule] = &[
     arm_rule!(Svc {
         bits(27..24 = 0b1111),
-    }),
+    } => imm(23..0)),
     arm_rule!(Branch {
         bits(27..25 = 0b101),
+    } => l(24), imm(23..0)),
+    // Coprocessors 10 and 11 are the floating-point extension, bits 11..9 = 101. The 64-bit
+    // transfers take P = U = W = 0 of the load/store space.
+    arm_rule!(VfpTransfer64 {
+        bits(27..21 = 0b1100010),
+        bits(11..9 = 0b101),
+        bits(7..6 = 0b00),
+        bit(4 = 1),
+    } => l(20), rt2(19..16), rt(15..12), size(8), vm(5, 3..0)),
+    arm_rule!(VfpLoadStore {
+        bits(27..25 = 0b110),
+        bits(11..9 = 0b101),
+    } => p(24), u(23), vd(22, 15..12), w(21), l(20), rn(19..16), size(8), imm(7..0)),
+    // opc1 = 1x11 are the other data-processing instructions, op is opc2:opc3 respectively the
+    // bits 23 and 21..20 of opc1 and bit 6 of opc3
+    arm_rule!(VfpOther {
+        bits(27..23 = 0b11101),
+        bits(21..20 = 0b11),
+        bits(11..9 = 0b101),
+        bit(4 = 0),
+    } => vd(22, 15..12), op(19..16, 7..6), imm(19..16, 3..0), size(8), vm(5, 3..0)),
+    arm_rule!(VfpDataProcessing {
+        bits(27..24 = 0b1110),
+        bits(11..9 = 0b101),
+        bit(4 = 0),
+    } => op(23, 21..20, 6), vd(22, 15..12), vn(7, 19..16), size(8), vm(5, 3..0)),
+    // op is C in bit 8, A in bits 23..21 and B in bits 6..5
+    arm_rule!(VfpTransfer {
+        bits(27..24 = 0b1110),
+        bits(11..9 = 0b101),
+        bit(4 = 1),
+    } => l(20), vn(7, 19..16), rt(15..12), op(8, 23..21, 6..5)),
+    // MRC/MCR and MRRC/MCRR to coprocessors 14 and 15, the rest of the coprocessor space (LDC,
+    // STC, CDP and the other coprocessors) is undefined, since there are no further coprocessors.
+    // op is the coprocessor followed by opc1, CRn, CRm and opc2 respectively opc1 and CRm.
+    arm_rule!(Coprocessor {
+        bits(27..24 = 0b1110),
+        bits(11..9 = 0b111),
+        bit(4 = 1),
+    } => l(20), rt(15..12), op(11..8, 23..21, 19..16, 3..0, 7..5)),
+    arm_rule!(Coprocessor64 {
+        bits(27..21 = 0b1100010),
+        bits(11..9 = 0b111),
+    } => l(20), rt2(19..16), rt(15..12), op(11..8, 7..4, 3..0)),
+    arm_rule!(Undefined {
+        bits(27..25 = 0b110),
     }),
-    // LDR literal: `ldr Rt, [pc, #imm12]`.
-    arm_rule!(LdrLiteral {
-        bits(27..26 = 0b01), // load/store class
-        bit(24 = 1),         // P: pre-indexed address
-        bit(23 = 1),         // U: add positive offset
-        bit(22 = 0),         // B: word transfer, not byte
-        bit(21 = 0),         // W: no writeback
-        bit(20 = 1),         // L: load, not store
-        bits(19..16 = 15),   // Rn: base register is pc/r15
+    arm_rule!(Undefined {
+        bits(27..24 = 0b1110),
     }),
-    // MOV immediate: data-processing immediate with opcode 1101.
-    arm_rule!(MovImm {
-        bits(27..25 = 0b001),
-        bits(24..21 = Op::Mov as u32),
+    arm_rule!(BlockTransfer {
+        bits(27..25 = 0b100),
+    } => p(24), u(23), s(22), w(21), l(20), rn(19..16), list(15..0)),
+    // The literal form `ldr Rt, [pc, #imm12]` is the immediate form with pc as its base.
+    arm_rule!(LoadStoreImm {
+        bits(27..25 = 0b010),
+    } => p(24), u(23), b(22), w(21), l(20), rn(19..16), rt(15..12), imm(11..0)),
+    arm_rule!(LoadStoreReg {
+        bits(27..25 = 0b011),
+        bit(4 = 0),
+    } => p(24), u(23), b(22), w(21), l(20), rn(19..16), rt(15..12), imm(11..7), shift(6..5),
+        rm(3..0)),
+    // The multiplies and divides of the media space hold Rd in bits 19..16, Ra in 15..12, Rm in
+    // 11..8 and Rn in 3..0.
+    arm_rule!(Divide {
+        bits(27..23 = 0b01110),
+        bit(22 = 0),
+        bit(20 = 1),
+        bits(15..12 = 0b1111),
+        bits(7..4 = 0b0001),
+    } => u(21), rd(19..16), rm(11..8), rn(3..0)),
+    arm_rule!(MediaMultiply {
+        bits(27..23 = 0b01110),
+        bit(4 = 1),
+    } => op(22..20, 7..5), rd(19..16), ra(15..12), rm(11..8), rn(3..0)),
+    arm_rule!(SumAbsDiff {
+        bits(27..20 = 0b01111000),
+        bits(7..4 = 0b0001),
+    } => rd(19..16), ra(15..12), rm(11..8), rn(3..0)),
+    // the media space with bits 24..20 and 7..5 all set is permanently undefined
+    arm_rule!(Undefined {
+        bits(27..20 = 0b01111111),
+        bits(7..4 = 0b1111),
+    } => imm(19..8, 3..0)),
+    // the parallel additions and subtractions, bits 22..20 select the signedness and saturating
+    // or halving, bits 7..5 the operation
+    arm_rule!(ParallelAddSub {
+        bits(27..23 = 0b01100),
+        bit(4 = 1),
+    } => op(22..20, 7..5), rn(19..16), rd(15..12), rm(3..0)),
+    arm_rule!(Select {
+        bits(27..20 = 0b01101000),
+        bits(11..4 = 0xfb),
+    } => rn(19..16), rd(15..12), rm(3..0)),
+    arm_rule!(Extend {
+        bits(27..23 = 0b01101),
+        bits(9..4 = 0b000111),
+    } => op(22..20), rn(19..16), rd(15..12), imm(11..10), rm(3..0)),
+    arm_rule!(Reverse {
+        bits(27..23 = 0b01101),
+        bits(21..16 = 0b111111),
+        bits(11..8 = 0b1111),
+        bits(6..4 = 0b011),
+    } => op(22, 7), rd(15..12), rm(3..0)),
+    // SSAT and USAT, the shift type is LSL or ASR, bit 5 being clear
+    arm_rule!(Saturate {
+        bits(27..23 = 0b01101),
+        bit(21 = 1),
+        bits(5..4 = 0b01),
+    } => u(22), rd(15..12), imm(20..16, 11..7), shift(6..5), rn(3..0)),
+    // SBFX with bits 22..21 = 01, BFI and BFC with 10, UBFX with 11. The immediate is the msb or
+    // the width - 1 followed by the lsb.
+    arm_rule!(Bitfield {
+        bits(27..21 = 0b0111101),
+        bits(5..4 = 0b01),
+    } => op(22..21), rd(15..12), imm(20..16, 11..7), rn(3..0)),
+    arm_rule!(Bitfield {
+        bits(27..23 = 0b01111),
+        bit(22 = 1),
+        bits(5..4 = 0b01),
+    } => op(22..21), rd(15..12), imm(20..16, 11..7), rn(3..0)),
+    // Bits 7..4 = 1001 of the data-processing space are the multiplies, the exclusives and SWP.
+    // The long multiplies hold RdHi in Rd and RdLo in Ra.
+    arm_rule!(Multiply {
+        bits(27..24 = 0b0000),
+        bits(7..4 = 0b1001),
+    } => op(23..21), s(20), rd(19..16), ra(15..12), rm(11..8), rn(3..0)),
+    // LDREX and STREX, bits 22..21 select word, doubleword, byte or halfword and bit 20 the load.
+    // Loads transfer bits 15..12, stores bits 3..0 and return their status in bits 15..12.
+    arm_rule!(Exclusive {
+        bits(27..23 = 0b00011),
+        bit(20 = 1),
+        bits(11..4 = 0xf9),
+    } => op(22..21), l(20), rn(19..16), rt(15..12)),
+    arm_rule!(Exclusive {
+        bits(27..23 = 0b00011),
+        bit(20 = 0),
+        bits(11..4 = 0xf9),
+    } => op(22..21), l(20), rn(19..16), rd(15..12), rt(3..0)),
+    // SWP and SWPB, not modeled, since they are deprecated in ARMv7.
+    arm_rule!(Unknown {
+        bits(27..25 = 0b000),
+        bits(7..4 = 0b1001),
+    }),
+    // The remaining combinations of bits 7 and 4 set, bits 6..5 != 00, are the extra
+    // load/stores, bits 6..5 select the transfer.
+    arm_rule!(ExtraLoadStoreImm {
+        bits(27..25 = 0b000),
+        bit(22 = 1),
+        bit(7 = 1),
+        bit(6 = 1),
+        bit(4 = 1),
+    } => p(24), u(23), w(21), l(20), rn(19..16), rt(15..12), imm(11..8, 3..0), op(6..5)),
+    arm_rule!(ExtraLoadStoreImm {
+        bits(27..25 = 0b000),
+        bit(22 = 1),
+        bits(7..4 = 0b1011),
+    } => p(24), u(23), w(21), l(20), rn(19..16), rt(15..12), imm(11..8, 3..0), op(6..5)),
+    arm_rule!(ExtraLoadStoreReg {
+        bits(27..25 = 0b000),
+        bit(22 = 0),
+        bit(7 = 1),
+        bit(6 = 1),
+        bit(4 = 1),
+    } => p(24), u(23), w(21), l(20), rn(19..16), rt(15..12), op(6..5), rm(3..0)),
+    arm_rule!(ExtraLoadStoreReg {
+        bits(27..25 = 0b000),
+        bit(22 = 0),
+        bits(7..4 = 0b1011),
+    } => p(24), u(23), w(21), l(20), rn(19..16), rt(15..12), op(6..5), rm(3..0)),
+    // TST, TEQ, CMP and CMN without the S bit are the miscellaneous instructions, respectively
+    // MOVW, MOVT, MSR and the hints for the immediate form. The halfword multiplies select the
+    // top halves of Rm and Rn with bits 6 and 5.
+    arm_rule!(HalfwordMultiply {
+        bits(27..23 = 0b00010),
+        bit(20 = 0),
+        bit(7 = 1),
+        bit(4 = 0),
+    } => op(22..21, 6..5), rd(19..16), ra(15..12), rm(11..8), rn(3..0)),
+    // ARMv8 takes bits 7..4 = 0100 of the miscellaneous instructions for CRC32, with the size in
+    // bits 22..21 and C in bit 9
+    arm_rule!(Crc32 {
+        bits(27..23 = 0b00010),
+        bit(20 = 0),
+        bits(7..4 = 0b0100),
+    } => op(22..21, 9), rn(19..16), rd(15..12), rm(3..0)),
+    // MRS and MSR (register), R in bit 22 selects the SPSR, the banked register forms set bit 9.
+    // MSR writes the bytes its mask in bits 19..16 selects.
+    arm_rule!(Mrs {
+        bits(27..23 = 0b00010),
+        bits(21..20 = 0b00),
+        bits(9..4 = 0b000000),
+    } => op(22), rd(15..12)),
+    arm_rule!(Msr {
+        bits(27..23 = 0b00010),
+        bits(21..20 = 0b10),
+        bits(9..4 = 0b000000),
+    } => op(22, 19..16), rm(3..0)),
+    // the banked register MRS and MSR and the other miscellaneous instructions with bit 4 clear,
+    // not modeled yet
+    arm_rule!(Unknown {
+        bits(27..23 = 0b00010),
+        bit(20 = 0),
+        bit(4 = 0),
     }),
+    arm_rule!(Bx {
+        bits(27..4 = 0x12fff1),
+    } => rm(3..0)),
+    arm_rule!(BlxReg {
+        bits(27..4 = 0x12fff3),
+    } => rm(3..0)),
+    arm_rule!(Clz {
+        bits(27..16 = 0x16f),
+        bits(11..4 = 0xf1),
+    } => rd(15..12), rm(3..0)),
+    arm_rule!(Bkpt {
+        bits(27..20 = 0b00010010),
+        bits(7..4 = 0b0111),
+    } => imm(19..8, 3..0)),
+    // HLT, HVC and SMC next to BKPT are undefined in user mode
+    arm_rule!(Undefined {
+        bits(27..23 = 0b00010),
+        bit(20 = 0),
+        bits(7..4 = 0b0111),
+    } => imm(19..8, 3..0)),
+    arm_rule!(SaturatingAddSub {
+        bits(27..23 = 0b00010),
+        bit(20 = 0),
+        bits(7..4 = 0b0101),
+    } => op(22..21), rn(19..16), rd(15..12), rm(3..0)),
+    // BXJ, not modeled yet
+    arm_rule!(Unknown {
+        bits(27..23 = 0b00010),
+        bit(20 = 0),
+        bit(7 = 0),
+        bit(4 = 1),
+    }),
+    // MOVW, respectively MOVT with bit 22 set
+    arm_rule!(MovImm16 {
+        bits(27..23 = 0b00110),
+        bits(21..20 = 0b00),
+    } => op(22), rd(15..12), imm(19..16, 11..0)),
+    // the hints are MSR (immediate) with an empty mask
+    arm_rule!(Hint {
+        bits(27..8 = 0x320f0),
+    } => imm(7..0)),
+    arm_rule!(MsrImm {
+        bits(27..23 = 0b00110),
+        bits(21..20 = 0b10),
+    } => op(22, 19..16), imm(11..0)),
+    // bits 24..21 select the operation and S in bit 20 sets the flags
+    arm_rule!(DataProcessingImm {
+        bits(27..25 = 0b001),
+    } => op(24..21), s(20), rn(19..16), rd(15..12), imm(11..0)),
+    arm_rule!(DataProcessingReg {
+        bits(27..25 = 0b000),
+        bit(4 = 0),
+    } => op(24..21), s(20), rn(19..16), rd(15..12), imm(11..7), shift(6..5), rm(3..0)),
+    arm_rule!(DataProcessingRegShift {
+        bits(27..25 = 0b000),
+        bit(7 = 0),
+        bit(4 = 1),
+    } => op(24..21), s(20), rn(19..16), rd(15..12), rs(11..8), shift(6..5), rm(3..0)),
 ];
 
-/// Classify a raw 32-bit ARM word into the subset of instructions currently modeled.
+/// Whether `decoded` was introduced along with Thumb-2 in ARMv6T2: MOVW, MOVT, the bitfield
+/// instructions, RBIT and MLS
+pub fn is_v6t2(decoded: &Decoded) -> bool {
+    match decoded.kind {
+        InstructionKind::MovImm16 | InstructionKind::Bitfield => true,
+        InstructionKind::Reverse => decoded.operands.op == 0b10,
+        InstructionKind::Multiply => decoded.operands.op == 0b011,
+        _ => false,
+    }
+}
+
+/// Classify a raw 32-bit ARM word into the subset of instructions currently modeled and extract
+/// its operands. Only the rules the decode table lists for the word's primary opcode are tested.
 pub fn decode_word(word: u32) -> Decoded {
     let cond = bits(word, 31, 28) as u8;
-    let kind = DECODE_RULES
-        .iter()
-        .find(|rule| rule.matches(word))
-        .map(|rule| rule.kind)
-        .unwrap_or(InstructionKind::Unknown);
+    let (table, unmatched) = if cond == 0b1111 {
+        (&UNCONDITIONAL_TABLE, InstructionKind::Undefined)
+    } else {
+        (&DECODE_TABLE, InstructionKind::Unknown)
+    };
+    let rule = table.find(word);
 
-    Decoded {
+    Decoded::new(
         cond,
-        kind,
-        raw: word,
+        rule.map_or(unmatched, |rule| rule.kind),
+        word,
+        rule.map(|rule| rule.operands(word)).unwrap_or_default(),
+    )
+}
+
+impl Op {
+    /// TST, TEQ, CMP and CMN only update the flags and discard their result.
+    pub fn is_test(self) -> bool {
+        matches!(self, Op::Tst | Op::Teq | Op::Cmp | Op::Cmn)
+    }
+
+    /// Logical operations take their carry from the shifter and leave V untouched.
+    pub fn is_logical(self) -> bool {
+        matches!(
+            self,
+            Op::And | Op::Eor | Op::Tst | Op::Teq | Op::Orr | Op::Mov | Op::Bic | Op::Mvn
+        )
     }
 }
 
@@ -103,6 +803,40 @@ impl ArmRule {
     fn matches(&self, word: u32) -> bool {
         (word & self.mask) == self.value
     }
+
+    fn operands(&self, word: u32) -> Operands {
+        let mut operands = Operands::default();
+        for &(field, high, low) in self.fields {
+            let value = bits(word, high, low);
+            // the parts of a split field are named most significant first
+            let append = |field: usize| (field << (high - low + 1)) | value as usize;
+            match field {
+                Field::rd => operands.rd = append(operands.rd),
+                Field::rn => operands.rn = append(operands.rn),
+                Field::rm => operands.rm = append(operands.rm),
+                Field::rs => operands.rs = append(operands.rs),
+                Field::ra => operands.ra = append(operands.ra),
+                Field::rt => operands.rt = append(operands.rt),
+                Field::rt2 => operands.rt2 = append(operands.rt2),
+                Field::vd => operands.vd = append(operands.vd),
+                Field::vn => operands.vn = append(operands.vn),
+                Field::vm => operands.vm = append(operands.vm),
+                Field::list => operands.list = value,
+                Field::imm => operands.imm = append(operands.imm as usize) as u32,
+                Field::op => operands.op = append(operands.op as usize) as u32,
+                Field::size => operands.size = value,
+                Field::shift => operands.shift = alu::Shift::from(value),
+                Field::p => operands.p = value != 0,
+                Field::u => operands.u = value != 0,
+                Field::b => operands.b = value != 0,
+                Field::w => operands.w = value != 0,
+                Field::l => operands.l = value != 0,
+                Field::s => operands.s = value != 0,
+                Field::q => operands.q = value != 0,
+            }
+        }
+        operands
+    }
 }
 
 pub fn bits(word: u32, high: u8, low: u8) -> u32 {
@@ -119,7 +853,7 @@ pub fn bit(word: u32, bit: u8) -> bool {
 /// Data-processing opcode field, encoded in bits 24..21.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
-enum Op {
+pub enum Op {
     And = 0b0000,
     Eor = 0b0001,
     Sub = 0b0010,
@@ -159,7 +893,7 @@ static OP_TABLE: [Op; 16] = [
 ];
 
 #[inline(always)]
-fn op_from_bits(bits: u8) -> Op {
+pub fn op_from_bits(bits: u8) -> Op {
     debug_assert!(bits <= 0b1111);
     unsafe { *OP_TABLE.get_unchecked(bits as usize) }
 }
@@ -186,14 +920,202 @@ pub fn sign_extend(value: u32, bits: u32) -> i32 {
     ((value << shift) as i32) >> shift
 }
 
+#[inline(always)]
+/// Extract the `width` bits of `value` starting at `lsb`, as done by UBFX.
+pub fn zero_extend_field(value: u32, lsb: u32, width: u32) -> u32 {
+    debug_assert!((1..=32).contains(&width) && lsb + width <= 32);
+
+    (value >> lsb) & (u32::MAX >> (32 - width))
+}
+
+#[inline(always)]
+/// Extract the `width` bits of `value` starting at `lsb` and sign extend them, as done by SBFX.
+pub fn sign_extend_field(value: u32, lsb: u32, width: u32) -> i32 {
+    sign_extend(zero_extend_field(value, lsb, width), width)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{InstructionKind, Op, decode_word, op_from_bits};
+    use super::{
+        ArmRule, DECODE_RULES, DECODE_TABLE, Field, InstructionKind, Op, Operands,
+        UNCONDITIONAL_RULES, UNCONDITIONAL_TABLE, bits, decode_word, is_v6t2, op_from_bits,
+        sign_extend_field, zero_extend_field,
+    };
+    use crate::cpu::alu::Shift;
 
     fn cond(word: u32) -> u8 {
         ((word >> 28) & 0xf) as u8
     }
 
+    /// Rules may only overlap if the earlier one matches a subset of the words the later one
+    /// does, so no rule depends on the table order beyond special cases preceding the general
+    /// ones. Register and immediate fields must lie in the bits a rule leaves open, the opcode,
+    /// size, shift and flag fields may name bits the rules split on.
+    fn assert_unambiguous(rules: &[ArmRule]) {
+        for (i, earlier) in rules.iter().enumerate() {
+            for later in &rules[i + 1..] {
+                let overlap = (earlier.value ^ later.value) & earlier.mask & later.mask == 0;
+                let special_case =
+                    earlier.mask & later.mask == later.mask && earlier.mask != later.mask;
+                assert!(
+                    !overlap || special_case,
+                    "{:?} ({:#010x}/{:#010x}) and {:?} ({:#010x}/{:#010x}) overlap ambiguously",
+                    earlier.kind,
+                    earlier.value,
+                    earlier.mask,
+                    later.kind,
+                    later.value,
+                    later.mask,
+                );
+            }
+
+            for &(field, high, low) in earlier.fields {
+                if matches!(
+                    field,
+                    Field::op
+                        | Field::size
+                        | Field::shift
+                        | Field::p
+                        | Field::u
+                        | Field::b
+                        | Field::w
+                        | Field::l
+                        | Field::s
+                        | Field::q
+                ) {
+                    continue;
+                }
+                assert_eq!(
+                    bits(earlier.mask, high, low),
+                    0,
+                    "{field:?} of {:?} overlaps its fixed bits",
+                    earlier.kind
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rules_only_overlap_as_special_cases() {
+        assert_unambiguous(DECODE_RULES);
+        assert_unambiguous(UNCONDITIONAL_RULES);
+    }
+
+    /// Words covering every table index, with the other bits cleared, set and scrambled
+    fn sample_words() -> impl Iterator<Item = u32> {
+        let mut state = 0x2545_f491u32;
+        (0..1u32 << 12).flat_map(move |i| {
+            let opcode = ((i >> 4) << 20) | ((i & 0xf) << 4);
+            (0..16).map(move |j| {
+                let rest = match j {
+                    0 => 0,
+                    1 => u32::MAX,
+                    _ => {
+                        state ^= state << 13;
+                        state ^= state >> 17;
+                        state ^= state << 5;
+                        state
+                    }
+                };
+                opcode | (rest & !0x0ff0_00f0)
+            })
+        })
+    }
+
+    #[test]
+    fn decode_tables_find_the_first_matching_rule() {
+        for word in sample_words() {
+            for (table, rules) in [
+                (&DECODE_TABLE, DECODE_RULES),
+                (&UNCONDITIONAL_TABLE, UNCONDITIONAL_RULES),
+            ] {
+                let scanned = rules.iter().find(|rule| rule.matches(word));
+                let key = |rule: &ArmRule| (rule.kind, rule.mask, rule.value);
+                assert_eq!(table.find(word).map(key), scanned.map(key), "{word:#010x}");
+            }
+        }
+    }
+
+    /// Decoding stays constant time as long as no opcode collects more than a handful of rules,
+    /// a rule that doesn't test bits 27..20 and 7..4 lands in every entry it leaves open.
+    #[test]
+    fn decode_tables_test_few_rules_per_word() {
+        assert!(DECODE_TABLE.longest_entry() <= 4);
+        assert!(UNCONDITIONAL_TABLE.longest_entry() <= 4);
+    }
+
+    #[test]
+    fn extracts_named_operands() {
+        // adds r0, r1, r2, asr #3
+        assert_eq!(
+            decode_word(0xe091_01c2).operands,
+            Operands {
+                op: Op::Add as u32,
+                s: true,
+                rn: 1,
+                rm: 2,
+                imm: 3,
+                shift: Shift::Asr,
+                ..Operands::default()
+            }
+        );
+        // orr r0, r1, r2, ror r3
+        assert_eq!(
+            decode_word(0xe181_0372).operands,
+            Operands {
+                op: Op::Orr as u32,
+                rn: 1,
+                rm: 2,
+                rs: 3,
+                shift: Shift::Ror,
+                ..Operands::default()
+            }
+        );
+        // ldrh r0, [r1, #0x2a] splits its immediate into bits 11..8 and 3..0
+        assert_eq!(
+            decode_word(0xe1d1_02ba).operands,
+            Operands {
+                p: true,
+                u: true,
+                l: true,
+                rn: 1,
+                imm: 0x2a,
+                op: 0b01,
+                ..Operands::default()
+            }
+        );
+        // ldrex r4, [r1] and strex r2, r5, [r1]
+        assert_eq!(decode_word(0xe191_4f9f).operands.rt, 4);
+        let strex = decode_word(0xe181_2f95).operands;
+        assert_eq!((strex.rd, strex.rt, strex.l), (2, 5, false));
+        // push {r4, lr}
+        assert_eq!(
+            decode_word(0xe92d_4010).operands,
+            Operands {
+                p: true,
+                w: true,
+                rn: 13,
+                list: 0x4010,
+                ..Operands::default()
+            }
+        );
+        // umull r0, r1, r2, r3 holds RdLo in Ra and RdHi in Rd
+        let umull = decode_word(0xe081_0392).operands;
+        assert_eq!(
+            (umull.op, umull.rd, umull.ra, umull.rm, umull.rn),
+            (0b100, 1, 0, 3, 2)
+        );
+        // vadd.f64 d16, d1, d18 with D and M set
+        let vadd = decode_word(0xee71_0b22).operands;
+        assert_eq!((vadd.vd, vadd.vn, vadd.vm), (16, 1, 18));
+        // bl with L set
+        assert!(decode_word(0xeb00_0001).operands.l);
+        // blx with H set, imm24:H
+        assert_eq!(decode_word(0xfb00_0001).operands.imm, 0b11);
+        // clrex names no operands
+        assert_eq!(decode_word(0xf57f_f01f).operands, Operands::default());
+    }
+
     #[test]
     fn op_from_bits_maps_data_processing_opcodes() {
         let expected = [
@@ -226,7 +1148,7 @@ mod tests {
         let decoded = decode_word(word);
 
         assert_eq!(decoded.cond, cond(word));
-        assert_eq!(decoded.kind, InstructionKind::MovImm);
+        assert_eq!(decoded.kind, InstructionKind::DataProcessingImm);
         assert_eq!(decoded.raw, word);
     }
 
@@ -236,7 +1158,7 @@ mod tests {
         let decoded = decode_word(word);
 
         assert_eq!(decoded.cond, cond(word));
-        assert_eq!(decoded.kind, InstructionKind::MovImm);
+        assert_eq!(decoded.kind, InstructionKind::DataProcessingImm);
         assert_eq!(decoded.raw, word);
     }
 
@@ -246,7 +1168,7 @@ mod tests {
         let decoded = decode_word(word);
 
         assert_eq!(decoded.cond, 0);
-        assert_eq!(decoded.kind, InstructionKind::MovImm);
+        assert_eq!(decoded.kind, InstructionKind::DataProcessingImm);
     }
 
     #[test]
@@ -255,7 +1177,169 @@ mod tests {
         let decoded = decode_word(word);
 
         assert_eq!(decoded.cond, cond(word));
-        assert_eq!(decoded.kind, InstructionKind::LdrLiteral);
+        assert_eq!(decoded.kind, InstructionKind::LoadStoreImm);
+    }
+
+    #[test]
+    fn classifies_store_immediate_pre_indexed() {
+        // str r0, [sp, #-4]!
+        let word = 0xe52d_0004;
+        let decoded = decode_word(word);
+
+        assert_eq!(decoded.cond, cond(word));
+        assert_eq!(decoded.kind, InstructionKind::LoadStoreImm);
+    }
+
+    #[test]
+    fn classifies_load_byte_scaled_register() {
+        // ldrb r0, [r1, r2, lsl #2]
+        let word = 0xe7d1_0102;
+        let decoded = decode_word(word);
+
+        assert_eq!(decoded.cond, cond(word));
+        assert_eq!(decoded.kind, InstructionKind::LoadStoreReg);
+    }
+
+    #[test]
+    fn classifies_extra_load_store_immediate() {
+        // ldrh r0, [r1, #2]
+        let word = 0xe1d1_00b2;
+        assert_eq!(decode_word(word).kind, InstructionKind::ExtraLoadStoreImm);
+        // strh r0, [r1, #-2], P=1 U=0 L=0 overlaps the miscellaneous space
+        let word = 0xe141_00b2;
+        assert_eq!(decode_word(word).kind, InstructionKind::ExtraLoadStoreImm);
+        // ldrsb r0, [r1], #1
+        let word = 0xe0d1_00d1;
+        assert_eq!(decode_word(word).kind, InstructionKind::ExtraLoadStoreImm);
+    }
+
+    #[test]
+    fn classifies_extra_load_store_register() {
+        // ldrd r0, r1, [r2, r3]
+        let word = 0xe182_00d3;
+        assert_eq!(decode_word(word).kind, InstructionKind::ExtraLoadStoreReg);
+        // ldrsh r0, [r1, -r2]!
+        let word = 0xe131_00f2;
+        assert_eq!(decode_word(word).kind, InstructionKind::ExtraLoadStoreReg);
+    }
+
+    #[test]
+    fn media_space_is_not_a_register_load_store() {
+        // sel r0, r1, r2
+        let word = 0xe681_0fb2;
+        let decoded = decode_word(word);
+
+        assert_eq!(decoded.cond, cond(word));
+        assert_eq!(decoded.kind, InstructionKind::Select);
+    }
+
+    #[test]
+    fn classifies_movw_and_movt() {
+        // movw r0, #0xabcd
+        let word = 0xe30a_0bcd;
+        assert_eq!(decode_word(word).kind, InstructionKind::MovImm16);
+        assert_eq!(decode_word(word).operands.imm, 0xabcd);
+        // movt r0, #0x1234
+        let word = 0xe341_0234;
+        assert_eq!(decode_word(word).kind, InstructionKind::MovImm16);
+        assert_eq!(decode_word(word).operands.imm, 0x1234);
+        // msr apsr_nzcvq, #0 shares the space
+        assert_eq!(decode_word(0xe328_f000).kind, InstructionKind::MsrImm);
+    }
+
+    #[test]
+    fn classifies_clz() {
+        // clz r0, r1
+        assert_eq!(decode_word(0xe16f_0f11).kind, InstructionKind::Clz);
+    }
+
+    #[test]
+    fn classifies_extends() {
+        // uxtb r0, r1
+        assert_eq!(decode_word(0xe6ef_0071).kind, InstructionKind::Extend);
+        // sxth r0, r1, ror #8
+        assert_eq!(decode_word(0xe6bf_0471).kind, InstructionKind::Extend);
+        // uxtab r0, r1, r2
+        assert_eq!(decode_word(0xe6e1_0072).kind, InstructionKind::Extend);
+        // sxtb16 r0, r1
+        assert_eq!(decode_word(0xe68f_0071).kind, InstructionKind::Extend);
+    }
+
+    #[test]
+    fn classifies_reversals() {
+        // rev r0, r1
+        assert_eq!(decode_word(0xe6bf_0f31).kind, InstructionKind::Reverse);
+        // rev16 r0, r1
+        assert_eq!(decode_word(0xe6bf_0fb1).kind, InstructionKind::Reverse);
+        // revsh r0, r1
+        assert_eq!(decode_word(0xe6ff_0fb1).kind, InstructionKind::Reverse);
+        // rbit r0, r1
+        assert_eq!(decode_word(0xe6ff_0f31).kind, InstructionKind::Reverse);
+    }
+
+    #[test]
+    fn classifies_saturations() {
+        // ssat r0, #8, r1
+        assert_eq!(decode_word(0xe6a7_0011).kind, InstructionKind::Saturate);
+        // usat r0, #8, r1, asr #2
+        assert_eq!(decode_word(0xe6e8_0151).kind, InstructionKind::Saturate);
+        // ssat16 r0, #8, r1 is not modeled yet
+        assert_eq!(decode_word(0xe6a7_0f31).kind, InstructionKind::Unknown);
+    }
+
+    #[test]
+    fn classifies_parallel_arithmetic() {
+        // sadd16 r0, r1, r2, uqsub8 r0, r1, r2 and shasx r0, r1, r2
+        assert_eq!(
+            decode_word(0xe611_0f12).kind,
+            InstructionKind::ParallelAddSub
+        );
+        assert_eq!(
+            decode_word(0xe661_0ff2).kind,
+            InstructionKind::ParallelAddSub
+        );
+        assert_eq!(
+            decode_word(0xe631_0f32).kind,
+            InstructionKind::ParallelAddSub
+        );
+        // pkhbt r0, r1, r2 next to SEL is not modeled yet
+        assert_eq!(decode_word(0xe681_0012).kind, InstructionKind::Unknown);
+        // qadd r0, r2, r1 and qdsub r0, r2, r1
+        assert_eq!(
+            decode_word(0xe101_0052).kind,
+            InstructionKind::SaturatingAddSub
+        );
+        assert_eq!(
+            decode_word(0xe161_0052).kind,
+            InstructionKind::SaturatingAddSub
+        );
+        // bxj r0 is not modeled
+        assert_eq!(decode_word(0xe12f_ff20).kind, InstructionKind::Unknown);
+    }
+
+    #[test]
+    fn classifies_bitfields() {
+        // sbfx r0, r1, #4, #8
+        assert_eq!(decode_word(0xe7a7_0251).kind, InstructionKind::Bitfield);
+        // ubfx r0, r1, #4, #8
+        assert_eq!(decode_word(0xe7e7_0251).kind, InstructionKind::Bitfield);
+        // bfi r0, r1, #4, #8
+        assert_eq!(decode_word(0xe7cb_0211).kind, InstructionKind::Bitfield);
+        // bfc r0, #4, #8
+        assert_eq!(decode_word(0xe7cb_021f).kind, InstructionKind::Bitfield);
+        // usad8 r0, r1, r2
+        assert_eq!(decode_word(0xe780_f211).kind, InstructionKind::SumAbsDiff);
+        // udf #0
+        assert_eq!(decode_word(0xe7f0_00f0).kind, InstructionKind::Undefined);
+    }
+
+    #[test]
+    fn extracts_bitfields() {
+        assert_eq!(zero_extend_field(0x1234_5678, 4, 8), 0x67);
+        assert_eq!(zero_extend_field(0xdead_beef, 0, 32), 0xdead_beef);
+        assert_eq!(sign_extend_field(0x0000_0f80, 4, 8), -8);
+        assert_eq!(sign_extend_field(0x0000_0700, 4, 8), 0x70);
+        assert_eq!(sign_extend_field(0x8000_0000, 31, 1), -1);
     }
 
     #[test]
@@ -285,6 +1369,19 @@ mod tests {
         assert_eq!(decoded.kind, InstructionKind::Branch);
     }
 
+    #[test]
+    fn classifies_push_and_pop_as_block_transfer() {
+        // push {r4, lr}
+        let word = 0xe92d_4010;
+        assert_eq!(decode_word(word).kind, InstructionKind::BlockTransfer);
+        // pop {r4, pc}
+        let word = 0xe8bd_8010;
+        assert_eq!(decode_word(word).kind, InstructionKind::BlockTransfer);
+        // ldmib r0, {r1, r2}
+        let word = 0xe990_0006;
+        assert_eq!(decode_word(word).kind, InstructionKind::BlockTransfer);
+    }
+
     #[test]
     fn classifies_svc() {
         let word = 0xef00_0000;
@@ -295,20 +1392,340 @@ mod tests {
     }
 
     #[test]
-    fn unsupported_data_processing_register_is_unknown() {
+    fn classifies_data_processing_register() {
+        // mov r0, r3
         let word = 0xe1a0_0003;
         let decoded = decode_word(word);
 
+        assert_eq!(decoded.cond, cond(word));
+        assert_eq!(decoded.kind, InstructionKind::DataProcessingReg);
+    }
+
+    #[test]
+    fn classifies_data_processing_register_shifted_register() {
+        // add r0, r1, r2, lsl r3
+        let word = 0xe081_0312;
+        let decoded = decode_word(word);
+
+        assert_eq!(decoded.cond, cond(word));
+        assert_eq!(decoded.kind, InstructionKind::DataProcessingRegShift);
+    }
+
+    #[test]
+    fn classifies_compare_with_s_bit_as_data_processing() {
+        // cmp r0, #1
+        let word = 0xe350_0001;
+        let decoded = decode_word(word);
+
+        assert_eq!(decoded.cond, cond(word));
+        assert_eq!(decoded.kind, InstructionKind::DataProcessingImm);
+    }
+
+    #[test]
+    fn classifies_multiplies() {
+        // mul r0, r1, r2
+        assert_eq!(decode_word(0xe000_0291).kind, InstructionKind::Multiply);
+        // mls r0, r1, r2, r3
+        assert_eq!(decode_word(0xe060_3291).kind, InstructionKind::Multiply);
+        // smull r0, r1, r2, r3
+        assert_eq!(decode_word(0xe0c1_0392).kind, InstructionKind::Multiply);
+        // smlabt r0, r1, r2, r3
+        assert_eq!(
+            decode_word(0xe100_32c1).kind,
+            InstructionKind::HalfwordMultiply
+        );
+        // smulwb r0, r1, r2
+        assert_eq!(
+            decode_word(0xe120_02a1).kind,
+            InstructionKind::HalfwordMultiply
+        );
+        // smmul r0, r1, r2
+        assert_eq!(
+            decode_word(0xe750_f211).kind,
+            InstructionKind::MediaMultiply
+        );
+        // smuad r0, r1, r2
+        assert_eq!(
+            decode_word(0xe700_f211).kind,
+            InstructionKind::MediaMultiply
+        );
+    }
+
+    #[test]
+    fn classifies_divides() {
+        // sdiv r0, r1, r2
+        assert_eq!(decode_word(0xe710_f211).kind, InstructionKind::Divide);
+        // udiv r0, r1, r2
+        assert_eq!(decode_word(0xe730_f211).kind, InstructionKind::Divide);
+    }
+
+    #[test]
+    fn classifies_exclusives() {
+        // ldrex r0, [r1]
+        assert_eq!(decode_word(0xe191_0f9f).kind, InstructionKind::Exclusive);
+        // strex r2, r0, [r1]
+        assert_eq!(decode_word(0xe181_2f90).kind, InstructionKind::Exclusive);
+        // ldrexd r4, r5, [r1]
+        assert_eq!(decode_word(0xe1b1_4f9f).kind, InstructionKind::Exclusive);
+        // strexb r2, r0, [r1]
+        assert_eq!(decode_word(0xe1c1_2f90).kind, InstructionKind::Exclusive);
+        // ldrexh r0, [r1]
+        assert_eq!(decode_word(0xe1f1_0f9f).kind, InstructionKind::Exclusive);
+    }
+
+    #[test]
+    fn classifies_clrex_and_barriers() {
+        assert_eq!(decode_word(0xf57f_f01f).kind, InstructionKind::Clrex);
+        // dsb sy, dmb ish, isb sy
+        assert_eq!(decode_word(0xf57f_f04f).kind, InstructionKind::Barrier);
+        assert_eq!(decode_word(0xf57f_f05b).kind, InstructionKind::Barrier);
+        assert_eq!(decode_word(0xf57f_f06f).kind, InstructionKind::Barrier);
+    }
+
+    #[test]
+    fn classifies_unconditional_space() {
+        // pld [r1], pldw [r1, #-4], pli [r1, r2] and pld [r1, r2, lsl #2]
+        assert_eq!(decode_word(0xf5d1_f000).kind, InstructionKind::Preload);
+        assert_eq!(decode_word(0xf511_f004).kind, InstructionKind::Preload);
+        assert_eq!(decode_word(0xf6d1_f002).kind, InstructionKind::Preload);
+        assert_eq!(decode_word(0xf7d1_f102).kind, InstructionKind::Preload);
+        // setend be and setend le
+        assert_eq!(decode_word(0xf101_0200).kind, InstructionKind::Setend);
+        assert_eq!(decode_word(0xf101_0000).kind, InstructionKind::Setend);
+        // the conditional encodings never apply: an SVC, a register store and MCR2 are undefined
+        assert_eq!(decode_word(0xff00_0000).kind, InstructionKind::Undefined);
+        assert_eq!(decode_word(0xf781_0002).kind, InstructionKind::Undefined);
+        assert_eq!(decode_word(0xfe01_0f10).kind, InstructionKind::Undefined);
+        // vselge.f32 s0, s1, s2
+        assert_eq!(decode_word(0xfe20_0a81).kind, InstructionKind::Unknown);
+    }
+
+    #[test]
+    fn classifies_vfp() {
+        // vadd.f64 d0, d1, d2
+        assert_eq!(
+            decode_word(0xee31_0b02).kind,
+            InstructionKind::VfpDataProcessing
+        );
+        // vsqrt.f64 d0, d1 and vmov.f32 s0, #1.0
+        let vsqrt = decode_word(0xeeb1_0bc1);
+        assert_eq!(vsqrt.kind, InstructionKind::VfpOther);
+        assert_eq!((vsqrt.operands.op, vsqrt.operands.size), (0b000111, 1));
+        let vmov = decode_word(0xeeb7_0a00);
+        assert_eq!(vmov.kind, InstructionKind::VfpOther);
+        assert_eq!(vmov.operands.imm, 0x70);
+        // vldr s0, [r1, #4]
+        assert_eq!(decode_word(0xed91_0a01).kind, InstructionKind::VfpLoadStore);
+        // vpush {d8-d15}
+        assert_eq!(decode_word(0xed2d_8b10).kind, InstructionKind::VfpLoadStore);
+        // vmov r0, r1, d0
+        assert_eq!(
+            decode_word(0xec51_0b10).kind,
+            InstructionKind::VfpTransfer64
+        );
+        // vmrs APSR_nzcv, fpscr
+        assert_eq!(decode_word(0xeef1_fa10).kind, InstructionKind::VfpTransfer);
+        // vmov s0, r0
+        assert_eq!(decode_word(0xee00_0a10).kind, InstructionKind::VfpTransfer);
+        // mcr p15, 0, r0, c13, c0, 3 is another coprocessor
+        assert_eq!(decode_word(0xee0d_0f70).kind, InstructionKind::Coprocessor);
+    }
+
+    #[test]
+    fn classifies_coprocessor() {
+        // mrc p15, 0, r0, c13, c0, 3
+        assert_eq!(decode_word(0xee1d_0f70).kind, InstructionKind::Coprocessor);
+        // mrrc p15, 1, r0, r1, c14
+        assert_eq!(
+            decode_word(0xec51_0f1e).kind,
+            InstructionKind::Coprocessor64
+        );
+        // mrc p14, 0, r0, c0, c0, 0 reaches the coprocessor handler, which rejects it
+        assert_eq!(decode_word(0xee10_0e10).kind, InstructionKind::Coprocessor);
+        // ldc p14, c5, [r0] and cdp p7, 0, c0, c0, c0, 0
+        assert_eq!(decode_word(0xed90_5e00).kind, InstructionKind::Undefined);
+        assert_eq!(decode_word(0xee00_0700).kind, InstructionKind::Undefined);
+    }
+
+    #[test]
+    fn classifies_neon() {
+        // vadd.i8 q0, q1, q2
+        let vadd = decode_word(0xf202_0844);
+        assert_eq!(vadd.kind, InstructionKind::NeonThreeSame);
+        assert_eq!((vadd.operands.size, vadd.operands.q), (0, true));
+        // vtbl.8 d0, {d1, d2}, d3
+        let vtbl = decode_word(0xf3b1_0903);
+        assert_eq!(vtbl.kind, InstructionKind::NeonVtbl);
+        assert_eq!((vtbl.operands.vn, vtbl.operands.imm), (1, 1));
+        // vmov.i32 q8, #0x4f00 and vshr.u32 d0, d1, #3
+        let vmov = decode_word(0xf2c4_025f);
+        assert_eq!(vmov.kind, InstructionKind::NeonModifiedImm);
+        assert_eq!((vmov.operands.imm, vmov.operands.op), (0x4f, 0b00100));
+        let vshr = decode_word(0xf3bd_0011);
+        assert_eq!(vshr.kind, InstructionKind::NeonShift);
+        assert_eq!(vshr.operands.imm, 0b0111101);
+        // vmull.s16 q0, d1, d2[1] and vaddl.u8 q0, d1, d2
+        assert_eq!(decode_word(0xf291_0a4a).kind, InstructionKind::NeonScalar);
+        assert_eq!(
+            decode_word(0xf381_0002).kind,
+            InstructionKind::NeonThreeDifferent
+        );
+        // vext.8 d0, d1, d2, #3, vrev64.8 d0, d1 and vdup.16 q0, d1[2]
+        assert_eq!(decode_word(0xf2b1_0302).kind, InstructionKind::NeonVext);
+        assert_eq!(decode_word(0xf3b0_0001).kind, InstructionKind::NeonMisc);
+        assert_eq!(
+            decode_word(0xf3ba_0c41).kind,
+            InstructionKind::NeonVdupScalar
+        );
+        // vld1.8 {d0, d1}, [r1]!
+        assert_eq!(
+            decode_word(0xf421_0a0d).kind,
+            InstructionKind::NeonLoadStoreMultiple
+        );
+        // vst1.32 {d0[1]}, [r0] and vld1.32 {d0[]}, [r0]
+        assert_eq!(
+            decode_word(0xf480_088f).kind,
+            InstructionKind::NeonLoadStoreLane
+        );
+        assert_eq!(
+            decode_word(0xf4a0_0c8f).kind,
+            InstructionKind::NeonLoadAllLanes
+        );
+        // vdup.8 q0, r1 and vmov.u8 r0, d0[3] are transfers of the floating-point space
+        assert_eq!(decode_word(0xeee0_1b10).kind, InstructionKind::VfpTransfer);
+        assert_eq!(decode_word(0xeed0_0b70).kind, InstructionKind::VfpTransfer);
+    }
+
+    #[test]
+    fn classifies_traps() {
+        // udf #0, udf #65535 and a udf with cond = 0000
+        for word in [0xe7f0_00f0, 0xe7ff_ffff, 0x07f0_00f0] {
+            assert_eq!(decode_word(word).kind, InstructionKind::Undefined);
+        }
+        // bkpt #0 and bkpt #0xffff
+        assert_eq!(decode_word(0xe120_0070).kind, InstructionKind::Bkpt);
+        assert_eq!(decode_word(0xe12f_ff7f).kind, InstructionKind::Bkpt);
+        // hlt #0 and smc #0
+        assert_eq!(decode_word(0xe100_0070).kind, InstructionKind::Undefined);
+        assert_eq!(decode_word(0xe160_0070).kind, InstructionKind::Undefined);
+        // ubfx r0, r1, #0, #32 is next to UDF in the media space
+        assert_eq!(decode_word(0xe7ff_0051).kind, InstructionKind::Bitfield);
+    }
+
+    #[test]
+    fn classifies_armv8_crypto_and_crc32() {
+        // crc32b r0, r1, r2 and crc32cw r3, r4, r5
+        assert_eq!(decode_word(0xe101_0042).kind, InstructionKind::Crc32);
+        assert_eq!(decode_word(0xe144_3245).kind, InstructionKind::Crc32);
+        // smlabb r0, r1, r2, r3 shares the space with bit 7 set
+        assert_eq!(
+            decode_word(0xe100_3281).kind,
+            InstructionKind::HalfwordMultiply
+        );
+        // aese.8 q0, q1, sha256h.32 q0, q1, q2 and vmull.p64 q0, d1, d2
+        assert_eq!(decode_word(0xf3b0_0302).kind, InstructionKind::NeonMisc);
+        assert_eq!(
+            decode_word(0xf302_0c44).kind,
+            InstructionKind::NeonThreeSame
+        );
+        assert_eq!(
+            decode_word(0xf2a1_0e02).kind,
+            InstructionKind::NeonThreeDifferent
+        );
+    }
+
+    #[test]
+    fn swap_is_not_a_multiply() {
+        // swp r0, r1, [r2]
+        let word = 0xe102_0091;
+        let decoded = decode_word(word);
+
         assert_eq!(decoded.cond, cond(word));
         assert_eq!(decoded.kind, InstructionKind::Unknown);
     }
 
     #[test]
-    fn unsupported_bx_is_unknown() {
+    fn classifies_bx() {
         let word = 0xe12f_ff1e;
         let decoded = decode_word(word);
 
+        assert_eq!(decoded.cond, cond(word));
+        assert_eq!(decoded.kind, InstructionKind::Bx);
+    }
+
+    #[test]
+    fn classifies_blx_register() {
+        // blxne r3
+        let word = 0x112f_ff33;
+        let decoded = decode_word(word);
+
+        assert_eq!(decoded.cond, cond(word));
+        assert_eq!(decoded.kind, InstructionKind::BlxReg);
+    }
+
+    #[test]
+    fn classifies_blx_immediate_before_branch() {
+        let word = 0xfb00_0001;
+        let decoded = decode_word(word);
+
+        assert_eq!(decoded.cond, 0xf);
+        assert_eq!(decoded.kind, InstructionKind::BlxImm);
+    }
+
+    #[test]
+    fn classifies_status_register_instructions() {
+        // mrs r0, apsr and mrs r0, spsr
+        assert_eq!(decode_word(0xe10f_0000).kind, InstructionKind::Mrs);
+        assert_eq!(decode_word(0xe14f_0000).kind, InstructionKind::Mrs);
+        // msr cpsr_c, r1 and msr spsr_fsxc, r2
+        assert_eq!(decode_word(0xe121_f001).kind, InstructionKind::Msr);
+        assert_eq!(decode_word(0xe16f_f002).operands.rm, 2);
+        // msr cpsr_f, #0xf0000000
+        let msr = decode_word(0xe328_f20f);
+        assert_eq!(msr.kind, InstructionKind::MsrImm);
+        assert_eq!((msr.operands.op, msr.operands.imm), (0b01000, 0x20f));
+        // nop and wfi
+        assert_eq!(decode_word(0xe320_f000).kind, InstructionKind::Hint);
+        assert_eq!(decode_word(0xe320_f003).kind, InstructionKind::Hint);
+    }
+
+    #[test]
+    fn classifies_mode_changes() {
+        // cpsid if, cps #0x13 and cpsie a, #0x1f
+        assert_eq!(decode_word(0xf10c_00c0).kind, InstructionKind::Cps);
+        assert_eq!(decode_word(0xf102_0013).kind, InstructionKind::Cps);
+        assert_eq!(decode_word(0xf10a_011f).kind, InstructionKind::Cps);
+        // setend be is next to CPS
+        assert_eq!(decode_word(0xf101_0200).kind, InstructionKind::Setend);
+        // srsdb sp!, #0x13 and rfeia sp!
+        assert_eq!(decode_word(0xf96d_0513).kind, InstructionKind::Srs);
+        assert_eq!(decode_word(0xf8bd_0a00).kind, InstructionKind::Rfe);
+        // stmdb sp!, {r0} with cond = 1110 is no SRS
+        assert_eq!(
+            decode_word(0xe92d_0001).kind,
+            InstructionKind::BlockTransfer
+        );
+    }
+
+    #[test]
+    fn unsupported_miscellaneous_is_unknown() {
+        // bxj r0
+        let word = 0xe12f_ff20;
+        let decoded = decode_word(word);
+
         assert_eq!(decoded.cond, cond(word));
         assert_eq!(decoded.kind, InstructionKind::Unknown);
     }
+
+    #[test]
+    fn flags_instructions_introduced_with_thumb2() {
+        // movw r0, #42, rbit r0, r1, mls r0, r1, r2, r3
+        for word in [0xe300_002a, 0xe6ff_0f31, 0xe060_3291] {
+            assert!(is_v6t2(&decode_word(word)), "{word:#x}");
+        }
+        // rev r0, r1, mla r0, r1, r2, r3
+        for word in [0xe6bf_0f31, 0xe020_3291] {
+            assert!(!is_v6t2(&decode_word(word)), "{word:#x}");
+        }
+    }
 }
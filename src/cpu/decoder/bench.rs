@@ -0,0 +1,104 @@
+//! Times [`decode_word`] against scanning every rule in order, the decoder before the decode
+//! tables, on the words of the `examples/` programs. Needs the same toolchain as `srun`, run with
+//! `cargo test --release decode_benchmark -- --ignored --nocapture`.
+
+#[path = "../../../tools/asm.rs"]
+mod asm;
+
+use std::{
+    fs,
+    hint::black_box,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use super::{DECODE_RULES, Decoded, InstructionKind, UNCONDITIONAL_RULES, bits, decode_word};
+use crate::elf::{self, pheader};
+
+/// Decodes per program and decoder, cycling through its words
+const DECODES: usize = 4_000_000;
+
+/// The linear scan [`decode_word`] replaced
+fn decode_scan(word: u32) -> Decoded {
+    let cond = bits(word, 31, 28) as u8;
+    let (rules, unmatched) = if cond == 0b1111 {
+        (UNCONDITIONAL_RULES, InstructionKind::Undefined)
+    } else {
+        (DECODE_RULES, InstructionKind::Unknown)
+    };
+    let rule = rules.iter().find(|rule| rule.matches(word));
+
+    Decoded::new(
+        cond,
+        rule.map_or(unmatched, |rule| rule.kind),
+        word,
+        rule.map(|rule| rule.operands(word)).unwrap_or_default(),
+    )
+}
+
+/// The words of the executable segments of an ELF
+fn text_words(raw: &[u8]) -> Vec<u32> {
+    let elf = elf::Elf::try_from(raw).expect("failed to parse example");
+    elf.pheaders
+        .iter()
+        .filter(|phdr| {
+            phdr.r#type == pheader::Type::LOAD && phdr.flags.bits() & pheader::Flags::X.bits() != 0
+        })
+        .flat_map(|phdr| {
+            raw[phdr.offset as usize..(phdr.offset + phdr.filesz) as usize]
+                .chunks_exact(4)
+                .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+        })
+        .collect()
+}
+
+fn time(words: &[u32], decode: fn(u32) -> Decoded) -> Duration {
+    let start = Instant::now();
+    for &word in words.iter().cycle().take(DECODES) {
+        black_box(decode(black_box(word)));
+    }
+    start.elapsed()
+}
+
+#[test]
+#[ignore = "benchmark, needs arm-none-eabi-as and arm-none-eabi-ld"]
+fn decode_benchmark() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let out_dir = root.join("target/decode-bench");
+    fs::create_dir_all(&out_dir).expect("failed to create benchmark directory");
+
+    let mut sources: Vec<_> = fs::read_dir(root.join("examples"))
+        .expect("failed to read examples")
+        .map(|entry| entry.expect("failed to read example").path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "S" || extension == "c")
+        })
+        .collect();
+    sources.sort();
+
+    println!(
+        "{:<24} {:>6} {:>14} {:>14}",
+        "program", "words", "table ns/word", "scan ns/word"
+    );
+    for source in sources {
+        let name = source.file_name().unwrap().to_string_lossy();
+        let elf = out_dir.join(&*name).with_extension("elf");
+        asm::build_source(&source, &elf, 0x8000)
+            .unwrap_or_else(|e| panic!("failed to build {name}: {e}"));
+        let words = text_words(&fs::read(&elf).expect("failed to read example"));
+
+        for &word in &words {
+            let (table, scan) = (decode_word(word), decode_scan(word));
+            assert_eq!((table.kind, table.operands), (scan.kind, scan.operands));
+        }
+        let per_word = |elapsed: Duration| elapsed.as_nanos() as f64 / DECODES as f64;
+        println!(
+            "{:<24} {:>6} {:>14.2} {:>14.2}",
+            name,
+            words.len(),
+            per_word(time(&words, decode_word)),
+            per_word(time(&words, decode_scan)),
+        );
+    }
+}
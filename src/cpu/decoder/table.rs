@@ -0,0 +1,76 @@
+//! Decode tables generated from the rules: like the tables of the ARMv7 ARM A5, they switch on
+//! the primary opcode in bits 27..20 and 7..4 of a word. Decoding a word only tests the few rules
+//! that can match its opcode, instead of every rule in order.
+
+use super::{ArmRule, bits};
+
+/// Bits 27..20 and 7..4 of an ARM word
+const INDEX_MASK: u32 = 0x0ff0_00f0;
+const ENTRIES: usize = 1 << 12;
+
+/// The 12 bit table index of a word or a rule's mask or value
+#[inline(always)]
+fn index(word: u32) -> usize {
+    ((bits(word, 27, 20) << 4) | bits(word, 7, 4)) as usize
+}
+
+pub(super) struct DecodeTable {
+    rules: &'static [ArmRule],
+    /// `candidates[starts[i]..starts[i + 1]]` are the rules that can match a word with index `i`
+    starts: Box<[u32]>,
+    /// indexes into `rules`, in rule order per table entry
+    candidates: Box<[u16]>,
+}
+
+impl DecodeTable {
+    /// Sorts the rules into the entries of the opcodes they can match. An entry ends at the first
+    /// rule that only tests index bits, since every word reaching it matches.
+    pub(super) fn new(rules: &'static [ArmRule]) -> Self {
+        assert!(rules.len() <= u16::MAX as usize, "too many rules to index");
+        let mut starts = Vec::with_capacity(ENTRIES + 1);
+        let mut candidates = Vec::new();
+
+        for i in 0..ENTRIES {
+            starts.push(candidates.len() as u32);
+            for (r, rule) in rules.iter().enumerate() {
+                if (index(rule.value) ^ i) & index(rule.mask) != 0 {
+                    continue;
+                }
+                candidates.push(r as u16);
+                if rule.mask & !INDEX_MASK == 0 {
+                    break;
+                }
+            }
+        }
+        starts.push(candidates.len() as u32);
+
+        DecodeTable {
+            rules,
+            starts: starts.into_boxed_slice(),
+            candidates: candidates.into_boxed_slice(),
+        }
+    }
+
+    /// The rules tested for a word with table index `i`
+    fn entry(&self, i: usize) -> impl Iterator<Item = &'static ArmRule> + '_ {
+        let rules = self.rules;
+        self.candidates[self.starts[i] as usize..self.starts[i + 1] as usize]
+            .iter()
+            .map(move |&r| &rules[r as usize])
+    }
+
+    /// The first rule matching `word`, the same one a scan of all rules in order would find
+    #[inline(always)]
+    pub(super) fn find(&self, word: u32) -> Option<&'static ArmRule> {
+        self.entry(index(word)).find(|rule| rule.matches(word))
+    }
+
+    /// The most rules any word is tested against
+    #[cfg(test)]
+    pub(super) fn longest_entry(&self) -> usize {
+        (0..ENTRIES)
+            .map(|i| self.entry(i).count())
+            .max()
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,608 @@
+//! Classification of 16-bit Thumb and 32-bit Thumb-2 instructions, see ARMv7 ARM A6 "Thumb
+//! Instruction Set Encoding".
+//!
+//! A 32-bit instruction is passed as `hw1 << 16 | hw2`, so the bit positions of its first
+//! halfword are offset by 16 compared to the architecture manual.
+
+#[derive(Debug, Copy, Clone)]
+pub struct Decoded {
+    pub kind: ThumbKind,
+    pub raw: u32,
+    /// 32-bit Thumb-2 encoding, advances pc by 4 instead of 2
+    pub wide: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ThumbKind {
+    /// `lsls Rd, Rm, #imm5`, also LSR and ASR
+    ShiftImm,
+    /// `adds Rd, Rn, Rm` and `subs Rd, Rn, Rm`
+    AddSubReg,
+    /// `adds Rd, Rn, #imm3` and `subs Rd, Rn, #imm3`
+    AddSubImm3,
+    /// MOV, CMP, ADD and SUB with an 8-bit immediate
+    Imm8,
+    /// two low register data-processing, `ands Rdn, Rm`
+    DataProcessing,
+    /// ADD, CMP and MOV for high registers
+    SpecialDataProcessing,
+    /// `bx Rm` and `blx Rm`
+    BxBlx,
+    /// `ldr Rt, [pc, #imm8]`
+    LdrLiteral,
+    /// LDR, STR and their byte, halfword and signed forms with a register offset
+    LoadStoreReg,
+    /// LDR, STR, LDRB and STRB with a 5-bit immediate offset
+    LoadStoreWordByteImm,
+    /// LDRH and STRH with a 5-bit immediate offset
+    LoadStoreHalfImm,
+    /// LDR and STR relative to sp
+    LoadStoreSp,
+    /// `adr Rd, label`
+    Adr,
+    /// `add Rd, sp, #imm8`
+    AddSpImm,
+    /// `add sp, #imm7` and `sub sp, #imm7`
+    AdjustSp,
+    /// `cbz Rn, label` and `cbnz Rn, label`
+    Cbz,
+    Push,
+    Pop,
+    /// If-Then, makes up to four following instructions conditional
+    It,
+    /// NOP, YIELD, WFE, WFI and SEV, both 16 and 32-bit
+    Hint,
+    /// `mrs Rd, apsr` and `mrs Rd, spsr`
+    Mrs,
+    /// `msr cpsr_fc, Rn` and `msr spsr_fc, Rn`
+    Msr,
+    /// CPSIE and CPSID, the 32-bit form also changes the mode
+    Cps,
+    /// `subs pc, lr, #imm8`
+    ExceptionReturn,
+    /// `setend le` and `setend be`
+    Setend,
+    /// `stmia Rn!, {...}`
+    Stm,
+    /// `ldmia Rn(!), {...}`
+    Ldm,
+    /// `b<cond> label`, both 16 and 32-bit
+    CondBranch,
+    Svc,
+    /// `b label`, both 16 and 32-bit
+    Branch,
+    /// LDM/STM in the IA and DB modes, including the 32-bit PUSH/POP
+    LoadStoreMultiple,
+    /// LDRD and STRD with an immediate offset
+    LoadStoreDual,
+    /// TBB and TBH
+    TableBranch,
+    /// LDREX, STREX and their byte, halfword and doubleword forms
+    Exclusive,
+    Clrex,
+    /// DMB, DSB and ISB
+    Barrier,
+    /// data-processing with a register operand shifted by an immediate
+    DataProcessingShiftedReg,
+    /// data-processing with a Thumb modified immediate
+    DataProcessingModImm,
+    /// ADDW, SUBW and ADR with a plain 12-bit immediate
+    DataProcessingPlainImm,
+    Bl,
+    /// `blx label`, switches to ARM
+    BlxImm,
+    /// single register loads and stores of any width, including PLD
+    LoadStoreSingle,
+    /// `lsl.w Rd, Rn, Rm`, also LSR, ASR and ROR
+    ShiftReg,
+    /// MUL, MLA, MLS and the signed halfword and most significant word multiplies
+    Multiply,
+    /// the 64-bit multiplies, SDIV and UDIV
+    LongMultiplyDivide,
+    /// SXTB, SXTH, UXTB and UXTH, the 32-bit forms also accumulate and extend both bytes
+    Extend,
+    /// REV, REV16 and REVSH
+    Reverse,
+    /// 32-bit REV, REV16, REVSH, RBIT, CLZ, SEL, QADD, QSUB, QDADD and QDSUB
+    MiscReg,
+    /// SADD16, UQSUB8 and the rest of the parallel additions and subtractions
+    ParallelAddSub,
+    /// CRC32 and CRC32C
+    Crc32,
+    /// the floating-point instructions, encoded like ARM with cond = 1110
+    Vfp,
+    /// Advanced SIMD, encoded like ARM with bit 28 moved to bit 24 for data-processing and
+    /// 11111001 instead of 11110100 for the element and structure loads and stores
+    Neon,
+    /// MRC, MCR, MRRC and MCRR, encoded like ARM with cond = 1110
+    Coprocessor,
+    /// UDF in both widths and HLT, raise SIGILL
+    Undefined,
+    /// `bkpt #imm8`, raises SIGTRAP even when its IT condition fails
+    Bkpt,
+    Unknown,
+}
+
+struct ThumbRule {
+    kind: ThumbKind,
+    mask: u32,
+    value: u32,
+}
+
+// Like `arm_rule!`, but producing a `ThumbKind`.
+macro_rules! thumb_rule {
+    ($kind:ident { $($field:ident($($args:tt)*)),* $(,)? }) => {
+        ThumbRule {
+            kind: ThumbKind::$kind,
+            mask: 0 $(| arm_mask!($field($($args)*)))*,
+            value: 0 $(| arm_value!($field($($args)*)))*,
+        }
+    };
+}
+
+const NARROW_RULES: &[ThumbRule] = &[
+    thumb_rule!(AddSubReg {
+        bits(15..10 = 0b000110)
+    }),
+    thumb_rule!(AddSubImm3 {
+        bits(15..10 = 0b000111)
+    }),
+    thumb_rule!(ShiftImm {
+        bits(15..13 = 0b000)
+    }),
+    thumb_rule!(Imm8 {
+        bits(15..13 = 0b001)
+    }),
+    thumb_rule!(DataProcessing {
+        bits(15..10 = 0b010000)
+    }),
+    thumb_rule!(BxBlx {
+        bits(15..8 = 0b01000111)
+    }),
+    thumb_rule!(SpecialDataProcessing {
+        bits(15..10 = 0b010001)
+    }),
+    thumb_rule!(LdrLiteral {
+        bits(15..11 = 0b01001)
+    }),
+    thumb_rule!(LoadStoreReg {
+        bits(15..12 = 0b0101)
+    }),
+    thumb_rule!(LoadStoreWordByteImm {
+        bits(15..13 = 0b011)
+    }),
+    thumb_rule!(LoadStoreHalfImm {
+        bits(15..12 = 0b1000)
+    }),
+    thumb_rule!(LoadStoreSp {
+        bits(15..12 = 0b1001)
+    }),
+    thumb_rule!(Adr {
+        bits(15..11 = 0b10100)
+    }),
+    thumb_rule!(AddSpImm {
+        bits(15..11 = 0b10101)
+    }),
+    // miscellaneous 16-bit instructions, bits 15..12 = 1011
+    thumb_rule!(AdjustSp {
+        bits(15..8 = 0b10110000)
+    }),
+    thumb_rule!(Cbz {
+        bits(15..12 = 0b1011),
+        bit(10 = 0),
+        bit(8 = 1),
+    }),
+    thumb_rule!(Push {
+        bits(15..9 = 0b1011010)
+    }),
+    thumb_rule!(Pop {
+        bits(15..9 = 0b1011110)
+    }),
+    thumb_rule!(Hint {
+        bits(15..8 = 0b10111111),
+        bits(3..0 = 0),
+    }),
+    thumb_rule!(It {
+        bits(15..8 = 0b10111111)
+    }),
+    thumb_rule!(Extend {
+        bits(15..8 = 0b10110010)
+    }),
+    // HLT in ARMv8, undefined before and from user mode
+    thumb_rule!(Undefined {
+        bits(15..6 = 0b1011101010)
+    }),
+    thumb_rule!(Reverse {
+        bits(15..8 = 0b10111010)
+    }),
+    thumb_rule!(Bkpt {
+        bits(15..8 = 0b10111110)
+    }),
+    thumb_rule!(Cps {
+        bits(15..5 = 0b10110110011)
+    }),
+    thumb_rule!(Setend {
+        bits(15..4 = 0xb65),
+        bits(2..0 = 0b000),
+    }),
+    // the unallocated rest of the miscellaneous instructions
+    thumb_rule!(Unknown {
+        bits(15..12 = 0b1011)
+    }),
+    thumb_rule!(Stm {
+        bits(15..11 = 0b11000)
+    }),
+    thumb_rule!(Ldm {
+        bits(15..11 = 0b11001)
+    }),
+    thumb_rule!(Undefined {
+        bits(15..8 = 0b11011110)
+    }),
+    thumb_rule!(Svc {
+        bits(15..8 = 0b11011111)
+    }),
+    thumb_rule!(CondBranch {
+        bits(15..12 = 0b1101)
+    }),
+    thumb_rule!(Branch {
+        bits(15..11 = 0b11100)
+    }),
+];
+
+const WIDE_RULES: &[ThumbRule] = &[
+    thumb_rule!(TableBranch {
+        bits(31..20 = 0xe8d),
+        bits(15..5 = 0b11110000000),
+    }),
+    // the exclusives share the dual space with P = W = 0
+    thumb_rule!(Exclusive {
+        bits(31..25 = 0b1110100),
+        bit(24 = 0),
+        bit(22 = 1),
+        bit(21 = 0),
+    }),
+    thumb_rule!(LoadStoreDual {
+        bits(31..25 = 0b1110100),
+        bit(22 = 1),
+    }),
+    thumb_rule!(LoadStoreMultiple {
+        bits(31..25 = 0b1110100),
+        bit(22 = 0),
+    }),
+    thumb_rule!(DataProcessingShiftedReg {
+        bits(31..25 = 0b1110101)
+    }),
+    // branches and miscellaneous control, bit 15 of the second halfword set
+    thumb_rule!(Bl {
+        bits(31..27 = 0b11110),
+        bit(15 = 1),
+        bit(14 = 1),
+        bit(12 = 1),
+    }),
+    thumb_rule!(BlxImm {
+        bits(31..27 = 0b11110),
+        bit(15 = 1),
+        bit(14 = 1),
+        bit(12 = 0),
+    }),
+    thumb_rule!(Branch {
+        bits(31..27 = 0b11110),
+        bit(15 = 1),
+        bit(14 = 0),
+        bit(12 = 1),
+    }),
+    thumb_rule!(Hint {
+        bits(31..16 = 0xf3af),
+        bits(15..14 = 0b10),
+        bit(12 = 0),
+        bits(10..8 = 0b000),
+    }),
+    thumb_rule!(Cps {
+        bits(31..16 = 0xf3af),
+        bits(15..14 = 0b10),
+        bit(12 = 0),
+    }),
+    thumb_rule!(Clrex {
+        bits(31..4 = 0xf3bf8f2),
+    }),
+    thumb_rule!(Barrier {
+        bits(31..4 = 0xf3bf8f4),
+    }),
+    thumb_rule!(Barrier {
+        bits(31..4 = 0xf3bf8f5),
+    }),
+    thumb_rule!(Barrier {
+        bits(31..4 = 0xf3bf8f6),
+    }),
+    thumb_rule!(Undefined {
+        bits(31..20 = 0xf7f),
+        bits(15..12 = 0b1010),
+    }),
+    thumb_rule!(Msr {
+        bits(31..21 = 0b11110011100),
+        bits(15..14 = 0b10),
+        bit(12 = 0),
+    }),
+    thumb_rule!(Mrs {
+        bits(31..21 = 0b11110011111),
+        bits(15..14 = 0b10),
+        bit(12 = 0),
+    }),
+    thumb_rule!(ExceptionReturn {
+        bits(31..16 = 0xf3de),
+        bits(15..8 = 0x8f),
+    }),
+    // BXJ, ENTERX and the banked register MRS and MSR, not modeled yet
+    thumb_rule!(Unknown {
+        bits(31..27 = 0b11110),
+        bits(25..23 = 0b111),
+        bit(15 = 1),
+        bit(14 = 0),
+        bit(12 = 0),
+    }),
+    thumb_rule!(CondBranch {
+        bits(31..27 = 0b11110),
+        bit(15 = 1),
+        bit(14 = 0),
+        bit(12 = 0),
+    }),
+    thumb_rule!(DataProcessingModImm {
+        bits(31..27 = 0b11110),
+        bit(25 = 0),
+        bit(15 = 0),
+    }),
+    thumb_rule!(DataProcessingPlainImm {
+        bits(31..27 = 0b11110),
+        bit(25 = 1),
+        bit(15 = 0),
+    }),
+    // the structure loads and stores sit in the store single space with bit 24 set
+    thumb_rule!(Neon {
+        bits(31..24 = 0b11111001),
+        bit(20 = 0),
+    }),
+    thumb_rule!(Neon {
+        bits(31..29 = 0b111),
+        bits(27..24 = 0b1111),
+    }),
+    thumb_rule!(LoadStoreSingle {
+        bits(31..25 = 0b1111100)
+    }),
+    thumb_rule!(ShiftReg {
+        bits(31..23 = 0b111110100),
+        bits(15..12 = 0b1111),
+        bits(7..4 = 0b0000),
+    }),
+    thumb_rule!(Extend {
+        bits(31..23 = 0b111110100),
+        bits(15..12 = 0b1111),
+        bit(7 = 1),
+    }),
+    thumb_rule!(ParallelAddSub {
+        bits(31..23 = 0b111110101),
+        bits(15..12 = 0b1111),
+        bit(7 = 0),
+    }),
+    // CRC32 with bit 20 clear and CRC32C with it set, the size in bits 5..4
+    thumb_rule!(Crc32 {
+        bits(31..21 = 0b11111010110),
+        bits(15..12 = 0b1111),
+        bits(7..6 = 0b10),
+    }),
+    thumb_rule!(MiscReg {
+        bits(31..22 = 0b1111101010),
+        bits(15..12 = 0b1111),
+        bits(7..6 = 0b10),
+    }),
+    thumb_rule!(Multiply {
+        bits(31..23 = 0b111110110),
+        bits(7..6 = 0b00),
+    }),
+    thumb_rule!(LongMultiplyDivide {
+        bits(31..23 = 0b111110111)
+    }),
+    // coprocessors 10 and 11, the 0xef space with bits 11..9 = 101 is Advanced SIMD
+    thumb_rule!(Vfp {
+        bits(31..25 = 0b1110110),
+        bits(11..9 = 0b101),
+    }),
+    thumb_rule!(Vfp {
+        bits(31..24 = 0b11101110),
+        bits(11..9 = 0b101),
+    }),
+    thumb_rule!(Coprocessor {
+        bits(31..24 = 0b11101110),
+        bit(4 = 1),
+    }),
+    thumb_rule!(Coprocessor {
+        bits(31..21 = 0b11101100010),
+    }),
+    // LDC, STC and CDP to the other coprocessors
+    thumb_rule!(Undefined {
+        bits(31..25 = 0b1110110),
+    }),
+    thumb_rule!(Undefined {
+        bits(31..24 = 0b11101110),
+    }),
+];
+
+/// Whether `hw1` is the first halfword of a 32-bit Thumb-2 instruction, bits 15..11 are 0b11101,
+/// 0b11110 or 0b11111.
+#[inline(always)]
+pub fn is_wide(hw1: u16) -> bool {
+    hw1 >> 11 >= 0b11101
+}
+
+/// Classify a 16-bit Thumb instruction or a 32-bit Thumb-2 instruction passed as `hw1 << 16 |
+/// hw2`.
+pub fn decode(raw: u32, wide: bool) -> Decoded {
+    let rules = if wide { WIDE_RULES } else { NARROW_RULES };
+    let kind = rules
+        .iter()
+        .find(|rule| (raw & rule.mask) == rule.value)
+        .map(|rule| rule.kind)
+        .unwrap_or(ThumbKind::Unknown);
+
+    Decoded { kind, raw, wide }
+}
+
+/// The 16-bit immediate of the 32-bit MOVW and MOVT, `imm4:i:imm3:imm8`.
+#[inline(always)]
+pub fn imm16(raw: u32) -> u32 {
+    (super::bits(raw, 19, 16) << 12) | imm12(raw)
+}
+
+/// Thumb-2 modified immediate, `i:imm3:imm8` gathered from both halfwords of a 32-bit
+/// data-processing instruction.
+#[inline(always)]
+pub fn imm12(raw: u32) -> u32 {
+    (super::bits(raw, 26, 26) << 11) | (super::bits(raw, 14, 12) << 8) | super::bits(raw, 7, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ThumbKind, decode, imm16, is_wide};
+
+    fn narrow(hw: u16) -> ThumbKind {
+        assert!(!is_wide(hw));
+        decode(hw as u32, false).kind
+    }
+
+    fn wide(hw1: u16, hw2: u16) -> ThumbKind {
+        assert!(is_wide(hw1));
+        decode(((hw1 as u32) << 16) | hw2 as u32, true).kind
+    }
+
+    #[test]
+    fn detects_32_bit_encodings() {
+        assert!(!is_wide(0x4770)); // bx lr
+        assert!(!is_wide(0xe7fe)); // b .
+        assert!(is_wide(0xe92d)); // push.w
+        assert!(is_wide(0xf000)); // bl
+        assert!(is_wide(0xf8d0)); // ldr.w
+    }
+
+    #[test]
+    fn classifies_narrow_data_processing() {
+        assert_eq!(narrow(0x0088), ThumbKind::ShiftImm); // lsls r0, r1, #2
+        assert_eq!(narrow(0x1888), ThumbKind::AddSubReg); // adds r0, r1, r2
+        assert_eq!(narrow(0x1e48), ThumbKind::AddSubImm3); // subs r0, r1, #1
+        assert_eq!(narrow(0x202a), ThumbKind::Imm8); // movs r0, #42
+        assert_eq!(narrow(0x4008), ThumbKind::DataProcessing); // ands r0, r1
+        assert_eq!(narrow(0x4468), ThumbKind::SpecialDataProcessing); // add r0, sp
+        assert_eq!(narrow(0x4608), ThumbKind::SpecialDataProcessing); // mov r0, r1
+    }
+
+    #[test]
+    fn classifies_narrow_branches() {
+        assert_eq!(narrow(0x4770), ThumbKind::BxBlx); // bx lr
+        assert_eq!(narrow(0x4798), ThumbKind::BxBlx); // blx r3
+        assert_eq!(narrow(0xd1fe), ThumbKind::CondBranch); // bne .
+        assert_eq!(narrow(0xe7fe), ThumbKind::Branch); // b .
+        assert_eq!(narrow(0xb108), ThumbKind::Cbz); // cbz r0, +2
+        assert_eq!(narrow(0xb908), ThumbKind::Cbz); // cbnz r0, +2
+        assert_eq!(narrow(0xdf00), ThumbKind::Svc); // svc #0
+        assert_eq!(narrow(0xdeff), ThumbKind::Undefined); // udf #255
+    }
+
+    #[test]
+    fn classifies_narrow_load_store() {
+        assert_eq!(narrow(0x4801), ThumbKind::LdrLiteral); // ldr r0, [pc, #4]
+        assert_eq!(narrow(0x5888), ThumbKind::LoadStoreReg); // ldr r0, [r1, r2]
+        assert_eq!(narrow(0x6848), ThumbKind::LoadStoreWordByteImm); // ldr r0, [r1, #4]
+        assert_eq!(narrow(0x7848), ThumbKind::LoadStoreWordByteImm); // ldrb r0, [r1, #1]
+        assert_eq!(narrow(0x8848), ThumbKind::LoadStoreHalfImm); // ldrh r0, [r1, #2]
+        assert_eq!(narrow(0x9001), ThumbKind::LoadStoreSp); // str r0, [sp, #4]
+        assert_eq!(narrow(0xb510), ThumbKind::Push); // push {r4, lr}
+        assert_eq!(narrow(0xbd10), ThumbKind::Pop); // pop {r4, pc}
+        assert_eq!(narrow(0xc806), ThumbKind::Ldm); // ldmia r0!, {r1, r2}
+        assert_eq!(narrow(0xc006), ThumbKind::Stm); // stmia r0!, {r1, r2}
+    }
+
+    #[test]
+    fn classifies_narrow_miscellaneous() {
+        assert_eq!(narrow(0xa001), ThumbKind::Adr); // adr r0, +4
+        assert_eq!(narrow(0xa801), ThumbKind::AddSpImm); // add r0, sp, #4
+        assert_eq!(narrow(0xb082), ThumbKind::AdjustSp); // sub sp, #8
+        assert_eq!(narrow(0xbf18), ThumbKind::It); // it ne
+        assert_eq!(narrow(0xbf00), ThumbKind::Hint); // nop
+        assert_eq!(narrow(0xb2c8), ThumbKind::Extend); // uxtb r0, r1
+        assert_eq!(narrow(0xb208), ThumbKind::Extend); // sxth r0, r1
+        assert_eq!(narrow(0xba08), ThumbKind::Reverse); // rev r0, r1
+        assert_eq!(narrow(0xbac8), ThumbKind::Reverse); // revsh r0, r1
+        assert_eq!(narrow(0xba80), ThumbKind::Undefined); // hlt #0
+        assert_eq!(narrow(0xbe00), ThumbKind::Bkpt); // bkpt #0
+        assert_eq!(narrow(0xb662), ThumbKind::Cps); // cpsie i
+        assert_eq!(narrow(0xb658), ThumbKind::Setend); // setend be
+        assert_eq!(narrow(0xb650), ThumbKind::Setend); // setend le
+        assert_eq!(narrow(0xb800), ThumbKind::Unknown); // unallocated
+    }
+
+    #[test]
+    fn assembles_movw_immediate() {
+        // movw r0, #0xabcd
+        assert_eq!(imm16(0xf64a_30cd), 0xabcd);
+        // movt r0, #0x1234
+        assert_eq!(imm16(0xf2c1_2034), 0x1234);
+    }
+
+    #[test]
+    fn classifies_wide_instructions() {
+        assert_eq!(wide(0xe92d, 0x4ff0), ThumbKind::LoadStoreMultiple); // push.w {r4-r11, lr}
+        assert_eq!(wide(0xe9c0, 0x2300), ThumbKind::LoadStoreDual); // strd r2, r3, [r0]
+        assert_eq!(wide(0xe8d0, 0xf001), ThumbKind::TableBranch); // tbb [r0, r1]
+        assert_eq!(wide(0xe850, 0x1f00), ThumbKind::Exclusive); // ldrex r1, [r0]
+        assert_eq!(wide(0xe841, 0x0201), ThumbKind::Exclusive); // strex r2, r0, [r1, #4]
+        assert_eq!(wide(0xe8c1, 0x4672), ThumbKind::Exclusive); // strexd r2, r4, r6, [r1]
+        assert_eq!(wide(0xf3bf, 0x8f2f), ThumbKind::Clrex); // clrex
+        assert_eq!(wide(0xeb01, 0x0082), ThumbKind::DataProcessingShiftedReg); // add.w r0, r1, r2, lsl #2
+        assert_eq!(wide(0xf04f, 0x30ff), ThumbKind::DataProcessingModImm); // mov.w r0, #-1
+        assert_eq!(wide(0xf201, 0x1023), ThumbKind::DataProcessingPlainImm); // addw r0, r1, #291
+        assert_eq!(wide(0xf000, 0xf800), ThumbKind::Bl); // bl +0
+        assert_eq!(wide(0xf000, 0xe800), ThumbKind::BlxImm); // blx +0
+        assert_eq!(wide(0xf000, 0xb800), ThumbKind::Branch); // b.w +0
+        assert_eq!(wide(0xf040, 0x8000), ThumbKind::CondBranch); // bne.w +0
+        assert_eq!(wide(0xf3af, 0x8000), ThumbKind::Hint); // nop.w
+        assert_eq!(wide(0xf3bf, 0x8f5f), ThumbKind::Barrier); // dmb sy
+        assert_eq!(wide(0xf3bf, 0x8f6f), ThumbKind::Barrier); // isb sy
+        assert_eq!(wide(0xf3ef, 0x8000), ThumbKind::Mrs); // mrs r0, apsr
+        assert_eq!(wide(0xf381, 0x8900), ThumbKind::Msr); // msr cpsr_fc, r1
+        assert_eq!(wide(0xf3af, 0x8113), ThumbKind::Cps); // cps #19
+        assert_eq!(wide(0xf3af, 0x8003), ThumbKind::Hint); // wfi.w
+        assert_eq!(wide(0xf3de, 0x8f04), ThumbKind::ExceptionReturn); // subs pc, lr, #4
+        assert_eq!(wide(0xf3c0, 0x8f00), ThumbKind::Unknown); // bxj r0
+        assert_eq!(wide(0xf7f0, 0xa000), ThumbKind::Undefined); // udf.w #0
+        assert_eq!(wide(0xf8d1, 0x0004), ThumbKind::LoadStoreSingle); // ldr.w r0, [r1, #4]
+        assert_eq!(wide(0xf911, 0x0c01), ThumbKind::LoadStoreSingle); // ldrsb r0, [r1, #-1]
+        assert_eq!(wide(0xfa01, 0xf002), ThumbKind::ShiftReg); // lsl.w r0, r1, r2
+        assert_eq!(wide(0xfa5f, 0xf881), ThumbKind::Extend); // uxtb.w r8, r1
+        assert_eq!(wide(0xfa02, 0xf091), ThumbKind::Extend); // sxtah r0, r2, r1, ror #8
+        assert_eq!(wide(0xfa91, 0xf0a1), ThumbKind::MiscReg); // rbit r0, r1
+        assert_eq!(wide(0xfab1, 0xf081), ThumbKind::MiscReg); // clz r0, r1
+        assert_eq!(wide(0xfa81, 0xf082), ThumbKind::MiscReg); // qadd r0, r2, r1
+        assert_eq!(wide(0xfaa1, 0xf082), ThumbKind::MiscReg); // sel r0, r1, r2
+        assert_eq!(wide(0xfa91, 0xf002), ThumbKind::ParallelAddSub); // sadd16 r0, r1, r2
+        assert_eq!(wide(0xfac1, 0xf052), ThumbKind::ParallelAddSub); // uqsub8 r0, r1, r2
+        assert_eq!(wide(0xfac1, 0xf082), ThumbKind::Crc32); // crc32b r0, r1, r2
+        assert_eq!(wide(0xfad4, 0xf3a5), ThumbKind::Crc32); // crc32cw r3, r4, r5
+        assert_eq!(wide(0xf2c1, 0x2034), ThumbKind::DataProcessingPlainImm); // movt r0, #0x1234
+        assert_eq!(wide(0xfb09, 0xf80a), ThumbKind::Multiply); // mul r8, r9, r10
+        assert_eq!(wide(0xfb51, 0xf002), ThumbKind::Multiply); // smmul r0, r1, r2
+        assert_eq!(wide(0xfb71, 0xf002), ThumbKind::Multiply); // usad8 r0, r1, r2
+        assert_eq!(wide(0xfb82, 0x0103), ThumbKind::LongMultiplyDivide); // smull r0, r1, r2, r3
+        assert_eq!(wide(0xfb91, 0xf0f2), ThumbKind::LongMultiplyDivide); // sdiv r0, r1, r2
+        assert_eq!(wide(0xfbe2, 0x0163), ThumbKind::LongMultiplyDivide); // umaal r0, r1, r2, r3
+        assert_eq!(wide(0xee31, 0x0b02), ThumbKind::Vfp); // vadd.f64 d0, d1, d2
+        assert_eq!(wide(0xed2d, 0x8b10), ThumbKind::Vfp); // vpush {d8-d15}
+        assert_eq!(wide(0xec51, 0x0b10), ThumbKind::Vfp); // vmov r0, r1, d0
+        assert_eq!(wide(0xeef1, 0xfa10), ThumbKind::Vfp); // vmrs APSR_nzcv, fpscr
+        assert_eq!(wide(0xef02, 0x0844), ThumbKind::Neon); // vadd.i8 q0, q1, q2
+        assert_eq!(wide(0xffb1, 0x0903), ThumbKind::Neon); // vtbl.8 d0, {d1, d2}, d3
+        assert_eq!(wide(0xf921, 0x0a0d), ThumbKind::Neon); // vld1.8 {d0, d1}, [r1]!
+        assert_eq!(wide(0xeee0, 0x1b10), ThumbKind::Vfp); // vdup.8 q0, r1
+        assert_eq!(wide(0xf911, 0x0002), ThumbKind::LoadStoreSingle); // ldrsb r0, [r1, r2]
+        assert_eq!(wide(0xee1d, 0x0f70), ThumbKind::Coprocessor); // mrc p15, 0, r0, c13, c0, 3
+        assert_eq!(wide(0xec51, 0x0f1e), ThumbKind::Coprocessor); // mrrc p15, 1, r0, r1, c14
+        assert_eq!(wide(0xed90, 0x5e00), ThumbKind::Undefined); // ldc p14, c5, [r0]
+    }
+}
@@ -0,0 +1,240 @@
+//! UNPREDICTABLE ARM encodings, see the pseudocode of the instructions in ARMv7 ARM A8.8. With
+//! `--strict` executing one faults, otherwise it behaves like one of the CONSTRAINED
+//! UNPREDICTABLE options ARMv8 allows for it in AArch32 state, see [`Rule`].
+//!
+//! Only the rules of the encodings [`super::decode_word`] classifies are checked. The forms that
+//! can't be executed in any sensible way, an odd register pair or a mode that doesn't exist,
+//! fault whether or not `--strict` is set.
+//!
+//! A Thumb instruction is checked as the ARM instruction it executes as, the rules only Thumb
+//! has, like sp as an operand, aren't. Neither are LDRD, STRD and the doubleword exclusives, the
+//! ARM rules assume Rt2 = Rt + 1. Without `--strict` a Thumb instruction breaking a rule is
+//! executed, not skipped like a [`Rule::is_nop`] one in ARM state.
+
+use std::fmt;
+
+use super::{Decoded, InstructionKind, Operands};
+
+/// The constraint an UNPREDICTABLE encoding violates, each documents what the instruction does
+/// without `--strict`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Rule {
+    /// pc as a register operand or the destination of an instruction that doesn't allow it,
+    /// executed as a NOP
+    Pc,
+    /// a load or store writing back to pc as its base, executed as a NOP
+    WritebackToPc,
+    /// a load or store writing back to a base it also transfers. A load keeps the loaded value,
+    /// a store stores the base before the writeback.
+    WritebackToTransferred,
+    /// an LDM writing back to a base in its register list, the loaded value is kept
+    WritebackToLoadedBase,
+    /// a long multiply with RdHi = RdLo, the register receives the high word
+    SameDestinations,
+    /// a store exclusive returning its status in its base or a stored register, the status is
+    /// written after the store
+    StatusOverlaps,
+}
+
+impl Rule {
+    /// Whether the instruction is executed as a NOP without `--strict`
+    pub fn is_nop(self) -> bool {
+        matches!(self, Rule::Pc | Rule::WritebackToPc)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Rule::Pc => "uses pc as a register operand or destination",
+            Rule::WritebackToPc => "writes back to pc as its base register",
+            Rule::WritebackToTransferred => "writes back to a base register it also transfers",
+            Rule::WritebackToLoadedBase => "writes back to a base register in its register list",
+            Rule::SameDestinations => "writes both halves of its result to the same register",
+            Rule::StatusOverlaps => "returns its status in its base or a stored register",
+        })
+    }
+}
+
+/// [`Rule::Pc`] if any of `registers` is pc
+fn pc(registers: &[usize]) -> Option<Rule> {
+    registers.contains(&15).then_some(Rule::Pc)
+}
+
+/// The first rule the decoded ARM instruction violates
+pub fn check(decoded: &Decoded) -> Option<Rule> {
+    let Operands {
+        rd,
+        rn,
+        rm,
+        rs,
+        ra,
+        rt,
+        list,
+        op,
+        p,
+        b,
+        w,
+        l,
+        ..
+    } = decoded.operands;
+    let wback = !p || w;
+
+    match decoded.kind {
+        InstructionKind::LoadStoreImm | InstructionKind::LoadStoreReg => {
+            let register = decoded.kind == InstructionKind::LoadStoreReg;
+            // LDRB and STRB can't transfer pc
+            if (register && rm == 15) || (b && rt == 15) {
+                Some(Rule::Pc)
+            } else if wback && rn == 15 {
+                Some(Rule::WritebackToPc)
+            } else if wback && rn == rt {
+                Some(Rule::WritebackToTransferred)
+            } else {
+                None
+            }
+        }
+        InstructionKind::ExtraLoadStoreImm | InstructionKind::ExtraLoadStoreReg => {
+            let register = decoded.kind == InstructionKind::ExtraLoadStoreReg;
+            let dual = !l && op != 0b01;
+            // LDRD and STRD transfer Rt and Rt+1, so Rt can't be lr either
+            let last = if dual { rt + 1 } else { rt };
+            if (register && rm == 15) || last == 15 {
+                Some(Rule::Pc)
+            } else if wback && rn == 15 {
+                Some(Rule::WritebackToPc)
+            } else if wback && (rt..=last).contains(&rn) {
+                Some(Rule::WritebackToTransferred)
+            } else {
+                None
+            }
+        }
+        InstructionKind::BlockTransfer => {
+            if rn == 15 {
+                Some(Rule::Pc)
+            } else if l && w && list & (1 << rn) != 0 {
+                Some(Rule::WritebackToLoadedBase)
+            } else {
+                None
+            }
+        }
+        // stores return their status in Rd
+        InstructionKind::Exclusive if l => pc(&[rn, rt]),
+        InstructionKind::Exclusive => {
+            let last = if op == 0b01 { rt + 1 } else { rt };
+            pc(&[rn, rd, rt])
+                .or_else(|| (rd == rn || (rt..=last).contains(&rd)).then_some(Rule::StatusOverlaps))
+        }
+        InstructionKind::DataProcessingRegShift => pc(&[rn, rd, rs, rm]),
+        InstructionKind::BlxReg | InstructionKind::Msr => pc(&[rm]),
+        InstructionKind::Mrs | InstructionKind::MovImm16 => pc(&[rd]),
+        InstructionKind::Clz | InstructionKind::Extend | InstructionKind::Reverse => pc(&[rd, rm]),
+        // BFC is BFI with Rn = pc
+        InstructionKind::Bitfield if op == 0b10 => pc(&[rd]),
+        InstructionKind::Saturate | InstructionKind::Bitfield => pc(&[rd, rn]),
+        InstructionKind::ParallelAddSub
+        | InstructionKind::Select
+        | InstructionKind::SaturatingAddSub
+        | InstructionKind::Crc32 => pc(&[rn, rd, rm]),
+        // Ra = pc selects USAD8, SMUAD, SMUSD and SMMUL
+        InstructionKind::SumAbsDiff | InstructionKind::Divide => pc(&[rd, rm, rn]),
+        InstructionKind::Multiply => match op {
+            0b000 => pc(&[rd, rm, rn]),
+            0b001 | 0b011 => pc(&[rd, ra, rm, rn]),
+            _ => long_multiply(&decoded.operands),
+        },
+        // op is bits 22..21 followed by the top half selectors of Rm and Rn
+        InstructionKind::HalfwordMultiply => match op >> 2 {
+            0b01 if op & 1 == 1 => pc(&[rd, rm, rn]),
+            0b00 | 0b01 => pc(&[rd, ra, rm, rn]),
+            0b10 => long_multiply(&decoded.operands),
+            _ => pc(&[rd, rm, rn]),
+        },
+        // op is bits 22..20 followed by bits 7..5
+        InstructionKind::MediaMultiply => match op >> 3 {
+            0b100 => long_multiply(&decoded.operands),
+            // SMMLS has no form without Ra
+            0b101 if (op >> 1) & 0b11 == 0b11 => pc(&[rd, ra, rm, rn]),
+            _ => pc(&[rd, rm, rn]),
+        },
+        _ => None,
+    }
+}
+
+/// The 64-bit multiplies with RdHi in Rd and RdLo in Ra
+fn long_multiply(operands: &Operands) -> Option<Rule> {
+    pc(&[operands.rd, operands.ra, operands.rm, operands.rn])
+        .or_else(|| (operands.rd == operands.ra).then_some(Rule::SameDestinations))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Rule, check};
+    use crate::cpu::decoder::decode_word;
+
+    fn rule(word: u32) -> Option<Rule> {
+        check(&decode_word(word))
+    }
+
+    #[test]
+    fn load_store_writeback_must_not_touch_the_transferred_register() {
+        // ldr r1, [r1, #4]! and ldr r1, [r1], #4
+        assert_eq!(rule(0xe5b1_1004), Some(Rule::WritebackToTransferred));
+        assert_eq!(rule(0xe491_1004), Some(Rule::WritebackToTransferred));
+        // ldr r1, [r1, #4] doesn't write back
+        assert_eq!(rule(0xe591_1004), None);
+        // ldrd r2, r3, [r3], #8 and strd r2, r3, [r2, #8]!
+        assert_eq!(rule(0xe0c3_20d8), Some(Rule::WritebackToTransferred));
+        assert_eq!(rule(0xe1e2_20f8), Some(Rule::WritebackToTransferred));
+        // ldr r0, [pc, #4]! and ldrb pc, [r0]
+        assert_eq!(rule(0xe5bf_0004), Some(Rule::WritebackToPc));
+        assert_eq!(rule(0xe5d0_f000), Some(Rule::Pc));
+        // ldr r0, [r1, pc]
+        assert_eq!(rule(0xe791_000f), Some(Rule::Pc));
+        // ldrd lr, pc, [r0]
+        assert_eq!(rule(0xe1c0_e0d0), Some(Rule::Pc));
+    }
+
+    #[test]
+    fn block_transfers_must_not_load_a_written_back_base() {
+        // ldm r1!, {r0, r1}
+        assert_eq!(rule(0xe8b1_0003), Some(Rule::WritebackToLoadedBase));
+        // ldm r1, {r0, r1} and stm r1!, {r0, r1}
+        assert_eq!(rule(0xe891_0003), None);
+        assert_eq!(rule(0xe8a1_0003), None);
+        // pop {r4, pc}
+        assert_eq!(rule(0xe8bd_8010), None);
+    }
+
+    #[test]
+    fn media_and_multiplies_reject_pc() {
+        // clz pc, r1 and uxtb r0, pc
+        assert_eq!(rule(0xe16f_ff11), Some(Rule::Pc));
+        assert_eq!(rule(0xe6ef_007f), Some(Rule::Pc));
+        // uxtb r0, r1 is UXTAB with Rn = pc, bfc r0, #4, #8 is BFI with Rn = pc
+        assert_eq!(rule(0xe6ef_0071), None);
+        assert_eq!(rule(0xe7cb_021f), None);
+        // sadd16 pc, r1, r2
+        assert_eq!(rule(0xe611_ff12), Some(Rule::Pc));
+        // mul r0, r1, r2 and smuad r0, r1, r2 have no Ra
+        assert_eq!(rule(0xe000_0291), None);
+        assert_eq!(rule(0xe700_f211), None);
+        // mla r0, r1, r2, pc
+        assert_eq!(rule(0xe020_f291), Some(Rule::Pc));
+        // umull r0, r0, r1, r2
+        assert_eq!(rule(0xe080_0291), Some(Rule::SameDestinations));
+        // add r0, r1, r2, lsl pc
+        assert_eq!(rule(0xe081_0f12), Some(Rule::Pc));
+    }
+
+    #[test]
+    fn store_exclusive_status_must_be_a_separate_register() {
+        // strex r1, r1, [r0] and strex r0, r1, [r0]
+        assert_eq!(rule(0xe180_1f91), Some(Rule::StatusOverlaps));
+        assert_eq!(rule(0xe180_0f91), Some(Rule::StatusOverlaps));
+        // strexd r3, r2, r3, [r0]
+        assert_eq!(rule(0xe1a0_3f92), Some(Rule::StatusOverlaps));
+        // strex r2, r1, [r0]
+        assert_eq!(rule(0xe180_2f91), None);
+    }
+}
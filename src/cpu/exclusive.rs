@@ -0,0 +1,160 @@
+//! Local exclusive monitor backing LDREX/STREX, see ARMv7 ARM A3.4 "Synchronization and
+//! semaphores". There is a single guest thread, so there is no global monitor: a store exclusive
+//! succeeds if the last load exclusive tagged the same address and size and nothing cleared the
+//! tag in between. CLREX, a store exclusive, a plain store to the tagged bytes and a context
+//! switch (every SVC) clear it.
+
+use crate::{
+    cpu::{
+        Cpu,
+        decoder::{Decoded, Operands},
+    },
+    err,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Monitor {
+    /// address and size of the last load exclusive, `None` in the Open Access state
+    tagged: Option<(u32, u32)>,
+}
+
+impl Monitor {
+    /// enter the Exclusive Access state for the `size` bytes at `addr`
+    pub fn mark(&mut self, addr: u32, size: u32) {
+        self.tagged = Some((addr, size));
+    }
+
+    pub fn clear(&mut self) {
+        self.tagged = None;
+    }
+
+    /// whether the monitor is in the Open Access state, so plain stores can't clear a tag
+    pub fn is_open(&self) -> bool {
+        self.tagged.is_none()
+    }
+
+    /// whether a store exclusive of `size` bytes to `addr` passes, leaving the monitor open either
+    /// way
+    pub fn check_and_clear(&mut self, addr: u32, size: u32) -> bool {
+        self.tagged.take() == Some((addr, size))
+    }
+
+    /// a plain store of `size` bytes to `addr` clears a tag it overlaps
+    pub fn store(&mut self, addr: u32, size: u32) {
+        if let Some((tagged, tagged_size)) = self.tagged {
+            let end = addr as u64 + size as u64;
+            let tagged_end = tagged as u64 + tagged_size as u64;
+            if (addr as u64) < tagged_end && (tagged as u64) < end {
+                self.tagged = None;
+            }
+        }
+    }
+}
+
+impl<'cpu, const PRINT_INSTR: bool> Cpu<'cpu, PRINT_INSTR> {
+    /// Execute a load or store exclusive at Rn + `offset`: bits 22..21 select a word (00),
+    /// doubleword (01), byte (10) or halfword (11) and L the load. A store reports its status in
+    /// Rd. `rt2` is the upper word of the doubleword forms.
+    ///
+    /// The access has to be aligned to its size. A store writes 0 to Rd if it passed the monitor
+    /// and 1 without touching memory otherwise.
+    pub(super) fn exclusive(
+        &mut self,
+        decoded: &Decoded,
+        offset: u32,
+        rt2: usize,
+    ) -> Result<(), err::Err> {
+        let Operands {
+            op, l, rn, rd, rt, ..
+        } = decoded.operands;
+        let size = match op {
+            0b00 => 4,
+            0b01 => 8,
+            0b10 => 1,
+            _ => 2,
+        };
+        let addr = self.r[rn].wrapping_add(offset);
+        let fault = err::Err::MemoryAccessViolation {
+            guest: addr,
+            instr: decoded.raw,
+        };
+        if !addr.is_multiple_of(size) {
+            return Err(fault);
+        }
+
+        if l {
+            let value = match size {
+                1 => self.mem.read_u8(addr).map(u64::from),
+                2 => self.mem.read_u16(addr).map(u64::from),
+                4 => self.mem.read_u32(addr).map(u64::from),
+                _ => self.mem.read_u64(addr),
+            }
+            .ok_or(fault)?;
+            self.r[rt] = value as u32;
+            if size == 8 {
+                self.r[rt2] = (value >> 32) as u32;
+            }
+            self.monitor.mark(addr, size);
+            return Ok(());
+        }
+
+        if !self.monitor.check_and_clear(addr, size) {
+            self.r[rd] = 1;
+            return Ok(());
+        }
+
+        let value = self.r[rt];
+        match size {
+            1 => self.mem.write_u8(addr, value as u8),
+            2 => self.mem.write_u16(addr, value as u16),
+            4 => self.mem.write_u32(addr, value),
+            _ => self
+                .mem
+                .write_u64(addr, ((self.r[rt2] as u64) << 32) | value as u64),
+        }
+        .map_err(|_| fault)?;
+        self.r[rd] = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Monitor;
+
+    #[test]
+    fn store_exclusive_needs_matching_tag() {
+        let mut monitor = Monitor::default();
+        assert!(!monitor.check_and_clear(0x1000, 4));
+
+        monitor.mark(0x1000, 4);
+        assert!(!monitor.check_and_clear(0x1004, 4));
+        // the failed attempt already cleared the tag
+        assert!(!monitor.check_and_clear(0x1000, 4));
+
+        monitor.mark(0x1000, 4);
+        assert!(!monitor.check_and_clear(0x1000, 1));
+
+        monitor.mark(0x1000, 4);
+        assert!(monitor.check_and_clear(0x1000, 4));
+        assert!(!monitor.check_and_clear(0x1000, 4));
+    }
+
+    #[test]
+    fn overlapping_stores_and_clrex_clear() {
+        let mut monitor = Monitor::default();
+
+        monitor.mark(0x1000, 8);
+        monitor.store(0x0ffc, 4);
+        monitor.store(0x1008, 4);
+        assert!(monitor.check_and_clear(0x1000, 8));
+
+        monitor.mark(0x1000, 8);
+        monitor.store(0x1007, 1);
+        assert!(!monitor.check_and_clear(0x1000, 8));
+
+        monitor.mark(0x1000, 4);
+        monitor.clear();
+        assert!(!monitor.check_and_clear(0x1000, 4));
+    }
+}
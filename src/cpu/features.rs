@@ -0,0 +1,104 @@
+use crate::config::CpuModel;
+
+// Not an enum, since extensions aren't mutually exclusive
+/// Optional architecture extensions of the emulated processor, gating which instructions decode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Features(u32);
+
+impl Features {
+    /// no optional extensions
+    pub const NONE: Features = Features(0);
+    /// SDIV and UDIV in both ARM and Thumb state
+    pub const IDIV: Features = Features(1 << 0);
+    /// VFPv3 with 32 double precision registers
+    pub const VFPV3: Features = Features(1 << 1);
+    /// Advanced SIMD, sharing the VFP register file
+    pub const NEON: Features = Features(1 << 2);
+    /// the ARMv8 AES, SHA-1, SHA-256 and 64-bit polynomial multiply instructions
+    pub const CRYPTO: Features = Features(1 << 3);
+    /// the ARMv8 CRC32 and CRC32C instructions
+    pub const CRC32: Features = Features(1 << 4);
+    /// VFPv2 with 16 double precision registers, [`Features::VFPV3`] extends it
+    pub const VFP: Features = Features(1 << 5);
+    /// the fused multiply accumulates VFMA, VFMS, VFNMA and VFNMS, in Advanced SIMD too
+    pub const VFPV4: Features = Features(1 << 6);
+    /// ARMv6T2: the 32-bit Thumb encodings, CBZ, IT and the ARM instructions introduced along
+    /// with them, MOVW, MOVT, the bitfield instructions, RBIT and MLS
+    pub const THUMB2: Features = Features(1 << 7);
+    /// the Large Physical Address Extension, which makes LDRD and STRD single-copy atomic
+    pub const LPAE: Features = Features(1 << 8);
+
+    /// the extensions of the processor `model`
+    pub fn of(model: CpuModel) -> Features {
+        let vfpv3 = Features::VFP | Features::VFPV3;
+        match model {
+            CpuModel::Arm1176 => Features::VFP,
+            CpuModel::CortexA9 => Features::THUMB2 | vfpv3 | Features::NEON,
+            CpuModel::CortexA7 | CpuModel::CortexA15 => {
+                Features::of(CpuModel::CortexA9) | Features::VFPV4 | Features::IDIV | Features::LPAE
+            }
+            CpuModel::CortexA53Aarch32 => {
+                Features::of(CpuModel::CortexA15) | Features::CRYPTO | Features::CRC32
+            }
+        }
+    }
+
+    /// whether every extension in `other` is present
+    pub fn contains(self, other: Features) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The AT_HWCAP bits Linux reports for these extensions, see arch/arm/include/uapi/asm/hwcap.h
+    pub fn hwcap(self) -> u32 {
+        // HALF, THUMB, FAST_MULT, EDSP and TLS, which every ARMv6K processor has
+        let mut hwcap = (1 << 1) | (1 << 2) | (1 << 4) | (1 << 7) | (1 << 15);
+        if self.contains(Features::VFP) {
+            hwcap |= 1 << 6;
+        }
+        if self.contains(Features::VFPV3) {
+            // VFPv3 and VFPD32
+            hwcap |= (1 << 13) | (1 << 19);
+        }
+        if self.contains(Features::VFPV4) {
+            hwcap |= 1 << 16;
+        }
+        if self.contains(Features::NEON) {
+            hwcap |= 1 << 12;
+        }
+        if self.contains(Features::IDIV) {
+            // IDIVA and IDIVT
+            hwcap |= (1 << 17) | (1 << 18);
+        }
+        if self.contains(Features::LPAE) {
+            hwcap |= 1 << 20;
+        }
+        hwcap
+    }
+
+    /// The AT_HWCAP2 bits, the ARMv8 extensions
+    pub fn hwcap2(self) -> u32 {
+        let mut hwcap2 = 0;
+        if self.contains(Features::CRYPTO) {
+            // AES, PMULL, SHA1 and SHA2
+            hwcap2 |= (1 << 0) | (1 << 1) | (1 << 2) | (1 << 3);
+        }
+        if self.contains(Features::CRC32) {
+            hwcap2 |= 1 << 4;
+        }
+        hwcap2
+    }
+}
+
+impl Default for Features {
+    /// the extensions of a Cortex-A15 class ARMv7-A processor
+    fn default() -> Self {
+        Features::of(CpuModel::CortexA15)
+    }
+}
+
+impl std::ops::BitOr for Features {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Features(self.0 | rhs.0)
+    }
+}
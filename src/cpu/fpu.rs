@@ -0,0 +1,1280 @@
+//! Software floating point for the VFP instructions, following the pseudocode of ARMv7 ARM A2.7
+//! "Floating-point data types and arithmetic". Every operation computes its exact result on
+//! integer significands and rounds once, so the result, the NaN propagation and the cumulative
+//! exception flags match real hardware instead of depending on the host FPU.
+//!
+//! Values of both widths are passed around as their bit patterns in a `u64`.
+
+use std::cmp::Ordering;
+
+/// Invalid Operation cumulative exception bit of the FPSCR
+pub const IOC: u32 = 1 << 0;
+/// Division by Zero cumulative exception bit of the FPSCR
+pub const DZC: u32 = 1 << 1;
+/// Overflow cumulative exception bit of the FPSCR
+pub const OFC: u32 = 1 << 2;
+/// Underflow cumulative exception bit of the FPSCR
+pub const UFC: u32 = 1 << 3;
+/// Inexact cumulative exception bit of the FPSCR
+pub const IXC: u32 = 1 << 4;
+/// Input Denormal cumulative exception bit of the FPSCR, set when FZ flushes an operand
+pub const IDC: u32 = 1 << 7;
+/// every cumulative exception bit of the FPSCR
+pub const CUMULATIVE: u32 = IOC | DZC | OFC | UFC | IXC | IDC;
+/// Flush-to-zero mode bit of the FPSCR
+pub const FZ: u32 = 1 << 24;
+/// Default NaN mode bit of the FPSCR
+pub const DN: u32 = 1 << 25;
+
+/// Rounding mode, the FPSCR RMode field in bits 23..22
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Nearest,
+    PlusInfinity,
+    MinusInfinity,
+    Zero,
+}
+
+impl Rounding {
+    pub fn from_fpscr(fpscr: u32) -> Self {
+        match (fpscr >> 22) & 0b11 {
+            0b00 => Self::Nearest,
+            0b01 => Self::PlusInfinity,
+            0b10 => Self::MinusInfinity,
+            _ => Self::Zero,
+        }
+    }
+}
+
+/// An IEEE 754 binary interchange format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Format {
+    frac_bits: u32,
+    exp_bits: u32,
+}
+
+pub const SINGLE: Format = Format {
+    frac_bits: 23,
+    exp_bits: 8,
+};
+
+pub const DOUBLE: Format = Format {
+    frac_bits: 52,
+    exp_bits: 11,
+};
+
+impl Format {
+    fn bias(self) -> i32 {
+        (1 << (self.exp_bits - 1)) - 1
+    }
+
+    /// unbiased exponent of the smallest normal number
+    fn emin(self) -> i32 {
+        1 - self.bias()
+    }
+
+    fn max_exp(self) -> u64 {
+        (1 << self.exp_bits) - 1
+    }
+
+    fn frac_mask(self) -> u64 {
+        (1 << self.frac_bits) - 1
+    }
+
+    fn sign_bit(self) -> u64 {
+        1 << (self.frac_bits + self.exp_bits)
+    }
+
+    fn quiet_bit(self) -> u64 {
+        1 << (self.frac_bits - 1)
+    }
+
+    fn sign(self, x: u64) -> bool {
+        x & self.sign_bit() != 0
+    }
+
+    fn exp(self, x: u64) -> u64 {
+        (x >> self.frac_bits) & self.max_exp()
+    }
+
+    fn frac(self, x: u64) -> u64 {
+        x & self.frac_mask()
+    }
+
+    pub fn is_nan(self, x: u64) -> bool {
+        self.exp(x) == self.max_exp() && self.frac(x) != 0
+    }
+
+    fn is_signaling(self, x: u64) -> bool {
+        self.is_nan(x) && x & self.quiet_bit() == 0
+    }
+
+    fn is_infinity(self, x: u64) -> bool {
+        self.exp(x) == self.max_exp() && self.frac(x) == 0
+    }
+
+    fn is_zero(self, x: u64) -> bool {
+        self.exp(x) == 0 && self.frac(x) == 0
+    }
+
+    fn is_subnormal(self, x: u64) -> bool {
+        self.exp(x) == 0 && self.frac(x) != 0
+    }
+
+    fn zero(self, sign: bool) -> u64 {
+        if sign { self.sign_bit() } else { 0 }
+    }
+
+    fn infinity(self, sign: bool) -> u64 {
+        self.zero(sign) | (self.max_exp() << self.frac_bits)
+    }
+
+    fn max_normal(self, sign: bool) -> u64 {
+        self.zero(sign) | ((self.max_exp() - 1) << self.frac_bits) | self.frac_mask()
+    }
+
+    /// positive quiet NaN with an all zero payload
+    pub fn default_nan(self) -> u64 {
+        self.infinity(false) | self.quiet_bit()
+    }
+
+    /// Split a finite nonzero value into its sign, integer significand and exponent, the value
+    /// being `significand * 2^exponent`.
+    fn unpack(self, x: u64) -> (bool, u128, i32) {
+        let frac = self.frac(x) as u128;
+        let exp = self.exp(x);
+        if exp == 0 {
+            (self.sign(x), frac, self.emin() - self.frac_bits as i32)
+        } else {
+            (
+                self.sign(x),
+                frac | (1 << self.frac_bits),
+                exp as i32 - self.bias() - self.frac_bits as i32,
+            )
+        }
+    }
+
+    /// Like [`Format::unpack`], with the leading one of the significand at bit `frac_bits`
+    fn unpack_normalized(self, x: u64) -> (bool, u128, i32) {
+        let (sign, m, e) = self.unpack(x);
+        let shift = m.leading_zeros() - (127 - self.frac_bits);
+        (sign, m << shift, e - shift as i32)
+    }
+}
+
+/// Shift `m` right by `n`, folding the bits shifted out into the least significant bit
+fn shift_right_jamming(m: u128, n: u32) -> u128 {
+    if n == 0 {
+        m
+    } else if n >= 128 {
+        (m != 0) as u128
+    } else {
+        (m >> n) | ((m & ((1 << n) - 1) != 0) as u128)
+    }
+}
+
+fn isqrt(value: u128) -> u128 {
+    let mut rem = value;
+    let mut root = 0u128;
+    let mut bit = 1u128 << ((127 - value.leading_zeros()) & !1);
+    while bit != 0 {
+        if rem >= root + bit {
+            rem -= root + bit;
+            root = (root >> 1) + bit;
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+    root
+}
+
+/// FPRound: round `(-1)^sign * m * 2^e` to `fmt`. Bits already lost have to be folded into the
+/// least significant bit of `m`, at least two places below the rounding position. Tininess is
+/// detected before rounding, FZ flushes tiny results to zero.
+fn round(fmt: Format, sign: bool, m: u128, e: i32, mode: Rounding, fpscr: &mut u32) -> u64 {
+    if m == 0 {
+        return fmt.zero(sign);
+    }
+
+    // floor(log2(|value|))
+    let exponent = (127 - m.leading_zeros() as i32) + e;
+    let tiny = exponent < fmt.emin();
+    if tiny && *fpscr & FZ != 0 {
+        *fpscr |= UFC;
+        return fmt.zero(sign);
+    }
+
+    // weight of the least significant bit we keep
+    let quantum = exponent.max(fmt.emin()) - fmt.frac_bits as i32;
+    let shift = quantum - e;
+    let (kept, rem, half) = if shift <= 0 {
+        (m << -shift, 0, Ordering::Less)
+    } else if shift >= 128 {
+        (0, m, Ordering::Less)
+    } else {
+        let rem = m & ((1 << shift) - 1);
+        (m >> shift, rem, rem.cmp(&(1 << (shift - 1))))
+    };
+
+    let round_up = match mode {
+        Rounding::Nearest => {
+            half == Ordering::Greater || (half == Ordering::Equal && kept & 1 == 1)
+        }
+        Rounding::PlusInfinity => rem != 0 && !sign,
+        Rounding::MinusInfinity => rem != 0 && sign,
+        Rounding::Zero => false,
+    };
+    let inexact = rem != 0;
+    if tiny && inexact {
+        *fpscr |= UFC;
+    }
+
+    let mut mant = kept + round_up as u128;
+    let mut quantum = quantum;
+    if mant == 1 << (fmt.frac_bits + 1) {
+        mant >>= 1;
+        quantum += 1;
+    }
+
+    let biased = if mant >> fmt.frac_bits == 0 {
+        0
+    } else {
+        (quantum + fmt.frac_bits as i32 + fmt.bias()) as u64
+    };
+    if biased >= fmt.max_exp() {
+        *fpscr |= OFC | IXC;
+        let to_infinity = match mode {
+            Rounding::Nearest => true,
+            Rounding::PlusInfinity => !sign,
+            Rounding::MinusInfinity => sign,
+            Rounding::Zero => false,
+        };
+        return if to_infinity {
+            fmt.infinity(sign)
+        } else {
+            fmt.max_normal(sign)
+        };
+    }
+    if inexact {
+        *fpscr |= IXC;
+    }
+
+    fmt.zero(sign) | (biased << fmt.frac_bits) | (mant as u64 & fmt.frac_mask())
+}
+
+/// Flush a subnormal operand to zero in FZ mode, raising Input Denormal
+fn flush(fmt: Format, x: u64, fpscr: &mut u32) -> u64 {
+    if *fpscr & FZ != 0 && fmt.is_subnormal(x) {
+        *fpscr |= IDC;
+        fmt.zero(fmt.sign(x))
+    } else {
+        x
+    }
+}
+
+/// FPProcessNaNs: the first signaling NaN, otherwise the first quiet NaN of `operands` is
+/// quietened and returned, or the default NaN in DN mode. Signaling NaNs raise Invalid Operation.
+fn process_nans(fmt: Format, operands: &[u64], fpscr: &mut u32) -> Option<u64> {
+    let nan = operands
+        .iter()
+        .find(|&&x| fmt.is_signaling(x))
+        .or_else(|| operands.iter().find(|&&x| fmt.is_nan(x)))?;
+    if fmt.is_signaling(*nan) {
+        *fpscr |= IOC;
+    }
+    Some(if *fpscr & DN != 0 {
+        fmt.default_nan()
+    } else {
+        nan | fmt.quiet_bit()
+    })
+}
+
+/// Invalid Operation producing the default NaN
+fn invalid(fmt: Format, fpscr: &mut u32) -> u64 {
+    *fpscr |= IOC;
+    fmt.default_nan()
+}
+
+/// FPAdd respectively FPSub for `subtract`
+pub fn add(fmt: Format, a: u64, b: u64, subtract: bool, fpscr: &mut u32) -> u64 {
+    add_scaled(fmt, a, b, subtract, 0, fpscr)
+}
+
+/// FPHalvedSub: (a - b) / 2 with a single rounding, for VRSQRTS
+pub fn halved_sub(fmt: Format, a: u64, b: u64, fpscr: &mut u32) -> u64 {
+    add_scaled(fmt, a, b, true, -1, fpscr)
+}
+
+/// the sum scaled by 2^`scale` before rounding
+fn add_scaled(fmt: Format, a: u64, b: u64, subtract: bool, scale: i32, fpscr: &mut u32) -> u64 {
+    let mode = Rounding::from_fpscr(*fpscr);
+    let a = flush(fmt, a, fpscr);
+    let b = flush(fmt, b, fpscr);
+    if let Some(nan) = process_nans(fmt, &[a, b], fpscr) {
+        return nan;
+    }
+    let b = if subtract { b ^ fmt.sign_bit() } else { b };
+    let (sign_a, sign_b) = (fmt.sign(a), fmt.sign(b));
+
+    match (fmt.is_infinity(a), fmt.is_infinity(b)) {
+        (true, true) if sign_a != sign_b => return invalid(fmt, fpscr),
+        (true, _) => return a,
+        (_, true) => return b,
+        _ => {}
+    }
+    match (fmt.is_zero(a), fmt.is_zero(b)) {
+        (true, true) if sign_a == sign_b => return a,
+        (true, true) => return fmt.zero(mode == Rounding::MinusInfinity),
+        (true, false) if scale == 0 => return b,
+        (false, true) if scale == 0 => return a,
+        _ => {}
+    }
+
+    // the operand with the larger exponent first
+    let (a, b) = (fmt.unpack(a), fmt.unpack(b));
+    let ((sign_a, ma, ea), (sign_b, mb, eb)) = if b.2 > a.2 { (b, a) } else { (a, b) };
+
+    // 64 guard bits below the larger operand, the smaller one is jammed into them
+    let e = ea - 64;
+    let ma = ma << 64;
+    let shift = eb - e;
+    let mb = if shift >= 0 {
+        mb << shift
+    } else {
+        shift_right_jamming(mb, (-shift) as u32)
+    };
+
+    let (sign, sum) = if sign_a == sign_b {
+        (sign_a, ma + mb)
+    } else if ma >= mb {
+        (sign_a, ma - mb)
+    } else {
+        (sign_b, mb - ma)
+    };
+    if sum == 0 {
+        return fmt.zero(mode == Rounding::MinusInfinity);
+    }
+    round(fmt, sign, sum, e + scale, mode, fpscr)
+}
+
+/// FPMul
+pub fn mul(fmt: Format, a: u64, b: u64, fpscr: &mut u32) -> u64 {
+    let a = flush(fmt, a, fpscr);
+    let b = flush(fmt, b, fpscr);
+    if let Some(nan) = process_nans(fmt, &[a, b], fpscr) {
+        return nan;
+    }
+    let sign = fmt.sign(a) != fmt.sign(b);
+
+    let (inf_a, inf_b) = (fmt.is_infinity(a), fmt.is_infinity(b));
+    let (zero_a, zero_b) = (fmt.is_zero(a), fmt.is_zero(b));
+    if (inf_a && zero_b) || (zero_a && inf_b) {
+        return invalid(fmt, fpscr);
+    }
+    if inf_a || inf_b {
+        return fmt.infinity(sign);
+    }
+    if zero_a || zero_b {
+        return fmt.zero(sign);
+    }
+
+    let (_, ma, ea) = fmt.unpack(a);
+    let (_, mb, eb) = fmt.unpack(b);
+    round(
+        fmt,
+        sign,
+        ma * mb,
+        ea + eb,
+        Rounding::from_fpscr(*fpscr),
+        fpscr,
+    )
+}
+
+/// FPMulAdd: `addend + a * b` with a single rounding, for the VFPv4 fused multiply accumulates
+pub fn mul_add(fmt: Format, addend: u64, a: u64, b: u64, fpscr: &mut u32) -> u64 {
+    let mode = Rounding::from_fpscr(*fpscr);
+    let addend = flush(fmt, addend, fpscr);
+    let a = flush(fmt, a, fpscr);
+    let b = flush(fmt, b, fpscr);
+    if let Some(nan) = process_nans(fmt, &[addend, a, b], fpscr) {
+        return nan;
+    }
+
+    let sign_c = fmt.sign(addend);
+    let sign_p = fmt.sign(a) != fmt.sign(b);
+    let inf_c = fmt.is_infinity(addend);
+    let inf_p = fmt.is_infinity(a) || fmt.is_infinity(b);
+    let zero_p = fmt.is_zero(a) || fmt.is_zero(b);
+    if (inf_p && zero_p) || (inf_c && inf_p && sign_c != sign_p) {
+        return invalid(fmt, fpscr);
+    }
+    if inf_c {
+        return addend;
+    }
+    if inf_p {
+        return fmt.infinity(sign_p);
+    }
+    match (fmt.is_zero(addend), zero_p) {
+        (true, true) if sign_c == sign_p => return addend,
+        (true, true) => return fmt.zero(mode == Rounding::MinusInfinity),
+        (false, true) => return addend,
+        _ => {}
+    }
+
+    // the exact product has at most 106 bits, the larger operand is moved to the top of the
+    // u128 with a spare bit for the carry and the smaller one jammed into the bits below it
+    let (_, ma, ea) = fmt.unpack(a);
+    let (_, mb, eb) = fmt.unpack(b);
+    let product = (sign_p, ma * mb, ea + eb);
+    if fmt.is_zero(addend) {
+        return round(fmt, product.0, product.1, product.2, mode, fpscr);
+    }
+    let top = |(_, m, e): (bool, u128, i32)| 127 - m.leading_zeros() as i32 + e;
+    let addend = fmt.unpack(addend);
+    let ((sign_x, mx, ex), (sign_y, my, ey)) = if top(addend) > top(product) {
+        (addend, product)
+    } else {
+        (product, addend)
+    };
+
+    let lead = mx.leading_zeros() as i32 - 1;
+    let (mx, e) = (mx << lead, ex - lead);
+    let shift = ey - e;
+    let my = if shift >= 0 {
+        my << shift
+    } else {
+        shift_right_jamming(my, (-shift) as u32)
+    };
+
+    let (sign, sum) = if sign_x == sign_y {
+        (sign_x, mx + my)
+    } else if mx >= my {
+        (sign_x, mx - my)
+    } else {
+        (sign_y, my - mx)
+    };
+    if sum == 0 {
+        return fmt.zero(mode == Rounding::MinusInfinity);
+    }
+    round(fmt, sign, sum, e, mode, fpscr)
+}
+
+/// FPDiv
+pub fn div(fmt: Format, a: u64, b: u64, fpscr: &mut u32) -> u64 {
+    let a = flush(fmt, a, fpscr);
+    let b = flush(fmt, b, fpscr);
+    if let Some(nan) = process_nans(fmt, &[a, b], fpscr) {
+        return nan;
+    }
+    let sign = fmt.sign(a) != fmt.sign(b);
+
+    let (inf_a, inf_b) = (fmt.is_infinity(a), fmt.is_infinity(b));
+    let (zero_a, zero_b) = (fmt.is_zero(a), fmt.is_zero(b));
+    if (inf_a && inf_b) || (zero_a && zero_b) {
+        return invalid(fmt, fpscr);
+    }
+    if inf_a || zero_b {
+        if zero_b && !inf_a {
+            *fpscr |= DZC;
+        }
+        return fmt.infinity(sign);
+    }
+    if zero_a || inf_b {
+        return fmt.zero(sign);
+    }
+
+    // both significands have their leading one at frac_bits, the quotient gets at least
+    // frac_bits + 3 bits
+    let (_, ma, ea) = fmt.unpack_normalized(a);
+    let (_, mb, eb) = fmt.unpack_normalized(b);
+    let k = fmt.frac_bits + 4;
+    let dividend = ma << k;
+    let q = dividend / mb;
+    let sticky = !dividend.is_multiple_of(mb) as u128;
+    round(
+        fmt,
+        sign,
+        q | sticky,
+        ea - eb - k as i32,
+        Rounding::from_fpscr(*fpscr),
+        fpscr,
+    )
+}
+
+/// FPSqrt
+pub fn sqrt(fmt: Format, a: u64, fpscr: &mut u32) -> u64 {
+    let a = flush(fmt, a, fpscr);
+    if let Some(nan) = process_nans(fmt, &[a], fpscr) {
+        return nan;
+    }
+    if fmt.is_zero(a) {
+        return a;
+    }
+    if fmt.sign(a) {
+        return invalid(fmt, fpscr);
+    }
+    if fmt.is_infinity(a) {
+        return a;
+    }
+
+    // widen to at least 2 * frac_bits + 6 bits with an even exponent, so the root has
+    // frac_bits + 3 bits
+    let (_, m, e) = fmt.unpack_normalized(a);
+    let mut shift = fmt.frac_bits as i32 + 6;
+    if (e - shift) % 2 != 0 {
+        shift += 1;
+    }
+    let radicand = m << shift;
+    let root = isqrt(radicand);
+    let sticky = (root * root != radicand) as u128;
+    round(
+        fmt,
+        false,
+        root | sticky,
+        (e - shift) / 2,
+        Rounding::from_fpscr(*fpscr),
+        fpscr,
+    )
+}
+
+pub fn neg(fmt: Format, a: u64) -> u64 {
+    a ^ fmt.sign_bit()
+}
+
+pub fn abs(fmt: Format, a: u64) -> u64 {
+    a & !fmt.sign_bit()
+}
+
+/// FPCompare: the NZCV flags in bits 31..28 for `a` compared to `b`. An unordered comparison
+/// raises Invalid Operation for signaling NaNs, for every NaN with `signal_nans` (VCMPE).
+pub fn compare(fmt: Format, a: u64, b: u64, signal_nans: bool, fpscr: &mut u32) -> u32 {
+    let a = flush(fmt, a, fpscr);
+    let b = flush(fmt, b, fpscr);
+    if fmt.is_nan(a) || fmt.is_nan(b) {
+        if signal_nans || fmt.is_signaling(a) || fmt.is_signaling(b) {
+            *fpscr |= IOC;
+        }
+        return 0b0011 << 28;
+    }
+
+    // sign-magnitude to a totally ordered key, both zeros compare equal
+    let key = |x: u64| -> i128 {
+        let magnitude = abs(fmt, x) as i128;
+        if fmt.sign(x) { -magnitude } else { magnitude }
+    };
+    match key(a).cmp(&key(b)) {
+        Ordering::Equal => 0b0110 << 28,
+        Ordering::Less => 0b1000 << 28,
+        Ordering::Greater => 0b0010 << 28,
+    }
+}
+
+/// FPMax and FPMin of Advanced SIMD: NaNs propagate and +0 is larger than -0
+pub fn max_min(fmt: Format, a: u64, b: u64, max: bool, fpscr: &mut u32) -> u64 {
+    let a = flush(fmt, a, fpscr);
+    let b = flush(fmt, b, fpscr);
+    if let Some(nan) = process_nans(fmt, &[a, b], fpscr) {
+        return nan;
+    }
+    if fmt.is_zero(a) && fmt.is_zero(b) {
+        let negative = if max {
+            fmt.sign(a) && fmt.sign(b)
+        } else {
+            fmt.sign(a) || fmt.sign(b)
+        };
+        return fmt.zero(negative);
+    }
+    let greater = compare(fmt, a, b, false, fpscr) == 0b0010 << 28;
+    if greater == max { a } else { b }
+}
+
+/// FPRecipStep: 2 - a * b for the Newton-Raphson iteration of VRECPS
+pub fn recip_step(fmt: Format, a: u64, b: u64, fpscr: &mut u32) -> u64 {
+    step(fmt, a, b, fpscr).map_or_else(
+        |nan| nan,
+        |product| add(fmt, expand_imm(fmt, 0x00), product, true, fpscr),
+    )
+}
+
+/// FPRSqrtStep: (3 - a * b) / 2 for the Newton-Raphson iteration of VRSQRTS
+pub fn rsqrt_step(fmt: Format, a: u64, b: u64, fpscr: &mut u32) -> u64 {
+    step(fmt, a, b, fpscr).map_or_else(
+        |nan| nan,
+        |product| halved_sub(fmt, expand_imm(fmt, 0x08), product, fpscr),
+    )
+}
+
+/// The product of the step functions, where infinity times zero is zero, or the NaN result
+fn step(fmt: Format, a: u64, b: u64, fpscr: &mut u32) -> Result<u64, u64> {
+    let a = flush(fmt, a, fpscr);
+    let b = flush(fmt, b, fpscr);
+    if let Some(nan) = process_nans(fmt, &[a, b], fpscr) {
+        return Err(nan);
+    }
+    if (fmt.is_infinity(a) && fmt.is_zero(b)) || (fmt.is_zero(a) && fmt.is_infinity(b)) {
+        return Ok(fmt.zero(false));
+    }
+    Ok(mul(fmt, a, b, fpscr))
+}
+
+/// RecipEstimate on the 9-bit significand `a` in 256..512, standing for `a / 512`: 1 / a
+/// rounded to 9 bits, also in 256..512.
+fn recip_estimate_significand(a: u64) -> u64 {
+    // round(256 * 512 / (a + 0.5))
+    let q = 2 * a + 1;
+    (2 * 262144 + q) / (2 * q)
+}
+
+/// RecipSqrtEstimate on the 8-bit significand `a` in 128..256, standing for `a / 256`, or for
+/// `a / 512` with `quarter`: 1 / sqrt(a) rounded to 9 bits, in 256..512.
+fn rsqrt_estimate_significand(a: u64, quarter: bool) -> u64 {
+    // round(256 / sqrt((a + 0.5) / scale)) through t = floor(2 * 256 / sqrt(...))
+    let numerator: u128 = if quarter { 1 << 28 } else { 1 << 27 };
+    let t = isqrt(numerator / (2 * a as u128 + 1)) as u64;
+    t.div_ceil(2)
+}
+
+/// FPRecipEstimate for single precision, the 8-bit accurate estimate of VRECPE
+pub fn recip_estimate(a: u32, fpscr: &mut u32) -> u32 {
+    let fmt = SINGLE;
+    let a = flush(fmt, a as u64, fpscr);
+    if let Some(nan) = process_nans(fmt, &[a], fpscr) {
+        return nan as u32;
+    }
+    let sign = fmt.sign(a);
+    if fmt.is_infinity(a) {
+        return fmt.zero(sign) as u32;
+    }
+    if fmt.is_zero(a) {
+        *fpscr |= DZC;
+        return fmt.infinity(sign) as u32;
+    }
+    let exp = fmt.exp(a);
+    // the reciprocal of anything at or above 2^126 would be subnormal
+    if exp >= 253 {
+        *fpscr |= UFC;
+        return fmt.zero(sign) as u32;
+    }
+    let estimate = recip_estimate_significand(256 | (fmt.frac(a) >> 15));
+    (((sign as u64) << 31) | ((253 - exp) << 23) | ((estimate & 0xff) << 15)) as u32
+}
+
+/// FPRSqrtEstimate for single precision, the 8-bit accurate estimate of VRSQRTE
+pub fn rsqrt_estimate(a: u32, fpscr: &mut u32) -> u32 {
+    let fmt = SINGLE;
+    let a = flush(fmt, a as u64, fpscr);
+    if let Some(nan) = process_nans(fmt, &[a], fpscr) {
+        return nan as u32;
+    }
+    if fmt.is_zero(a) {
+        *fpscr |= DZC;
+        return fmt.infinity(fmt.sign(a)) as u32;
+    }
+    if fmt.sign(a) {
+        return invalid(fmt, fpscr) as u32;
+    }
+    if fmt.is_infinity(a) {
+        return 0;
+    }
+    // an odd exponent scales the significand into 0.25..0.5, an even one into 0.5..1
+    let exp = fmt.exp(a);
+    let estimate = rsqrt_estimate_significand(128 | (fmt.frac(a) >> 16), exp & 1 == 1);
+    ((((380 - exp) / 2) << 23) | ((estimate & 0xff) << 15)) as u32
+}
+
+/// UnsignedRecipEstimate: VRECPE.U32 on a fixed-point value with 32 fraction bits
+pub fn unsigned_recip_estimate(a: u32) -> u32 {
+    if a >> 31 == 0 {
+        return u32::MAX;
+    }
+    (recip_estimate_significand(a as u64 >> 23) << 23) as u32
+}
+
+/// UnsignedRSqrtEstimate: VRSQRTE.U32 on a fixed-point value with 32 fraction bits
+pub fn unsigned_rsqrt_estimate(a: u32) -> u32 {
+    let estimate = match a >> 30 {
+        0 => return u32::MAX,
+        1 => rsqrt_estimate_significand(a as u64 >> 23, true),
+        _ => rsqrt_estimate_significand(a as u64 >> 24, false),
+    };
+    (estimate << 23) as u32
+}
+
+/// FPToFixed: convert `a` to a `width` bit integer with `frac_bits` fraction bits, rounded with
+/// `mode`. NaNs convert to zero, out of range values saturate, both raise Invalid Operation. The
+/// result is sign respectively zero extended to 32 bits.
+pub fn to_fixed(
+    fmt: Format,
+    a: u64,
+    frac_bits: u32,
+    unsigned: bool,
+    width: u32,
+    mode: Rounding,
+    fpscr: &mut u32,
+) -> u32 {
+    let a = flush(fmt, a, fpscr);
+    if fmt.is_nan(a) {
+        *fpscr |= IOC;
+        return 0;
+    }
+
+    let (min, max) = if unsigned {
+        (0i128, (1i128 << width) - 1)
+    } else {
+        (-(1i128 << (width - 1)), (1i128 << (width - 1)) - 1)
+    };
+    let saturate = |fpscr: &mut u32, negative: bool| {
+        *fpscr |= IOC;
+        (if negative { min } else { max }) as u32
+    };
+    if fmt.is_infinity(a) {
+        return saturate(fpscr, fmt.sign(a));
+    }
+    if fmt.is_zero(a) {
+        return 0;
+    }
+
+    let (sign, m, e) = fmt.unpack(a);
+    let e = e + frac_bits as i32;
+    // anything at or above 2^64 saturates regardless of the rounding
+    if (127 - m.leading_zeros() as i32) + e >= 64 {
+        return saturate(fpscr, sign);
+    }
+    let (int, rem, half) = if e >= 0 {
+        (m << e, 0, Ordering::Less)
+    } else if e <= -128 {
+        (0, m, Ordering::Less)
+    } else {
+        let shift = -e as u32;
+        let rem = m & ((1 << shift) - 1);
+        (m >> shift, rem, rem.cmp(&(1 << (shift - 1))))
+    };
+    let round_up = match mode {
+        Rounding::Nearest => half == Ordering::Greater || (half == Ordering::Equal && int & 1 == 1),
+        Rounding::PlusInfinity => rem != 0 && !sign,
+        Rounding::MinusInfinity => rem != 0 && sign,
+        Rounding::Zero => false,
+    };
+    let magnitude = (int + round_up as u128) as i128;
+    let value = if sign { -magnitude } else { magnitude };
+
+    if value < min || value > max {
+        return saturate(fpscr, sign);
+    }
+    if rem != 0 {
+        *fpscr |= IXC;
+    }
+    value as u32
+}
+
+/// FixedToFP: convert the low `width` bits of `value`, an integer with `frac_bits` fraction bits,
+/// rounding with the FPSCR mode.
+pub fn from_fixed(
+    fmt: Format,
+    value: u32,
+    frac_bits: u32,
+    unsigned: bool,
+    width: u32,
+    fpscr: &mut u32,
+) -> u64 {
+    let value = value as u64 & (u64::MAX >> (64 - width));
+    let (sign, magnitude) = if !unsigned && value >> (width - 1) == 1 {
+        (true, (1u64 << width) - value)
+    } else {
+        (false, value)
+    };
+    let mode = Rounding::from_fpscr(*fpscr);
+    round(
+        fmt,
+        sign,
+        magnitude as u128,
+        -(frac_bits as i32),
+        mode,
+        fpscr,
+    )
+}
+
+/// FPSingleToDouble and FPDoubleToSingle. NaNs keep the top of their payload.
+pub fn convert(from: Format, to: Format, a: u64, fpscr: &mut u32) -> u64 {
+    let a = flush(from, a, fpscr);
+    let sign = from.sign(a);
+    if from.is_nan(a) {
+        if from.is_signaling(a) {
+            *fpscr |= IOC;
+        }
+        if *fpscr & DN != 0 {
+            return to.default_nan();
+        }
+        let frac = from.frac(a);
+        let payload = if to.frac_bits > from.frac_bits {
+            frac << (to.frac_bits - from.frac_bits)
+        } else {
+            frac >> (from.frac_bits - to.frac_bits)
+        };
+        return to.infinity(sign) | payload | to.quiet_bit();
+    }
+    if from.is_infinity(a) {
+        return to.infinity(sign);
+    }
+    if from.is_zero(a) {
+        return to.zero(sign);
+    }
+    let (sign, m, e) = from.unpack(a);
+    round(to, sign, m, e, Rounding::from_fpscr(*fpscr), fpscr)
+}
+
+/// VFPExpandImm: the 8-bit immediate of VMOV is a sign, a 3-bit exponent and a 4-bit fraction
+pub fn expand_imm(fmt: Format, imm8: u32) -> u64 {
+    let imm8 = imm8 as u64;
+    let b6 = (imm8 >> 6) & 1;
+    let exp = ((b6 ^ 1) << (fmt.exp_bits - 1))
+        | (if b6 == 1 {
+            ((1 << (fmt.exp_bits - 3)) - 1) << 2
+        } else {
+            0
+        })
+        | ((imm8 >> 4) & 0b11);
+    ((imm8 >> 7) << (fmt.frac_bits + fmt.exp_bits))
+        | (exp << fmt.frac_bits)
+        | ((imm8 & 0xf) << (fmt.frac_bits - 4))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        DN, DOUBLE, DZC, FZ, IDC, IOC, IXC, OFC, Rounding, SINGLE, UFC, add, compare, convert, div,
+        expand_imm, from_fixed, halved_sub, max_min, mul, mul_add, recip_estimate, recip_step,
+        rsqrt_estimate, rsqrt_step, sqrt, to_fixed, unsigned_recip_estimate,
+        unsigned_rsqrt_estimate,
+    };
+
+    const RP: u32 = 0b01 << 22;
+    const RM: u32 = 0b10 << 22;
+    const RZ: u32 = 0b11 << 22;
+
+    fn s(x: f32) -> u64 {
+        x.to_bits() as u64
+    }
+
+    fn d(x: f64) -> u64 {
+        x.to_bits()
+    }
+
+    /// xorshift, to compare against the host in round to nearest
+    fn values(count: usize) -> impl Iterator<Item = u64> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        (0..count).map(move |_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        })
+    }
+
+    #[test]
+    fn nearest_matches_host() {
+        let mut pairs = values(20_000).zip(values(20_001).skip(1));
+        for (x, y) in pairs.by_ref().take(10_000) {
+            let (a, b) = (f64::from_bits(x), f64::from_bits(y));
+            if a.is_nan() || b.is_nan() {
+                continue;
+            }
+            let mut fpscr = 0;
+            let check = |got: u64, want: f64| want.is_nan() || got == want.to_bits();
+            assert!(
+                check(add(DOUBLE, x, y, false, &mut fpscr), a + b),
+                "{a} + {b}"
+            );
+            assert!(
+                check(add(DOUBLE, x, y, true, &mut fpscr), a - b),
+                "{a} - {b}"
+            );
+            assert!(check(mul(DOUBLE, x, y, &mut fpscr), a * b), "{a} * {b}");
+            assert!(check(div(DOUBLE, x, y, &mut fpscr), a / b), "{a} / {b}");
+            assert!(check(sqrt(DOUBLE, x, &mut fpscr), a.sqrt()), "sqrt {a}");
+        }
+        for (i, (x, y)) in pairs.enumerate() {
+            // every other pair is tiny, to exercise subnormal operands and results
+            let mask = if i % 2 == 0 { 0xffff_ffff } else { 0x80ff_ffff };
+            let (x, y) = (x & mask, y as u32 as u64);
+            let (a, b) = (f32::from_bits(x as u32), f32::from_bits(y as u32));
+            if a.is_nan() || b.is_nan() {
+                continue;
+            }
+            let mut fpscr = 0;
+            let check = |got: u64, want: f32| want.is_nan() || got == want.to_bits() as u64;
+            assert!(
+                check(add(SINGLE, x, y, false, &mut fpscr), a + b),
+                "{a} + {b}"
+            );
+            assert!(check(mul(SINGLE, x, y, &mut fpscr), a * b), "{a} * {b}");
+            assert!(check(div(SINGLE, x, y, &mut fpscr), a / b), "{a} / {b}");
+            assert!(check(sqrt(SINGLE, x, &mut fpscr), a.sqrt()), "sqrt {a}");
+            assert!(
+                check(
+                    convert(DOUBLE, SINGLE, d(a as f64 * 1.1), &mut fpscr),
+                    (a as f64 * 1.1) as f32
+                ),
+                "narrow {a}"
+            );
+        }
+    }
+
+    #[test]
+    fn fused_mul_add_matches_host() {
+        let triples = values(30_000)
+            .zip(values(30_001).skip(1))
+            .zip(values(30_002).skip(2));
+        for (i, ((x, y), z)) in triples.enumerate() {
+            let (a, b, c) = (f64::from_bits(x), f64::from_bits(y), f64::from_bits(z));
+            // every third addend cancels the rounded product, leaving only its rounding error
+            let c = if i % 3 == 0 { -(a * b) } else { c };
+            let mut fpscr = 0;
+            let want = a.mul_add(b, c);
+            let got = mul_add(DOUBLE, d(c), x, y, &mut fpscr);
+            assert!(want.is_nan() || got == d(want), "{a} * {b} + {c}");
+
+            let (a, b) = (f32::from_bits(x as u32), f32::from_bits(y as u32));
+            let c = if i % 3 == 0 {
+                -(a * b)
+            } else {
+                f32::from_bits(z as u32)
+            };
+            let want = a.mul_add(b, c);
+            let got = mul_add(SINGLE, s(c), s(a), s(b), &mut fpscr);
+            assert!(want.is_nan() || got == s(want), "{a} * {b} + {c}");
+        }
+
+        // the product isn't rounded before the addition
+        let mut fpscr = 0;
+        let x = 1.0 + 2f64.powi(-30);
+        assert_eq!(
+            mul_add(DOUBLE, d(-1.0), d(x), d(x), &mut fpscr),
+            d(2f64.powi(-29) + 2f64.powi(-60))
+        );
+        assert_eq!(mul_add(SINGLE, s(-0.0), s(0.0), s(1.0), &mut fpscr), s(0.0));
+        assert_eq!(fpscr, 0);
+        mul_add(SINGLE, s(1.0), s(0.0), s(f32::INFINITY), &mut fpscr);
+        assert_eq!(fpscr, IOC);
+    }
+
+    #[test]
+    fn directed_rounding() {
+        let third = |fpscr: &mut u32| div(SINGLE, s(1.0), s(3.0), fpscr);
+        let mut fpscr = 0;
+        assert_eq!(third(&mut fpscr), s(1.0 / 3.0));
+        assert_eq!(fpscr, IXC);
+
+        // 1/3 rounds up to nearest, so rounding down loses the last bit
+        let mut fpscr = RP;
+        assert_eq!(third(&mut fpscr), s(1.0 / 3.0));
+        let mut fpscr = RM;
+        assert_eq!(
+            third(&mut fpscr),
+            s(f32::from_bits((1.0f32 / 3.0).to_bits() - 1))
+        );
+        let mut fpscr = RZ;
+        assert_eq!(
+            div(SINGLE, s(-1.0), s(3.0), &mut fpscr),
+            s(f32::from_bits((-1.0f32 / 3.0).to_bits() - 1))
+        );
+        let mut fpscr = RM;
+        assert_eq!(div(SINGLE, s(-1.0), s(3.0), &mut fpscr), s(-1.0 / 3.0));
+
+        // x - x is -0 only when rounding towards minus infinity
+        let mut fpscr = 0;
+        assert_eq!(add(DOUBLE, d(1.5), d(1.5), true, &mut fpscr), d(0.0));
+        let mut fpscr = RM;
+        assert_eq!(add(DOUBLE, d(1.5), d(1.5), true, &mut fpscr), d(-0.0));
+        assert_eq!(Rounding::from_fpscr(RZ), Rounding::Zero);
+    }
+
+    #[test]
+    fn overflow_depends_on_rounding() {
+        let mut fpscr = 0;
+        assert_eq!(
+            mul(SINGLE, s(f32::MAX), s(2.0), &mut fpscr),
+            s(f32::INFINITY)
+        );
+        assert_eq!(fpscr, OFC | IXC);
+        let mut fpscr = RZ;
+        assert_eq!(mul(SINGLE, s(f32::MAX), s(2.0), &mut fpscr), s(f32::MAX));
+        let mut fpscr = RP;
+        assert_eq!(mul(SINGLE, s(-f32::MAX), s(2.0), &mut fpscr), s(-f32::MAX));
+    }
+
+    #[test]
+    fn nan_propagation_and_default_nan() {
+        let snan = 0x7f80_0001;
+        let qnan = 0x7fc0_0002;
+        let mut fpscr = 0;
+        // a signaling NaN wins over an earlier quiet one and gets quietened
+        assert_eq!(add(SINGLE, qnan, snan, false, &mut fpscr), 0x7fc0_0001);
+        assert_eq!(fpscr, IOC);
+        let mut fpscr = 0;
+        assert_eq!(mul(SINGLE, s(1.0), qnan, &mut fpscr), qnan);
+        assert_eq!(fpscr, 0);
+        // the sign of a NaN operand survives a subtraction
+        let mut fpscr = 0;
+        assert_eq!(
+            add(SINGLE, s(1.0), qnan | 0x8000_0000, true, &mut fpscr),
+            qnan | 0x8000_0000
+        );
+        let mut fpscr = DN;
+        assert_eq!(add(SINGLE, qnan, s(1.0), false, &mut fpscr), 0x7fc0_0000);
+        let mut fpscr = 0;
+        assert_eq!(
+            mul(DOUBLE, d(0.0), d(f64::INFINITY), &mut fpscr),
+            0x7ff8_0000_0000_0000
+        );
+        assert_eq!(fpscr, IOC);
+        let mut fpscr = 0;
+        assert_eq!(sqrt(SINGLE, s(-1.0), &mut fpscr), 0x7fc0_0000);
+        assert_eq!(sqrt(SINGLE, s(-0.0), &mut fpscr), s(-0.0));
+    }
+
+    #[test]
+    fn division_by_zero() {
+        let mut fpscr = 0;
+        assert_eq!(
+            div(DOUBLE, d(-2.0), d(0.0), &mut fpscr),
+            d(f64::NEG_INFINITY)
+        );
+        assert_eq!(fpscr, DZC);
+        let mut fpscr = 0;
+        assert_eq!(
+            div(DOUBLE, d(0.0), d(0.0), &mut fpscr),
+            0x7ff8_0000_0000_0000
+        );
+        assert_eq!(fpscr, IOC);
+    }
+
+    #[test]
+    fn flush_to_zero() {
+        let tiny = s(f32::MIN_POSITIVE / 4.0);
+        let mut fpscr = FZ;
+        assert_eq!(add(SINGLE, tiny, s(1.0), false, &mut fpscr), s(1.0));
+        assert_eq!(fpscr & IDC, IDC);
+
+        let mut fpscr = FZ;
+        assert_eq!(
+            mul(SINGLE, s(f32::MIN_POSITIVE), s(0.5), &mut fpscr),
+            s(0.0)
+        );
+        assert_eq!(fpscr, FZ | UFC);
+
+        // without FZ the subnormal result is exact, so no underflow
+        let mut fpscr = 0;
+        assert_eq!(
+            mul(SINGLE, s(f32::MIN_POSITIVE), s(0.5), &mut fpscr),
+            s(f32::MIN_POSITIVE / 2.0)
+        );
+        assert_eq!(fpscr, 0);
+        let mut fpscr = 0;
+        mul(SINGLE, s(f32::MIN_POSITIVE), s(1.0 / 3.0), &mut fpscr);
+        assert_eq!(fpscr, UFC | IXC);
+    }
+
+    #[test]
+    fn compare_flags() {
+        let mut fpscr = 0;
+        assert_eq!(
+            compare(DOUBLE, d(1.0), d(2.0), false, &mut fpscr),
+            0x8000_0000
+        );
+        assert_eq!(
+            compare(DOUBLE, d(2.0), d(1.0), false, &mut fpscr),
+            0x2000_0000
+        );
+        assert_eq!(
+            compare(DOUBLE, d(0.0), d(-0.0), false, &mut fpscr),
+            0x6000_0000
+        );
+        assert_eq!(
+            compare(DOUBLE, d(-3.0), d(-2.0), false, &mut fpscr),
+            0x8000_0000
+        );
+        assert_eq!(fpscr, 0);
+        let nan = d(f64::NAN);
+        assert_eq!(compare(DOUBLE, nan, d(1.0), false, &mut fpscr), 0x3000_0000);
+        assert_eq!(fpscr, 0);
+        assert_eq!(compare(DOUBLE, nan, d(1.0), true, &mut fpscr), 0x3000_0000);
+        assert_eq!(fpscr, IOC);
+    }
+
+    #[test]
+    fn integer_conversions() {
+        let mut fpscr = 0;
+        assert_eq!(
+            to_fixed(DOUBLE, d(-2.5), 0, false, 32, Rounding::Zero, &mut fpscr),
+            -2i32 as u32
+        );
+        assert_eq!(fpscr, IXC);
+        let mut fpscr = 0;
+        assert_eq!(
+            to_fixed(DOUBLE, d(-2.5), 0, false, 32, Rounding::Nearest, &mut fpscr),
+            -2i32 as u32
+        );
+        assert_eq!(
+            to_fixed(DOUBLE, d(3.5), 0, false, 32, Rounding::Nearest, &mut fpscr),
+            4
+        );
+        assert_eq!(
+            to_fixed(
+                DOUBLE,
+                d(-2.5),
+                0,
+                false,
+                32,
+                Rounding::MinusInfinity,
+                &mut fpscr
+            ),
+            -3i32 as u32
+        );
+
+        let mut fpscr = 0;
+        assert_eq!(
+            to_fixed(SINGLE, s(3e9), 0, false, 32, Rounding::Zero, &mut fpscr),
+            i32::MAX as u32
+        );
+        assert_eq!(fpscr, IOC);
+        let mut fpscr = 0;
+        assert_eq!(
+            to_fixed(SINGLE, s(3e9), 0, true, 32, Rounding::Zero, &mut fpscr),
+            3_000_000_000
+        );
+        assert_eq!(
+            to_fixed(SINGLE, s(-1.0), 0, true, 32, Rounding::Zero, &mut fpscr),
+            0
+        );
+        assert_eq!(fpscr, IOC);
+        let mut fpscr = 0;
+        assert_eq!(
+            to_fixed(
+                SINGLE,
+                0x7fc0_0000,
+                0,
+                false,
+                32,
+                Rounding::Zero,
+                &mut fpscr
+            ),
+            0
+        );
+        assert_eq!(fpscr, IOC);
+
+        // Q8.8 fixed point in 16 bits, sign extended
+        let mut fpscr = 0;
+        assert_eq!(
+            to_fixed(SINGLE, s(-1.5), 8, false, 16, Rounding::Zero, &mut fpscr),
+            0xffff_fe80
+        );
+        assert_eq!(
+            from_fixed(SINGLE, 0xfe80, 8, false, 16, &mut fpscr),
+            s(-1.5)
+        );
+        assert_eq!(
+            from_fixed(DOUBLE, u32::MAX, 0, true, 32, &mut fpscr),
+            d(4294967295.0)
+        );
+        assert_eq!(
+            from_fixed(DOUBLE, u32::MAX, 0, false, 32, &mut fpscr),
+            d(-1.0)
+        );
+        assert_eq!(fpscr, 0);
+        // 2^24 + 1 is not representable in single precision
+        assert_eq!(
+            from_fixed(SINGLE, 16_777_217, 0, false, 32, &mut fpscr),
+            s(16_777_216.0)
+        );
+        assert_eq!(fpscr, IXC);
+    }
+
+    #[test]
+    fn precision_conversions() {
+        let mut fpscr = 0;
+        assert_eq!(convert(SINGLE, DOUBLE, s(1.25), &mut fpscr), d(1.25));
+        assert_eq!(convert(DOUBLE, SINGLE, d(0.1), &mut fpscr), s(0.1));
+        assert_eq!(fpscr, IXC);
+        let mut fpscr = 0;
+        assert_eq!(
+            convert(SINGLE, DOUBLE, 0x7f80_0001, &mut fpscr),
+            0x7ff8_0000_2000_0000
+        );
+        assert_eq!(fpscr, IOC);
+    }
+
+    #[test]
+    fn vmov_immediates() {
+        assert_eq!(expand_imm(SINGLE, 0x70), s(1.0));
+        assert_eq!(expand_imm(SINGLE, 0x00), s(2.0));
+        assert_eq!(expand_imm(DOUBLE, 0xe0), d(-0.5));
+        assert_eq!(expand_imm(DOUBLE, 0x24), d(10.0));
+    }
+
+    #[test]
+    fn advanced_simd_max_min_and_steps() {
+        let standard = FZ | DN;
+        let mut fpscr = standard;
+        assert_eq!(max_min(SINGLE, s(0.0), s(-0.0), true, &mut fpscr), s(0.0));
+        assert_eq!(max_min(SINGLE, s(0.0), s(-0.0), false, &mut fpscr), s(-0.0));
+        assert_eq!(max_min(SINGLE, s(-3.0), s(2.0), true, &mut fpscr), s(2.0));
+        assert_eq!(max_min(SINGLE, s(-3.0), s(2.0), false, &mut fpscr), s(-3.0));
+        assert_eq!(fpscr, standard);
+        assert_eq!(
+            max_min(SINGLE, s(1.0), s(f32::NAN), true, &mut fpscr),
+            SINGLE.default_nan()
+        );
+
+        assert_eq!(recip_step(SINGLE, s(2.0), s(0.5), &mut fpscr), s(1.0));
+        assert_eq!(
+            recip_step(SINGLE, s(f32::INFINITY), s(0.0), &mut fpscr),
+            s(2.0)
+        );
+        assert_eq!(rsqrt_step(SINGLE, s(1.0), s(1.0), &mut fpscr), s(1.0));
+        assert_eq!(
+            rsqrt_step(SINGLE, s(0.0), s(f32::INFINITY), &mut fpscr),
+            s(1.5)
+        );
+        assert_eq!(halved_sub(SINGLE, s(3.0), s(0.0), &mut fpscr), s(1.5));
+        // the halving is part of the single rounding
+        let mut fpscr = 0;
+        assert_eq!(
+            halved_sub(SINGLE, 0x0080_0003, 0x0080_0000, &mut fpscr),
+            0x0000_0002
+        );
+        assert_eq!(fpscr, UFC | IXC);
+    }
+
+    #[test]
+    fn reciprocal_estimates() {
+        let mut fpscr = FZ | DN;
+        assert_eq!(recip_estimate(s(1.0) as u32, &mut fpscr), 0x3f7f_8000);
+        assert_eq!(recip_estimate(s(3.0) as u32, &mut fpscr), 0x3eaa_8000);
+        assert_eq!(recip_estimate(s(-0.5) as u32, &mut fpscr), 0xbfff_8000);
+        assert_eq!(rsqrt_estimate(s(1.0) as u32, &mut fpscr), 0x3f7f_8000);
+        assert_eq!(rsqrt_estimate(s(2.0) as u32, &mut fpscr), 0x3f34_8000);
+        assert_eq!(rsqrt_estimate(s(4.0) as u32, &mut fpscr), 0x3eff_8000);
+        assert_eq!(fpscr, FZ | DN);
+
+        assert_eq!(
+            recip_estimate(s(0.0) as u32, &mut fpscr),
+            s(f32::INFINITY) as u32
+        );
+        assert_eq!(fpscr & DZC, DZC);
+        let mut fpscr = FZ | DN;
+        assert_eq!(recip_estimate(s(2e38) as u32, &mut fpscr), 0);
+        assert_eq!(fpscr & UFC, UFC);
+        assert_eq!(
+            rsqrt_estimate(s(-1.0) as u32, &mut fpscr),
+            SINGLE.default_nan() as u32
+        );
+        assert_eq!(fpscr & IOC, IOC);
+
+        assert_eq!(unsigned_recip_estimate(0x8000_0000), 0xff80_0000);
+        assert_eq!(unsigned_recip_estimate(0x7fff_ffff), u32::MAX);
+        assert_eq!(unsigned_rsqrt_estimate(0x4000_0000), 0xff80_0000);
+        assert_eq!(unsigned_rsqrt_estimate(0x3fff_ffff), u32::MAX);
+    }
+}
@@ -0,0 +1,754 @@
+//! Compiles the decoded blocks of [`super::blocks`] to x86-64, see `--engine`. A translation runs
+//! the longest prefix of a block made of instructions it supports: data-processing with an
+//! immediate or an immediate shifted register, MUL and MLA, MOVW and MOVT, LDR, STR, LDRB and STRB
+//! and B and BL. The interpreter executes the rest of the block.
+//!
+//! While a translation runs, up to eight guest registers live in host registers and the CPSR in
+//! r15d. It takes a side exit before an instruction that needs more than the translation checks,
+//! like an access outside the arena or a store to a page holding decoded code, so the interpreter
+//! executes that one with its usual faults and invalidation.
+
+/// encoding the x86-64 instructions
+mod x86;
+
+use std::{mem::offset_of, ptr::NonNull};
+
+use x86::{Alu, Assembler, Cond, Jump, R11, R15, RAX, RBP, RBX, RCX, RDI, RDX, Shift};
+
+use crate::{
+    cpu::{
+        alu,
+        decoder::{self, Decoded, InstructionKind, Op, Operands},
+        features::Features,
+    },
+    mem::{self, mmap},
+};
+
+/// Interpreted runs of a block before `--engine both` compiles it
+pub(super) const HOT_RUNS: u32 = 16;
+
+/// Host memory reserved for translations, compiling stops once it is full. The code of
+/// invalidated blocks isn't reclaimed.
+const CODE_SIZE: usize = 16 * 1024 * 1024;
+const HOST_PAGE_SIZE: usize = 0x1000;
+
+/// the guest register file
+const REGS: u8 = RBX;
+/// the [`Context`]
+const CONTEXT: u8 = RBP;
+/// the guest CPSR
+const CPSR: u8 = R15;
+/// host registers guest registers are kept in, rax, rcx, rdx and r11 are scratch
+const ALLOCATABLE: [u8; 8] = [
+    x86::RSI,
+    RDI,
+    x86::R8,
+    x86::R9,
+    x86::R10,
+    x86::R12,
+    x86::R13,
+    x86::R14,
+];
+/// the registers the System V ABI wants preserved that a translation uses
+const CALLEE_SAVED: [u8; 6] = [RBX, RBP, x86::R12, x86::R13, x86::R14, R15];
+
+const REGS_PTR: i32 = offset_of!(Context, regs) as i32;
+const CPSR_PTR: i32 = offset_of!(Context, cpsr) as i32;
+const BASE: i32 = (offset_of!(Context, arena) + offset_of!(mem::Arena, base)) as i32;
+const LEN: i32 = (offset_of!(Context, arena) + offset_of!(mem::Arena, len)) as i32;
+const LOW: i32 = (offset_of!(Context, arena) + offset_of!(mem::Arena, low)) as i32;
+const CODE_PAGES: i32 = (offset_of!(Context, arena) + offset_of!(mem::Arena, code_pages)) as i32;
+/// pc in the register file
+const PC: i32 = 15 * 4;
+
+/// The guest state a translation runs on
+#[repr(C)]
+pub(super) struct Context {
+    /// r0-r15
+    pub regs: *mut u32,
+    pub cpsr: *mut u32,
+    pub arena: mem::Arena,
+}
+
+/// A translated block. It returns 1 once it executed the whole block and wrote the address of the
+/// next one to pc, 0 after a side exit with pc at the instruction the interpreter continues with.
+pub(super) type Entry = unsafe extern "sysv64" fn(*mut Context) -> u32;
+
+pub(super) struct Jit {
+    code: NonNull<u8>,
+    /// bytes of `code` holding translations
+    used: usize,
+    /// runs of a block before it is compiled
+    pub threshold: u32,
+    /// MOVW and MOVT are only translated with Thumb-2
+    thumb2: bool,
+}
+
+impl Jit {
+    pub fn new(threshold: u32, features: Features) -> Self {
+        let code = mmap::mmap(
+            None,
+            CODE_SIZE,
+            mmap::MmapProt::READ | mmap::MmapProt::WRITE,
+            mmap::MmapFlags::ANONYMOUS | mmap::MmapFlags::PRIVATE,
+            -1,
+            0,
+        )
+        .expect("failed to allocate memory for translations");
+
+        Self {
+            code,
+            used: 0,
+            threshold,
+            thumb2: features.contains(Features::THUMB2),
+        }
+    }
+
+    /// Translate the block of `instrs` at `start`. None if it starts with an unsupported
+    /// instruction or there is no room left.
+    pub fn compile(&mut self, start: u32, instrs: &[Decoded]) -> Option<Entry> {
+        let len = instrs
+            .iter()
+            .take_while(|decoded| supported(decoded, self.thumb2))
+            .count();
+        if len == 0 {
+            return None;
+        }
+
+        let code =
+            Compiler::new(&instrs[..len]).compile(start, &instrs[..len], len == instrs.len());
+        self.install(&code)
+    }
+
+    /// Copy `code` behind the last translation, making only the pages it lands on writable
+    fn install(&mut self, code: &[u8]) -> Option<Entry> {
+        let offset = self.used.next_multiple_of(16);
+        let end = offset
+            .checked_add(code.len())
+            .filter(|&end| end <= CODE_SIZE)?;
+        let pages = offset / HOST_PAGE_SIZE * HOST_PAGE_SIZE..end.next_multiple_of(HOST_PAGE_SIZE);
+        let first_page = NonNull::new(self.code.as_ptr().wrapping_add(pages.start))?;
+
+        mmap::mprotect(
+            first_page,
+            pages.len(),
+            mmap::MmapProt::READ | mmap::MmapProt::WRITE,
+        )
+        .expect("failed to make translations writable");
+        // SAFETY: offset..end lies in the mapping, which is writable now
+        let entry = unsafe {
+            let entry = self.code.as_ptr().add(offset);
+            std::ptr::copy_nonoverlapping(code.as_ptr(), entry, code.len());
+            entry
+        };
+        mmap::mprotect(
+            first_page,
+            pages.len(),
+            mmap::MmapProt::READ | mmap::MmapProt::EXEC,
+        )
+        .expect("failed to make translations executable");
+
+        self.used = end;
+        // SAFETY: the compiler emits a function with the signature of Entry
+        Some(unsafe { std::mem::transmute::<*mut u8, Entry>(entry) })
+    }
+}
+
+impl Drop for Jit {
+    fn drop(&mut self) {
+        if let Err(e) = mmap::munmap(self.code, CODE_SIZE) {
+            eprintln!("Warning: failed to munmap translations: {e}");
+        }
+    }
+}
+
+/// Whether the translation can execute `decoded`. UNPREDICTABLE encodings are left to the
+/// interpreter, which implements `--strict`.
+fn supported(decoded: &Decoded, thumb2: bool) -> bool {
+    let Decoded {
+        kind,
+        cond,
+        operands,
+        ..
+    } = *decoded;
+    if cond == 0b1111 || decoded.unpredictable.is_some() {
+        return false;
+    }
+
+    match kind {
+        InstructionKind::DataProcessingImm | InstructionKind::DataProcessingReg => {
+            operands.rd != 15
+        }
+        // MUL and MLA
+        InstructionKind::Multiply => operands.op <= 0b001,
+        InstructionKind::MovImm16 => thumb2,
+        InstructionKind::LoadStoreImm | InstructionKind::LoadStoreReg => {
+            !operands.l || operands.rt != 15
+        }
+        InstructionKind::Branch => true,
+        _ => false,
+    }
+}
+
+/// The registers of the supported instructions `decoded` names, fields holding zero are counted
+/// as r0
+fn registers(decoded: &Decoded) -> u16 {
+    let Operands {
+        rd,
+        rn,
+        rm,
+        ra,
+        rt,
+        l,
+        ..
+    } = decoded.operands;
+    let mask = |registers: &[usize]| registers.iter().fold(0u16, |mask, r| mask | 1 << r);
+    let fields = match decoded.kind {
+        InstructionKind::DataProcessingImm => mask(&[rn, rd]),
+        InstructionKind::LoadStoreImm => mask(&[rn, rt]),
+        InstructionKind::DataProcessingReg => mask(&[rn, rd, rm]),
+        InstructionKind::LoadStoreReg => mask(&[rn, rt, rm]),
+        InstructionKind::Multiply => mask(&[rd, ra, rm, rn]),
+        InstructionKind::MovImm16 => mask(&[rd]),
+        InstructionKind::Branch if l => 1 << 14,
+        _ => 0,
+    };
+    fields & !(1 << 15)
+}
+
+/// Where the carry flag an instruction sets comes from
+#[derive(Clone, Copy)]
+enum Carry {
+    Unchanged,
+    Const(bool),
+    /// dl holds C as 0 or 1
+    Dl,
+}
+
+struct Compiler {
+    asm: Assembler,
+    /// the host register each guest register is kept in, if any
+    mapped: [Option<u8>; 16],
+    /// address of the instruction being compiled
+    pc: u32,
+    /// jumps to the epilogue
+    exits: Vec<Jump>,
+    /// jumps to a side exit before the instruction at the address
+    side_exits: Vec<(Jump, u32)>,
+}
+
+impl Compiler {
+    /// Keep the guest registers `instrs` use most in host registers
+    fn new(instrs: &[Decoded]) -> Self {
+        let mut uses = [0u32; 16];
+        for decoded in instrs {
+            let registers = registers(decoded);
+            for (reg, uses) in uses.iter_mut().enumerate() {
+                *uses += (registers >> reg & 1) as u32;
+            }
+        }
+        let mut used: Vec<usize> = (0..15).filter(|&reg| uses[reg] > 0).collect();
+        used.sort_by_key(|&reg| std::cmp::Reverse(uses[reg]));
+
+        let mut mapped = [None; 16];
+        for (&reg, &host) in used.iter().zip(&ALLOCATABLE) {
+            mapped[reg] = Some(host);
+        }
+
+        Self {
+            asm: Assembler::default(),
+            mapped,
+            pc: 0,
+            exits: Vec::new(),
+            side_exits: Vec::new(),
+        }
+    }
+
+    /// Emit the translation of the block at `start`, `whole` if `instrs` are all of it
+    fn compile(mut self, start: u32, instrs: &[Decoded], whole: bool) -> Vec<u8> {
+        for reg in CALLEE_SAVED {
+            self.asm.push(reg);
+        }
+        self.asm.mov64(CONTEXT, RDI);
+        self.asm.load64(REGS, CONTEXT, REGS_PTR);
+        self.asm.load64(R11, CONTEXT, CPSR_PTR);
+        self.asm.load(CPSR, R11, 0);
+        for (reg, host) in self.host_registers() {
+            self.asm.load(host, REGS, reg as i32 * 4);
+        }
+
+        let body = self.asm.code.len();
+        for (i, decoded) in instrs.iter().enumerate() {
+            self.pc = start.wrapping_add(i as u32 * 4);
+            self.instruction(decoded, start, body);
+        }
+        self.exit(start.wrapping_add(instrs.len() as u32 * 4), whole);
+
+        for (jump, pc) in std::mem::take(&mut self.side_exits) {
+            self.asm.bind(jump);
+            self.exit(pc, false);
+        }
+        for jump in std::mem::take(&mut self.exits) {
+            self.asm.bind(jump);
+        }
+        for (reg, host) in self.host_registers() {
+            self.asm.store(REGS, reg as i32 * 4, host);
+        }
+        self.asm.load64(R11, CONTEXT, CPSR_PTR);
+        self.asm.store(R11, 0, CPSR);
+        for reg in CALLEE_SAVED.into_iter().rev() {
+            self.asm.pop(reg);
+        }
+        self.asm.ret();
+
+        self.asm.code
+    }
+
+    fn host_registers(&self) -> Vec<(usize, u8)> {
+        (0..16)
+            .filter_map(|reg| self.mapped[reg].map(|host| (reg, host)))
+            .collect()
+    }
+
+    /// Leave the translation with `pc` as the next instruction
+    fn exit(&mut self, pc: u32, done: bool) {
+        self.asm.store_imm(REGS, PC, pc);
+        self.asm.mov_imm(RAX, done as u32);
+        let jump = self.asm.jmp();
+        self.exits.push(jump);
+    }
+
+    /// Take a side exit before the current instruction on `cond`
+    fn side_exit(&mut self, cond: Cond) {
+        let jump = self.asm.jcc(cond, false);
+        self.side_exits.push((jump, self.pc));
+    }
+
+    /// Copy guest register `reg` to the host register `dst`, pc reads as the instruction + 8
+    fn read_into(&mut self, dst: u8, reg: usize) {
+        match (reg, self.mapped[reg]) {
+            (15, _) => self.asm.mov_imm(dst, self.pc.wrapping_add(8)),
+            (_, Some(host)) => self.asm.mov(dst, host),
+            (_, None) => self.asm.load(dst, REGS, reg as i32 * 4),
+        }
+    }
+
+    /// The host register holding guest register `reg`, loading it into `scratch` if none does
+    fn read(&mut self, reg: usize, scratch: u8) -> u8 {
+        match self.mapped[reg] {
+            Some(host) if reg != 15 => host,
+            _ => {
+                self.read_into(scratch, reg);
+                scratch
+            }
+        }
+    }
+
+    /// Write the host register `src` to guest register `reg`, which isn't pc
+    fn write(&mut self, reg: usize, src: u8) {
+        debug_assert!(reg != 15, "translations only write pc when they exit");
+        match self.mapped[reg] {
+            Some(host) => self.asm.mov(host, src),
+            None => self.asm.store(REGS, reg as i32 * 4, src),
+        }
+    }
+
+    fn instruction(&mut self, decoded: &Decoded, start: u32, body: usize) {
+        let skip = (decoded.cond != 0b1110).then(|| self.condition(decoded.cond));
+        match decoded.kind {
+            InstructionKind::DataProcessingImm | InstructionKind::DataProcessingReg => {
+                self.data_processing(decoded)
+            }
+            InstructionKind::Multiply => self.multiply(decoded),
+            InstructionKind::MovImm16 => self.move_imm16(decoded),
+            InstructionKind::LoadStoreImm | InstructionKind::LoadStoreReg => {
+                self.load_store(decoded)
+            }
+            InstructionKind::Branch => self.branch(decoded, start, body),
+            kind => unreachable!("{kind:?} isn't translated"),
+        }
+        if let Some(skip) = skip {
+            self.asm.bind(skip);
+        }
+    }
+
+    /// Jump past the instruction unless `cond` passes. The flags index a 16 bit mask of the
+    /// NZCV values it passes for.
+    fn condition(&mut self, cond: u8) -> Jump {
+        let passes = (0..16)
+            .filter(|&nzcv| alu::condition_passed(cond, nzcv << 28))
+            .fold(0, |mask, nzcv| mask | 1 << nzcv);
+        self.asm.mov(RAX, CPSR);
+        self.asm.shift(Shift::Shr, RAX, 28);
+        self.asm.mov_imm(RCX, passes);
+        self.asm.bt(RCX, RAX);
+        self.asm.jcc(Cond::Below, true)
+    }
+
+    /// Shift ecx by the immediate shift of an operand. Returns whether CF holds the shifter
+    /// carry out, it is C for a plain register.
+    fn shift_operand(&mut self, kind: alu::Shift, imm5: u32) -> bool {
+        match alu::decode_imm_shift(kind, imm5) {
+            (alu::Shift::Lsl, 0) => return false,
+            (alu::Shift::Lsr, 32) => {
+                self.asm.bt_imm(RCX, 31);
+                self.asm.mov_imm(RCX, 0);
+            }
+            (alu::Shift::Asr, 32) => {
+                self.asm.shift(Shift::Sar, RCX, 31);
+                self.asm.bt_imm(RCX, 0);
+            }
+            (alu::Shift::Rrx, _) => {
+                self.asm.bt_imm(CPSR, 29);
+                self.asm.shift(Shift::Rcr, RCX, 1);
+            }
+            (shift, amount) => {
+                let shift = match shift {
+                    alu::Shift::Lsl => Shift::Shl,
+                    alu::Shift::Lsr => Shift::Shr,
+                    alu::Shift::Asr => Shift::Sar,
+                    _ => Shift::Ror,
+                };
+                self.asm.shift(shift, RCX, amount as u8);
+            }
+        }
+        true
+    }
+
+    /// Set N and Z from eax, C as `carry` says and V from cl if `overflow`
+    fn set_flags(&mut self, carry: Carry, overflow: bool) {
+        let mut written = alu::N | alu::Z;
+        match carry {
+            Carry::Unchanged => self.asm.mov_imm(RDX, 0),
+            Carry::Const(c) => {
+                self.asm.mov_imm(RDX, (c as u32) << 29);
+                written |= alu::C;
+            }
+            Carry::Dl => {
+                self.asm.movzx_byte(RDX, RDX);
+                self.asm.shift(Shift::Shl, RDX, 29);
+                written |= alu::C;
+            }
+        }
+        if overflow {
+            self.asm.movzx_byte(RCX, RCX);
+            self.asm.shift(Shift::Shl, RCX, 28);
+            self.asm.alu(Alu::Or, RDX, RCX);
+            written |= alu::V;
+        }
+        self.asm.test(RAX, RAX);
+        self.asm.setcc(Cond::Zero, false, RCX);
+        self.asm.movzx_byte(RCX, RCX);
+        self.asm.shift(Shift::Shl, RCX, 30);
+        self.asm.alu(Alu::Or, RDX, RCX);
+        self.asm.mov(RCX, RAX);
+        self.asm.alu_imm(Alu::And, RCX, alu::N);
+        self.asm.alu(Alu::Or, RDX, RCX);
+
+        self.asm.alu_imm(Alu::And, CPSR, !written);
+        self.asm.alu(Alu::Or, CPSR, RDX);
+    }
+
+    /// See [`super::Cpu::data_processing`], eax is Rn and ecx the second operand
+    fn data_processing(&mut self, decoded: &Decoded) {
+        let Operands { op, s, rd, rn, .. } = decoded.operands;
+        let op = decoder::op_from_bits(op as u8);
+
+        let carry = if decoded.kind == InstructionKind::DataProcessingImm {
+            let imm12 = decoded.operands.imm;
+            let (imm32, carry) = alu::expand_imm_c(imm12, false);
+            self.asm.mov_imm(RCX, imm32);
+            if imm12 >> 8 == 0 {
+                Carry::Unchanged
+            } else {
+                Carry::Const(carry)
+            }
+        } else {
+            self.read_into(RCX, decoded.operands.rm);
+            if self.shift_operand(decoded.operands.shift, decoded.operands.imm) {
+                if s && op.is_logical() {
+                    self.asm.setcc(Cond::Below, false, RDX);
+                }
+                Carry::Dl
+            } else {
+                Carry::Unchanged
+            }
+        };
+        if !matches!(op, Op::Mov | Op::Mvn) {
+            self.read_into(RAX, rn);
+        }
+
+        match op {
+            Op::And | Op::Tst => self.asm.alu(Alu::And, RAX, RCX),
+            Op::Eor | Op::Teq => self.asm.alu(Alu::Xor, RAX, RCX),
+            Op::Orr => self.asm.alu(Alu::Or, RAX, RCX),
+            Op::Bic => {
+                self.asm.not(RCX);
+                self.asm.alu(Alu::And, RAX, RCX);
+            }
+            Op::Mov => self.asm.mov(RAX, RCX),
+            Op::Mvn => {
+                self.asm.mov(RAX, RCX);
+                self.asm.not(RAX);
+            }
+            Op::Add | Op::Cmn => self.asm.alu(Alu::Add, RAX, RCX),
+            Op::Adc => {
+                self.asm.bt_imm(CPSR, 29);
+                self.asm.alu(Alu::Adc, RAX, RCX);
+            }
+            Op::Sub | Op::Cmp => self.asm.alu(Alu::Sub, RAX, RCX),
+            Op::Rsb => {
+                self.asm.xchg(RAX, RCX);
+                self.asm.alu(Alu::Sub, RAX, RCX);
+            }
+            // x86 subtracts the borrow, the complement of ARM's carry
+            Op::Sbc | Op::Rsc => {
+                if op == Op::Rsc {
+                    self.asm.xchg(RAX, RCX);
+                }
+                self.asm.bt_imm(CPSR, 29);
+                self.asm.cmc();
+                self.asm.alu(Alu::Sbb, RAX, RCX);
+            }
+        }
+
+        if s {
+            if op.is_logical() {
+                self.set_flags(carry, false);
+            } else {
+                let borrow = matches!(op, Op::Sub | Op::Cmp | Op::Rsb | Op::Sbc | Op::Rsc);
+                self.asm.setcc(Cond::Below, borrow, RDX);
+                self.asm.setcc(Cond::Overflow, false, RCX);
+                self.set_flags(Carry::Dl, true);
+            }
+        }
+        if !op.is_test() {
+            self.write(rd, RAX);
+        }
+    }
+
+    /// MUL and MLA, see [`super::Cpu::multiply`]
+    fn multiply(&mut self, decoded: &Decoded) {
+        let Operands {
+            op,
+            s,
+            rd,
+            ra,
+            rm,
+            rn,
+            ..
+        } = decoded.operands;
+
+        self.read_into(RAX, rn);
+        let rm = self.read(rm, RCX);
+        self.asm.imul(RAX, rm);
+        // MLA
+        if op == 0b001 {
+            let ra = self.read(ra, RCX);
+            self.asm.alu(Alu::Add, RAX, ra);
+        }
+        if s {
+            self.set_flags(Carry::Unchanged, false);
+        }
+        self.write(rd, RAX);
+    }
+
+    /// MOVW and MOVT
+    fn move_imm16(&mut self, decoded: &Decoded) {
+        let Operands { op, rd, imm, .. } = decoded.operands;
+        if op == 1 {
+            self.read_into(RAX, rd);
+            self.asm.alu_imm(Alu::And, RAX, 0xffff);
+            self.asm.alu_imm(Alu::Or, RAX, imm << 16);
+        } else {
+            self.asm.mov_imm(RAX, imm);
+        }
+        self.write(rd, RAX);
+    }
+
+    /// See [`super::Cpu::load_store`]. The address is in rcx and the offset address in edx, the
+    /// access takes a side exit unless it lies in the arena and, for a store, outside the pages
+    /// holding decoded code.
+    fn load_store(&mut self, decoded: &Decoded) {
+        let Operands {
+            p,
+            u,
+            b,
+            w,
+            l,
+            rn,
+            rt,
+            ..
+        } = decoded.operands;
+        let size = if b { 1 } else { 4 };
+        let op = if u { Alu::Add } else { Alu::Sub };
+
+        let register = decoded.kind == InstructionKind::LoadStoreReg;
+        if register {
+            self.read_into(RCX, decoded.operands.rm);
+            self.shift_operand(decoded.operands.shift, decoded.operands.imm);
+        }
+        if rn == 15 {
+            self.asm.mov_imm(RAX, self.pc.wrapping_add(8) & !3);
+        } else {
+            self.read_into(RAX, rn);
+        }
+        self.asm.mov(RDX, RAX);
+        if register {
+            self.asm.alu(op, RDX, RCX);
+        } else {
+            self.asm.alu_imm(op, RDX, decoded.operands.imm);
+        }
+        self.asm.mov(RCX, if p { RDX } else { RAX });
+
+        self.asm.cmp64_mem(RCX, CONTEXT, LOW);
+        self.side_exit(Cond::Below);
+        self.asm.lea64(RAX, RCX, size);
+        self.asm.cmp64_mem(RAX, CONTEXT, LEN);
+        self.side_exit(Cond::Above);
+
+        if l {
+            self.asm.load64(R11, CONTEXT, BASE);
+            if b {
+                self.asm.load_byte_indexed(RAX, R11, RCX);
+            } else {
+                self.asm.load_indexed(RAX, R11, RCX);
+            }
+            if !p || w {
+                self.write(rn, RDX);
+            }
+            self.write(rt, RAX);
+        } else {
+            self.asm.load64(R11, CONTEXT, CODE_PAGES);
+            let page_shift = mem::CODE_PAGE_SIZE.trailing_zeros() as u8;
+            for byte in if b { &[0][..] } else { &[0, 3] } {
+                self.asm.lea64(RAX, RCX, *byte);
+                self.asm.shift(Shift::Shr, RAX, page_shift);
+                self.asm.bt_mem(R11, RAX);
+                self.side_exit(Cond::Below);
+            }
+
+            self.read_into(RAX, rt);
+            self.asm.load64(R11, CONTEXT, BASE);
+            if b {
+                self.asm.store_byte_indexed(R11, RCX, RAX);
+            } else {
+                self.asm.store_indexed(R11, RCX, RAX);
+            }
+            if !p || w {
+                self.write(rn, RDX);
+            }
+        }
+    }
+
+    /// B and BL, a branch to the start of the block loops without leaving the translation
+    fn branch(&mut self, decoded: &Decoded, start: u32, body: usize) {
+        let offset = decoder::sign_extend(decoded.operands.imm << 2, 26);
+        let target = self.pc.wrapping_add(8).wrapping_add(offset as u32);
+        if decoded.operands.l {
+            self.asm.mov_imm(RAX, self.pc.wrapping_add(4));
+            self.write(14, RAX);
+        }
+
+        if target == start {
+            self.asm.jmp_back(body);
+        } else {
+            self.exit(target, true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Context, Jit};
+    use crate::{
+        cpu::{alu, decoder::decode_word, features::Features},
+        mem::Mem,
+    };
+
+    /// Translate `words` at 0x1000 and run them on `regs` and `cpsr`, returning what the
+    /// translation returned
+    fn run(mem: &mut Mem, words: &[u32], regs: &mut [u32; 16], cpsr: &mut u32) -> u32 {
+        let instrs: Vec<_> = words.iter().map(|&word| decode_word(word)).collect();
+        let mut jit = Jit::new(1, Features::THUMB2);
+        let entry = jit
+            .compile(0x1000, &instrs)
+            .expect("block should translate");
+        let mut context = Context {
+            regs: regs.as_mut_ptr(),
+            cpsr,
+            arena: mem.arena(),
+        };
+        unsafe { entry(&mut context) }
+    }
+
+    #[test]
+    fn translations_compute_flags_like_the_interpreter() {
+        let mut mem = Mem::with_size(0x4000);
+        let mut regs = [0; 16];
+        regs[1] = 0x7fff_ffff;
+        regs[2] = 0x8000_0001;
+        let mut cpsr = 0x10;
+        // adds r0, r1, #1, subs r3, r2, r1, sbc r4, r2, #0 and svc #0, which isn't translated
+        let words = [0xe291_0001, 0xe052_3001, 0xe2c2_4000, 0xef00_0000];
+        assert_eq!(run(&mut mem, &words, &mut regs, &mut cpsr), 0);
+
+        assert_eq!(regs[0], 0x8000_0000);
+        assert_eq!(regs[3], 2);
+        // subs borrowed nothing, so C is set and sbc subtracts no extra 1
+        assert_eq!(regs[4], 0x8000_0001);
+        assert_eq!(cpsr, alu::C | alu::V | 0x10);
+        assert_eq!(regs[15], 0x100c);
+
+        let svc = [decode_word(0xef00_0000)];
+        assert!(
+            Jit::new(1, Features::THUMB2)
+                .compile(0x1000, &svc)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn branches_to_the_block_start_loop_in_the_translation() {
+        let mut mem = Mem::with_size(0x4000);
+        let mut regs = [0; 16];
+        regs[0] = 10;
+        let mut cpsr = 0x10;
+        // add r1, r1, r0, subs r0, r0, #1 and bne to the add
+        let words = [0xe081_1000, 0xe250_0001, 0x1aff_fffc];
+        assert_eq!(run(&mut mem, &words, &mut regs, &mut cpsr), 1);
+
+        assert_eq!((regs[0], regs[1], regs[15]), (0, 55, 0x100c));
+        assert_eq!(cpsr, alu::Z | alu::C | 0x10);
+    }
+
+    #[test]
+    fn translations_exit_before_accesses_they_cannot_check() {
+        let mut mem = Mem::with_size(0x4000);
+        mem.mark_code(0x1000, 4);
+        let mut regs = [0; 16];
+        let mut cpsr = 0x10;
+        // str r0, [r1] and ldr r2, [r3]
+        let words = [0xe581_0000, 0xe593_2000];
+
+        regs[0] = 0x1234;
+        (regs[1], regs[3]) = (0x2000, 0x2000);
+        assert_eq!(run(&mut mem, &words, &mut regs, &mut cpsr), 1);
+        assert_eq!((regs[2], regs[15]), (0x1234, 0x1008));
+
+        // the last byte of the load is out of bounds
+        (regs[0], regs[3]) = (5, 0x3ffd);
+        assert_eq!(run(&mut mem, &words, &mut regs, &mut cpsr), 0);
+        assert_eq!((regs[2], regs[15]), (0x1234, 0x1004));
+        assert_eq!(mem.read_u32(0x2000), Some(5));
+
+        // the null page, the store's first byte is in a page holding code
+        for addr in [0xffc, 0x1ffe] {
+            regs[1] = addr;
+            assert_eq!(run(&mut mem, &words, &mut regs, &mut cpsr), 0);
+            assert_eq!(regs[15], 0x1000);
+        }
+        assert!(!mem.code_written());
+        assert_eq!(mem.read_u32(0x1ffc), Some(0));
+    }
+}
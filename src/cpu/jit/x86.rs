@@ -0,0 +1,444 @@
+//! The x86-64 instructions the translator emits, see the Intel SDM volume 2. Registers are their
+//! 4 bit encodings, the 32-bit forms zero the upper half of the 64-bit register.
+
+pub const RAX: u8 = 0;
+pub const RCX: u8 = 1;
+pub const RDX: u8 = 2;
+pub const RBX: u8 = 3;
+pub const RBP: u8 = 5;
+pub const RSI: u8 = 6;
+pub const RDI: u8 = 7;
+pub const R8: u8 = 8;
+pub const R9: u8 = 9;
+pub const R10: u8 = 10;
+pub const R11: u8 = 11;
+pub const R12: u8 = 12;
+pub const R13: u8 = 13;
+pub const R14: u8 = 14;
+pub const R15: u8 = 15;
+
+/// Condition codes of `jcc` and `setcc`
+#[derive(Debug, Clone, Copy)]
+pub enum Cond {
+    Overflow = 0x0,
+    Below = 0x2,
+    Zero = 0x4,
+    Above = 0x7,
+}
+
+/// The two operand ALU instructions, their `op r/m32, r32` opcode and `/digit` of the `81`
+/// immediate form
+#[derive(Debug, Clone, Copy)]
+pub enum Alu {
+    Add = 0,
+    Or = 1,
+    Adc = 2,
+    Sbb = 3,
+    And = 4,
+    Sub = 5,
+    Xor = 6,
+}
+
+impl Alu {
+    fn digit(self) -> u8 {
+        self as u8
+    }
+
+    fn opcode(self) -> u8 {
+        (self as u8) << 3 | 1
+    }
+}
+
+/// The shifts and rotations of the `C1 /digit ib` form
+#[derive(Debug, Clone, Copy)]
+pub enum Shift {
+    Ror = 1,
+    /// rotates through CF
+    Rcr = 3,
+    Shl = 4,
+    Shr = 5,
+    Sar = 7,
+}
+
+/// A forward jump whose rel32 is patched by [`Assembler::bind`]
+#[derive(Debug, Clone, Copy)]
+pub struct Jump(usize);
+
+#[derive(Default)]
+pub struct Assembler {
+    pub code: Vec<u8>,
+}
+
+impl Assembler {
+    fn rex(&mut self, w: bool, reg: u8, index: u8, base: u8) {
+        let rex = 0x40 | (w as u8) << 3 | (reg >> 3) << 2 | (index >> 3) << 1 | base >> 3;
+        if rex != 0x40 {
+            self.code.push(rex);
+        }
+    }
+
+    fn modrm(&mut self, mode: u8, reg: u8, rm: u8) {
+        self.code.push(mode << 6 | (reg & 7) << 3 | rm & 7);
+    }
+
+    /// `op reg, rm` with both operands registers
+    fn op_rr(&mut self, w: bool, opcode: &[u8], reg: u8, rm: u8) {
+        self.rex(w, reg, 0, rm);
+        self.code.extend_from_slice(opcode);
+        self.modrm(0b11, reg, rm);
+    }
+
+    /// `op reg, [base + disp32]`, base must not need a SIB byte
+    fn op_rm(&mut self, w: bool, opcode: &[u8], reg: u8, base: u8, disp: i32) {
+        debug_assert!(base & 7 != 4, "rsp and r12 need a SIB byte");
+        self.rex(w, reg, 0, base);
+        self.code.extend_from_slice(opcode);
+        self.modrm(0b10, reg, base);
+        self.code.extend_from_slice(&disp.to_le_bytes());
+    }
+
+    /// `op reg, [base + index]`, base must not need a displacement
+    fn op_rsib(&mut self, opcode: &[u8], reg: u8, base: u8, index: u8) {
+        debug_assert!(base & 7 != 5, "rbp and r13 need a displacement");
+        self.rex(false, reg, index, base);
+        self.code.extend_from_slice(opcode);
+        self.modrm(0b00, reg, 0b100);
+        self.code.push((index & 7) << 3 | base & 7);
+    }
+
+    /// `mov dst, src`
+    pub fn mov(&mut self, dst: u8, src: u8) {
+        if dst != src {
+            self.op_rr(false, &[0x89], src, dst);
+        }
+    }
+
+    /// `mov dst, src` on the 64-bit registers
+    pub fn mov64(&mut self, dst: u8, src: u8) {
+        self.op_rr(true, &[0x89], src, dst);
+    }
+
+    /// `mov dst, imm32`
+    pub fn mov_imm(&mut self, dst: u8, imm: u32) {
+        self.rex(false, 0, 0, dst);
+        self.code.push(0xb8 | dst & 7);
+        self.code.extend_from_slice(&imm.to_le_bytes());
+    }
+
+    /// `mov dst, dword [base + disp]`
+    pub fn load(&mut self, dst: u8, base: u8, disp: i32) {
+        self.op_rm(false, &[0x8b], dst, base, disp);
+    }
+
+    /// `mov dst, qword [base + disp]`
+    pub fn load64(&mut self, dst: u8, base: u8, disp: i32) {
+        self.op_rm(true, &[0x8b], dst, base, disp);
+    }
+
+    /// `mov dword [base + disp], src`
+    pub fn store(&mut self, base: u8, disp: i32, src: u8) {
+        self.op_rm(false, &[0x89], src, base, disp);
+    }
+
+    /// `mov dword [base + disp], imm32`
+    pub fn store_imm(&mut self, base: u8, disp: i32, imm: u32) {
+        self.op_rm(false, &[0xc7], 0, base, disp);
+        self.code.extend_from_slice(&imm.to_le_bytes());
+    }
+
+    /// `mov dst, dword [base + index]`
+    pub fn load_indexed(&mut self, dst: u8, base: u8, index: u8) {
+        self.op_rsib(&[0x8b], dst, base, index);
+    }
+
+    /// `movzx dst, byte [base + index]`
+    pub fn load_byte_indexed(&mut self, dst: u8, base: u8, index: u8) {
+        self.op_rsib(&[0x0f, 0xb6], dst, base, index);
+    }
+
+    /// `mov dword [base + index], src`
+    pub fn store_indexed(&mut self, base: u8, index: u8, src: u8) {
+        self.op_rsib(&[0x89], src, base, index);
+    }
+
+    /// `mov byte [base + index], src`, src is al, cl, dl or bl
+    pub fn store_byte_indexed(&mut self, base: u8, index: u8, src: u8) {
+        debug_assert!(
+            src < 4,
+            "only the low byte of rax to rbx is addressable without REX"
+        );
+        self.op_rsib(&[0x88], src, base, index);
+    }
+
+    /// `lea dst, [base + disp]` on the 64-bit registers
+    pub fn lea64(&mut self, dst: u8, base: u8, disp: i32) {
+        self.op_rm(true, &[0x8d], dst, base, disp);
+    }
+
+    /// `cmp reg, qword [base + disp]`
+    pub fn cmp64_mem(&mut self, reg: u8, base: u8, disp: i32) {
+        self.op_rm(true, &[0x3b], reg, base, disp);
+    }
+
+    /// `op dst, src`
+    pub fn alu(&mut self, op: Alu, dst: u8, src: u8) {
+        self.op_rr(false, &[op.opcode()], src, dst);
+    }
+
+    /// `op dst, imm32`
+    pub fn alu_imm(&mut self, op: Alu, dst: u8, imm: u32) {
+        self.op_rr(false, &[0x81], op.digit(), dst);
+        self.code.extend_from_slice(&imm.to_le_bytes());
+    }
+
+    /// `test dst, src`
+    pub fn test(&mut self, dst: u8, src: u8) {
+        self.op_rr(false, &[0x85], src, dst);
+    }
+
+    /// `imul dst, src`, the low 32 bits are the same for signed and unsigned operands
+    pub fn imul(&mut self, dst: u8, src: u8) {
+        self.op_rr(false, &[0x0f, 0xaf], dst, src);
+    }
+
+    /// `not dst`
+    pub fn not(&mut self, dst: u8) {
+        self.op_rr(false, &[0xf7], 2, dst);
+    }
+
+    /// `xchg a, b`
+    pub fn xchg(&mut self, a: u8, b: u8) {
+        self.op_rr(false, &[0x87], a, b);
+    }
+
+    /// `op dst, amount`, CF is the last bit shifted out, for a rotation the new top bit
+    pub fn shift(&mut self, op: Shift, dst: u8, amount: u8) {
+        self.op_rr(false, &[0xc1], op as u8, dst);
+        self.code.push(amount);
+    }
+
+    /// `bt reg, bit`, copies the bit to CF
+    pub fn bt_imm(&mut self, reg: u8, bit: u8) {
+        self.op_rr(false, &[0x0f, 0xba], 4, reg);
+        self.code.push(bit);
+    }
+
+    /// `bt reg, index`, copies bit `index % 32` to CF
+    pub fn bt(&mut self, reg: u8, index: u8) {
+        self.op_rr(false, &[0x0f, 0xa3], index, reg);
+    }
+
+    /// `bt qword [base], index`, the bit string at base is as long as it needs to be
+    pub fn bt_mem(&mut self, base: u8, index: u8) {
+        debug_assert!(
+            base & 7 != 4 && base & 7 != 5,
+            "base needs a SIB byte or displacement"
+        );
+        self.rex(true, index, 0, base);
+        self.code.extend_from_slice(&[0x0f, 0xa3]);
+        self.modrm(0b00, index, base);
+    }
+
+    /// `cmc`, complements CF
+    pub fn cmc(&mut self) {
+        self.code.push(0xf5);
+    }
+
+    /// `setcc dst` or, with `negate`, the opposite condition. dst is al, cl, dl or bl.
+    pub fn setcc(&mut self, cond: Cond, negate: bool, dst: u8) {
+        debug_assert!(
+            dst < 4,
+            "only the low byte of rax to rbx is addressable without REX"
+        );
+        self.code
+            .extend_from_slice(&[0x0f, 0x90 | cond as u8 ^ negate as u8]);
+        self.modrm(0b11, 0, dst);
+    }
+
+    /// `movzx dst, src` from the low byte of al, cl, dl or bl
+    pub fn movzx_byte(&mut self, dst: u8, src: u8) {
+        debug_assert!(
+            src < 4,
+            "only the low byte of rax to rbx is addressable without REX"
+        );
+        self.op_rr(false, &[0x0f, 0xb6], dst, src);
+    }
+
+    /// `jcc rel32` or, with `negate`, the jump on the opposite condition
+    pub fn jcc(&mut self, cond: Cond, negate: bool) -> Jump {
+        self.code
+            .extend_from_slice(&[0x0f, 0x80 | cond as u8 ^ negate as u8]);
+        self.code.extend_from_slice(&[0; 4]);
+        Jump(self.code.len())
+    }
+
+    /// `jmp rel32`
+    pub fn jmp(&mut self) -> Jump {
+        self.code.push(0xe9);
+        self.code.extend_from_slice(&[0; 4]);
+        Jump(self.code.len())
+    }
+
+    /// `jmp rel32` back to `target`, an offset into the code
+    pub fn jmp_back(&mut self, target: usize) {
+        let rel = target as i64 - (self.code.len() + 5) as i64;
+        self.code.push(0xe9);
+        self.code.extend_from_slice(&(rel as i32).to_le_bytes());
+    }
+
+    /// Point `jump` at the next instruction emitted
+    pub fn bind(&mut self, jump: Jump) {
+        let rel = (self.code.len() - jump.0) as i32;
+        self.code[jump.0 - 4..jump.0].copy_from_slice(&rel.to_le_bytes());
+    }
+
+    pub fn push(&mut self, reg: u8) {
+        self.rex(false, 0, 0, reg);
+        self.code.push(0x50 | reg & 7);
+    }
+
+    pub fn pop(&mut self, reg: u8) {
+        self.rex(false, 0, 0, reg);
+        self.code.push(0x58 | reg & 7);
+    }
+
+    pub fn ret(&mut self) {
+        self.code.push(0xc3);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emit(f: impl FnOnce(&mut Assembler)) -> Vec<u8> {
+        let mut asm = Assembler::default();
+        f(&mut asm);
+        asm.code
+    }
+
+    #[test]
+    fn encodes_moves_and_memory_operands() {
+        // mov esi, eax and mov r9d, r14d
+        assert_eq!(emit(|a| a.mov(RSI, RAX)), [0x89, 0xc6]);
+        assert_eq!(emit(|a| a.mov(R9, R14)), [0x45, 0x89, 0xf1]);
+        // mov rbp, rdi
+        assert_eq!(emit(|a| a.mov64(RBP, RDI)), [0x48, 0x89, 0xfd]);
+        // mov r12d, 0x12345678
+        assert_eq!(
+            emit(|a| a.mov_imm(R12, 0x1234_5678)),
+            [0x41, 0xbc, 0x78, 0x56, 0x34, 0x12]
+        );
+        // mov r8d, [rbx + 0x10] and mov [rbx + 0x3c], r15d
+        assert_eq!(
+            emit(|a| a.load(R8, RBX, 0x10)),
+            [0x44, 0x8b, 0x83, 0x10, 0, 0, 0]
+        );
+        assert_eq!(
+            emit(|a| a.store(RBX, 0x3c, R15)),
+            [0x44, 0x89, 0xbb, 0x3c, 0, 0, 0]
+        );
+        // mov rax, [rbp + 8] and mov dword [rbx + 0x3c], 0x8000
+        assert_eq!(
+            emit(|a| a.load64(RAX, RBP, 8)),
+            [0x48, 0x8b, 0x85, 8, 0, 0, 0]
+        );
+        assert_eq!(
+            emit(|a| a.store_imm(RBX, 0x3c, 0x8000)),
+            [0xc7, 0x83, 0x3c, 0, 0, 0, 0, 0x80, 0, 0]
+        );
+        // mov eax, [r11 + rcx], movzx eax, byte [r11 + rcx] and mov [r11 + rcx], al
+        assert_eq!(
+            emit(|a| a.load_indexed(RAX, R11, RCX)),
+            [0x41, 0x8b, 0x04, 0x0b]
+        );
+        assert_eq!(
+            emit(|a| a.load_byte_indexed(RAX, R11, RCX)),
+            [0x41, 0x0f, 0xb6, 0x04, 0x0b]
+        );
+        assert_eq!(
+            emit(|a| a.store_byte_indexed(R11, RCX, RAX)),
+            [0x41, 0x88, 0x04, 0x0b]
+        );
+        // lea rax, [rcx + 4] and cmp rax, [rbp + 0x18]
+        assert_eq!(
+            emit(|a| a.lea64(RAX, RCX, 4)),
+            [0x48, 0x8d, 0x81, 4, 0, 0, 0]
+        );
+        assert_eq!(
+            emit(|a| a.cmp64_mem(RAX, RBP, 0x18)),
+            [0x48, 0x3b, 0x85, 0x18, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn encodes_arithmetic_and_flags() {
+        // add eax, ecx, sbb eax, r10d and and r15d, 0x0fffffff
+        assert_eq!(emit(|a| a.alu(Alu::Add, RAX, RCX)), [0x01, 0xc8]);
+        assert_eq!(emit(|a| a.alu(Alu::Sbb, RAX, R10)), [0x44, 0x19, 0xd0]);
+        assert_eq!(
+            emit(|a| a.alu_imm(Alu::And, R15, 0x0fff_ffff)),
+            [0x41, 0x81, 0xe7, 0xff, 0xff, 0xff, 0x0f]
+        );
+        // imul eax, ecx, not ecx, xchg ecx, eax and test eax, eax
+        assert_eq!(emit(|a| a.imul(RAX, RCX)), [0x0f, 0xaf, 0xc1]);
+        assert_eq!(emit(|a| a.not(RCX)), [0xf7, 0xd1]);
+        assert_eq!(emit(|a| a.xchg(RAX, RCX)), [0x87, 0xc1]);
+        assert_eq!(emit(|a| a.test(RAX, RAX)), [0x85, 0xc0]);
+        // shl ecx, 3, ror ecx, 8 and sar esi, 31
+        assert_eq!(emit(|a| a.shift(Shift::Shl, RCX, 3)), [0xc1, 0xe1, 3]);
+        assert_eq!(emit(|a| a.shift(Shift::Ror, RCX, 8)), [0xc1, 0xc9, 8]);
+        assert_eq!(emit(|a| a.shift(Shift::Sar, RSI, 31)), [0xc1, 0xfe, 31]);
+        // rcr ecx, 1
+        assert_eq!(emit(|a| a.shift(Shift::Rcr, RCX, 1)), [0xc1, 0xd9, 1]);
+        // bt r15d, 29, bt ecx, eax and bt [r11], rax
+        assert_eq!(emit(|a| a.bt_imm(R15, 29)), [0x41, 0x0f, 0xba, 0xe7, 29]);
+        assert_eq!(emit(|a| a.bt(RCX, RAX)), [0x0f, 0xa3, 0xc1]);
+        assert_eq!(emit(|a| a.bt_mem(R11, RAX)), [0x49, 0x0f, 0xa3, 0x03]);
+        // setc dl, setnc dl, seto cl, setz cl and movzx edx, dl
+        assert_eq!(
+            emit(|a| a.setcc(Cond::Below, false, RDX)),
+            [0x0f, 0x92, 0xc2]
+        );
+        assert_eq!(
+            emit(|a| a.setcc(Cond::Below, true, RDX)),
+            [0x0f, 0x93, 0xc2]
+        );
+        assert_eq!(
+            emit(|a| a.setcc(Cond::Overflow, false, RCX)),
+            [0x0f, 0x90, 0xc1]
+        );
+        assert_eq!(
+            emit(|a| a.setcc(Cond::Zero, false, RCX)),
+            [0x0f, 0x94, 0xc1]
+        );
+        assert_eq!(emit(|a| a.movzx_byte(RDX, RDX)), [0x0f, 0xb6, 0xd2]);
+    }
+
+    #[test]
+    fn encodes_control_flow() {
+        // push r12, pop rbx and ret
+        assert_eq!(emit(|a| a.push(R12)), [0x41, 0x54]);
+        assert_eq!(emit(|a| a.pop(RBX)), [0x5b]);
+        assert_eq!(emit(|a| a.ret()), [0xc3]);
+
+        // jnc over a cmc, then jmp to the next instruction, ja to it and jmp back to the cmc
+        let code = emit(|a| {
+            let skip = a.jcc(Cond::Below, true);
+            a.cmc();
+            a.bind(skip);
+            let next = a.jmp();
+            a.bind(next);
+            let above = a.jcc(Cond::Above, false);
+            a.bind(above);
+            a.jmp_back(6);
+        });
+        assert_eq!(
+            code,
+            [
+                0x0f, 0x83, 1, 0, 0, 0, 0xf5, 0xe9, 0, 0, 0, 0, 0x0f, 0x87, 0, 0, 0, 0, 0xe9, 0xef,
+                0xff, 0xff, 0xff
+            ]
+        );
+    }
+}
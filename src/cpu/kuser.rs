@@ -0,0 +1,106 @@
+//! The kernel user helpers Linux places at the end of the high vector page, see the kernel's
+//! Documentation/arch/arm/kernel_user_helpers.rst. Older libcs and runtimes call them with `blx`
+//! instead of using the instructions directly. Instead of placing the kernel's code in the page,
+//! their entry points are intercepted and executed here, every other word of the page is an
+//! undefined instruction, like the kernel poisons it.
+
+use crate::{
+    cpu::{Cpu, alu},
+    err,
+    mem::{self, VECTORS, VECTORS_SIZE},
+};
+
+/// `__kuser_cmpxchg64`, since version 5
+const CMPXCHG64: u32 = 0xffff_0f60;
+/// `__kuser_memory_barrier`, since version 3
+const MEMORY_BARRIER: u32 = 0xffff_0fa0;
+/// `__kuser_cmpxchg`, since version 2
+const CMPXCHG: u32 = 0xffff_0fc0;
+/// `__kuser_get_tls`, since version 1
+const GET_TLS: u32 = 0xffff_0fe0;
+/// `__kuser_helper_version`, the number of 32 byte helper slots
+const HELPER_VERSION: u32 = 0xffff_0ffc;
+const VERSION: u32 = (VECTORS + VECTORS_SIZE as u32 - CMPXCHG64) / 32;
+
+/// `udf #0xdef1`, the kernel fills unused space in the vector page with it
+const POISON: u32 = 0xe7fd_def1;
+
+/// Map the vector page with the helper version word into `mem`.
+pub fn map(mem: &mut mem::Mem) {
+    let mut page = [0; VECTORS_SIZE];
+    for word in page.chunks_exact_mut(4) {
+        word.copy_from_slice(&POISON.to_le_bytes());
+    }
+    let version = (HELPER_VERSION - VECTORS) as usize;
+    page[version..version + 4].copy_from_slice(&VERSION.to_le_bytes());
+    mem.map_vectors(page);
+}
+
+impl<'cpu, const PRINT_INSTR: bool> Cpu<'cpu, PRINT_INSTR> {
+    /// Run the helper pc points to and return to lr like its `bx lr` would. Returns false if pc
+    /// isn't the entry point of a helper, which is then fetched as usual.
+    pub(super) fn kuser_helper(&mut self) -> Result<bool, err::Err> {
+        let pc = self.r[15];
+        if !self.mem.is_vectors(pc) {
+            return Ok(false);
+        }
+
+        let fault = |guest| err::Err::MemoryAccessViolation { guest, instr: pc };
+        match pc {
+            // r0 = pointer to oldval, r1 = pointer to newval, r2 = pointer to the target
+            CMPXCHG64 => {
+                let old = self.mem.read_u64(self.r[0]).ok_or(fault(self.r[0]))?;
+                let new = self.mem.read_u64(self.r[1]).ok_or(fault(self.r[1]))?;
+                let current = self.mem.read_u64(self.r[2]).ok_or(fault(self.r[2]))?;
+                // the kernel compares the low words first, r3 holds the first that differs
+                let diff = current ^ old;
+                let r3 = if diff as u32 != 0 {
+                    diff as u32
+                } else {
+                    (diff >> 32) as u32
+                };
+                if r3 == 0 {
+                    self.monitor.store(self.r[2], 8);
+                    self.mem
+                        .write_u64(self.r[2], new)
+                        .map_err(|_| fault(self.r[2]))?;
+                }
+                self.kuser_return(r3);
+            }
+            // a single guest thread observes its own accesses in program order
+            MEMORY_BARRIER => self.bx_write_pc(self.r[14]),
+            // r0 = oldval, r1 = newval, r2 = pointer to the target
+            CMPXCHG => {
+                let current = self.mem.read_u32(self.r[2]).ok_or(fault(self.r[2]))?;
+                let r3 = current.wrapping_sub(self.r[0]);
+                if r3 == 0 {
+                    self.monitor.store(self.r[2], 4);
+                    self.mem
+                        .write_u32(self.r[2], self.r[1])
+                        .map_err(|_| fault(self.r[2]))?;
+                }
+                self.kuser_return(r3);
+            }
+            GET_TLS => {
+                self.r[0] = self.cp15.tpidruro;
+                self.bx_write_pc(self.r[14]);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// The common tail of the compare and exchange helpers, `rsbs r0, r3, #0` followed by `bx
+    /// lr`: r0 is zero and C set if the target was written. The ldrex/strex pairs of the kernel
+    /// leave the exclusive monitor open.
+    fn kuser_return(&mut self, r3: u32) {
+        let (r0, carry, overflow) = alu::add_with_carry(0, !r3, true);
+        self.r[0] = r0;
+        self.r[3] = r3;
+        self.set_nz(r0);
+        self.set_flag(alu::C, carry);
+        self.set_flag(alu::V, overflow);
+        self.monitor.clear();
+        self.bx_write_pc(self.r[14]);
+    }
+}
@@ -0,0 +1,144 @@
+//! The ARMv6 and ARMv7 media and bitfield instructions, see ARMv7 ARM A5.4 "Media instructions"
+//! and A5.2.12 "Miscellaneous instructions" for CLZ. Compilers emit these for constants
+//! (MOVW/MOVT), narrowing casts, byte swaps and bitfield accesses. The Thumb-2 encodings are
+//! executed through the same ARM control words and read their decoded [`Operands`].
+
+use crate::{
+    cpu::{
+        Cpu, alu,
+        decoder::{self, Decoded, Operands},
+    },
+    err,
+};
+
+impl<'cpu, const PRINT_INSTR: bool> Cpu<'cpu, PRINT_INSTR> {
+    /// MOVW writes the zero extended imm16, MOVT (bit 22) replaces only the top halfword.
+    pub(super) fn move_imm16(&mut self, decoded: &Decoded) {
+        let Operands { op, rd, imm, .. } = decoded.operands;
+        self.r[rd] = if op == 1 {
+            (self.r[rd] & 0xffff) | (imm << 16)
+        } else {
+            imm
+        };
+    }
+
+    pub(super) fn clz(&mut self, decoded: &Decoded) {
+        let Operands { rd, rm, .. } = decoded.operands;
+        self.r[rd] = self.r[rm].leading_zeros();
+    }
+
+    /// Sign or zero extend a byte, a halfword or both bytes at bits 7..0 and 23..16 of Rm rotated
+    /// right by 8 times bits 11..10, adding Rn unless it is pc. Bits 22..20 select the width and
+    /// bit 22 the zero extension.
+    pub(super) fn extend(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            op,
+            rn,
+            rd,
+            imm,
+            rm,
+            ..
+        } = decoded.operands;
+        let rotated = self.r[rm].rotate_right(imm * 8);
+        let base = if rn == 15 { 0 } else { self.r[rn] };
+
+        self.r[rd] = match op {
+            // SXTAB16 and UXTAB16 add each halfword separately
+            op @ (0b000 | 0b100) => {
+                let extend = |byte: u32| {
+                    if op == 0b000 {
+                        byte as i8 as u16
+                    } else {
+                        byte as u8 as u16
+                    }
+                };
+                let low = (base as u16).wrapping_add(extend(rotated));
+                let high = ((base >> 16) as u16).wrapping_add(extend(rotated >> 16));
+                ((high as u32) << 16) | low as u32
+            }
+            0b010 => base.wrapping_add(rotated as i8 as u32),
+            0b011 => base.wrapping_add(rotated as i16 as u32),
+            0b110 => base.wrapping_add(rotated as u8 as u32),
+            0b111 => base.wrapping_add(rotated as u16 as u32),
+            _ => return self.undefined(decoded.raw),
+        };
+
+        Ok(())
+    }
+
+    /// REV, REV16, RBIT and REVSH, selected by bits 22 and 7.
+    pub(super) fn reverse(&mut self, decoded: &Decoded) {
+        let Operands { op, rd, rm, .. } = decoded.operands;
+        let rm = self.r[rm];
+        self.r[rd] = match op {
+            0b00 => rm.swap_bytes(),
+            0b01 => ((rm & 0x00ff_00ff) << 8) | ((rm >> 8) & 0x00ff_00ff),
+            0b10 => rm.reverse_bits(),
+            _ => (rm as u16).swap_bytes() as i16 as u32,
+        };
+    }
+
+    /// SSAT saturates to the signed range of bits 20..16 + 1 bits, USAT (bit 22) to the unsigned
+    /// range of bits 20..16 bits. Rn is shifted first, LSL or ASR (bit 6) by bits 11..7. Sets Q
+    /// if the value was clamped.
+    pub(super) fn saturate(&mut self, decoded: &Decoded) {
+        let Operands {
+            u,
+            rd,
+            imm,
+            shift,
+            rn,
+            ..
+        } = decoded.operands;
+        let saturate_to = imm >> 5;
+        let (shift, amount) = alu::decode_imm_shift(shift, imm & 0x1f);
+        let (operand, _) = alu::shift_c(self.r[rn], shift, amount, false);
+
+        let (result, saturated) = if u {
+            alu::unsigned_sat_q(operand as i32 as i64, saturate_to)
+        } else {
+            alu::signed_sat_q(operand as i32 as i64, saturate_to + 1)
+        };
+        if saturated {
+            self.set_flag(alu::Q, true);
+        }
+        self.r[rd] = result;
+    }
+
+    /// SBFX and UBFX extract bits 20..16 + 1 bits of Rn starting at bits 11..7, BFI inserts the
+    /// low bits of Rn into Rd between the lsb and the msb in bits 20..16, BFC (Rn = pc) clears
+    /// them. Fields running past bit 31 are UNPREDICTABLE.
+    pub(super) fn bitfield(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            op, rd, imm, rn, ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+        let field = imm >> 5;
+        let lsb = imm & 0x1f;
+
+        match op {
+            // BFI and BFC, field is the msb
+            0b10 => {
+                if field < lsb {
+                    return Err(err::Err::UnpredictableInstruction(raw));
+                }
+                let mask = (u32::MAX >> (31 - (field - lsb))) << lsb;
+                let source = if rn == 15 { 0 } else { self.r[rn] << lsb };
+                self.r[rd] = (self.r[rd] & !mask) | (source & mask);
+            }
+            // SBFX and UBFX, field is the width - 1
+            op => {
+                if lsb + field > 31 {
+                    return Err(err::Err::UnpredictableInstruction(raw));
+                }
+                self.r[rd] = if op == 0b01 {
+                    decoder::sign_extend_field(self.r[rn], lsb, field + 1) as u32
+                } else {
+                    decoder::zero_extend_field(self.r[rn], lsb, field + 1)
+                };
+            }
+        }
+
+        Ok(())
+    }
+}
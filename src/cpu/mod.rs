@@ -1,38 +1,105 @@
 use crate::{
-    config::{self, Log, SyscallMode},
+    config::{self, Engine, Log, SyscallMode},
     cpu::{
-        decoder::{Decoded, InstructionKind},
-        translation::ArmSyscall,
+        decoder::{Decoded, InstructionKind, Op, Operands},
+        translation::{ARM_NR_BASE, ArmSyscall},
     },
     err, mem, stinkln, sys,
 };
 
+/// shifter and condition flag arithmetic
+mod alu;
+/// the initial process stack and auxiliary vector
+mod auxv;
+/// the cache of decoded basic blocks
+mod blocks;
+/// the user-visible CP15 registers
+mod cp15;
+/// the ARMv8 AES, SHA and CRC32 instructions
+mod crypto;
 /// decoding ARM instructions
 mod decoder;
+/// the local exclusive monitor for LDREX/STREX
+mod exclusive;
+/// optional architecture extensions
+pub mod features;
+/// IEEE 754 arithmetic for the floating-point instructions
+mod fpu;
+/// compiling hot blocks to x86-64
+mod jit;
+/// the kernel user helpers in the high vector page
+mod kuser;
+/// media and bitfield instructions
+mod media;
+/// processor modes, banked registers and exceptions
+mod mode;
+/// multiplies and hardware divide
+mod multiply;
+/// Advanced SIMD (NEON) instructions on the VFP register file
+mod neon;
+/// parallel additions and subtractions on the core registers and saturating arithmetic
+mod parallel;
 /// sandboxing the emulator
 mod sandbox;
+/// semihosting calls of bare-metal images
+mod semihosting;
+/// executing Thumb and Thumb-2 instructions
+mod thumb;
 /// translating various things from arm to x86
 mod translation;
+/// the VFPv3 register file and instructions
+mod vfp;
+
+/// Thumb execution state bit of the CPSR
+const CPSR_T: u32 = 1 << 5;
+
+/// the `instr` of a [`err::Err::MemoryAccessViolation`] raised by fetching an instruction
+const FETCH_FAULT: u32 = 0xDEAD_AFFE;
 
 type SyscallHandlerFn<'cpu, const PRINT_INSTR: bool> =
     fn(&mut Cpu<'cpu, PRINT_INSTR>, ArmSyscall) -> i32;
 
-/// Usermode emulation
+/// Usermode and bare-metal emulation
 pub struct Cpu<'cpu, const PRINT_INSTR: bool> {
-    /// r0-r15 (r13=SP, r14=LR, r15=PC)
+    /// r0-r15 (r13=SP, r14=LR, r15=PC) of the current mode
     pub r: [u32; 16],
     pub cpsr: u32,
+    /// the registers of the other modes
+    banks: mode::Banks,
+    /// D0-D31, S0-S31 are the halves of D0-D15
+    pub d: [u64; 32],
+    /// floating-point status and control register
+    pub fpscr: u32,
     pub mem: &'cpu mut mem::Mem,
+    /// the emulated processor, named by `uname` and AT_PLATFORM
+    pub model: config::CpuModel,
+    /// extensions gating which instructions are defined
+    pub features: features::Features,
+    /// thread ID registers and counters of the system control coprocessor
+    pub cp15: cp15::Cp15,
+    /// the files the emulator synthesizes for the guest, like /proc/cpuinfo
+    pub files: sys::Files,
+    monitor: exclusive::Monitor,
+    /// decoded ARM code, see [`Cpu::step_block`]
+    blocks: blocks::Blocks,
+    /// compiles blocks unless only the interpreter runs, see [`Cpu::step_block`]
+    jit: Option<jit::Jit>,
     syscall_handler: SyscallHandlerFn<'cpu, PRINT_INSTR>,
+    /// take exceptions through the vector table instead of raising signals and forwarding
+    /// syscalls
+    bare_metal: bool,
+    /// fault on UNPREDICTABLE encodings instead of executing them
+    strict: bool,
+    /// the exception the current instruction raised, taken once it finished
+    pending: Option<mode::Exception>,
     /// only set by ArmSyscall::Exit, necessary to propagate exit code to the host
     pub status: Option<i32>,
 }
 
 fn print_i32_or_errno(r: i32) -> i32 {
-    if r < 0 {
-        println!("={:?}", sys::Errno::from(r));
-    } else {
-        println!("={}", r);
+    match sys::Errno::try_from(r) {
+        Ok(errno) => println!("={:?}", errno),
+        Err(r) => println!("={}", r),
     }
 
     r
@@ -66,30 +133,98 @@ impl<'cpu, const PRINT_INSTR: bool> Cpu<'cpu, PRINT_INSTR> {
             }
         };
 
+        let features = features::Features::of(conf.cpu);
+        let top = mem.stack_top();
+        // a bare-metal image sets up its own stacks, sp merely starts out valid
+        let sp = if conf.bare_metal {
+            mem.map_writable_vectors();
+            top
+        } else {
+            auxv::initial_stack(
+                mem,
+                top,
+                conf.target.as_os_str().as_encoded_bytes(),
+                conf.cpu,
+            )
+            .expect("failed to set up the initial stack")
+        };
+        if conf.kuser_helpers {
+            kuser::map(mem);
+        }
         let mut s = Self {
             r: [0; 16],
-            cpsr: 0x60000010,
+            cpsr: if conf.bare_metal {
+                mode::RESET_CPSR
+            } else {
+                mode::USER_CPSR
+            },
+            banks: mode::Banks::default(),
+            d: [0; 32],
+            fpscr: 0,
             mem,
+            model: conf.cpu,
+            features,
+            cp15: cp15::Cp15::new(conf.counters),
+            files: sys::Files::default(),
+            monitor: exclusive::Monitor::default(),
+            blocks: blocks::Blocks::default(),
+            jit: match conf.engine {
+                Engine::Interpreter => None,
+                Engine::Jit => Some(jit::Jit::new(1, features)),
+                Engine::Both => Some(jit::Jit::new(jit::HOT_RUNS, features)),
+            },
             syscall_handler,
+            bare_metal: conf.bare_metal,
+            strict: conf.strict,
+            pending: None,
             status: None,
         };
-        s.r[15] = pc;
+        if conf.high_vectors {
+            s.cp15.sctlr |= cp15::SCTLR_V;
+        }
+        s.r[13] = sp;
+        // entry points with bit 0 set are Thumb code
+        s.bx_write_pc(pc);
         s
     }
 
     pub fn reset(&mut self) {
         self.r = [0; 16];
-        self.cpsr = 0x60000010;
+        self.cpsr = if self.bare_metal {
+            mode::RESET_CPSR
+        } else {
+            mode::USER_CPSR
+        };
+        self.banks = mode::Banks::default();
+        self.pending = None;
+        self.d = [0; 32];
+        self.fpscr = 0;
+        self.cp15 = cp15::Cp15::new(self.cp15.counters);
     }
 
     #[inline(always)]
     pub fn instr_addr(&self) -> u32 {
-        self.r[15] & !3
+        if self.thumb() {
+            self.r[15] & !1
+        } else {
+            self.r[15] & !3
+        }
+    }
+
+    /// whether the CPSR T bit selects the Thumb instruction set
+    #[inline(always)]
+    pub fn thumb(&self) -> bool {
+        self.cpsr & CPSR_T != 0
     }
 
+    /// value of pc as an operand: the current instruction + 8 in ARM and + 4 in Thumb state
     #[inline(always)]
-    fn arm_pc(&self) -> u32 {
-        self.instr_addr().wrapping_add(8)
+    fn read_pc(&self) -> u32 {
+        if self.thumb() {
+            self.instr_addr().wrapping_add(4)
+        } else {
+            self.instr_addr().wrapping_add(8)
+        }
     }
 
     /// moves pc forward a word
@@ -98,35 +233,222 @@ impl<'cpu, const PRINT_INSTR: bool> Cpu<'cpu, PRINT_INSTR> {
         self.r[15] = self.r[15].wrapping_add(4);
     }
 
+    /// read a register as an operand, r15 reads as [`Cpu::read_pc`]
+    #[inline(always)]
+    fn reg(&self, n: usize) -> u32 {
+        if n == 15 { self.read_pc() } else { self.r[n] }
+    }
+
+    /// BranchWritePC: branch to `addr` without changing the instruction set, forcing word
+    /// alignment in ARM and halfword alignment in Thumb state
+    #[inline(always)]
+    fn write_pc(&mut self, addr: u32) {
+        if self.thumb() {
+            self.r[15] = addr & !1;
+        } else {
+            self.r[15] = addr & !3;
+        }
+    }
+
+    /// BXWritePC: branch to `addr` and switch to Thumb if bit 0 is set, to ARM otherwise. Loads
+    /// that write pc interwork the same way on ARMv7, as does data-processing in ARM state.
+    #[inline(always)]
+    fn bx_write_pc(&mut self, addr: u32) {
+        if addr & 1 == 1 {
+            self.cpsr |= CPSR_T;
+            self.r[15] = addr & !1;
+        } else {
+            self.cpsr &= !CPSR_T;
+            self.r[15] = addr & !3;
+        }
+    }
+
+    #[inline(always)]
+    fn flag(&self, flag: u32) -> bool {
+        self.cpsr & flag != 0
+    }
+
+    #[inline(always)]
+    fn set_flag(&mut self, flag: u32, value: bool) {
+        if value {
+            self.cpsr |= flag;
+        } else {
+            self.cpsr &= !flag;
+        }
+    }
+
+    /// set N and Z from `result`
+    #[inline(always)]
+    fn set_nz(&mut self, result: u32) {
+        self.set_flag(alu::N, result >> 31 == 1);
+        self.set_flag(alu::Z, result == 0);
+    }
+
     #[inline(always)]
     /// see [ARMv7 Condition code suffixes](https://support.arm.com/documentation/den0042/0100/Unified-Assembly-Language-Instructions/Instruction-set-basics/Conditional-execution?lang=en#md260-conditional-execution__tbl_cond_code_suffixes)
     fn cond_passes(&self, cond: u8) -> bool {
-        match cond {
-            0x0 => (self.cpsr >> 30) & 1 == 1, // EQ: Z == 1
-            0x1 => (self.cpsr >> 30) & 1 == 0, // NE
-            0xE => true,                       // AL (always)
-            0xF => false,                      // NV (never)
-            _ => false,                        // strict false
-        }
+        alu::condition_passed(cond, self.cpsr)
     }
 
     /// fetch-decode-execute step, will only return false on exit svc
     pub fn step(&mut self) -> Result<bool, err::Err> {
+        if self.bare_metal {
+            return self.step_bare_metal();
+        }
+
+        if self.r[15] >= mem::VECTORS && self.kuser_helper()? {
+            return Ok(true);
+        }
+
+        self.step_instruction()
+    }
+
+    /// Execute the basic block at pc from its cached decoded instructions, decoding it first if it
+    /// isn't cached yet. Blocks end before `end`. Like [`Cpu::step`], but it returns after a
+    /// branch, a write to a cached block or once the guest exits.
+    ///
+    /// Only ARM code in the arena of a Linux process is cached, everything else is stepped. With a
+    /// JIT, the block runs as its translation once it ran often enough, the interpreter continues
+    /// after a side exit.
+    pub fn step_block(&mut self, end: u32) -> Result<bool, err::Err> {
+        let start = self.r[15];
+        if self.bare_metal || self.thumb() || start >= mem::VECTORS {
+            return self.step();
+        }
+        // stepped instructions may have written to cached blocks, too
+        self.invalidate_written_blocks();
+        let Some(block) = self
+            .blocks
+            .get(start)
+            .or_else(|| self.blocks.build(self.mem, start, end))
+        else {
+            return self.step();
+        };
+
+        let mut next = start;
+        let mut instrs = &block.instrs[..];
+        if let Some(entry) = self.translation(&block, start) {
+            let mut context = jit::Context {
+                regs: self.r.as_mut_ptr(),
+                cpsr: &mut self.cpsr,
+                arena: self.mem.arena(),
+            };
+            // SAFETY: the context points at the live registers and arena, the translation only
+            // accesses the arena within its bounds
+            if unsafe { entry(&mut context) } != 0 {
+                return Ok(true);
+            }
+            next = self.r[15];
+            instrs = &instrs[((next - start) / 4) as usize..];
+        }
+
+        for &decoded in instrs {
+            next += 4;
+            let running = self.execute(decoded)?;
+            if self.invalidate_written_blocks()
+                || !running
+                || self.status.is_some()
+                || self.r[15] != next
+                || self.thumb()
+            {
+                return Ok(running);
+            }
+        }
+        Ok(true)
+    }
+
+    /// The translation of `block` at `start`, compiling it once it ran [`jit::Jit::threshold`]
+    /// times. Translations neither log instructions nor clear an exclusive tag when they store.
+    fn translation(&mut self, block: &blocks::Block, start: u32) -> Option<jit::Entry> {
+        if PRINT_INSTR || !self.monitor.is_open() {
+            return None;
+        }
+        let jit = self.jit.as_mut()?;
+        if let Some(&entry) = block.compiled.get() {
+            return entry;
+        }
+
+        let runs = block.runs.get() + 1;
+        block.runs.set(runs);
+        if runs < jit.threshold {
+            return None;
+        }
+        *block
+            .compiled
+            .get_or_init(|| jit.compile(start, &block.instrs))
+    }
+
+    /// Drop the cached blocks overlapping `start..end`
+    fn invalidate_blocks(&mut self, start: u32, end: u32) {
+        self.blocks.invalidate(self.mem, start, end);
+    }
+
+    /// Drop the cached blocks written to since the last call, returns whether there were any
+    /// writes to code pages
+    #[inline(always)]
+    fn invalidate_written_blocks(&mut self) -> bool {
+        if !self.mem.code_written() {
+            return false;
+        }
+        for (start, end) in self.mem.take_code_writes() {
+            self.invalidate_blocks(start, end);
+        }
+        true
+    }
+
+    /// execute the instruction at pc in the current instruction set
+    fn step_instruction(&mut self) -> Result<bool, err::Err> {
+        if self.thumb() {
+            return self.step_thumb();
+        }
+
         let Some(word) = self.mem.read_u32(self.instr_addr()) else {
             return Err(err::Err::MemoryAccessViolation {
                 guest: self.instr_addr(),
-                instr: 0xDEADAFFE,
+                instr: FETCH_FAULT,
             });
         };
 
-        let Decoded { kind, cond, raw } = decoder::decode_word(word);
+        self.execute(decoder::decode_word(word))
+    }
+
+    /// execute the ARM instruction at pc, already fetched and decoded
+    fn execute(&mut self, decoded: Decoded) -> Result<bool, err::Err> {
+        let Decoded {
+            kind,
+            cond,
+            raw,
+            operands,
+            ..
+        } = decoded;
 
         if PRINT_INSTR {
             stinkln!("{:?} {:04b} {:X}", kind, cond, raw);
         }
 
-        // we dont execute this instruction, moving along
-        if !self.cond_passes(cond) {
+        // checked before the condition, so --strict catches them on every path
+        if let Some(rule) = decoded.unpredictable {
+            if self.strict {
+                stinkln!(
+                    "UNPREDICTABLE {:?} at {:#x} {}: {:#x}",
+                    kind,
+                    self.instr_addr(),
+                    rule,
+                    raw
+                );
+                return Err(err::Err::UnpredictableInstruction(raw));
+            }
+            if rule.is_nop() {
+                self.advance();
+                return Ok(true);
+            }
+        }
+
+        // we dont execute this instruction, moving along. cond = 1111 is the unconditional space
+        // with its own encodings, UDF and BKPT trap whatever the condition says.
+        let unconditional =
+            cond == 0b1111 || matches!(kind, InstructionKind::Undefined | InstructionKind::Bkpt);
+        if !unconditional && !self.cond_passes(cond) {
             self.advance();
             return Ok(true);
         }
@@ -136,46 +458,144 @@ impl<'cpu, const PRINT_INSTR: bool> Cpu<'cpu, PRINT_INSTR> {
         let mut pc_changed = false;
 
         match kind {
-            InstructionKind::MovImm => {
-                let rd = decoder::bits(raw, 15, 12) as usize;
-                let imm12 = decoder::bits(raw, 11, 0);
-                self.r[rd] = decoder::rotated_imm(imm12);
-            }
-            InstructionKind::Svc => {
-                self.r[0] = match ArmSyscall::try_from(self.r[7]) {
-                    Ok(kind) => (self.syscall_handler)(self, kind) as u32,
-                    Err(_) => sys::Errno::ENOSYS.as_ret(),
-                };
+            _ if decoder::is_v6t2(&decoded)
+                && !self.features.contains(features::Features::THUMB2) =>
+            {
+                self.undefined(raw)?
+            }
+            InstructionKind::DataProcessingImm => {
+                let (imm32, carry) = alu::expand_imm_c(operands.imm, self.flag(alu::C));
+                pc_changed = self.data_processing(&decoded, imm32, carry);
+            }
+            InstructionKind::DataProcessingReg => {
+                let (shift, amount) = alu::decode_imm_shift(operands.shift, operands.imm);
+                let rm = self.reg(operands.rm);
+                let (shifted, carry) = alu::shift_c(rm, shift, amount, self.flag(alu::C));
+                pc_changed = self.data_processing(&decoded, shifted, carry);
+            }
+            InstructionKind::DataProcessingRegShift => {
+                let amount = self.reg(operands.rs) & 0xff;
+                let rm = self.reg(operands.rm);
+                let (shifted, carry) = alu::shift_c(rm, operands.shift, amount, self.flag(alu::C));
+                pc_changed = self.data_processing(&decoded, shifted, carry);
+            }
+            InstructionKind::Svc => self.svc(raw)?,
+            InstructionKind::LoadStoreImm => {
+                pc_changed = self.load_store(&decoded, operands.imm)?;
+            }
+            InstructionKind::LoadStoreReg => {
+                let (shift, amount) = alu::decode_imm_shift(operands.shift, operands.imm);
+                let rm = self.reg(operands.rm);
+                let (offset, _) = alu::shift_c(rm, shift, amount, self.flag(alu::C));
+                pc_changed = self.load_store(&decoded, offset)?;
             }
-            InstructionKind::LdrLiteral => {
-                let rd = decoder::bits(raw, 15, 12) as usize;
-                let imm12 = decoder::bits(raw, 11, 0);
-                let addr = self.arm_pc().wrapping_add(imm12);
-                self.r[rd] =
-                    self.mem
-                        .read_u32(addr)
-                        .ok_or_else(|| err::Err::MemoryAccessViolation {
-                            guest: addr,
-                            instr: raw,
-                        })?;
+            InstructionKind::ExtraLoadStoreImm => {
+                pc_changed = self.extra_load_store(&decoded, operands.imm)?;
+            }
+            InstructionKind::ExtraLoadStoreReg => {
+                let rm = self.reg(operands.rm);
+                pc_changed = self.extra_load_store(&decoded, rm)?;
+            }
+            InstructionKind::BlockTransfer => {
+                pc_changed = self.block_transfer(&decoded)?;
+            }
+            InstructionKind::Exclusive => {
+                // ARM pairs the doubleword forms with Rt+1, Rt being the loaded respectively the
+                // stored register
+                let rt = operands.rt;
+                if operands.op == 0b01 && rt & 1 == 1 {
+                    return Err(err::Err::UnpredictableInstruction(raw));
+                }
+                self.exclusive(&decoded, 0, rt + 1)?;
+            }
+            InstructionKind::Clrex => self.monitor.clear(),
+            // a single guest thread observes its own accesses in program order
+            InstructionKind::Barrier => {}
+            // WFI and WFE return at once, the timer interrupt is checked before every instruction
+            InstructionKind::Hint => {}
+            InstructionKind::Mrs => self.mrs(&decoded)?,
+            InstructionKind::Msr => self.msr(&decoded, self.reg(operands.rm))?,
+            InstructionKind::MsrImm => self.msr(&decoded, decoder::rotated_imm(operands.imm))?,
+            InstructionKind::Cps => self.cps(&decoded)?,
+            InstructionKind::Setend => self.setend(&decoded)?,
+            // caches aren't modeled, so there is nothing to preload
+            InstructionKind::Preload => {}
+            InstructionKind::Srs => self.srs(&decoded)?,
+            InstructionKind::Rfe => {
+                self.rfe(&decoded)?;
+                pc_changed = true;
             }
+            InstructionKind::Multiply => self.multiply(&decoded)?,
+            InstructionKind::HalfwordMultiply => self.halfword_multiply(&decoded)?,
+            InstructionKind::MediaMultiply => self.media_multiply(&decoded)?,
+            InstructionKind::Divide => self.divide(&decoded)?,
+            InstructionKind::MovImm16 => self.move_imm16(&decoded),
+            InstructionKind::Clz => self.clz(&decoded),
+            InstructionKind::Crc32 => self.crc32(&decoded)?,
+            InstructionKind::Extend => self.extend(&decoded)?,
+            InstructionKind::Reverse => self.reverse(&decoded),
+            InstructionKind::Saturate => self.saturate(&decoded),
+            InstructionKind::ParallelAddSub => self.parallel_add_sub(&decoded)?,
+            InstructionKind::Select => self.select(&decoded),
+            InstructionKind::SumAbsDiff => self.sum_abs_diff(&decoded),
+            InstructionKind::SaturatingAddSub => self.saturating_add_sub(&decoded),
+            InstructionKind::Bitfield => self.bitfield(&decoded)?,
+            InstructionKind::VfpDataProcessing
+            | InstructionKind::VfpOther
+            | InstructionKind::VfpTransfer
+            | InstructionKind::VfpTransfer64
+            | InstructionKind::VfpLoadStore => self.vfp(&decoded)?,
+            InstructionKind::NeonThreeSame
+            | InstructionKind::NeonThreeDifferent
+            | InstructionKind::NeonScalar
+            | InstructionKind::NeonShift
+            | InstructionKind::NeonModifiedImm
+            | InstructionKind::NeonMisc
+            | InstructionKind::NeonVext
+            | InstructionKind::NeonVtbl
+            | InstructionKind::NeonVdupScalar
+            | InstructionKind::NeonLoadStoreMultiple
+            | InstructionKind::NeonLoadStoreLane
+            | InstructionKind::NeonLoadAllLanes => self.neon(&decoded)?,
+            InstructionKind::Coprocessor => self.coprocessor(&decoded)?,
+            InstructionKind::Coprocessor64 => self.coprocessor64(&decoded)?,
             InstructionKind::Branch => {
-                let l = decoder::bit(raw, 24);
                 // BL
-                if l {
+                if operands.l {
                     // save return addr to LR (next addr though)
                     self.r[14] = self.instr_addr().wrapping_add(4);
                 }
 
-                let imm24 = decoder::bits(raw, 23, 0);
-                let imm26 = imm24 << 2;
+                let imm26 = operands.imm << 2;
                 let imm32 = decoder::sign_extend(imm26, 26);
 
-                self.r[15] = self.arm_pc().wrapping_add(imm32 as u32);
+                self.write_pc(self.read_pc().wrapping_add(imm32 as u32));
                 pc_changed = true;
             }
+            InstructionKind::Bx => {
+                let rm = self.reg(operands.rm);
+                self.bx_write_pc(rm);
+                pc_changed = true;
+            }
+            InstructionKind::BlxReg => {
+                let rm = self.reg(operands.rm);
+                self.r[14] = self.instr_addr().wrapping_add(4);
+                self.bx_write_pc(rm);
+                pc_changed = true;
+            }
+            InstructionKind::BlxImm => {
+                // imm24:H, H supplies bit 1 of the halfword aligned Thumb target
+                let imm26 = operands.imm << 1;
+                let imm32 = decoder::sign_extend(imm26, 26);
+
+                self.r[14] = self.instr_addr().wrapping_add(4);
+                self.bx_write_pc(self.read_pc().wrapping_add(imm32 as u32) | 1);
+                pc_changed = true;
+            }
+            InstructionKind::Undefined => self.undefined(raw)?,
+            InstructionKind::Bkpt => self.breakpoint(raw),
             InstructionKind::Unknown => {
-                stinkln!("found unimplemented instruction, exiting: {:#x}", word);
+                stinkln!("found unimplemented instruction, exiting: {:#x}", raw);
                 return Err(err::Err::UnknownOrUnsupportedInstruction(raw));
             }
         }
@@ -186,4 +606,415 @@ impl<'cpu, const PRINT_INSTR: bool> Cpu<'cpu, PRINT_INSTR> {
 
         Ok(true)
     }
+
+    /// Take the undefined instruction exception for an encoding that is undefined or needs a
+    /// missing extension. A Linux process gets SIGILL, like the kernel sends it. Instructions the
+    /// emulator doesn't model are [`err::Err::UnknownOrUnsupportedInstruction`] instead.
+    fn undefined(&mut self, raw: u32) -> Result<(), err::Err> {
+        if self.bare_metal {
+            self.pending = Some(mode::Exception::Undefined);
+        } else {
+            self.raise(sys::Signal::SIGILL, raw);
+        }
+        Ok(())
+    }
+
+    /// BKPT, a debug event taken as a prefetch abort by bare-metal images and SIGTRAP for a Linux
+    /// process
+    fn breakpoint(&mut self, raw: u32) {
+        if self.bare_metal {
+            self.cp15.ifsr = cp15::DEBUG_EVENT;
+            self.pending = Some(mode::Exception::PrefetchAbort);
+        } else {
+            self.raise(sys::Signal::SIGTRAP, raw);
+        }
+    }
+
+    /// Terminate the guest with `signal`, raised by the instruction `raw` at the current pc
+    fn raise(&mut self, signal: sys::Signal, raw: u32) {
+        stinkln!(
+            "guest killed by {:?} at {:#x}: {:#x}",
+            signal,
+            self.instr_addr(),
+            raw
+        );
+        self.status = Some(signal.exit_status());
+    }
+
+    /// Dispatch the syscall in r7 to the configured handler, the result is returned in r0. Like
+    /// any exception return, this clears the exclusive monitor. The ARM private syscalls only
+    /// touch the emulated processor and are handled the same in every [`SyscallMode`].
+    ///
+    /// Bare-metal images take the supervisor call exception instead, unless the immediate is the
+    /// semihosting one.
+    fn svc(&mut self, raw: u32) -> Result<(), err::Err> {
+        if self.bare_metal {
+            if semihosting::is_call(raw, self.thumb()) {
+                return self.semihosting(raw);
+            }
+            self.pending = Some(mode::Exception::SupervisorCall);
+            return Ok(());
+        }
+
+        self.monitor.clear();
+        let nr = self.r[7];
+        self.r[0] = if nr & !0xffff == ARM_NR_BASE {
+            translation::syscall_private(self, nr, raw) as u32
+        } else {
+            match ArmSyscall::try_from(nr) {
+                Ok(kind) => (self.syscall_handler)(self, kind) as u32,
+                Err(_) => sys::Errno::ENOSYS.as_ret(),
+            }
+        };
+        Ok(())
+    }
+
+    /// Execute the data-processing instruction `decoded` with its already shifted second operand
+    /// and the shifter carry out. Returns whether pc was written.
+    fn data_processing(&mut self, decoded: &Decoded, operand2: u32, shifter_carry: bool) -> bool {
+        let Operands {
+            op, s, rd, rn: n, ..
+        } = decoded.operands;
+        let op = decoder::op_from_bits(op as u8);
+        // ADR in Thumb state is the only immediate form reading pc, which it aligns
+        let rn = if n == 15 && self.thumb() && decoded.kind == InstructionKind::DataProcessingImm {
+            self.read_pc() & !3
+        } else {
+            self.reg(n)
+        };
+        let carry = self.flag(alu::C);
+
+        let (result, carry, overflow) = match op {
+            Op::And | Op::Tst => (rn & operand2, shifter_carry, false),
+            Op::Eor | Op::Teq => (rn ^ operand2, shifter_carry, false),
+            Op::Orr => (rn | operand2, shifter_carry, false),
+            Op::Mov => (operand2, shifter_carry, false),
+            Op::Bic => (rn & !operand2, shifter_carry, false),
+            Op::Mvn => (!operand2, shifter_carry, false),
+            Op::Sub | Op::Cmp => alu::add_with_carry(rn, !operand2, true),
+            Op::Rsb => alu::add_with_carry(operand2, !rn, true),
+            Op::Add | Op::Cmn => alu::add_with_carry(rn, operand2, false),
+            Op::Adc => alu::add_with_carry(rn, operand2, carry),
+            Op::Sbc => alu::add_with_carry(rn, !operand2, carry),
+            Op::Rsc => alu::add_with_carry(operand2, !rn, carry),
+        };
+
+        // `<op>s pc, ...` is an exception return, which copies the SPSR instead of setting the
+        // flags. There is no SPSR in User mode, so there we only write pc.
+        if s && (rd != 15 || op.is_test()) {
+            self.set_nz(result);
+            self.set_flag(alu::C, carry);
+            if !op.is_logical() {
+                self.set_flag(alu::V, overflow);
+            }
+        }
+
+        if op.is_test() {
+            return false;
+        }
+
+        if rd == 15 {
+            if s && self.exception_return(result) {
+                return true;
+            }
+            // ALUWritePC only interworks in ARM state
+            if self.thumb() {
+                self.write_pc(result);
+            } else {
+                self.bx_write_pc(result);
+            }
+            return true;
+        }
+
+        self.r[rd] = result;
+        false
+    }
+
+    /// Execute LDR, STR, LDRB or STRB with an already computed offset. P selects pre- or
+    /// post-indexing, U adds or subtracts the offset, B selects a byte transfer, W requests base
+    /// writeback and L selects a load. Returns whether pc was written.
+    ///
+    /// Post-indexing with W set (LDRT/STRT) behaves like the plain form, since there are no
+    /// memory permissions that would differ for the User mode access.
+    fn load_store(&mut self, decoded: &Decoded, offset: u32) -> Result<bool, err::Err> {
+        let Operands {
+            p,
+            u,
+            b,
+            w,
+            l,
+            rn,
+            rt,
+            ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+
+        // the literal form aligns pc before adding the offset
+        let base = if rn == 15 {
+            self.reg(rn) & !3
+        } else {
+            self.reg(rn)
+        };
+        let offset_addr = if u {
+            base.wrapping_add(offset)
+        } else {
+            base.wrapping_sub(offset)
+        };
+        let addr = if p { offset_addr } else { base };
+        let fault = err::Err::MemoryAccessViolation {
+            guest: addr,
+            instr: raw,
+        };
+
+        if l {
+            let value = if b {
+                self.mem.read_u8(addr).map(u32::from)
+            } else {
+                self.mem.read_u32(addr)
+            }
+            .ok_or(fault)?;
+
+            if !p || w {
+                self.r[rn] = offset_addr;
+            }
+
+            if rt == 15 {
+                self.bx_write_pc(value);
+                return Ok(true);
+            }
+            self.r[rt] = value;
+        } else {
+            let value = self.reg(rt);
+            self.monitor.store(addr, if b { 1 } else { 4 });
+            if b {
+                self.mem.write_u8(addr, value as u8)
+            } else {
+                self.mem.write_u32(addr, value)
+            }
+            .map_err(|_| fault)?;
+
+            if !p || w {
+                self.r[rn] = offset_addr;
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Execute the halfword, signed and doubleword transfers, bits 6..5 and L select:
+    ///
+    /// | op2 | L=0  | L=1   |
+    /// | --- | ---- | ----- |
+    /// | 01  | STRH | LDRH  |
+    /// | 10  | LDRD | LDRSB |
+    /// | 11  | STRD | LDRSH |
+    ///
+    /// The doubleword forms transfer Rt and Rt+1. Returns whether pc was written.
+    fn extra_load_store(&mut self, decoded: &Decoded, offset: u32) -> Result<bool, err::Err> {
+        let Operands {
+            p,
+            u,
+            w,
+            l,
+            rn,
+            rt,
+            op: op2,
+            ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+
+        let base = if rn == 15 {
+            self.reg(rn) & !3
+        } else {
+            self.reg(rn)
+        };
+        let offset_addr = if u {
+            base.wrapping_add(offset)
+        } else {
+            base.wrapping_sub(offset)
+        };
+        let addr = if p { offset_addr } else { base };
+        let fault = err::Err::MemoryAccessViolation {
+            guest: addr,
+            instr: raw,
+        };
+
+        // the doubleword forms need an even Rt, an odd one is UNPREDICTABLE
+        if !l && op2 != 0b01 {
+            if rt & 1 == 1 {
+                return Err(err::Err::UnpredictableInstruction(raw));
+            }
+            return self.load_store_dual(decoded, offset, rt + 1);
+        }
+
+        match (op2, l) {
+            // STRH
+            (0b01, false) => {
+                self.monitor.store(addr, 2);
+                self.mem
+                    .write_u16(addr, self.reg(rt) as u16)
+                    .map_err(|_| fault)?
+            }
+            // LDRH, LDRSB, LDRSH
+            (_, true) => {
+                let value = match op2 {
+                    0b01 => self.mem.read_u16(addr).map(u32::from),
+                    0b10 => self.mem.read_u8(addr).map(|b| b as i8 as u32),
+                    _ => self.mem.read_u16(addr).map(|h| h as i16 as u32),
+                }
+                .ok_or(fault)?;
+                if !p || w {
+                    self.r[rn] = offset_addr;
+                }
+                self.r[rt] = value;
+                return Ok(false);
+            }
+            _ => unreachable!("bits 6..5 = 00 is the multiply space"),
+        }
+
+        if !p || w {
+            self.r[rn] = offset_addr;
+        }
+
+        Ok(false)
+    }
+
+    /// Execute LDRD (bits 6..5 = 10) or STRD (bits 6..5 = 11) of Rt and `rt2`, laid out like
+    /// [`Cpu::extra_load_store`]. ARM always pairs Rt with Rt+1, Thumb-2 encodes Rt2 separately.
+    fn load_store_dual(
+        &mut self,
+        decoded: &Decoded,
+        offset: u32,
+        rt2: usize,
+    ) -> Result<bool, err::Err> {
+        let Operands {
+            p,
+            u,
+            w,
+            rn,
+            rt,
+            op,
+            ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+        let load = op == 0b10;
+
+        let base = if rn == 15 {
+            self.reg(rn) & !3
+        } else {
+            self.reg(rn)
+        };
+        let offset_addr = if u {
+            base.wrapping_add(offset)
+        } else {
+            base.wrapping_sub(offset)
+        };
+        let addr = if p { offset_addr } else { base };
+        let fault = err::Err::MemoryAccessViolation {
+            guest: addr,
+            instr: raw,
+        };
+
+        if load {
+            let value = self.mem.read_u64(addr).ok_or(fault)?;
+            if !p || w {
+                self.r[rn] = offset_addr;
+            }
+            self.r[rt] = value as u32;
+            self.r[rt2] = (value >> 32) as u32;
+        } else {
+            let value = ((self.reg(rt2) as u64) << 32) | self.reg(rt) as u64;
+            self.monitor.store(addr, 8);
+            self.mem.write_u64(addr, value).map_err(|_| fault)?;
+            if !p || w {
+                self.r[rn] = offset_addr;
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Execute LDM/STM and their PUSH/POP aliases. P and U select the addressing mode
+    /// (IA/IB/DA/DB), W requests base writeback and L selects a load. Registers are always
+    /// transferred lowest-numbered to lowest address. Returns whether pc was written.
+    ///
+    /// S selects the User mode registers in the privileged modes, an LDM with pc in its list
+    /// instead loads the current registers and returns from an exception.
+    fn block_transfer(&mut self, decoded: &Decoded) -> Result<bool, err::Err> {
+        let Operands {
+            p,
+            u,
+            s,
+            w,
+            l,
+            rn,
+            list,
+            ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+
+        // the user bank and exception return forms (`ldm sp, {r0-r14}^`) are UNPREDICTABLE in
+        // User and System mode, so is an empty register list and writeback to the user bank
+        let exception_return = s && l && list & (1 << 15) != 0;
+        let user_bank = s && !exception_return;
+        if (s && self.spsr().is_none()) || (user_bank && w) || list == 0 {
+            return Err(err::Err::UnpredictableInstruction(raw));
+        }
+
+        let base = self.r[rn];
+        let len = list.count_ones() * 4;
+        let mut addr = match (p, u) {
+            (false, true) => base,                                    // IA
+            (true, true) => base.wrapping_add(4),                     // IB
+            (false, false) => base.wrapping_sub(len).wrapping_add(4), // DA
+            (true, false) => base.wrapping_sub(len),                  // DB
+        };
+        let written_back = if u {
+            base.wrapping_add(len)
+        } else {
+            base.wrapping_sub(len)
+        };
+
+        let mut pc = None;
+        for i in (0..16).filter(|i| list & (1 << i) != 0) {
+            let fault = err::Err::MemoryAccessViolation {
+                guest: addr,
+                instr: raw,
+            };
+
+            if l {
+                let value = self.mem.read_u32(addr).ok_or(fault)?;
+                if i == 15 {
+                    pc = Some(value);
+                } else if user_bank {
+                    self.set_user_reg(i, value);
+                } else {
+                    self.r[i] = value;
+                }
+            } else {
+                let value = if user_bank {
+                    self.user_reg(i)
+                } else {
+                    self.reg(i)
+                };
+                self.monitor.store(addr, 4);
+                self.mem.write_u32(addr, value).map_err(|_| fault)?;
+            }
+            addr = addr.wrapping_add(4);
+        }
+
+        // loading the base register takes precedence over writing it back
+        if w && !(l && list & (1 << rn) != 0) {
+            self.r[rn] = written_back;
+        }
+
+        match pc {
+            Some(pc) if exception_return => {
+                self.exception_return(pc);
+            }
+            Some(pc) => self.bx_write_pc(pc),
+            None => {}
+        }
+        Ok(pc.is_some())
+    }
 }
@@ -0,0 +1,479 @@
+//! Processor modes, banked registers and exceptions, see ARMv7 ARM B1.3 "ARM processor modes and
+//! ARM core registers" and B1.8 "Exception handling". Linux binaries run in User mode and never
+//! leave it. Bare-metal images start in Supervisor mode and take their exceptions through the
+//! vector table at 0, or at 0xffff0000 with SCTLR.V set.
+//!
+//! [`Cpu::r`] always holds the registers of the current mode, a mode change swaps the banked ones
+//! in and out. Monitor and Hyp mode are not implemented, switching to them is UNPREDICTABLE. The
+//! virtual timer is the only interrupt source, there is none for FIQs.
+
+use crate::{
+    cpu::{
+        CPSR_T, Cpu, FETCH_FAULT, alu, cp15,
+        decoder::{Decoded, Operands},
+    },
+    err, mem,
+};
+
+/// the mode field of the CPSR
+pub const MODE: u32 = 0x1f;
+pub const USR: u32 = 0b10000;
+pub const FIQ: u32 = 0b10001;
+pub const IRQ: u32 = 0b10010;
+pub const SVC: u32 = 0b10011;
+pub const ABT: u32 = 0b10111;
+pub const UND: u32 = 0b11011;
+pub const SYS: u32 = 0b11111;
+
+/// FIQ mask bit of the CPSR
+pub const F: u32 = 1 << 6;
+/// IRQ mask bit of the CPSR
+pub const I: u32 = 1 << 7;
+/// asynchronous abort mask bit of the CPSR
+pub const A: u32 = 1 << 8;
+/// endianness bit of the CPSR
+const E: u32 = 1 << 9;
+/// the If-Then state in bits 26..25 and 15..10 and the Jazelle bit
+const IT_J: u32 = 0x0700_fc00;
+
+/// The CPSR a Linux process starts with, User mode in ARM state
+pub const USER_CPSR: u32 = 0x6000_0000 | USR;
+/// The CPSR out of reset: Supervisor mode with every interrupt masked
+pub const RESET_CPSR: u32 = A | I | F | SVC;
+
+/// bank of FIQ mode, the only one with its own r8-r12
+const FIQ_BANK: usize = 5;
+
+/// Index of the register bank of `mode`, `None` for the reserved encodings and the modes we don't
+/// implement
+fn bank(mode: u32) -> Option<usize> {
+    Some(match mode {
+        USR | SYS => 0,
+        SVC => 1,
+        ABT => 2,
+        UND => 3,
+        IRQ => 4,
+        FIQ => 5,
+        _ => return None,
+    })
+}
+
+/// The registers of the modes that aren't current
+#[derive(Debug, Default)]
+pub struct Banks {
+    /// SP and LR of User and System, Supervisor, Abort, Undefined, IRQ and FIQ mode
+    sp_lr: [[u32; 2]; 6],
+    /// r8-r12 of FIQ mode outside of it, those of every other mode in it
+    r8_r12: [u32; 5],
+    /// SPSR of each exception mode, indexed like `sp_lr`, User and System mode have none
+    spsr: [u32; 6],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exception {
+    Undefined,
+    SupervisorCall,
+    PrefetchAbort,
+    DataAbort,
+    Irq,
+}
+
+impl Exception {
+    /// offset of the entry in the vector table
+    fn vector(self) -> u32 {
+        match self {
+            Exception::Undefined => 0x04,
+            Exception::SupervisorCall => 0x08,
+            Exception::PrefetchAbort => 0x0c,
+            Exception::DataAbort => 0x10,
+            Exception::Irq => 0x18,
+        }
+    }
+
+    fn mode(self) -> u32 {
+        match self {
+            Exception::Undefined => UND,
+            Exception::SupervisorCall => SVC,
+            Exception::PrefetchAbort | Exception::DataAbort => ABT,
+            Exception::Irq => IRQ,
+        }
+    }
+
+    /// LR of the exception mode relative to the instruction that caused the exception, or the
+    /// first one not executed for interrupts, see ARMv7 ARM "Table B1-7". The handlers return
+    /// with `movs pc, lr` respectively `subs pc, lr, #4` and `subs pc, lr, #8`.
+    fn return_offset(self, thumb: bool) -> u32 {
+        match self {
+            Exception::Undefined | Exception::SupervisorCall if thumb => 2,
+            Exception::Undefined | Exception::SupervisorCall => 4,
+            Exception::PrefetchAbort | Exception::Irq => 4,
+            Exception::DataAbort => 8,
+        }
+    }
+
+    /// the CPSR mask bits set on entry, only FIQs would mask FIQs
+    fn masks(self) -> u32 {
+        match self {
+            Exception::Undefined | Exception::SupervisorCall => I,
+            Exception::PrefetchAbort | Exception::DataAbort | Exception::Irq => A | I,
+        }
+    }
+}
+
+impl<'cpu, const PRINT_INSTR: bool> Cpu<'cpu, PRINT_INSTR> {
+    #[inline(always)]
+    pub(super) fn mode(&self) -> u32 {
+        self.cpsr & MODE
+    }
+
+    /// every mode but User executes at PL1
+    #[inline(always)]
+    pub(super) fn privileged(&self) -> bool {
+        self.mode() != USR
+    }
+
+    /// Write the whole CPSR, swapping in the registers of its mode. Returns false and leaves
+    /// everything as is if the mode doesn't exist.
+    pub(super) fn write_cpsr(&mut self, value: u32) -> bool {
+        let (Some(from), Some(to)) = (bank(self.mode()), bank(value & MODE)) else {
+            return false;
+        };
+        if from != to {
+            self.banks.sp_lr[from] = [self.r[13], self.r[14]];
+            [self.r[13], self.r[14]] = self.banks.sp_lr[to];
+        }
+        if (from == FIQ_BANK) != (to == FIQ_BANK) {
+            std::mem::swap(
+                &mut self.banks.r8_r12,
+                (&mut self.r[8..13]).try_into().unwrap(),
+            );
+        }
+        self.cpsr = value;
+        true
+    }
+
+    /// SPSR of the current mode, `None` in User and System mode
+    pub(super) fn spsr(&self) -> Option<u32> {
+        match self.mode() {
+            USR | SYS => None,
+            mode => bank(mode).map(|b| self.banks.spsr[b]),
+        }
+    }
+
+    fn set_spsr(&mut self, value: u32) {
+        match (self.mode(), bank(self.mode())) {
+            (USR | SYS, _) | (_, None) => {}
+            (_, Some(current)) => self.banks.spsr[current] = value,
+        }
+    }
+
+    /// CPSRWriteByInstr for MSR and CPS: write the bytes of `value` selected by `mask`, bit 3
+    /// for the flags down to bit 0 for the control byte, as far as the current mode may. User
+    /// mode only changes the flags and GE. The execution state bits are left alone, writing a
+    /// mode that doesn't exist is UNPREDICTABLE.
+    pub(super) fn write_cpsr_by_instr(
+        &mut self,
+        value: u32,
+        mask: u32,
+        raw: u32,
+    ) -> Result<(), err::Err> {
+        let mut writable = 0;
+        if mask & 0b1000 != 0 {
+            writable |= 0xf800_0000;
+        }
+        if mask & 0b0100 != 0 {
+            writable |= alu::GE;
+        }
+        if mask & 0b0010 != 0 {
+            writable |= E | if self.privileged() { A } else { 0 };
+        }
+        if mask & 0b0001 != 0 && self.privileged() {
+            writable |= I | F | MODE;
+        }
+
+        let cpsr = (self.cpsr & !writable) | (value & writable);
+        if !self.write_cpsr(cpsr) {
+            return Err(err::Err::UnpredictableInstruction(raw));
+        }
+        Ok(())
+    }
+
+    /// MSR to the SPSR, UNPREDICTABLE in User and System mode. Every byte may be written, but a
+    /// mode that doesn't exist is rejected here instead of at the exception return using it.
+    pub(super) fn write_spsr_by_instr(
+        &mut self,
+        value: u32,
+        mask: u32,
+        raw: u32,
+    ) -> Result<(), err::Err> {
+        let Some(spsr) = self.spsr() else {
+            return Err(err::Err::UnpredictableInstruction(raw));
+        };
+        let bytes = (0..4)
+            .filter(|i| mask & (1 << i) != 0)
+            .fold(0, |bytes, i| bytes | (0xff << (8 * i)));
+        let spsr = (spsr & !bytes) | (value & bytes);
+        if bank(spsr & MODE).is_none() {
+            return Err(err::Err::UnpredictableInstruction(raw));
+        }
+        self.set_spsr(spsr);
+        Ok(())
+    }
+
+    /// Exception return, `movs pc, lr`, `subs pc, lr, #4` and `ldm sp!, {..., pc}^`: copy the
+    /// SPSR to the CPSR and branch to `addr` in the restored instruction set. Returns false in
+    /// User and System mode, which have no SPSR.
+    pub(super) fn exception_return(&mut self, addr: u32) -> bool {
+        self.spsr().is_some_and(|spsr| self.return_to(spsr, addr))
+    }
+
+    /// Restore `cpsr`, clear the exclusive monitor and branch to `addr`. Returns false if the
+    /// mode doesn't exist.
+    fn return_to(&mut self, cpsr: u32, addr: u32) -> bool {
+        if !self.write_cpsr(cpsr) {
+            return false;
+        }
+        self.monitor.clear();
+        self.write_pc(addr);
+        true
+    }
+
+    /// Take `exception`, caused by the instruction at `addr` or, for interrupts, taken before
+    /// executing it. The CPSR is saved to the SPSR of the exception mode, LR holds the return
+    /// address and execution continues in ARM state at the vector.
+    pub(super) fn take_exception(&mut self, exception: Exception, addr: u32) {
+        let cpsr = self.cpsr;
+        let lr = addr.wrapping_add(exception.return_offset(self.thumb()));
+        let entry = (cpsr & !(MODE | IT_J | CPSR_T | E)) | exception.masks() | exception.mode();
+        self.write_cpsr(entry);
+        self.set_spsr(cpsr);
+        self.r[14] = lr;
+        let base = if self.cp15.sctlr & cp15::SCTLR_V != 0 {
+            mem::VECTORS
+        } else {
+            0
+        };
+        self.r[15] = base + exception.vector();
+    }
+
+    /// One step of a bare-metal image: take a pending interrupt, otherwise execute the next
+    /// instruction and take the exception it raised. A memory access violation aborts the
+    /// instruction, a fetch with a prefetch abort, anything else with a data abort. Registers the
+    /// aborted instruction already wrote are not restored.
+    pub(super) fn step_bare_metal(&mut self) -> Result<bool, err::Err> {
+        let addr = self.instr_addr();
+        if self.cpsr & I == 0 && self.cp15.timer_fired() {
+            self.take_exception(Exception::Irq, addr);
+            return Ok(true);
+        }
+
+        match self.step_instruction() {
+            Err(err::Err::MemoryAccessViolation { guest, instr }) => {
+                self.pending = None;
+                if instr == FETCH_FAULT {
+                    self.cp15.abort(guest, true);
+                    self.take_exception(Exception::PrefetchAbort, addr);
+                } else {
+                    self.cp15.abort(guest, false);
+                    self.take_exception(Exception::DataAbort, addr);
+                }
+                Ok(true)
+            }
+            Ok(running) => {
+                if let Some(exception) = self.pending.take() {
+                    self.take_exception(exception, addr);
+                }
+                Ok(running)
+            }
+            err => err,
+        }
+    }
+
+    /// SP of `mode`, which SRS stores to
+    fn banked_sp(&self, mode: u32) -> Option<u32> {
+        let index = bank(mode)?;
+        if Some(index) == bank(self.mode()) {
+            Some(self.r[13])
+        } else {
+            Some(self.banks.sp_lr[index][0])
+        }
+    }
+
+    fn set_banked_sp(&mut self, mode: u32, value: u32) {
+        match bank(mode) {
+            Some(index) if Some(index) == bank(self.mode()) => self.r[13] = value,
+            Some(index) => self.banks.sp_lr[index][0] = value,
+            None => {}
+        }
+    }
+
+    /// Register `n` of User mode, for LDM and STM with the user bank (`^`)
+    pub(super) fn user_reg(&self, n: usize) -> u32 {
+        match (n, self.mode()) {
+            (13 | 14, USR | SYS) | (0..=7, _) | (15, _) => self.r[n],
+            (13 | 14, _) => self.banks.sp_lr[0][n - 13],
+            (_, FIQ) => self.banks.r8_r12[n - 8],
+            _ => self.r[n],
+        }
+    }
+
+    pub(super) fn set_user_reg(&mut self, n: usize, value: u32) {
+        match (n, self.mode()) {
+            (13 | 14, USR | SYS) | (0..=7, _) | (15, _) => self.r[n] = value,
+            (13 | 14, _) => self.banks.sp_lr[0][n - 13] = value,
+            (_, FIQ) => self.banks.r8_r12[n - 8] = value,
+            _ => self.r[n] = value,
+        }
+    }
+
+    /// MRS, R in bit 22 reads the SPSR instead of the CPSR. The execution state bits read as zero.
+    pub(super) fn mrs(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands { op: r, rd, .. } = decoded.operands;
+        let value = if r == 1 {
+            self.spsr()
+        } else {
+            Some(self.cpsr & !(IT_J | CPSR_T))
+        };
+        match value {
+            Some(value) if rd != 15 => {
+                self.r[rd] = value;
+                Ok(())
+            }
+            _ => Err(err::Err::UnpredictableInstruction(decoded.raw)),
+        }
+    }
+
+    /// MSR of `value`, the bytes to write are selected by the mask in bits 19..16, R in bit 22
+    /// writes the SPSR instead of the CPSR
+    pub(super) fn msr(&mut self, decoded: &Decoded, value: u32) -> Result<(), err::Err> {
+        // op is R:mask
+        let (r, mask) = (decoded.operands.op >> 4, decoded.operands.op & 0xf);
+        let raw = decoded.raw;
+        if mask == 0 {
+            return Err(err::Err::UnpredictableInstruction(raw));
+        }
+        if r == 1 {
+            self.write_spsr_by_instr(value, mask, raw)
+        } else {
+            self.write_cpsr_by_instr(value, mask, raw)
+        }
+    }
+
+    /// CPS, imod in bits 19..18 enables (10) or disables (11) the interrupts selected by A, I and
+    /// F in bits 8..6, M in bit 17 changes to the mode in bits 4..0. A no-op in User mode.
+    pub(super) fn cps(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        // op is imod:M, imm the A, I and F bits followed by the mode
+        let Operands { op, imm, .. } = decoded.operands;
+        let raw = decoded.raw;
+        let imod = op >> 1;
+        let change_mode = op & 1 == 1;
+        if imod == 0b01 || (imod == 0b00 && !change_mode) {
+            return Err(err::Err::UnpredictableInstruction(raw));
+        }
+        if !self.privileged() {
+            return Ok(());
+        }
+
+        let masks = (imm >> 5) << 6;
+        let mut cpsr = match imod {
+            0b10 => self.cpsr & !masks,
+            0b11 => self.cpsr | masks,
+            _ => self.cpsr,
+        };
+        if change_mode {
+            cpsr = (cpsr & !MODE) | (imm & MODE);
+        }
+        if !self.write_cpsr(cpsr) {
+            return Err(err::Err::UnpredictableInstruction(raw));
+        }
+        Ok(())
+    }
+
+    /// SETEND clears or, with bit 9, sets the E bit selecting big-endian data accesses. Memory
+    /// is only accessed little-endian, so SETEND BE is unsupported.
+    pub(super) fn setend(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        if decoded.operands.op == 1 {
+            return Err(err::Err::UnknownOrUnsupportedInstruction(decoded.raw));
+        }
+        self.cpsr &= !E;
+        Ok(())
+    }
+
+    /// The lowest address SRS and RFE transfer, P and U select DA, IA, DB or IB of two words
+    fn return_state_addr(operands: &Operands, base: u32) -> u32 {
+        match (operands.p, operands.u) {
+            (false, true) => base,
+            (true, true) => base.wrapping_add(4),
+            (false, false) => base.wrapping_sub(4),
+            (true, false) => base.wrapping_sub(8),
+        }
+    }
+
+    /// SRS, store LR and the SPSR to the stack of the mode in bits 4..0, W in bit 21 writes its
+    /// SP back
+    pub(super) fn srs(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let operands = decoded.operands;
+        let raw = decoded.raw;
+        let mode = operands.imm;
+        let (Some(spsr), Some(base)) = (self.spsr(), self.banked_sp(mode)) else {
+            return Err(err::Err::UnpredictableInstruction(raw));
+        };
+
+        let addr = Self::return_state_addr(&operands, base);
+        for (addr, value) in [(addr, self.r[14]), (addr.wrapping_add(4), spsr)] {
+            self.monitor.store(addr, 4);
+            self.mem
+                .write_u32(addr, value)
+                .map_err(|_| err::Err::MemoryAccessViolation {
+                    guest: addr,
+                    instr: raw,
+                })?;
+        }
+
+        if operands.w {
+            let sp = if operands.u {
+                base.wrapping_add(8)
+            } else {
+                base.wrapping_sub(8)
+            };
+            self.set_banked_sp(mode, sp);
+        }
+        Ok(())
+    }
+
+    /// RFE, load pc and the CPSR from the address in Rn and return to them, W in bit 21 writes Rn
+    /// back. UNPREDICTABLE in User mode.
+    pub(super) fn rfe(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let operands = decoded.operands;
+        let raw = decoded.raw;
+        let rn = operands.rn;
+        if !self.privileged() || rn == 15 {
+            return Err(err::Err::UnpredictableInstruction(raw));
+        }
+
+        let base = self.r[rn];
+        let addr = Self::return_state_addr(&operands, base);
+        let load = |addr: u32| {
+            self.mem
+                .read_u32(addr)
+                .ok_or(err::Err::MemoryAccessViolation {
+                    guest: addr,
+                    instr: raw,
+                })
+        };
+        let pc = load(addr)?;
+        let cpsr = load(addr.wrapping_add(4))?;
+
+        if operands.w {
+            self.r[rn] = if operands.u {
+                base.wrapping_add(8)
+            } else {
+                base.wrapping_sub(8)
+            };
+        }
+        if !self.return_to(cpsr, pc) {
+            return Err(err::Err::UnpredictableInstruction(raw));
+        }
+        Ok(())
+    }
+}
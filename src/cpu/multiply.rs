@@ -0,0 +1,211 @@
+//! Multiplies and hardware divide, see ARMv7 ARM A5.2.5 "Multiply and multiply accumulate",
+//! A5.2.7 "Halfword multiply and multiply accumulate" and A5.4.4 "Signed multiply, signed and
+//! unsigned divide". The Thumb-2 encodings are executed through the same ARM control words.
+//!
+//! The register fields are shared by all forms: Rd (or RdHi) in bits 19..16, Ra (or RdLo) in bits
+//! 15..12, Rm in bits 11..8 and Rn in bits 3..0, decoded into [`Operands`] as `rd`, `ra`, `rm` and
+//! `rn`.
+
+use crate::{
+    cpu::{
+        Cpu, alu,
+        decoder::{Decoded, Operands},
+        features::Features,
+    },
+    err,
+};
+
+/// The top or bottom halfword of `value`, sign extended
+#[inline(always)]
+fn half(value: u32, top: bool) -> i64 {
+    if top {
+        (value as i32 >> 16) as i64
+    } else {
+        value as i16 as i64
+    }
+}
+
+impl<'cpu, const PRINT_INSTR: bool> Cpu<'cpu, PRINT_INSTR> {
+    /// Write the 64-bit `value` to RdHi:RdLo, setting N and Z from all 64 bits for `s`
+    fn write_long(&mut self, hi: usize, lo: usize, value: u64, s: bool) {
+        self.r[lo] = value as u32;
+        self.r[hi] = (value >> 32) as u32;
+        if s {
+            self.set_flag(alu::N, value >> 63 == 1);
+            self.set_flag(alu::Z, value == 0);
+        }
+    }
+
+    /// Write a signed 64-bit sum to Rd, setting the sticky Q flag if it does not fit into 32 bits
+    fn write_saturating(&mut self, rd: usize, value: i64) {
+        if value != value as i32 as i64 {
+            self.set_flag(alu::Q, true);
+        }
+        self.r[rd] = value as u32;
+    }
+
+    /// MUL, MLA, MLS, UMAAL, UMULL, UMLAL, SMULL and SMLAL selected by bits 23..21, S in bit 20
+    /// only updates N and Z, C and V are left untouched since ARMv6.
+    pub(super) fn multiply(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            op,
+            s,
+            rd,
+            ra,
+            rm,
+            rn,
+            ..
+        } = decoded.operands;
+        let (x, y) = (self.r[rn], self.r[rm]);
+        let long = ((self.r[rd] as u64) << 32) | self.r[ra] as u64;
+
+        match op {
+            // MUL
+            0b000 => {
+                let result = x.wrapping_mul(y);
+                self.r[rd] = result;
+                if s {
+                    self.set_nz(result);
+                }
+            }
+            // MLA
+            0b001 => {
+                let result = x.wrapping_mul(y).wrapping_add(self.r[ra]);
+                self.r[rd] = result;
+                if s {
+                    self.set_nz(result);
+                }
+            }
+            // UMAAL, cannot overflow 64 bits
+            0b010 if !s => {
+                let result = x as u64 * y as u64 + self.r[rd] as u64 + self.r[ra] as u64;
+                self.write_long(rd, ra, result, false);
+            }
+            // MLS
+            0b011 if !s => self.r[rd] = self.r[ra].wrapping_sub(x.wrapping_mul(y)),
+            // UMULL
+            0b100 => self.write_long(rd, ra, x as u64 * y as u64, s),
+            // UMLAL
+            0b101 => self.write_long(rd, ra, (x as u64 * y as u64).wrapping_add(long), s),
+            // SMULL
+            0b110 => {
+                let result = x as i32 as i64 * y as i32 as i64;
+                self.write_long(rd, ra, result as u64, s);
+            }
+            // SMLAL
+            0b111 => {
+                let result = (x as i32 as i64 * y as i32 as i64) as u64;
+                self.write_long(rd, ra, result.wrapping_add(long), s);
+            }
+            _ => return self.undefined(decoded.raw),
+        }
+
+        Ok(())
+    }
+
+    /// SMLA<x><y>, SMLAW<y>, SMULW<y>, SMLAL<x><y> and SMUL<x><y> selected by bits 22..21, with
+    /// bit 5 choosing the top half of Rn and bit 6 the top half of Rm.
+    pub(super) fn halfword_multiply(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            op, rd, ra, rm, rn, ..
+        } = decoded.operands;
+        // op is bits 22..21:6..5
+        let n_top = op & 1 == 1;
+        let m = half(self.r[rm], op & 2 != 0);
+        let n = half(self.r[rn], n_top);
+
+        match op >> 2 {
+            // SMLA<x><y>
+            0b00 => self.write_saturating(rd, n * m + self.r[ra] as i32 as i64),
+            // SMULW<y>, only the top 32 bits of the 48-bit product
+            0b01 if n_top => self.r[rd] = ((self.r[rn] as i32 as i64 * m) >> 16) as u32,
+            // SMLAW<y>
+            0b01 => {
+                let product = (self.r[rn] as i32 as i64 * m) >> 16;
+                self.write_saturating(rd, product + self.r[ra] as i32 as i64);
+            }
+            // SMLAL<x><y>
+            0b10 => {
+                let long = ((self.r[rd] as u64) << 32) | self.r[ra] as u64;
+                self.write_long(rd, ra, long.wrapping_add((n * m) as u64), false);
+            }
+            // SMUL<x><y>, cannot overflow
+            _ => self.r[rd] = (n * m) as u32,
+        }
+
+        Ok(())
+    }
+
+    /// The dual halfword multiplies SMLAD, SMUAD, SMLSD, SMUSD, SMLALD and SMLSLD, and the most
+    /// significant word multiplies SMMLA, SMMUL and SMMLS. Bits 22..20 select the group, bits 7..6
+    /// the operation and bit 5 swaps the halves of Rm for the dual forms respectively rounds the
+    /// most significant word ones. Ra = pc selects the forms without accumulate.
+    pub(super) fn media_multiply(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            op, rd, ra, rm, rn, ..
+        } = decoded.operands;
+        // op is bits 22..20:7..5
+        let (x, y) = (self.r[rn], self.r[rm]);
+        let subtract = op & 2 != 0;
+        let swap_or_round = op & 1 == 1;
+        let m = if swap_or_round { y.rotate_right(16) } else { y };
+        let low = half(x, false) * half(m, false);
+        let high = half(x, true) * half(m, true);
+        let dual = if subtract { low - high } else { low + high };
+
+        match (op >> 3, (op >> 1) & 0b11) {
+            // SMUAD and SMUSD
+            (0b000, 0b00 | 0b01) if ra == 15 => self.write_saturating(rd, dual),
+            // SMLAD and SMLSD
+            (0b000, 0b00 | 0b01) => self.write_saturating(rd, dual + self.r[ra] as i32 as i64),
+            // SMLALD and SMLSLD
+            (0b100, 0b00 | 0b01) => {
+                let long = ((self.r[rd] as u64) << 32) | self.r[ra] as u64;
+                self.write_long(rd, ra, long.wrapping_add(dual as u64), false);
+            }
+            // SMMUL, SMMLA and SMMLS
+            (0b101, 0b00 | 0b11) => {
+                let product = x as i32 as i64 * y as i32 as i64;
+                let accumulate = if ra == 15 {
+                    0
+                } else {
+                    (self.r[ra] as i32 as i64) << 32
+                };
+                let mut result = if subtract {
+                    accumulate.wrapping_sub(product)
+                } else {
+                    accumulate.wrapping_add(product)
+                };
+                if swap_or_round {
+                    result = result.wrapping_add(0x8000_0000);
+                }
+                self.r[rd] = (result >> 32) as u32;
+            }
+            _ => return self.undefined(decoded.raw),
+        }
+
+        Ok(())
+    }
+
+    /// SDIV and UDIV, bit 21 selects the unsigned form. Both round towards zero and a division by
+    /// zero returns zero, as on every A-profile core implementing them. Undefined without
+    /// [`Features::IDIV`].
+    pub(super) fn divide(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        if !self.features.contains(Features::IDIV) {
+            return self.undefined(decoded.raw);
+        }
+
+        let Operands { u, rd, rm, rn, .. } = decoded.operands;
+        let (x, y) = (self.r[rn], self.r[rm]);
+        self.r[rd] = if y == 0 {
+            0
+        } else if u {
+            x / y
+        } else {
+            // i32::MIN / -1 overflows to i32::MIN
+            (x as i32).wrapping_div(y as i32) as u32
+        };
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1152 @@
+//! Advanced SIMD (NEON), see ARMv7 ARM A7.4 "Advanced SIMD data-processing instructions" and
+//! A7.7 "Advanced SIMD element or structure load/store instructions". The instructions share
+//! the VFP register file, the quadword registers Q0-Q15 being the pairs D2n:D2n+1 with D2n as
+//! the low half. Vectors are handled as `u128` with element 0 in the least significant bits, a
+//! doubleword operand in the low 64 bits.
+//!
+//! Floating-point operations only exist in single precision and use the standard FPSCR value:
+//! round to nearest, flush to zero and default NaN, whatever the FPSCR says. Only the cumulative
+//! exception flags they raise are written back. Saturating instructions set the sticky QC flag.
+
+use crate::{
+    cpu::{
+        Cpu,
+        decoder::{Decoded, InstructionKind, Operands},
+        features::Features,
+        fpu::{self, SINGLE},
+    },
+    err,
+};
+
+/// VLD1-VLD4 and VST1-VST4
+mod structure;
+
+/// Cumulative saturation bit of the FPSCR
+const QC: u32 = 1 << 27;
+
+fn mask(esize: u32) -> u64 {
+    u64::MAX >> (64 - esize)
+}
+
+/// element `e` of `esize` bits
+pub(super) fn elem(v: u128, e: usize, esize: u32) -> u64 {
+    (v >> (e as u32 * esize)) as u64 & mask(esize)
+}
+
+/// replace element `e` of `esize` bits with the low bits of `x`
+pub(super) fn set_elem(v: u128, e: usize, esize: u32, x: u64) -> u128 {
+    let shift = e as u32 * esize;
+    (v & !((mask(esize) as u128) << shift)) | (((x & mask(esize)) as u128) << shift)
+}
+
+pub(super) fn sext(x: u64, esize: u32) -> i64 {
+    ((x << (64 - esize)) as i64) >> (64 - esize)
+}
+
+/// an element as a signed or unsigned integer
+fn ext(x: u64, esize: u32, unsigned: bool) -> i128 {
+    if unsigned {
+        x as i128
+    } else {
+        sext(x, esize) as i128
+    }
+}
+
+/// SatQ: clamp `value` to the range of an `esize` bit integer, noting saturation in `qc`
+fn sat(value: i128, esize: u32, unsigned: bool, qc: &mut bool) -> u64 {
+    let (min, max) = if unsigned {
+        (0, (1i128 << esize) - 1)
+    } else {
+        (-(1i128 << (esize - 1)), (1i128 << (esize - 1)) - 1)
+    };
+    *qc |= value < min || value > max;
+    value.clamp(min, max) as u64
+}
+
+fn ones_if(condition: bool) -> u64 {
+    if condition { u64::MAX } else { 0 }
+}
+
+/// Build a vector of `elements` elements of `esize` bits from `f(e)`
+fn map(elements: usize, esize: u32, mut f: impl FnMut(usize) -> u64) -> u128 {
+    (0..elements).fold(0, |v, e| set_elem(v, e, esize, f(e)))
+}
+
+/// Carry-less multiplication of the polynomials over {0, 1} `a` and `b`
+fn poly_mul(a: u64, b: u64) -> u64 {
+    (0..32)
+        .filter(|i| b >> i & 1 == 1)
+        .fold(0, |acc, i| acc ^ (a << i))
+}
+
+/// Shift `value` left by `shift`, right by `-shift` if negative, as VSHL, VRSHL, VQSHL and
+/// VQRSHL do with the shift amounts of a register
+fn shift(
+    value: i128,
+    shift: i32,
+    round: bool,
+    saturating: bool,
+    esize: u32,
+    unsigned: bool,
+    qc: &mut bool,
+) -> u64 {
+    if shift >= 0 {
+        if !saturating {
+            return if shift >= 64 {
+                0
+            } else {
+                (value as u64) << shift
+            };
+        }
+        // anything nonzero shifted out of an i128 saturates anyway
+        let shift = shift as u32;
+        let exact = if shift >= esize || value.unsigned_abs() >> (126 - shift) != 0 {
+            value.signum() << 100
+        } else {
+            value << shift
+        };
+        return sat(exact, esize, unsigned, qc);
+    }
+    let amount = (-shift).min(100) as u32;
+    let exact = if round {
+        (value + (1 << (amount - 1))) >> amount
+    } else {
+        value >> amount
+    };
+    if saturating {
+        sat(exact, esize, unsigned, qc)
+    } else {
+        exact as u64
+    }
+}
+
+/// VQDMULH and VQRDMULH: the high half of the doubled product, saturated
+fn doubling_high(x: i128, y: i128, round: bool, esize: u32, qc: &mut bool) -> u64 {
+    let product = 2 * x * y + if round { 1 << (esize - 1) } else { 0 };
+    sat(product >> esize, esize, false, qc)
+}
+
+/// AdvSIMDExpandImm: the 64-bit pattern of the one register and modified immediate
+/// instructions, `None` for the undefined cmode 1111 with op set
+fn expand_imm(op: bool, cmode: u32, imm8: u32) -> Option<u64> {
+    let imm8 = imm8 as u64;
+    let replicate32 = |x: u64| x | (x << 32);
+    Some(match cmode >> 1 {
+        0..=3 => replicate32(imm8 << (8 * (cmode >> 1))),
+        4 | 5 => (imm8 << (8 * ((cmode >> 1) & 1))) * 0x0001_0001_0001_0001,
+        6 if cmode & 1 == 0 => replicate32((imm8 << 8) | 0xff),
+        6 => replicate32((imm8 << 16) | 0xffff),
+        _ => match (cmode & 1, op) {
+            (0, false) => imm8 * 0x0101_0101_0101_0101,
+            // every bit of imm8 expands to a byte
+            (0, true) => (0..8).fold(0, |x, i| x | ((imm8 >> i & 1) * 0xff) << (8 * i)),
+            (_, false) => replicate32(fpu::expand_imm(SINGLE, imm8 as u32)),
+            (_, true) => return None,
+        },
+    })
+}
+
+/// FPCompareEQ, FPCompareGE and FPCompareGT as all ones or zero, unordered is false
+fn compare(a: u64, b: u64, greater: bool, equal: bool, fpscr: &mut u32) -> u64 {
+    // VCEQ only signals on signaling NaNs, the ordered comparisons on every NaN
+    let flags = fpu::compare(SINGLE, a, b, greater, fpscr);
+    ones_if((greater && flags == 0b0010 << 28) || (equal && flags == 0b0110 << 28))
+}
+
+impl<'cpu, const PRINT_INSTR: bool> Cpu<'cpu, PRINT_INSTR> {
+    /// Q`n`, the pair D2n:D2n+1
+    pub fn q(&self, n: usize) -> u128 {
+        self.d[2 * n] as u128 | (self.d[2 * n + 1] as u128) << 64
+    }
+
+    pub fn set_q(&mut self, n: usize, value: u128) {
+        self.d[2 * n] = value as u64;
+        self.d[2 * n + 1] = (value >> 64) as u64;
+    }
+
+    /// read D`d`, or with `quad` the Q register starting at D`d`
+    fn vreg(&self, d: usize, quad: bool) -> u128 {
+        if quad {
+            self.q(d / 2)
+        } else {
+            self.d[d] as u128
+        }
+    }
+
+    fn set_vreg(&mut self, d: usize, quad: bool, value: u128) {
+        if quad {
+            self.set_q(d / 2, value);
+        } else {
+            self.d[d] = value as u64;
+        }
+    }
+
+    /// Set QC if a saturating instruction saturated
+    fn set_qc(&mut self, saturated: bool) {
+        if saturated {
+            self.fpscr |= QC;
+        }
+    }
+
+    /// Execute an Advanced SIMD instruction from the unconditional space.
+    pub(super) fn neon(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        if !self.features.contains(Features::NEON) {
+            return self.undefined(decoded.raw);
+        }
+
+        match decoded.kind {
+            InstructionKind::NeonThreeSame => self.neon_three_same(decoded),
+            InstructionKind::NeonThreeDifferent => self.neon_three_different(decoded),
+            InstructionKind::NeonScalar => self.neon_scalar(decoded),
+            InstructionKind::NeonShift => self.neon_shift(decoded),
+            InstructionKind::NeonModifiedImm => self.neon_modified_imm(decoded),
+            InstructionKind::NeonMisc => self.neon_misc(decoded),
+            InstructionKind::NeonVext => self.neon_vext(decoded),
+            InstructionKind::NeonVtbl => self.neon_vtbl(decoded),
+            InstructionKind::NeonVdupScalar => self.neon_vdup_scalar(decoded),
+            InstructionKind::NeonLoadStoreMultiple
+            | InstructionKind::NeonLoadStoreLane
+            | InstructionKind::NeonLoadAllLanes => self.neon_load_store(decoded),
+            _ => self.undefined(decoded.raw),
+        }
+    }
+
+    /// VDUP (ARM core register): replicate the low byte, halfword (E) or word (B clear) of
+    /// `value` into D`d`, into Q with Q set. B and Q are bits 3 and 2 of the op of
+    /// [`InstructionKind::VfpTransfer`], E is bit 0.
+    pub(super) fn vdup_core(
+        &mut self,
+        decoded: &Decoded,
+        d: usize,
+        value: u32,
+    ) -> Result<(), err::Err> {
+        let (op, raw) = (decoded.operands.op, decoded.raw);
+        let quad = op & 0b100 != 0;
+        let esize = match (op & 0b1000 != 0, op & 1 == 1) {
+            (false, false) => 32,
+            (false, true) => 16,
+            (true, false) => 8,
+            (true, true) => return self.undefined(raw),
+        };
+        if !self.features.contains(Features::NEON) || (quad && d & 1 == 1) {
+            return self.undefined(raw);
+        }
+        let elements = if quad { 128 } else { 64 } / esize as usize;
+        self.set_vreg(d, quad, map(elements, esize, |_| value as u64));
+        Ok(())
+    }
+
+    /// Three registers of the same length, the operation selected by A in bits 11..8, B in bit 4
+    /// and U. Element size is 8 << size.
+    fn neon_three_same(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            vd: d,
+            vn: n,
+            vm: m,
+            op,
+            size,
+            u,
+            q: quad,
+            ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+        if quad && (d | n | m) & 1 == 1 {
+            return self.undefined(raw);
+        }
+        let (a, b) = (op >> 1, op & 1 == 1);
+        match a {
+            // 1100 without b are the ARMv8 SHA instructions
+            0b1100 if !b => return self.crypto_three_registers(decoded),
+            0b1100..=0b1111 => return self.neon_three_same_float(decoded),
+            _ => {}
+        }
+
+        let (x, y, acc) = (self.vreg(n, quad), self.vreg(m, quad), self.vreg(d, quad));
+        let esize = 8 << size;
+        let elements = if quad { 128 } else { 64 } / esize as usize;
+        let width = if quad { u128::MAX } else { u64::MAX as u128 };
+
+        // VAND, VBIC, VORR, VORN, VEOR, VBSL, VBIT and VBIF work on the whole register
+        if a == 0b0001 && b {
+            let result = match (u, size) {
+                (false, 0b00) => x & y,
+                (false, 0b01) => x & !y,
+                (false, 0b10) => x | y,
+                (false, _) => x | !y,
+                (true, 0b00) => x ^ y,
+                (true, 0b01) => (acc & x) | (!acc & y),
+                (true, 0b10) => (x & y) | (acc & !y),
+                (true, _) => (acc & y) | (x & !y),
+            };
+            self.set_vreg(d, quad, result & width);
+            return Ok(());
+        }
+
+        // VPMAX, VPMIN and VPADD combine adjacent elements of Dn, then of Dm
+        if a == 0b1010 || (a == 0b1011 && b) {
+            if quad || size == 0b11 {
+                return self.undefined(raw);
+            }
+            let half = elements / 2;
+            let result = map(elements, esize, |e| {
+                let (src, i) = if e < half {
+                    (x, 2 * e)
+                } else {
+                    (y, 2 * (e - half))
+                };
+                let (p, q) = (elem(src, i, esize), elem(src, i + 1, esize));
+                let (sp, sq) = (ext(p, esize, u), ext(q, esize, u));
+                match (a, b) {
+                    (0b1010, false) => sp.max(sq) as u64,
+                    (0b1010, true) => sp.min(sq) as u64,
+                    _ => p.wrapping_add(q),
+                }
+            });
+            self.set_vreg(d, quad, result);
+            return Ok(());
+        }
+
+        // only the saturating and plain additions, subtractions and the shifts have 64-bit
+        // elements
+        let doubleword = matches!(
+            (a, b),
+            (0b0000, true) | (0b0010, true) | (0b0100, _) | (0b0101, _) | (0b1000, false)
+        );
+        let multiply_size = match (a, b, u) {
+            (0b1001, true, true) => size == 0b00,
+            (0b1011, false, _) => size == 0b01 || size == 0b10,
+            _ => true,
+        };
+        if (size == 0b11 && !doubleword) || !multiply_size {
+            return self.undefined(raw);
+        }
+
+        let mut qc = false;
+        let result = map(elements, esize, |e| {
+            let (p, q, r) = (elem(x, e, esize), elem(y, e, esize), elem(acc, e, esize));
+            let (sp, sq) = (ext(p, esize, u), ext(q, esize, u));
+            match (a, b) {
+                // VHADD, VQADD, VRHADD, VHSUB and VQSUB
+                (0b0000, false) => ((sp + sq) >> 1) as u64,
+                (0b0000, true) => sat(sp + sq, esize, u, &mut qc),
+                (0b0001, _) => ((sp + sq + 1) >> 1) as u64,
+                (0b0010, false) => ((sp - sq) >> 1) as u64,
+                (0b0010, true) => sat(sp - sq, esize, u, &mut qc),
+                // VCGT and VCGE
+                (0b0011, false) => ones_if(sp > sq),
+                (0b0011, true) => ones_if(sp >= sq),
+                // VSHL, VQSHL, VRSHL and VQRSHL shift Vm by the signed low byte of Vn
+                (0b0100 | 0b0101, saturating) => shift(
+                    sq,
+                    p as i8 as i32,
+                    a == 0b0101,
+                    saturating,
+                    esize,
+                    u,
+                    &mut qc,
+                ),
+                (0b0110, false) => sp.max(sq) as u64,
+                (0b0110, true) => sp.min(sq) as u64,
+                // VABD and VABA
+                (0b0111, false) => (sp - sq).unsigned_abs() as u64,
+                (0b0111, true) => r.wrapping_add((sp - sq).unsigned_abs() as u64),
+                (0b1000, false) if u => p.wrapping_sub(q),
+                (0b1000, false) => p.wrapping_add(q),
+                // VCEQ and VTST
+                (0b1000, true) if u => ones_if(p == q),
+                (0b1000, true) => ones_if(p & q != 0),
+                // VMLS and VMLA
+                (0b1001, false) if u => r.wrapping_sub(p.wrapping_mul(q)),
+                (0b1001, false) => r.wrapping_add(p.wrapping_mul(q)),
+                // VMUL, polynomial with U
+                (0b1001, true) if u => poly_mul(p, q),
+                (0b1001, true) => p.wrapping_mul(q),
+                // VQDMULH and VQRDMULH
+                _ => doubling_high(
+                    sext(p, esize) as i128,
+                    sext(q, esize) as i128,
+                    u,
+                    esize,
+                    &mut qc,
+                ),
+            }
+        });
+        self.set_qc(qc);
+        self.set_vreg(d, quad, result);
+        Ok(())
+    }
+
+    /// The single precision operations of the three register space, the high bit of size
+    /// distinguishes the pairs of operations sharing A, B and U
+    fn neon_three_same_float(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            vd: d,
+            vn: n,
+            vm: m,
+            size,
+            u,
+            q: quad,
+            ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+        // the low bit of size would be half precision
+        if size & 1 == 1 {
+            return self.undefined(raw);
+        }
+        let (a, b) = (decoded.operands.op >> 1, decoded.operands.op & 1 == 1);
+        let op = size >> 1 == 1;
+        let (x, y, acc) = (self.vreg(n, quad), self.vreg(m, quad), self.vreg(d, quad));
+        let elements = if quad { 4 } else { 2 };
+        let mut fpscr = fpu::FZ | fpu::DN;
+        let fused = self.features.contains(Features::VFPV4);
+
+        let operation: fn(u64, u64, u64, bool, &mut u32) -> u64 = match (a, b, u) {
+            // VADD and VSUB, VPADD and VABD
+            (0b1101, false, false) => |p, q, _, op, fs| fpu::add(SINGLE, p, q, op, fs),
+            (0b1101, false, true) if !op => |p, q, _, _, fs| fpu::add(SINGLE, p, q, false, fs),
+            (0b1101, false, true) => {
+                |p, q, _, _, fs| fpu::abs(SINGLE, fpu::add(SINGLE, p, q, true, fs))
+            }
+            // VMLA and VMLS round the product first
+            (0b1101, true, false) => |p, q, r, op, fs| {
+                let product = fpu::mul(SINGLE, p, q, fs);
+                fpu::add(SINGLE, r, product, op, fs)
+            },
+            (0b1101, true, true) if !op => |p, q, _, _, fs| fpu::mul(SINGLE, p, q, fs),
+            // VFMA and VFMS (op negates the first operand) are VFPv4
+            (0b1100, true, false) if fused => |p, q, r, op, fs| {
+                let p = if op { fpu::neg(SINGLE, p) } else { p };
+                fpu::mul_add(SINGLE, r, p, q, fs)
+            },
+            // VCEQ, VCGE and VCGT, VACGE and VACGT compare the absolute values
+            (0b1110, false, false) if !op => |p, q, _, _, fs| compare(p, q, false, true, fs),
+            (0b1110, false, true) => |p, q, _, op, fs| compare(p, q, true, !op, fs),
+            (0b1110, true, true) => {
+                |p, q, _, op, fs| compare(fpu::abs(SINGLE, p), fpu::abs(SINGLE, q), true, !op, fs)
+            }
+            // VMAX and VMIN, VPMAX and VPMIN
+            (0b1111, false, _) => |p, q, _, op, fs| fpu::max_min(SINGLE, p, q, !op, fs),
+            // VRECPS and VRSQRTS
+            (0b1111, true, false) if !op => |p, q, _, _, fs| fpu::recip_step(SINGLE, p, q, fs),
+            (0b1111, true, false) => |p, q, _, _, fs| fpu::rsqrt_step(SINGLE, p, q, fs),
+            _ => return self.undefined(raw),
+        };
+
+        let pairwise = u && !b && (a == 0b1111 || (a == 0b1101 && !op));
+        let result = if pairwise {
+            if quad {
+                return self.undefined(raw);
+            }
+            map(2, 32, |e| {
+                let src = if e == 0 { x } else { y };
+                operation(elem(src, 0, 32), elem(src, 1, 32), 0, op, &mut fpscr)
+            })
+        } else {
+            map(elements, 32, |e| {
+                let (p, q, r) = (elem(x, e, 32), elem(y, e, 32), elem(acc, e, 32));
+                operation(p, q, r, op, &mut fpscr)
+            })
+        };
+        self.fpscr |= fpscr & fpu::CUMULATIVE;
+        self.set_vreg(d, quad, result);
+        Ok(())
+    }
+
+    /// VMOV, VMVN, VORR and VBIC with the immediate of [`expand_imm`], selected by cmode and op
+    fn neon_modified_imm(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            vd: d,
+            imm: imm8,
+            q: quad,
+            ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+        let (cmode, op) = (decoded.operands.op >> 1, decoded.operands.op & 1 == 1);
+        let Some(imm64) = expand_imm(op, cmode, imm8) else {
+            return self.undefined(raw);
+        };
+        if quad && d & 1 == 1 {
+            return self.undefined(raw);
+        }
+
+        let imm = if quad {
+            imm64 as u128 | (imm64 as u128) << 64
+        } else {
+            imm64 as u128
+        };
+        let width = if quad { u128::MAX } else { u64::MAX as u128 };
+        let old = self.vreg(d, quad);
+        // the odd cmodes below 1100 are VORR and VBIC, op inverts the rest but for the 64-bit
+        // byte mask
+        let result = if cmode < 0b1100 && cmode & 1 == 1 {
+            if op { old & !imm } else { old | imm }
+        } else if op && cmode != 0b1110 {
+            !imm & width
+        } else {
+            imm
+        };
+        self.set_vreg(d, quad, result);
+        Ok(())
+    }
+
+    /// Two registers and a shift amount, the operation selected by A in bits 11..8 and U. L and
+    /// the position of the leading one of imm6 give the element size, the rest of imm6 the shift
+    /// amount.
+    fn neon_shift(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            vd: d,
+            vm: m,
+            op: a,
+            imm,
+            u,
+            q: quad,
+            ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+        let imm6 = imm & 0b11_1111;
+        let esize = if imm >> 6 == 1 {
+            64
+        } else if imm6 >= 32 {
+            32
+        } else if imm6 >= 16 {
+            16
+        } else {
+            8
+        };
+        let (right, left) = if esize == 64 {
+            (64 - imm6, imm6)
+        } else {
+            (2 * esize - imm6, imm6 - esize)
+        };
+
+        // the narrowing shifts read a Q register and write a D register, VSHLL the other way
+        // around, their element size is the one of the narrow vector
+        match a {
+            0b1000 | 0b1001 => {
+                if esize == 64 || m & 1 == 1 {
+                    return self.undefined(raw);
+                }
+                let x = self.q(m / 2);
+                // bit 6 is no Q bit here but selects the rounding forms
+                let round = quad;
+                let mut qc = false;
+                let result = map(64 / esize as usize, esize, |e| {
+                    // VSHRN and VRSHRN, VQSHRUN and VQRSHRUN, VQSHRN and VQRSHRN
+                    let (src_unsigned, dst_unsigned) = match (a, u) {
+                        (0b1000, false) => (true, true),
+                        (0b1000, true) => (false, true),
+                        _ => (u, u),
+                    };
+                    let value = ext(elem(x, e, 2 * esize), 2 * esize, src_unsigned);
+                    let saturating = a == 0b1001 || u;
+                    shift(
+                        value,
+                        -(right as i32),
+                        round,
+                        saturating,
+                        esize,
+                        dst_unsigned,
+                        &mut qc,
+                    )
+                });
+                self.set_qc(qc);
+                self.d[d] = result as u64;
+                return Ok(());
+            }
+            // VSHLL, with a zero shift VMOVL
+            0b1010 if !quad => {
+                if esize == 64 || d & 1 == 1 {
+                    return self.undefined(raw);
+                }
+                let x = self.d[m] as u128;
+                let result = map(64 / esize as usize, 2 * esize, |e| {
+                    (ext(elem(x, e, esize), esize, u) << left) as u64
+                });
+                self.set_q(d / 2, result);
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        if quad && (d | m) & 1 == 1 {
+            return self.undefined(raw);
+        }
+        let (x, acc) = (self.vreg(m, quad), self.vreg(d, quad));
+        let elements = if quad { 128 } else { 64 } / esize as usize;
+
+        // VCVT between single precision and fixed point with 64 - imm6 fraction bits, op in
+        // bit 8 converts to fixed point
+        if a & 0b1110 == 0b1110 {
+            if esize != 32 {
+                return self.undefined(raw);
+            }
+            let frac_bits = 64 - imm6;
+            let mut fpscr = fpu::FZ | fpu::DN;
+            let result = map(elements, 32, |e| {
+                let value = elem(x, e, 32);
+                if a & 1 == 1 {
+                    fpu::to_fixed(
+                        SINGLE,
+                        value,
+                        frac_bits,
+                        u,
+                        32,
+                        fpu::Rounding::Zero,
+                        &mut fpscr,
+                    ) as u64
+                } else {
+                    fpu::from_fixed(SINGLE, value as u32, frac_bits, u, 32, &mut fpscr)
+                }
+            });
+            self.fpscr |= fpscr & fpu::CUMULATIVE;
+            self.set_vreg(d, quad, result);
+            return Ok(());
+        }
+
+        let defined = matches!(
+            (a, u),
+            (0b0000..=0b0011, _) | (0b0100, true) | (0b0101, _) | (0b0110, true) | (0b0111, _)
+        );
+        if !defined {
+            return self.undefined(raw);
+        }
+        let mut qc = false;
+        let result = map(elements, esize, |e| {
+            let (value, old) = (elem(x, e, esize), elem(acc, e, esize));
+            match (a, u) {
+                // VSHR, VSRA, VRSHR and VRSRA
+                (0b0000..=0b0011, _) => {
+                    let round = a & 0b10 != 0;
+                    let shifted = shift(
+                        ext(value, esize, u),
+                        -(right as i32),
+                        round,
+                        false,
+                        esize,
+                        u,
+                        &mut qc,
+                    );
+                    if a & 1 == 1 {
+                        old.wrapping_add(shifted)
+                    } else {
+                        shifted
+                    }
+                }
+                // VSRI keeps the top bits of the destination
+                (0b0100, true) => {
+                    let kept = if right == esize {
+                        mask(esize)
+                    } else {
+                        !(mask(esize) >> right)
+                    };
+                    (old & kept) | value.checked_shr(right).unwrap_or(0)
+                }
+                (0b0101, false) => value << left,
+                // VSLI keeps the bottom bits of the destination
+                (0b0101, true) => (old & ((1 << left) - 1)) | (value << left),
+                // VQSHLU reads signed and saturates unsigned, VQSHL keeps the signedness
+                (0b0110, true) => shift(
+                    ext(value, esize, false),
+                    left as i32,
+                    false,
+                    true,
+                    esize,
+                    true,
+                    &mut qc,
+                ),
+                (0b0111, _) => shift(
+                    ext(value, esize, u),
+                    left as i32,
+                    false,
+                    true,
+                    esize,
+                    u,
+                    &mut qc,
+                ),
+                _ => unreachable!("undefined shifts are rejected above"),
+            }
+        });
+        self.set_qc(qc);
+        self.set_vreg(d, quad, result);
+        Ok(())
+    }
+
+    /// Three registers of different lengths: the long operations write Q from two D, the wide
+    /// ones Q from Q and D, the narrowing ones the high halves of a Q operation to D. Element
+    /// size is the one of the narrow vector, 8 << size, the operation selected by A in bits 11..8
+    /// and U.
+    fn neon_three_different(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            vd: d,
+            vn: n,
+            vm: m,
+            op: a,
+            size,
+            u,
+            ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+        let esize = 8 << size;
+        let elements = 64 / esize as usize;
+
+        // VADDHN, VRADDHN, VSUBHN and VRSUBHN
+        if a == 0b0100 || a == 0b0110 {
+            if (n | m) & 1 == 1 {
+                return self.undefined(raw);
+            }
+            let (x, y) = (self.q(n / 2), self.q(m / 2));
+            let round = if u { 1 << (esize - 1) } else { 0 };
+            self.d[d] = map(elements, esize, |e| {
+                let (p, q) = (elem(x, e, 2 * esize), elem(y, e, 2 * esize));
+                let result = if a == 0b0100 {
+                    p.wrapping_add(q)
+                } else {
+                    p.wrapping_sub(q)
+                };
+                result.wrapping_add(round) >> esize
+            }) as u64;
+            return Ok(());
+        }
+
+        if a == 0b1110 && !u && size == 0b10 {
+            return self.crypto_vmull_p64(decoded);
+        }
+
+        let wide = a == 0b0001 || a == 0b0011;
+        let doubling = matches!(a, 0b1001 | 0b1011 | 0b1101);
+        let defined = match a {
+            0b0000..=0b0011 | 0b0101 | 0b0111 | 0b1000 | 0b1010 | 0b1100 => true,
+            _ if doubling => !u && size != 0b00,
+            0b1110 => !u && size == 0b00,
+            _ => false,
+        };
+        if !defined || d & 1 == 1 || (wide && n & 1 == 1) {
+            return self.undefined(raw);
+        }
+
+        let x = if wide {
+            self.q(n / 2)
+        } else {
+            self.d[n] as u128
+        };
+        let (y, acc) = (self.d[m] as u128, self.q(d / 2));
+        let mut qc = false;
+        let result = map(elements, 2 * esize, |e| {
+            let p = if wide {
+                ext(elem(x, e, 2 * esize), 2 * esize, u)
+            } else {
+                ext(elem(x, e, esize), esize, u)
+            };
+            let q = ext(elem(y, e, esize), esize, u);
+            let r = elem(acc, e, 2 * esize);
+            long_op(a, p, q, r, esize, &mut qc)
+        });
+        self.set_qc(qc);
+        self.set_q(d / 2, result);
+        Ok(())
+    }
+
+    /// Two registers and a scalar, the scalar being element M:Vm<3> of D0-D7 for halfwords and
+    /// element M of D0-D15 for words. For the operations keeping the element size bit 24 is Q,
+    /// for the long ones U. The operation is selected by A in bits 11..8.
+    fn neon_scalar(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            vd: d,
+            vn: n,
+            vm,
+            op: a,
+            size,
+            u,
+            q: quad,
+            ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+        let esize = 8 << size;
+        // Vm is M:Vm<3:0>
+        let (dm, index) = match size {
+            0b01 => (vm & 0b111, ((vm >> 4) << 1) | ((vm >> 3) & 1)),
+            0b10 => (vm & 0b1111, vm >> 4),
+            _ => return self.undefined(raw),
+        };
+        let scalar = elem(self.d[dm] as u128, index, esize);
+        let mut qc = false;
+
+        match a {
+            // VMLA, VMLS and VMUL, bit 8 selects floating point
+            0b0000 | 0b0001 | 0b0100 | 0b0101 | 0b1000 | 0b1001 | 0b1100 | 0b1101 => {
+                let float = a & 0b1100 != 0b1100 && a & 1 == 1;
+                if (quad && (d | n) & 1 == 1) || (float && size != 0b10) {
+                    return self.undefined(raw);
+                }
+                let (x, acc) = (self.vreg(n, quad), self.vreg(d, quad));
+                let elements = if quad { 128 } else { 64 } / esize as usize;
+                let mut fpscr = fpu::FZ | fpu::DN;
+                let result = map(elements, esize, |e| {
+                    let (p, r) = (elem(x, e, esize), elem(acc, e, esize));
+                    match a {
+                        0b1100 | 0b1101 => doubling_high(
+                            sext(p, esize) as i128,
+                            sext(scalar, esize) as i128,
+                            a == 0b1101,
+                            esize,
+                            &mut qc,
+                        ),
+                        _ if float => {
+                            let product = fpu::mul(SINGLE, p, scalar, &mut fpscr);
+                            match a {
+                                0b0001 => fpu::add(SINGLE, r, product, false, &mut fpscr),
+                                0b0101 => fpu::add(SINGLE, r, product, true, &mut fpscr),
+                                _ => product,
+                            }
+                        }
+                        0b0000 => r.wrapping_add(p.wrapping_mul(scalar)),
+                        0b0100 => r.wrapping_sub(p.wrapping_mul(scalar)),
+                        _ => p.wrapping_mul(scalar),
+                    }
+                });
+                self.fpscr |= fpscr & fpu::CUMULATIVE;
+                self.set_vreg(d, quad, result);
+            }
+            // VMLAL, VMLSL, VMULL and their saturating doubling forms, same as the three
+            // register forms with a different opcode
+            0b0010 | 0b0011 | 0b0110 | 0b0111 | 0b1010 | 0b1011 => {
+                if d & 1 == 1 || (a & 1 == 1 && u) {
+                    return self.undefined(raw);
+                }
+                let op = match a {
+                    0b0010 => 0b1000,
+                    0b0011 => 0b1001,
+                    0b0110 => 0b1010,
+                    0b0111 => 0b1011,
+                    0b1010 => 0b1100,
+                    _ => 0b1101,
+                };
+                let (x, acc) = (self.d[n] as u128, self.q(d / 2));
+                let q = ext(scalar, esize, u);
+                let result = map(64 / esize as usize, 2 * esize, |e| {
+                    let p = ext(elem(x, e, esize), esize, u);
+                    long_op(op, p, q, elem(acc, e, 2 * esize), esize, &mut qc)
+                });
+                self.set_q(d / 2, result);
+            }
+            _ => return self.undefined(raw),
+        }
+        self.set_qc(qc);
+        Ok(())
+    }
+
+    /// VEXT: the bytes of Vm:Vn starting at byte imm4
+    fn neon_vext(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            vd: d,
+            vn: n,
+            vm: m,
+            imm: imm4,
+            q: quad,
+            ..
+        } = decoded.operands;
+        if (quad && (d | n | m) & 1 == 1) || (!quad && imm4 > 7) {
+            return self.undefined(decoded.raw);
+        }
+        let bytes = if quad { 16 } else { 8 };
+        let (x, y) = (self.vreg(n, quad), self.vreg(m, quad));
+        let result = map(bytes, 8, |e| {
+            let i = e + imm4 as usize;
+            if i < bytes {
+                elem(x, i, 8)
+            } else {
+                elem(y, i - bytes, 8)
+            }
+        });
+        self.set_vreg(d, quad, result);
+        Ok(())
+    }
+
+    /// VTBL and VTBX (op): look up the bytes of Dm in a table of 1 + imm registers starting at
+    /// Dn. Out of range indices give zero respectively keep the destination byte.
+    fn neon_vtbl(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            vd: d,
+            vn: n,
+            vm: m,
+            imm,
+            op,
+            ..
+        } = decoded.operands;
+        let length = imm as usize + 1;
+        if n + length > 32 {
+            return Err(err::Err::UnpredictableInstruction(decoded.raw));
+        }
+        let table = &self.d[n..n + length];
+        let (indices, old) = (self.d[m] as u128, self.d[d] as u128);
+        self.d[d] = map(8, 8, |e| {
+            let i = elem(indices, e, 8) as usize;
+            if i < 8 * length {
+                table[i / 8] >> (8 * (i % 8)) & 0xff
+            } else if op == 1 {
+                elem(old, e, 8)
+            } else {
+                0
+            }
+        }) as u64;
+        Ok(())
+    }
+
+    /// VDUP (scalar): the lowest set bit of imm4 gives the element size, the bits above it the
+    /// index into Dm
+    fn neon_vdup_scalar(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            vd: d,
+            vm: m,
+            imm: imm4,
+            q: quad,
+            ..
+        } = decoded.operands;
+        if imm4 & 0b0111 == 0 || (quad && d & 1 == 1) {
+            return self.undefined(decoded.raw);
+        }
+        let size = imm4.trailing_zeros();
+        let esize = 8 << size;
+        let scalar = elem(self.d[m] as u128, (imm4 >> (size + 1)) as usize, esize);
+        let elements = if quad { 128 } else { 64 } / esize as usize;
+        self.set_vreg(d, quad, map(elements, esize, |_| scalar));
+        Ok(())
+    }
+
+    /// Two registers, miscellaneous: selected by A in bits 17..16 and B in bits 10..6, the
+    /// element size is 8 << size
+    fn neon_misc(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            vd: d,
+            vm: m,
+            op,
+            size,
+            q: quad,
+            ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+        let (a, b) = (op >> 5, op & 0b1_1111);
+        let esize = 8 << size;
+
+        // the ARMv8 AES and SHA instructions take unallocated ARMv7 encodings
+        if matches!(
+            (a, b),
+            (0b00, 0b01100..=0b01111) | (0b01, 0b01011) | (0b10, 0b01110 | 0b01111)
+        ) {
+            return self.crypto_two_registers(decoded);
+        }
+
+        // VMOVN, VQMOVUN, VQMOVN and VSHLL (maximum shift) change the element size
+        if a == 0b10 && (b >> 2 == 0b010 || b == 0b01100) {
+            if size == 0b11 {
+                return self.undefined(raw);
+            }
+            if b == 0b01100 {
+                if d & 1 == 1 {
+                    return self.undefined(raw);
+                }
+                let x = self.d[m] as u128;
+                let result = map(64 / esize as usize, 2 * esize, |e| {
+                    elem(x, e, esize) << esize
+                });
+                self.set_q(d / 2, result);
+                return Ok(());
+            }
+            if m & 1 == 1 {
+                return self.undefined(raw);
+            }
+            let x = self.q(m / 2);
+            let mut qc = false;
+            self.d[d] = map(64 / esize as usize, esize, |e| {
+                let value = elem(x, e, 2 * esize);
+                match b & 0b11 {
+                    0b00 => value,
+                    // VQMOVUN, VQMOVN.S and VQMOVN.U
+                    0b01 => sat(ext(value, 2 * esize, false), esize, true, &mut qc),
+                    0b10 => sat(ext(value, 2 * esize, false), esize, false, &mut qc),
+                    _ => sat(value as i128, esize, true, &mut qc),
+                }
+            }) as u64;
+            self.set_qc(qc);
+            return Ok(());
+        }
+
+        if quad && (d | m) & 1 == 1 {
+            return self.undefined(raw);
+        }
+        let x = self.vreg(m, quad);
+        let old = self.vreg(d, quad);
+        let elements = if quad { 128 } else { 64 } / esize as usize;
+        let op = b >> 1;
+
+        // VSWP, VTRN, VUZP and VZIP write both registers
+        if a == 0b10 && op <= 0b0011 {
+            let (new_d, new_m) = match op {
+                0b0000 if size == 0b00 => (x, old),
+                // VTRN swaps the odd elements of Vd with the even elements of Vm
+                0b0001 if size != 0b11 => {
+                    let new_d = map(elements, esize, |e| {
+                        elem(if e % 2 == 1 { x } else { old }, e & !1, esize)
+                    });
+                    let new_m = map(elements, esize, |e| {
+                        elem(if e % 2 == 0 { old } else { x }, e | 1, esize)
+                    });
+                    (new_d, new_m)
+                }
+                // VUZP takes the even elements of Vd:Vm to Vd and the odd ones to Vm
+                0b0010 if size != 0b11 && (quad || size != 0b10) => {
+                    let pick = |odd: usize| {
+                        map(elements, esize, |e| {
+                            let i = 2 * e + odd;
+                            if i < elements {
+                                elem(old, i, esize)
+                            } else {
+                                elem(x, i - elements, esize)
+                            }
+                        })
+                    };
+                    (pick(0), pick(1))
+                }
+                // VZIP interleaves Vd and Vm, the low half going to Vd
+                0b0011 if size != 0b11 && (quad || size != 0b10) => {
+                    let zip = |half: usize| {
+                        map(elements, esize, |e| {
+                            let i = half * elements + e;
+                            elem(if i & 1 == 0 { old } else { x }, i / 2, esize)
+                        })
+                    };
+                    (zip(0), zip(1))
+                }
+                _ => return self.undefined(raw),
+            };
+            self.set_vreg(d, quad, new_d);
+            self.set_vreg(m, quad, new_m);
+            return Ok(());
+        }
+
+        let float = a == 0b01 && b & 0b10000 != 0;
+        let defined = match (a, op) {
+            // VREV64, VREV32 and VREV16 reverse elements smaller than their region
+            (0b00, 0b0000..=0b0010) => esize < 64 >> op,
+            (0b00, 0b0100 | 0b0101 | 0b1100 | 0b1101 | 0b1000 | 0b1001 | 0b1110 | 0b1111) => {
+                size != 0b11
+            }
+            (0b00, 0b1010 | 0b1011) => size == 0b00,
+            (0b01, _) => op & 0b0111 != 0b0101 && size != 0b11 && (!float || size == 0b10),
+            (0b11, 0b1000..=0b1111) => size == 0b10,
+            _ => false,
+        };
+        if !defined {
+            return self.undefined(raw);
+        }
+
+        let mut qc = false;
+        let mut fpscr = fpu::FZ | fpu::DN;
+        let result = match (a, op) {
+            (0b00, 0b0000..=0b0010) => {
+                let per_region = (64 >> op) / esize as usize;
+                map(elements, esize, |e| {
+                    let base = e / per_region * per_region;
+                    elem(x, base + per_region - 1 - e % per_region, esize)
+                })
+            }
+            // VPADDL and VPADAL, bit 7 unsigned
+            (0b00, 0b0100 | 0b0101 | 0b1100 | 0b1101) => {
+                let unsigned = op & 1 == 1;
+                map(elements / 2, 2 * esize, |e| {
+                    let sum = ext(elem(x, 2 * e, esize), esize, unsigned)
+                        + ext(elem(x, 2 * e + 1, esize), esize, unsigned);
+                    let acc = if op & 0b1000 != 0 {
+                        elem(old, e, 2 * esize)
+                    } else {
+                        0
+                    };
+                    acc.wrapping_add(sum as u64)
+                })
+            }
+            (0b00, 0b1011) => !x & if quad { u128::MAX } else { u64::MAX as u128 },
+            _ => map(elements, esize, |e| {
+                let value = elem(x, e, esize);
+                let signed = sext(value, esize) as i128;
+                match (a, op) {
+                    // VCLS counts the bits below the sign bit equal to it
+                    (0b00, 0b1000) => {
+                        let flipped = (signed ^ (signed >> 1)) as u64 & mask(esize);
+                        (flipped.leading_zeros() - (64 - esize) - 1) as u64
+                    }
+                    (0b00, 0b1001) => (value.leading_zeros() - (64 - esize)) as u64,
+                    (0b00, 0b1010) => value.count_ones() as u64,
+                    (0b00, 0b1110) => sat(signed.abs(), esize, false, &mut qc),
+                    (0b00, 0b1111) => sat(-signed, esize, false, &mut qc),
+                    // VCGT, VCGE, VCEQ, VCLE and VCLT with zero
+                    (0b01, op) if float => {
+                        let fs = &mut fpscr;
+                        match op & 0b0111 {
+                            0b000 => compare(value, 0, true, false, fs),
+                            0b001 => compare(value, 0, true, true, fs),
+                            0b010 => compare(value, 0, false, true, fs),
+                            0b011 => compare(0, value, true, true, fs),
+                            0b100 => compare(0, value, true, false, fs),
+                            0b110 => fpu::abs(SINGLE, value),
+                            _ => fpu::neg(SINGLE, value),
+                        }
+                    }
+                    (0b01, op) => match op & 0b0111 {
+                        0b000 => ones_if(signed > 0),
+                        0b001 => ones_if(signed >= 0),
+                        0b010 => ones_if(signed == 0),
+                        0b011 => ones_if(signed <= 0),
+                        0b100 => ones_if(signed < 0),
+                        // VABS and VNEG wrap for the most negative value
+                        0b110 => signed.unsigned_abs() as u64,
+                        _ => (-signed) as u64,
+                    },
+                    // VRECPE and VRSQRTE, bit 8 selects floating point
+                    (_, 0b1000) => fpu::unsigned_recip_estimate(value as u32) as u64,
+                    (_, 0b1001) => fpu::unsigned_rsqrt_estimate(value as u32) as u64,
+                    (_, 0b1010) => fpu::recip_estimate(value as u32, &mut fpscr) as u64,
+                    (_, 0b1011) => fpu::rsqrt_estimate(value as u32, &mut fpscr) as u64,
+                    // VCVT, bits 8..7 are the signed and unsigned conversions to and from integer
+                    (_, 0b1100) => fpu::from_fixed(SINGLE, value as u32, 0, false, 32, &mut fpscr),
+                    (_, 0b1101) => fpu::from_fixed(SINGLE, value as u32, 0, true, 32, &mut fpscr),
+                    (_, op) => {
+                        let unsigned = op == 0b1111;
+                        let rounding = fpu::Rounding::Zero;
+                        fpu::to_fixed(SINGLE, value, 0, unsigned, 32, rounding, &mut fpscr) as u64
+                    }
+                }
+            }),
+        };
+        self.set_qc(qc);
+        self.fpscr |= fpscr & fpu::CUMULATIVE;
+        self.set_vreg(d, quad, result);
+        Ok(())
+    }
+}
+
+/// The element operation of the long instructions by opcode of the three register form:
+/// VADDL/VADDW, VSUBL/VSUBW, VABAL, VABDL, VMLAL, VMLSL, VMULL and the saturating doubling
+/// VQDMLAL, VQDMLSL and VQDMULL. `r` is the accumulator element of twice `esize` bits.
+fn long_op(a: u32, p: i128, q: i128, r: u64, esize: u32, qc: &mut bool) -> u64 {
+    let wide = 2 * esize;
+    match a {
+        0b0000 | 0b0001 => (p + q) as u64,
+        0b0010 | 0b0011 => (p - q) as u64,
+        0b0101 => r.wrapping_add((p - q).unsigned_abs() as u64),
+        0b0111 => (p - q).unsigned_abs() as u64,
+        0b1000 => r.wrapping_add((p * q) as u64),
+        0b1010 => r.wrapping_sub((p * q) as u64),
+        0b1100 => (p * q) as u64,
+        0b1110 => poly_mul(p as u64 & mask(esize), q as u64 & mask(esize)),
+        _ => {
+            let product = sat(2 * p * q, wide, false, qc) as i128;
+            let product = sext(product as u64, wide) as i128;
+            let acc = sext(r, wide) as i128;
+            match a {
+                0b1001 => sat(acc + product, wide, false, qc),
+                0b1011 => sat(acc - product, wide, false, qc),
+                _ => product as u64,
+            }
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! The element and structure loads and stores, see ARMv7 ARM A7.7. A structure of n elements
+//! is spread over n registers spaced 1 or 2 apart. The multiple structure forms transfer every
+//! element of the registers, the single lane forms one element of each register and the all
+//! lanes forms load one structure and replicate it into every element.
+
+use super::{elem, set_elem};
+use crate::{
+    cpu::{
+        Cpu,
+        decoder::{Decoded, InstructionKind, Operands},
+    },
+    err,
+};
+
+/// What a VLDn/VSTn transfers, in memory order
+struct Transfer {
+    /// register and element index of every element
+    elements: Vec<(usize, usize)>,
+    /// element size in bytes
+    ebytes: u32,
+    /// the address must be a multiple of this
+    alignment: u32,
+    /// all lanes: every element loaded is replicated into the whole register
+    replicate: bool,
+    /// number of consecutive registers starting at the given one receiving each element, only
+    /// VLD1 to all lanes has two
+    copies: usize,
+}
+
+impl<'cpu, const PRINT_INSTR: bool> Cpu<'cpu, PRINT_INSTR> {
+    /// VLD1-VLD4 and VST1-VST4, L loads. The base Rn is written back with the transfer size for
+    /// Rm = sp, increased by Rm for the other registers but pc.
+    pub(super) fn neon_load_store(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let operands = &decoded.operands;
+        let Operands {
+            l: load, rn, rm, ..
+        } = *operands;
+        let raw = decoded.raw;
+        let transfer = match decoded.kind {
+            InstructionKind::NeonLoadStoreMultiple => multiple(operands),
+            InstructionKind::NeonLoadAllLanes => all_lanes(operands),
+            _ => single_lane(operands),
+        };
+        let Some(transfer) = transfer else {
+            return self.undefined(raw);
+        };
+        if rn == 15
+            || transfer
+                .elements
+                .iter()
+                .any(|&(d, _)| d + transfer.copies > 32)
+        {
+            return Err(err::Err::UnpredictableInstruction(raw));
+        }
+
+        let base = self.r[rn];
+        let fault = |addr: u32| err::Err::MemoryAccessViolation {
+            guest: addr,
+            instr: raw,
+        };
+        if !base.is_multiple_of(transfer.alignment) {
+            return Err(fault(base));
+        }
+
+        let esize = 8 * transfer.ebytes;
+        let mut addr = base;
+        for &(d, e) in &transfer.elements {
+            if load {
+                let value = match transfer.ebytes {
+                    1 => self.mem.read_u8(addr).map(u64::from),
+                    2 => self.mem.read_u16(addr).map(u64::from),
+                    4 => self.mem.read_u32(addr).map(u64::from),
+                    _ => self.mem.read_u64(addr),
+                }
+                .ok_or(fault(addr))?;
+                if transfer.replicate {
+                    let replicated = super::map(64 / esize as usize, esize, |_| value) as u64;
+                    self.d[d..d + transfer.copies].fill(replicated);
+                } else {
+                    self.d[d] = set_elem(self.d[d] as u128, e, esize, value) as u64;
+                }
+            } else {
+                self.monitor.store(addr, transfer.ebytes);
+                let value = elem(self.d[d] as u128, e, esize);
+                match transfer.ebytes {
+                    1 => self.mem.write_u8(addr, value as u8),
+                    2 => self.mem.write_u16(addr, value as u16),
+                    4 => self.mem.write_u32(addr, value as u32),
+                    _ => self.mem.write_u64(addr, value),
+                }
+                .map_err(|_| fault(addr))?;
+            }
+            addr = addr.wrapping_add(transfer.ebytes);
+        }
+
+        match rm {
+            15 => {}
+            13 => self.r[rn] = addr,
+            _ => self.r[rn] = base.wrapping_add(self.r[rm]),
+        }
+        Ok(())
+    }
+}
+
+/// The multiple structures forms starting at D`d`, with the type in `op` and align in `imm`
+fn multiple(operands: &Operands) -> Option<Transfer> {
+    let Operands {
+        vd: d,
+        op,
+        size,
+        imm: align,
+        ..
+    } = *operands;
+    // structure size, registers per element of the structure and their spacing
+    let (n, regs, inc) = match op {
+        0b0111 if align & 0b10 == 0 => (1, 1, 1),
+        0b1010 if align != 0b11 => (1, 2, 1),
+        0b0110 if align & 0b10 == 0 => (1, 3, 1),
+        0b0010 => (1, 4, 1),
+        0b1000 if align != 0b11 => (2, 1, 1),
+        0b1001 if align != 0b11 => (2, 1, 2),
+        0b0011 => (2, 2, 2),
+        0b0100 if align & 0b10 == 0 => (3, 1, 1),
+        0b0101 if align & 0b10 == 0 => (3, 1, 2),
+        0b0000 => (4, 1, 1),
+        0b0001 => (4, 1, 2),
+        _ => return None,
+    };
+    if n > 1 && size == 0b11 {
+        return None;
+    }
+
+    let ebytes = 1 << size;
+    let mut elements = Vec::new();
+    for r in 0..regs {
+        for e in 0..8 / ebytes as usize {
+            elements.extend((0..n).map(|s| (d + r + s * inc, e)));
+        }
+    }
+    Some(Transfer {
+        elements,
+        ebytes,
+        alignment: if align == 0 { 1 } else { 4 << align },
+        replicate: false,
+        copies: 1,
+    })
+}
+
+/// The all lanes forms starting at D`d`, with T:a in `imm` and the structure size minus one in
+/// `op`
+fn all_lanes(operands: &Operands) -> Option<Transfer> {
+    let Operands {
+        vd: d,
+        op,
+        size,
+        imm,
+        ..
+    } = *operands;
+    let n = op as usize + 1;
+    let (t, a) = (imm >> 1 == 1, imm & 1 == 1);
+    let (ebytes, alignment) = match (n, size) {
+        (4, 0b11) => (4, if a { 16 } else { 1 }),
+        (_, 0b11) => return None,
+        (3, _) if a => return None,
+        (4, 0b10) if a => (4, 8),
+        (n, size) => (1 << size, if a { n as u32 * (1 << size) } else { 1 }),
+    };
+    // VLD1 loads one element into one or two registers
+    let (elements, copies) = if n == 1 {
+        (vec![(d, 0)], 1 + t as usize)
+    } else {
+        ((0..n).map(|s| (d + s * (1 + t as usize), 0)).collect(), 1)
+    };
+    Some(Transfer {
+        elements,
+        ebytes,
+        alignment,
+        replicate: true,
+        copies,
+    })
+}
+
+/// The single lane forms starting at D`d`, with index_align in `imm` and the structure size
+/// minus one in `op`. Size 11 are the all lanes forms, which only load.
+fn single_lane(operands: &Operands) -> Option<Transfer> {
+    let Operands {
+        vd: d,
+        op,
+        size,
+        imm: ia,
+        ..
+    } = *operands;
+    if size == 0b11 {
+        return None;
+    }
+    let n = op as usize + 1;
+    let ebytes = 1 << size;
+    let (index, inc) = match size {
+        0b00 => (ia >> 1, 1),
+        0b01 => (ia >> 2, 1 + ((ia >> 1) & 1) as usize),
+        _ => (ia >> 3, 1 + ((ia >> 2) & 1) as usize),
+    };
+    let aligned = ia & 1 == 1;
+    let alignment = match (n, size) {
+        (1, 0b00) if !aligned => 1,
+        (1, 0b01) if ia & 0b10 == 0 => {
+            if aligned {
+                2
+            } else {
+                1
+            }
+        }
+        (1, 0b10) if ia & 0b100 == 0 => match ia & 0b11 {
+            0b00 => 1,
+            0b11 => 4,
+            _ => return None,
+        },
+        (2, 0b10) if ia & 0b10 != 0 => return None,
+        (2, _) => {
+            if aligned {
+                2 * ebytes
+            } else {
+                1
+            }
+        }
+        (3, 0b10) if ia & 0b11 != 0 => return None,
+        (3, _) if aligned => return None,
+        (3, _) => 1,
+        (4, 0b10) => match ia & 0b11 {
+            0b00 => 1,
+            0b01 => 8,
+            0b10 => 16,
+            _ => return None,
+        },
+        (4, _) => {
+            if aligned {
+                4 * ebytes
+            } else {
+                1
+            }
+        }
+        _ => return None,
+    };
+    // VLD1 has no register spacing
+    let inc = if n == 1 { 1 } else { inc };
+    Some(Transfer {
+        elements: (0..n).map(|s| (d + s * inc, index as usize)).collect(),
+        ebytes,
+        alignment,
+        replicate: false,
+        copies: 1,
+    })
+}
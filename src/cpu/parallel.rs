@@ -0,0 +1,140 @@
+//! The ARMv6 SIMD instructions on the core registers and the saturating arithmetic, see ARMv7
+//! ARM A5.4.1 and A5.4.2 "Parallel addition and subtraction", A5.4.3 for SEL and USAD8 and
+//! A5.2.6 "Saturating addition and subtraction". The Thumb-2 encodings are executed through the
+//! same ARM control words.
+//!
+//! A parallel addition or subtraction treats Rn and Rm as two halfwords or four bytes. The plain
+//! signed and unsigned forms set the GE flags, one per byte, which SEL then uses to pick bytes.
+
+use crate::{
+    cpu::{
+        Cpu, alu,
+        decoder::{Decoded, Operands, bits},
+    },
+    err,
+};
+
+/// Lane `i` of `value` with `width` bits, sign extended for `signed`
+#[inline(always)]
+fn lane(value: u32, i: u32, width: u32, signed: bool) -> i64 {
+    let shifted = value >> (i * width);
+    match (width, signed) {
+        (8, true) => shifted as i8 as i64,
+        (8, false) => shifted as u8 as i64,
+        (_, true) => shifted as i16 as i64,
+        (_, false) => shifted as u16 as i64,
+    }
+}
+
+impl<'cpu, const PRINT_INSTR: bool> Cpu<'cpu, PRINT_INSTR> {
+    /// SADD16, QASX, SHSAX, USUB16, UQADD8, UHSUB8 and the rest: bits 22..20 select signed (001)
+    /// or unsigned (101), saturating (+1) or halving (+2) and bits 7..5 the operation.
+    pub(super) fn parallel_add_sub(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands { op, rn, rd, rm, .. } = decoded.operands;
+        let raw = decoded.raw;
+        let (n, m) = (self.r[rn], self.r[rm]);
+        // op is bits 22..20:7..5
+        let signed = op & 0b100_000 == 0;
+        let variant = (op >> 3) & 0b11;
+
+        // the width and, per lane, whether it subtracts and which lane of Rm it takes. ASX and
+        // SAX exchange the halfwords of Rm.
+        let (width, lanes): (u32, &[(bool, u32)]) = match op & 0b111 {
+            // ADD16
+            0b000 => (16, &[(false, 0), (false, 1)]),
+            // ASX
+            0b001 => (16, &[(true, 1), (false, 0)]),
+            // SAX
+            0b010 => (16, &[(false, 1), (true, 0)]),
+            // SUB16
+            0b011 => (16, &[(true, 0), (true, 1)]),
+            // ADD8
+            0b100 => (8, &[(false, 0), (false, 1), (false, 2), (false, 3)]),
+            // SUB8
+            0b111 => (8, &[(true, 0), (true, 1), (true, 2), (true, 3)]),
+            _ => return self.undefined(raw),
+        };
+        if variant == 0b00 {
+            return self.undefined(raw);
+        }
+
+        let mut result = 0;
+        let mut ge = 0;
+        for (i, &(subtract, j)) in lanes.iter().enumerate() {
+            let i = i as u32;
+            let (x, y) = (lane(n, i, width, signed), lane(m, j, width, signed));
+            let value = if subtract { x - y } else { x + y };
+            let lane_result = match variant {
+                0b01 => {
+                    // GE is set for a signed result >= 0, an unsigned sum that carried out or
+                    // an unsigned difference that didn't borrow
+                    let ge_set = if signed || subtract {
+                        value >= 0
+                    } else {
+                        value >= 1 << width
+                    };
+                    if ge_set {
+                        ge |= ((1 << (width / 8)) - 1) << (i * width / 8);
+                    }
+                    value as u32
+                }
+                0b10 if signed => alu::signed_sat_q(value, width).0,
+                0b10 => alu::unsigned_sat_q(value, width).0,
+                _ => (value >> 1) as u32,
+            };
+            result |= (lane_result & (u32::MAX >> (32 - width))) << (i * width);
+        }
+
+        if variant == 0b01 {
+            self.cpsr = (self.cpsr & !alu::GE) | (ge << 16);
+        }
+        self.r[rd] = result;
+        Ok(())
+    }
+
+    /// SEL takes each byte from Rn if its GE flag is set, otherwise from Rm.
+    pub(super) fn select(&mut self, decoded: &Decoded) {
+        let Operands { rn, rd, rm, .. } = decoded.operands;
+        let (n, m) = (self.r[rn], self.r[rm]);
+        let ge = bits(self.cpsr, 19, 16);
+
+        let mask = (0..4)
+            .filter(|i| (ge >> i) & 1 == 1)
+            .fold(0, |mask, i| mask | (0xff << (i * 8)));
+        self.r[rd] = (n & mask) | (m & !mask);
+    }
+
+    /// USAD8 sums the absolute differences of the bytes of Rn and Rm, USADA8 adds Ra unless it is
+    /// pc.
+    pub(super) fn sum_abs_diff(&mut self, decoded: &Decoded) {
+        let Operands { rd, ra, rm, rn, .. } = decoded.operands;
+        let (n, m) = (self.r[rn], self.r[rm]);
+
+        let sum = (0..4)
+            .map(|i| lane(n, i, 8, false).abs_diff(lane(m, i, 8, false)) as u32)
+            .sum::<u32>();
+        let base = if ra == 15 { 0 } else { self.r[ra] };
+        self.r[rd] = base.wrapping_add(sum);
+    }
+
+    /// QADD, QSUB, QDADD and QDSUB selected by bits 22..21 compute Rm +/- Rn, the doubling
+    /// forms saturate 2 * Rn first. Sets Q if either step saturated.
+    pub(super) fn saturating_add_sub(&mut self, decoded: &Decoded) {
+        let Operands { op, rn, rd, rm, .. } = decoded.operands;
+        let n = self.r[rn] as i32 as i64;
+        let m = self.r[rm] as i32 as i64;
+
+        let (n, doubled) = if op & 0b10 != 0 {
+            alu::signed_sat_q(2 * n, 32)
+        } else {
+            (n as u32, false)
+        };
+        let n = n as i32 as i64;
+        let (result, saturated) = alu::signed_sat_q(if op & 1 == 1 { m - n } else { m + n }, 32);
+
+        if doubled || saturated {
+            self.set_flag(alu::Q, true);
+        }
+        self.r[rd] = result;
+    }
+}
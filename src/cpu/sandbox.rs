@@ -12,13 +12,26 @@ pub fn syscall_sandbox<'cpu, const PRINT_INSTR: bool>(
         }
         ArmSyscall::write => {
             let (r0, r1, r2) = (cpu.r[0], cpu.r[1], cpu.r[2]);
-            // only allow writing to stdout, stderr and stdin
-            if r0 > 2 {
+            // only allow writing to stdout, stderr and stdin, sys::write rejects synthesized files
+            if r0 > 2 && !cpu.files.is_open(r0) {
                 return -(sys::Errno::ENOSYS as i32);
             }
 
             sys::write(cpu, r0, r1, r2)
         }
+        ArmSyscall::read => {
+            let (r0, r1, r2) = (cpu.r[0], cpu.r[1], cpu.r[2]);
+            if r0 > 2 && !cpu.files.is_open(r0) {
+                return -(sys::Errno::ENOSYS as i32);
+            }
+
+            sys::read(cpu, r0, r1, r2)
+        }
+        // the synthesized files don't touch the host
+        ArmSyscall::open => sys::open(cpu, cpu.r[0], cpu.r[1]),
+        ArmSyscall::openat => sys::open(cpu, cpu.r[1], cpu.r[2]),
+        ArmSyscall::close => sys::close(cpu, cpu.r[0]),
+        ArmSyscall::uname => sys::uname(cpu, cpu.r[0]),
         c => todo!("{:?}", c),
     }
 }
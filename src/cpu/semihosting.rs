@@ -0,0 +1,68 @@
+//! Semihosting, the debugger interface bare-metal images print and exit through, see Arm's
+//! "Semihosting for AArch32 and AArch64". The operation is passed in r0, its parameter in r1.
+//! Only the console output and the exits are implemented, every other operation returns -1.
+
+use crate::{
+    cpu::{Cpu, decoder::bits},
+    err, sys,
+};
+
+/// the SVC immediate of a semihosting call in ARM state
+const ARM_SVC: u32 = 0x12_3456;
+/// the SVC immediate of a semihosting call in Thumb state
+const THUMB_SVC: u32 = 0xab;
+
+/// write the character r1 points to
+const SYS_WRITEC: u32 = 0x03;
+/// write the NUL-terminated string r1 points to
+const SYS_WRITE0: u32 = 0x04;
+/// exit with the reason in r1
+const SYS_EXIT: u32 = 0x18;
+/// exit with the reason and the status in the two words r1 points to
+const SYS_EXIT_EXTENDED: u32 = 0x20;
+
+/// the reason of a normal exit
+const ADP_STOPPED_APPLICATION_EXIT: u32 = 0x2_0026;
+
+/// Whether the SVC `raw` is a semihosting call
+pub fn is_call(raw: u32, thumb: bool) -> bool {
+    if thumb {
+        bits(raw, 7, 0) == THUMB_SVC
+    } else {
+        bits(raw, 23, 0) == ARM_SVC
+    }
+}
+
+impl<'cpu, const PRINT_INSTR: bool> Cpu<'cpu, PRINT_INSTR> {
+    /// Execute the semihosting operation in r0, the result is returned in r0
+    pub(super) fn semihosting(&mut self, raw: u32) -> Result<(), err::Err> {
+        let arg = self.r[1];
+        let fault = |guest| err::Err::MemoryAccessViolation { guest, instr: raw };
+        match self.r[0] {
+            SYS_WRITEC => {
+                sys::write(self, 1, arg, 1);
+            }
+            SYS_WRITE0 => {
+                let mut len = 0;
+                while self.mem.read_u8(arg.wrapping_add(len)).ok_or(fault(arg))? != 0 {
+                    len += 1;
+                }
+                sys::write(self, 1, arg, len);
+            }
+            SYS_EXIT => {
+                self.status = Some((arg != ADP_STOPPED_APPLICATION_EXIT) as i32);
+            }
+            SYS_EXIT_EXTENDED => {
+                let reason = self.mem.read_u32(arg).ok_or(fault(arg))?;
+                let code = self.mem.read_u32(arg.wrapping_add(4)).ok_or(fault(arg))?;
+                self.status = Some(if reason == ADP_STOPPED_APPLICATION_EXIT {
+                    code as i32
+                } else {
+                    1
+                });
+            }
+            _ => self.r[0] = u32::MAX,
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1108 @@
+//! Thumb execution. Most Thumb instructions share their semantics with an ARM encoding, so they
+//! are executed by building and decoding the ARM control word whose operands the shared helpers
+//! read (op, S, P/U/W/L, Rn, Rd) and passing the already computed operand separately.
+//! Everything without an ARM counterpart, like CBZ, TBB or IT, is executed here.
+
+use crate::{
+    cpu::{
+        Cpu, FETCH_FAULT, alu,
+        decoder::{
+            self, Decoded, InstructionKind, Op, Operands, bit, bits,
+            thumb::{self, ThumbKind},
+        },
+        features::Features,
+    },
+    err, stinkln,
+};
+
+/// ARM data-processing register form, `<op>{s} Rd, Rn, <operand2>`
+fn dp(op: Op, s: bool, rn: u32, rd: u32) -> Decoded {
+    decoder::decode_word(
+        0xe000_0000 | ((op as u32) << 21) | ((s as u32) << 20) | (rn << 16) | (rd << 12),
+    )
+}
+
+/// ARM data-processing immediate form, `<op>{s} Rd, Rn, #imm`
+fn dp_imm(op: Op, s: bool, rn: u32, rd: u32) -> Decoded {
+    decoder::decode_word(dp(op, s, rn, rd).raw | (1 << 25))
+}
+
+/// ARM LDR/STR/LDRB/STRB with P, U, B, W and L
+fn ls(p: bool, u: bool, b: bool, w: bool, l: bool, rn: u32, rt: u32) -> Decoded {
+    decoder::decode_word(
+        0xe400_0000
+            | ((p as u32) << 24)
+            | ((u as u32) << 23)
+            | ((b as u32) << 22)
+            | ((w as u32) << 21)
+            | ((l as u32) << 20)
+            | (rn << 16)
+            | (rt << 12),
+    )
+}
+
+/// ARM extra load/store with P, U, W, L and op2 (01 halfword, 10 signed byte/LDRD, 11 signed
+/// halfword/STRD)
+fn extra(p: bool, u: bool, w: bool, l: bool, rn: u32, rt: u32, op2: u32) -> Decoded {
+    decoder::decode_word(
+        0xe040_0090
+            | ((p as u32) << 24)
+            | ((u as u32) << 23)
+            | ((w as u32) << 21)
+            | ((l as u32) << 20)
+            | (rn << 16)
+            | (rt << 12)
+            | (op2 << 5),
+    )
+}
+
+/// ARM LDM/STM with P, U, W and L
+fn block(p: bool, u: bool, w: bool, l: bool, rn: u32, list: u32) -> Decoded {
+    decoder::decode_word(
+        0xe800_0000
+            | ((p as u32) << 24)
+            | ((u as u32) << 23)
+            | ((w as u32) << 21)
+            | ((l as u32) << 20)
+            | (rn << 16)
+            | list,
+    )
+}
+
+/// ARM multiply with op in bits 23..21 and S, `<op>{s} Rd, Rn, Rm, Ra` respectively `<op>{s}
+/// RdLo, RdHi, Rn, Rm`
+fn mul(op: u32, s: bool, rd: u32, ra: u32, rm: u32, rn: u32) -> Decoded {
+    decoder::decode_word(
+        0xe000_0090 | (op << 21) | ((s as u32) << 20) | (rd << 16) | (ra << 12) | (rm << 8) | rn,
+    )
+}
+
+/// ARM halfword multiply with op in bits 22..21, `m` and `n` select the top halves
+fn mul_half(op: u32, rd: u32, ra: u32, rm: u32, rn: u32, m: bool, n: bool) -> Decoded {
+    decoder::decode_word(
+        0xe100_0080
+            | (op << 21)
+            | (rd << 16)
+            | (ra << 12)
+            | (rm << 8)
+            | ((m as u32) << 6)
+            | ((n as u32) << 5)
+            | rn,
+    )
+}
+
+/// ARM signed dual and most significant word multiply with op1 in bits 22..20 and op2 in 7..5
+fn mul_media(op1: u32, op2: u32, rd: u32, ra: u32, rm: u32, rn: u32) -> Decoded {
+    decoder::decode_word(
+        0xe700_0010 | (op1 << 20) | (rd << 16) | (ra << 12) | (rm << 8) | (op2 << 5) | rn,
+    )
+}
+
+/// ARM SDIV and UDIV
+fn div(unsigned: bool, rd: u32, rm: u32, rn: u32) -> Decoded {
+    decoder::decode_word(0xe710_f010 | ((unsigned as u32) << 21) | (rd << 16) | (rm << 8) | rn)
+}
+
+/// ARM load and store exclusive with size in bits 22..21, `rd` is Rt of a load respectively the
+/// status register of a store
+fn ex(size: u32, l: bool, rn: u32, rd: u32, rt: u32) -> Decoded {
+    decoder::decode_word(
+        0xe180_0f90 | (size << 21) | ((l as u32) << 20) | (rn << 16) | (rd << 12) | rt,
+    )
+}
+
+/// ARM MOVW, respectively MOVT for `top`
+fn mov16(top: bool, rd: u32, imm16: u32) -> Decoded {
+    decoder::decode_word(
+        0xe300_0000 | ((top as u32) << 22) | ((imm16 >> 12) << 16) | (rd << 12) | (imm16 & 0xfff),
+    )
+}
+
+/// ARM sign and zero extension with op in bits 22..20, Rn = pc for the non-accumulating forms
+fn xt(op: u32, rn: u32, rd: u32, rotate: u32, rm: u32) -> Decoded {
+    decoder::decode_word(0xe680_0070 | (op << 20) | (rn << 16) | (rd << 12) | (rotate << 10) | rm)
+}
+
+/// ARM REV (0, 0), REV16 (0, 1), RBIT (1, 0) and REVSH (1, 1) selected by bits 22 and 7
+fn rev(b22: bool, b7: bool, rd: u32, rm: u32) -> Decoded {
+    decoder::decode_word(0xe6bf_0f30 | ((b22 as u32) << 22) | (rd << 12) | ((b7 as u32) << 7) | rm)
+}
+
+/// ARM CLZ
+fn clz(rd: u32, rm: u32) -> Decoded {
+    decoder::decode_word(0xe16f_0f10 | (rd << 12) | rm)
+}
+
+/// ARM parallel addition or subtraction, op1 in bits 22..20 selects the signedness and
+/// saturating or halving, op2 in bits 7..5 the operation
+fn parallel(op1: u32, op2: u32, rn: u32, rd: u32, rm: u32) -> Decoded {
+    decoder::decode_word(0xe600_0f10 | (op1 << 20) | (rn << 16) | (rd << 12) | (op2 << 5) | rm)
+}
+
+/// ARM QADD (op 00), QSUB (01), QDADD (10) and QDSUB (11), `<op> Rd, Rm, Rn`
+fn qadd(op: u32, rn: u32, rd: u32, rm: u32) -> Decoded {
+    decoder::decode_word(0xe100_0050 | (op << 21) | (rn << 16) | (rd << 12) | rm)
+}
+
+/// ARM SEL
+fn sel(rn: u32, rd: u32, rm: u32) -> Decoded {
+    decoder::decode_word(0xe680_0fb0 | (rn << 16) | (rd << 12) | rm)
+}
+
+/// ARM USADA8, respectively USAD8 for Ra = pc
+fn usad8(rd: u32, ra: u32, rm: u32, rn: u32) -> Decoded {
+    decoder::decode_word(0xe780_0010 | (rd << 16) | (ra << 12) | (rm << 8) | rn)
+}
+
+/// ARM CRC32 with the size in bits 22..21, CRC32C for `c`
+fn crc32(size: u32, c: bool, rn: u32, rd: u32, rm: u32) -> Decoded {
+    decoder::decode_word(
+        0xe100_0040 | (size << 21) | (rn << 16) | (rd << 12) | ((c as u32) << 9) | rm,
+    )
+}
+
+/// ARM SSAT, respectively USAT for `unsigned`, with an ASR instead of LSL for `asr`
+fn sat(unsigned: bool, saturate_to: u32, rd: u32, imm5: u32, asr: bool, rn: u32) -> Decoded {
+    decoder::decode_word(
+        0xe6a0_0010
+            | ((unsigned as u32) << 22)
+            | (saturate_to << 16)
+            | (rd << 12)
+            | (imm5 << 7)
+            | ((asr as u32) << 6)
+            | rn,
+    )
+}
+
+/// ARM SBFX (op 01), BFI/BFC (10) and UBFX (11), `field` is the width - 1 or the msb
+fn bfx(op: u32, field: u32, rd: u32, lsb: u32, rn: u32) -> Decoded {
+    let extract = (op != 0b10) as u32;
+    decoder::decode_word(
+        0xe780_0010 | (op << 21) | (field << 16) | (rd << 12) | (lsb << 7) | (extract << 6) | rn,
+    )
+}
+
+/// A conditionless `kind` of the Thumb word `raw`, for the instructions whose operands are picked
+/// from the Thumb encoding directly
+fn decoded(kind: InstructionKind, raw: u32, operands: Operands) -> Decoded {
+    Decoded::new(0b1110, kind, raw, operands)
+}
+
+/// `S:I1:I2:imm10:imm11:0` of the 32-bit B and BL encodings, with I1 = !(J1 ^ S) and I2 = !(J2 ^
+/// S)
+fn branch_offset(raw: u32) -> i32 {
+    let s = bits(raw, 26, 26);
+    let i1 = !(bits(raw, 13, 13) ^ s) & 1;
+    let i2 = !(bits(raw, 11, 11) ^ s) & 1;
+    let imm25 =
+        (s << 24) | (i1 << 23) | (i2 << 22) | (bits(raw, 25, 16) << 12) | (bits(raw, 10, 0) << 1);
+    decoder::sign_extend(imm25, 25)
+}
+
+impl<'cpu, const PRINT_INSTR: bool> Cpu<'cpu, PRINT_INSTR> {
+    /// ITSTATE, split over CPSR bits 15..10 (IT[7:2]) and 26..25 (IT[1:0])
+    #[inline(always)]
+    fn itstate(&self) -> u8 {
+        (((self.cpsr >> 8) & 0xfc) | ((self.cpsr >> 25) & 0b11)) as u8
+    }
+
+    #[inline(always)]
+    fn set_itstate(&mut self, it: u8) {
+        let it = it as u32;
+        self.cpsr =
+            (self.cpsr & !(0xfc00 | (0b11 << 25))) | ((it & 0xfc) << 8) | ((it & 0b11) << 25);
+    }
+
+    /// ITAdvance: shift the next condition bit into place, leaving the block after the last
+    /// instruction
+    #[inline(always)]
+    fn advance_itstate(&mut self) {
+        let it = self.itstate();
+        if it & 0b111 == 0 {
+            self.set_itstate(0);
+        } else {
+            self.set_itstate((it & 0xe0) | ((it << 1) & 0x1f));
+        }
+    }
+
+    fn fetch_u16(&self, addr: u32) -> Result<u16, err::Err> {
+        self.mem
+            .read_u16(addr)
+            .ok_or(err::Err::MemoryAccessViolation {
+                guest: addr,
+                instr: FETCH_FAULT,
+            })
+    }
+
+    /// `decoded`, the ARM instruction the Thumb word `raw` executes as, unless `--strict` rejects
+    /// it for the UNPREDICTABLE rule it violates. Without `--strict` it executes as it is.
+    fn checked(&self, raw: u32, decoded: Decoded) -> Result<Decoded, err::Err> {
+        match decoded.unpredictable {
+            Some(rule) if self.strict => {
+                stinkln!(
+                    "UNPREDICTABLE {:?} at {:#x} {}: {:#x}",
+                    decoded.kind,
+                    self.instr_addr(),
+                    rule,
+                    raw
+                );
+                Err(err::Err::UnpredictableInstruction(raw))
+            }
+            _ => Ok(decoded),
+        }
+    }
+
+    /// fetch-decode-execute step in Thumb state
+    pub(super) fn step_thumb(&mut self) -> Result<bool, err::Err> {
+        let addr = self.instr_addr();
+        let hw1 = self.fetch_u16(addr)?;
+        let raw = if thumb::is_wide(hw1) {
+            ((hw1 as u32) << 16) | self.fetch_u16(addr.wrapping_add(2))? as u32
+        } else {
+            hw1 as u32
+        };
+        let thumb::Decoded { kind, raw, wide } = thumb::decode(raw, thumb::is_wide(hw1));
+        let size = if wide { 4 } else { 2 };
+
+        // before Thumb-2 BL and BLX were the only 32-bit instructions, CBZ and IT are new too
+        let thumb2 = kind == ThumbKind::Cbz
+            || kind == ThumbKind::It
+            || (wide && !matches!(kind, ThumbKind::Bl | ThumbKind::BlxImm));
+        let kind = if thumb2 && !self.features.contains(Features::THUMB2) {
+            ThumbKind::Undefined
+        } else {
+            kind
+        };
+
+        if PRINT_INSTR {
+            stinkln!("{:?} {:X}", kind, raw);
+        }
+
+        if kind == ThumbKind::It {
+            self.set_itstate(bits(raw, 7, 0) as u8);
+            self.r[15] = self.r[15].wrapping_add(2);
+            return Ok(true);
+        }
+
+        let it = self.itstate();
+        let in_it = it & 0xf != 0;
+        let cond = match kind {
+            ThumbKind::CondBranch if wide => bits(raw, 25, 22) as u8,
+            ThumbKind::CondBranch => bits(raw, 11, 8) as u8,
+            ThumbKind::Bkpt => 0xe,
+            _ if in_it => it >> 4,
+            _ => 0xe,
+        };
+        if in_it {
+            self.advance_itstate();
+        }
+
+        if !self.cond_passes(cond) {
+            self.r[15] = self.r[15].wrapping_add(size);
+            return Ok(true);
+        }
+
+        if !self.execute_thumb(kind, raw, size, in_it)? {
+            self.r[15] = self.r[15].wrapping_add(size);
+        }
+
+        Ok(true)
+    }
+
+    /// Execute a decoded Thumb instruction, 16-bit instructions only set flags outside of IT
+    /// blocks. Returns whether pc was written.
+    fn execute_thumb(
+        &mut self,
+        kind: ThumbKind,
+        raw: u32,
+        size: u32,
+        in_it: bool,
+    ) -> Result<bool, err::Err> {
+        let carry = self.flag(alu::C);
+        // return address for BL and BLX, with bit 0 set to come back to Thumb
+        let link = self.instr_addr().wrapping_add(size) | 1;
+        let unknown = || {
+            stinkln!("found unimplemented Thumb instruction, exiting: {:#x}", raw);
+            Err(err::Err::UnknownOrUnsupportedInstruction(raw))
+        };
+
+        let pc_changed = match kind {
+            ThumbKind::ShiftImm => {
+                let (shift, amount) =
+                    alu::decode_imm_shift(alu::Shift::from(bits(raw, 12, 11)), bits(raw, 10, 6));
+                let rm = self.r[bits(raw, 5, 3) as usize];
+                let (value, carry) = alu::shift_c(rm, shift, amount, carry);
+                self.data_processing(&dp(Op::Mov, !in_it, 0, bits(raw, 2, 0)), value, carry)
+            }
+            ThumbKind::AddSubReg | ThumbKind::AddSubImm3 => {
+                let op = if bit(raw, 9) { Op::Sub } else { Op::Add };
+                let (rn, rd) = (bits(raw, 5, 3), bits(raw, 2, 0));
+                if kind == ThumbKind::AddSubImm3 {
+                    self.data_processing(&dp_imm(op, !in_it, rn, rd), bits(raw, 8, 6), carry)
+                } else {
+                    let rm = self.r[bits(raw, 8, 6) as usize];
+                    self.data_processing(&dp(op, !in_it, rn, rd), rm, carry)
+                }
+            }
+            ThumbKind::Imm8 => {
+                let rdn = bits(raw, 10, 8);
+                let (op, s) = match bits(raw, 12, 11) {
+                    0b00 => (Op::Mov, !in_it),
+                    0b01 => (Op::Cmp, true),
+                    0b10 => (Op::Add, !in_it),
+                    _ => (Op::Sub, !in_it),
+                };
+                self.data_processing(&dp_imm(op, s, rdn, rdn), bits(raw, 7, 0), carry)
+            }
+            ThumbKind::DataProcessing => {
+                let rm = bits(raw, 5, 3);
+                let rdn = bits(raw, 2, 0);
+                let s = !in_it;
+                match bits(raw, 9, 6) {
+                    // LSL, LSR, ASR and ROR by register
+                    op @ (0b0010 | 0b0011 | 0b0100 | 0b0111) => {
+                        let shift = match op {
+                            0b0010 => alu::Shift::Lsl,
+                            0b0011 => alu::Shift::Lsr,
+                            0b0100 => alu::Shift::Asr,
+                            _ => alu::Shift::Ror,
+                        };
+                        let amount = self.r[rm as usize] & 0xff;
+                        let (value, carry) =
+                            alu::shift_c(self.r[rdn as usize], shift, amount, carry);
+                        self.data_processing(&dp(Op::Mov, s, 0, rdn), value, carry)
+                    }
+                    // RSB Rd, Rn, #0
+                    0b1001 => self.data_processing(&dp_imm(Op::Rsb, s, rm, rdn), 0, carry),
+                    // MULS Rdm, Rn, Rdm
+                    0b1101 => {
+                        self.multiply(&self.checked(raw, mul(0b000, s, rdn, 0, rdn, rm))?)?;
+                        false
+                    }
+                    op => {
+                        let op = decoder::op_from_bits(op as u8);
+                        let rm = self.r[rm as usize];
+                        self.data_processing(&dp(op, s || op.is_test(), rdn, rdn), rm, carry)
+                    }
+                }
+            }
+            ThumbKind::SpecialDataProcessing => {
+                let rdn = (bits(raw, 7, 7) << 3) | bits(raw, 2, 0);
+                let rm = self.reg(bits(raw, 6, 3) as usize);
+                let (op, s) = match bits(raw, 9, 8) {
+                    0b00 => (Op::Add, false),
+                    0b01 => (Op::Cmp, true),
+                    _ => (Op::Mov, false),
+                };
+                self.data_processing(&dp(op, s, rdn, rdn), rm, carry)
+            }
+            ThumbKind::BxBlx => {
+                let rm = self.reg(bits(raw, 6, 3) as usize);
+                if bit(raw, 7) {
+                    self.r[14] = link;
+                }
+                self.bx_write_pc(rm);
+                true
+            }
+            ThumbKind::LdrLiteral => {
+                let arm = self.checked(
+                    raw,
+                    ls(true, true, false, false, true, 15, bits(raw, 10, 8)),
+                )?;
+                self.load_store(&arm, bits(raw, 7, 0) << 2)?
+            }
+            ThumbKind::LoadStoreReg => {
+                let offset = self.r[bits(raw, 8, 6) as usize];
+                let (rn, rt) = (bits(raw, 5, 3), bits(raw, 2, 0));
+                match bits(raw, 11, 9) {
+                    0b000 => self.load_store(
+                        &self.checked(raw, ls(true, true, false, false, false, rn, rt))?,
+                        offset,
+                    )?,
+                    0b010 => self.load_store(
+                        &self.checked(raw, ls(true, true, true, false, false, rn, rt))?,
+                        offset,
+                    )?,
+                    0b100 => self.load_store(
+                        &self.checked(raw, ls(true, true, false, false, true, rn, rt))?,
+                        offset,
+                    )?,
+                    0b110 => self.load_store(
+                        &self.checked(raw, ls(true, true, true, false, true, rn, rt))?,
+                        offset,
+                    )?,
+                    0b001 => self.extra_load_store(
+                        &self.checked(raw, extra(true, true, false, false, rn, rt, 0b01))?,
+                        offset,
+                    )?,
+                    0b011 => self.extra_load_store(
+                        &self.checked(raw, extra(true, true, false, true, rn, rt, 0b10))?,
+                        offset,
+                    )?,
+                    0b101 => self.extra_load_store(
+                        &self.checked(raw, extra(true, true, false, true, rn, rt, 0b01))?,
+                        offset,
+                    )?,
+                    _ => self.extra_load_store(
+                        &self.checked(raw, extra(true, true, false, true, rn, rt, 0b11))?,
+                        offset,
+                    )?,
+                }
+            }
+            ThumbKind::LoadStoreWordByteImm => {
+                let b = bit(raw, 12);
+                let imm5 = bits(raw, 10, 6);
+                let offset = if b { imm5 } else { imm5 << 2 };
+                let arm = self.checked(
+                    raw,
+                    ls(
+                        true,
+                        true,
+                        b,
+                        false,
+                        bit(raw, 11),
+                        bits(raw, 5, 3),
+                        bits(raw, 2, 0),
+                    ),
+                )?;
+                self.load_store(&arm, offset)?
+            }
+            ThumbKind::LoadStoreHalfImm => {
+                let arm = self.checked(
+                    raw,
+                    extra(
+                        true,
+                        true,
+                        false,
+                        bit(raw, 11),
+                        bits(raw, 5, 3),
+                        bits(raw, 2, 0),
+                        0b01,
+                    ),
+                )?;
+                self.extra_load_store(&arm, bits(raw, 10, 6) << 1)?
+            }
+            ThumbKind::LoadStoreSp => {
+                let arm = self.checked(
+                    raw,
+                    ls(true, true, false, false, bit(raw, 11), 13, bits(raw, 10, 8)),
+                )?;
+                self.load_store(&arm, bits(raw, 7, 0) << 2)?
+            }
+            ThumbKind::Adr => self.data_processing(
+                &dp_imm(Op::Add, false, 15, bits(raw, 10, 8)),
+                bits(raw, 7, 0) << 2,
+                carry,
+            ),
+            ThumbKind::AddSpImm => self.data_processing(
+                &dp_imm(Op::Add, false, 13, bits(raw, 10, 8)),
+                bits(raw, 7, 0) << 2,
+                carry,
+            ),
+            ThumbKind::AdjustSp => {
+                let op = if bit(raw, 7) { Op::Sub } else { Op::Add };
+                self.data_processing(&dp_imm(op, false, 13, 13), bits(raw, 6, 0) << 2, carry)
+            }
+            ThumbKind::Cbz => {
+                let nonzero = bit(raw, 11);
+                let imm = (bits(raw, 9, 9) << 6) | (bits(raw, 7, 3) << 1);
+                if (self.r[bits(raw, 2, 0) as usize] != 0) == nonzero {
+                    self.write_pc(self.read_pc().wrapping_add(imm));
+                    true
+                } else {
+                    false
+                }
+            }
+            ThumbKind::Push => {
+                let list = bits(raw, 7, 0) | (bits(raw, 8, 8) << 14);
+                self.block_transfer(&self.checked(raw, block(true, false, true, false, 13, list))?)?
+            }
+            ThumbKind::Pop => {
+                let list = bits(raw, 7, 0) | (bits(raw, 8, 8) << 15);
+                self.block_transfer(&self.checked(raw, block(false, true, true, true, 13, list))?)?
+            }
+            ThumbKind::Stm => self.block_transfer(&self.checked(
+                raw,
+                block(false, true, true, false, bits(raw, 10, 8), bits(raw, 7, 0)),
+            )?)?,
+            ThumbKind::Ldm => {
+                let rn = bits(raw, 10, 8);
+                let list = bits(raw, 7, 0);
+                // writeback unless the base register is loaded
+                let w = list & (1 << rn) == 0;
+                self.block_transfer(&self.checked(raw, block(false, true, w, true, rn, list))?)?
+            }
+            ThumbKind::Hint | ThumbKind::It => false,
+            // R in bit 20, the register respectively the mask in bits 11..8
+            ThumbKind::Mrs => {
+                let operands = Operands {
+                    op: bits(raw, 20, 20),
+                    rd: bits(raw, 11, 8) as usize,
+                    ..Operands::default()
+                };
+                self.mrs(&self.checked(raw, decoded(InstructionKind::Mrs, raw, operands))?)?;
+                false
+            }
+            ThumbKind::Msr => {
+                let operands = Operands {
+                    op: (bits(raw, 20, 20) << 4) | bits(raw, 11, 8),
+                    ..Operands::default()
+                };
+                let value = self.r[bits(raw, 19, 16) as usize];
+                self.msr(&decoded(InstructionKind::Msr, raw, operands), value)?;
+                false
+            }
+            // imod:M in bits 10..8, A, I and F in 7..5 and the mode in 4..0
+            ThumbKind::Cps if size == 4 => {
+                let operands = Operands {
+                    op: bits(raw, 10, 8),
+                    imm: (bits(raw, 7, 5) << 5) | bits(raw, 4, 0),
+                    ..Operands::default()
+                };
+                self.cps(&decoded(InstructionKind::Cps, raw, operands))?;
+                false
+            }
+            // CPSIE and CPSID without a mode change, imod is 1:im
+            ThumbKind::Cps => {
+                let operands = Operands {
+                    op: (0b10 | bits(raw, 4, 4)) << 1,
+                    imm: bits(raw, 2, 0) << 5,
+                    ..Operands::default()
+                };
+                self.cps(&decoded(InstructionKind::Cps, raw, operands))?;
+                false
+            }
+            ThumbKind::Setend => {
+                let operands = Operands {
+                    op: bits(raw, 3, 3),
+                    ..Operands::default()
+                };
+                self.setend(&decoded(InstructionKind::Setend, raw, operands))?;
+                false
+            }
+            // SUBS PC, LR, #imm8, an exception return
+            ThumbKind::ExceptionReturn => {
+                self.data_processing(&dp_imm(Op::Sub, true, 14, 15), bits(raw, 7, 0), carry)
+            }
+            ThumbKind::Svc => {
+                self.svc(raw)?;
+                false
+            }
+            ThumbKind::CondBranch => {
+                let offset = if size == 4 {
+                    let imm21 = (bits(raw, 26, 26) << 20)
+                        | (bits(raw, 11, 11) << 19)
+                        | (bits(raw, 13, 13) << 18)
+                        | (bits(raw, 21, 16) << 12)
+                        | (bits(raw, 10, 0) << 1);
+                    decoder::sign_extend(imm21, 21)
+                } else {
+                    decoder::sign_extend(bits(raw, 7, 0) << 1, 9)
+                };
+                self.write_pc(self.read_pc().wrapping_add(offset as u32));
+                true
+            }
+            ThumbKind::Branch => {
+                let offset = if size == 4 {
+                    branch_offset(raw)
+                } else {
+                    decoder::sign_extend(bits(raw, 10, 0) << 1, 12)
+                };
+                self.write_pc(self.read_pc().wrapping_add(offset as u32));
+                true
+            }
+            ThumbKind::Bl => {
+                self.r[14] = link;
+                self.write_pc(self.read_pc().wrapping_add(branch_offset(raw) as u32));
+                true
+            }
+            ThumbKind::BlxImm => {
+                // the target is computed from the word aligned pc and is always ARM code
+                let target = (self.read_pc() & !3).wrapping_add(branch_offset(raw) as u32);
+                self.r[14] = link;
+                self.bx_write_pc(target & !3);
+                true
+            }
+            ThumbKind::LoadStoreMultiple => {
+                let (p, u) = match bits(raw, 24, 23) {
+                    0b01 => (false, true), // IA
+                    0b10 => (true, false), // DB
+                    // SRS and RFE, DB with 00 and IA with 11
+                    op => {
+                        let operands = Operands {
+                            p: op == 0b00,
+                            u: op == 0b11,
+                            w: bit(raw, 21),
+                            ..Operands::default()
+                        };
+                        if bit(raw, 20) {
+                            let rn = bits(raw, 19, 16) as usize;
+                            let operands = Operands { rn, ..operands };
+                            self.rfe(&decoded(InstructionKind::Rfe, raw, operands))?;
+                            return Ok(true);
+                        }
+                        let operands = Operands {
+                            imm: bits(raw, 4, 0),
+                            ..operands
+                        };
+                        self.srs(&decoded(InstructionKind::Srs, raw, operands))?;
+                        return Ok(false);
+                    }
+                };
+                let arm = self.checked(
+                    raw,
+                    block(
+                        p,
+                        u,
+                        bit(raw, 21),
+                        bit(raw, 20),
+                        bits(raw, 19, 16),
+                        bits(raw, 15, 0),
+                    ),
+                )?;
+                self.block_transfer(&arm)?
+            }
+            ThumbKind::LoadStoreDual => {
+                let (rn, rt, rt2) = (bits(raw, 19, 16), bits(raw, 15, 12), bits(raw, 11, 8));
+                let op2 = if bit(raw, 20) { 0b10 } else { 0b11 };
+                let arm = extra(bit(raw, 24), bit(raw, 23), bit(raw, 21), false, rn, rt, op2);
+                self.load_store_dual(&arm, bits(raw, 7, 0) << 2, rt2 as usize)?
+            }
+            ThumbKind::TableBranch => {
+                let base = self.reg(bits(raw, 19, 16) as usize);
+                let index = self.r[bits(raw, 3, 0) as usize];
+                let tbh = bit(raw, 4);
+                let addr = base.wrapping_add(if tbh { index << 1 } else { index });
+                let halfwords = if tbh {
+                    self.mem.read_u16(addr).map(u32::from)
+                } else {
+                    self.mem.read_u8(addr).map(u32::from)
+                }
+                .ok_or(err::Err::MemoryAccessViolation {
+                    guest: addr,
+                    instr: raw,
+                })?;
+                self.write_pc(self.read_pc().wrapping_add(halfwords << 1));
+                true
+            }
+            ThumbKind::DataProcessingShiftedReg => {
+                let (shift, amount) = alu::decode_imm_shift(
+                    alu::Shift::from(bits(raw, 5, 4)),
+                    (bits(raw, 14, 12) << 2) | bits(raw, 7, 6),
+                );
+                let rm = self.reg(bits(raw, 3, 0) as usize);
+                let (value, carry) = alu::shift_c(rm, shift, amount, carry);
+                match self.thumb_data_processing(raw, false, value, carry) {
+                    Some(pc_changed) => pc_changed,
+                    None => return unknown(),
+                }
+            }
+            ThumbKind::DataProcessingModImm => {
+                let (value, carry) = alu::thumb_expand_imm_c(thumb::imm12(raw), carry);
+                match self.thumb_data_processing(raw, true, value, carry) {
+                    Some(pc_changed) => pc_changed,
+                    None => return unknown(),
+                }
+            }
+            ThumbKind::DataProcessingPlainImm => {
+                let (rn, rd) = (bits(raw, 19, 16), bits(raw, 11, 8));
+                // shift amount respectively lsb, imm3:imm2
+                let imm5 = (bits(raw, 14, 12) << 2) | bits(raw, 7, 6);
+                let field = bits(raw, 4, 0);
+                match bits(raw, 24, 20) {
+                    // ADDW, ADR after the current instruction
+                    0b00000 => self.data_processing(
+                        &dp_imm(Op::Add, false, rn, rd),
+                        thumb::imm12(raw),
+                        carry,
+                    ),
+                    // SUBW, ADR before the current instruction
+                    0b01010 => self.data_processing(
+                        &dp_imm(Op::Sub, false, rn, rd),
+                        thumb::imm12(raw),
+                        carry,
+                    ),
+                    op @ (0b00100 | 0b01100) => {
+                        self.move_imm16(
+                            &self.checked(raw, mov16(op == 0b01100, rd, thumb::imm16(raw)))?,
+                        );
+                        false
+                    }
+                    // SSAT16 and USAT16 are the ASR forms shifting by zero
+                    0b10010 | 0b11010 if imm5 == 0 => return unknown(),
+                    op @ (0b10000 | 0b10010 | 0b11000 | 0b11010) => {
+                        let unsigned = op & 0b01000 != 0;
+                        self.saturate(
+                            &self.checked(raw, sat(unsigned, field, rd, imm5, bit(raw, 21), rn))?,
+                        );
+                        false
+                    }
+                    0b10100 => {
+                        self.bitfield(&self.checked(raw, bfx(0b01, field, rd, imm5, rn))?)?;
+                        false
+                    }
+                    0b10110 => {
+                        self.bitfield(&self.checked(raw, bfx(0b10, field, rd, imm5, rn))?)?;
+                        false
+                    }
+                    0b11100 => {
+                        self.bitfield(&self.checked(raw, bfx(0b11, field, rd, imm5, rn))?)?;
+                        false
+                    }
+                    _ => return unknown(),
+                }
+            }
+            ThumbKind::LoadStoreSingle => match self.thumb_load_store_single(raw)? {
+                Some(pc_changed) => pc_changed,
+                None => return unknown(),
+            },
+            ThumbKind::ShiftReg => {
+                let shift = alu::Shift::from(bits(raw, 22, 21));
+                let rn = self.r[bits(raw, 19, 16) as usize];
+                let amount = self.r[bits(raw, 3, 0) as usize] & 0xff;
+                let (value, carry) = alu::shift_c(rn, shift, amount, carry);
+                self.data_processing(
+                    &dp(Op::Mov, bit(raw, 20), 0, bits(raw, 11, 8)),
+                    value,
+                    carry,
+                )
+            }
+            ThumbKind::Extend if size == 4 => {
+                let op = match bits(raw, 22, 20) {
+                    0b000 => 0b011, // SXTAH
+                    0b001 => 0b111, // UXTAH
+                    0b010 => 0b000, // SXTAB16
+                    0b011 => 0b100, // UXTAB16
+                    0b100 => 0b010, // SXTAB
+                    0b101 => 0b110, // UXTAB
+                    _ => return unknown(),
+                };
+                if bit(raw, 6) {
+                    return unknown();
+                }
+                let (rn, rd, rm) = (bits(raw, 19, 16), bits(raw, 11, 8), bits(raw, 3, 0));
+                self.extend(&self.checked(raw, xt(op, rn, rd, bits(raw, 5, 4), rm))?)?;
+                false
+            }
+            ThumbKind::Extend => {
+                let op = match bits(raw, 7, 6) {
+                    0b00 => 0b011, // SXTH
+                    0b01 => 0b010, // SXTB
+                    0b10 => 0b111, // UXTH
+                    _ => 0b110,    // UXTB
+                };
+                self.extend(&self.checked(raw, xt(op, 15, bits(raw, 2, 0), 0, bits(raw, 5, 3)))?)?;
+                false
+            }
+            ThumbKind::Reverse => {
+                // REV, REV16 and REVSH, 0b10 is filtered by the decoder
+                let op = bits(raw, 7, 6);
+                self.reverse(&self.checked(
+                    raw,
+                    rev(op == 0b11, op & 1 == 1, bits(raw, 2, 0), bits(raw, 5, 3)),
+                )?);
+                false
+            }
+            ThumbKind::MiscReg => {
+                let (rn, rd, rm) = (bits(raw, 19, 16), bits(raw, 11, 8), bits(raw, 3, 0));
+                match (bits(raw, 21, 20), bits(raw, 5, 4)) {
+                    // QADD, QDADD, QSUB and QDSUB, ARM swaps the order of the doubling and the
+                    // subtracting bit
+                    (0b00, op) => {
+                        let op = ((op & 1) << 1) | (op >> 1);
+                        self.saturating_add_sub(&self.checked(raw, qadd(op, rn, rd, rm))?)
+                    }
+                    (0b01, op) => {
+                        self.reverse(&self.checked(raw, rev(op & 0b10 != 0, op & 1 == 1, rd, rm))?)
+                    }
+                    (0b10, 0b00) => self.select(&self.checked(raw, sel(rn, rd, rm))?),
+                    (0b11, 0b00) => self.clz(&self.checked(raw, clz(rd, rm))?),
+                    _ => return unknown(),
+                }
+                false
+            }
+            ThumbKind::ParallelAddSub => {
+                let (rn, rd, rm) = (bits(raw, 19, 16), bits(raw, 11, 8), bits(raw, 3, 0));
+                // bits 22..20 select the operation, ARM's bits 7..5
+                let op2 = match bits(raw, 22, 20) {
+                    0b000 => 0b100,
+                    0b001 => 0b000,
+                    0b010 => 0b001,
+                    0b100 => 0b111,
+                    0b101 => 0b011,
+                    0b110 => 0b010,
+                    _ => return unknown(),
+                };
+                // U in bit 6, saturating or halving in bits 5..4
+                let variant = bits(raw, 5, 4);
+                if variant == 0b11 {
+                    return unknown();
+                }
+                let op1 = (bits(raw, 6, 6) << 2) | (variant + 1);
+                self.parallel_add_sub(&self.checked(raw, parallel(op1, op2, rn, rd, rm))?)?;
+                false
+            }
+            ThumbKind::Exclusive => {
+                let l = bit(raw, 20);
+                let (rn, rt) = (bits(raw, 19, 16), bits(raw, 15, 12));
+                // LDREX and STREX are word sized with an imm8 offset in words, the other sizes
+                // are selected by bits 7..4 and pair Rt with Rt2 in bits 11..8
+                let (size, rd, offset, rt2) = if !bit(raw, 23) {
+                    (0b00, bits(raw, 11, 8), bits(raw, 7, 0) << 2, 0)
+                } else {
+                    let size = match bits(raw, 7, 4) {
+                        0b0100 => 0b10,
+                        0b0101 => 0b11,
+                        0b0111 => 0b01,
+                        _ => return unknown(),
+                    };
+                    (size, bits(raw, 3, 0), 0, bits(raw, 11, 8) as usize)
+                };
+                let word = if l {
+                    ex(size, true, rn, rt, 0b1111)
+                } else {
+                    ex(size, false, rn, rd, rt)
+                };
+                self.exclusive(&word, offset, rt2)?;
+                false
+            }
+            ThumbKind::Clrex => {
+                self.monitor.clear();
+                false
+            }
+            // a single guest thread observes its own accesses in program order
+            ThumbKind::Barrier => false,
+            ThumbKind::Multiply | ThumbKind::LongMultiplyDivide => {
+                match self.thumb_multiply(kind, raw)? {
+                    Some(pc_changed) => pc_changed,
+                    None => return unknown(),
+                }
+            }
+            ThumbKind::Vfp => {
+                self.vfp(&decoder::decode_word(raw))?;
+                false
+            }
+            ThumbKind::Crc32 => {
+                let (rn, rd, rm) = (bits(raw, 19, 16), bits(raw, 11, 8), bits(raw, 3, 0));
+                self.crc32(&self.checked(raw, crc32(bits(raw, 5, 4), bit(raw, 20), rn, rd, rm))?)?;
+                false
+            }
+            ThumbKind::Neon => {
+                let arm = if raw >> 24 == 0b11111001 {
+                    0xf400_0000 | (raw & 0x00ff_ffff)
+                } else {
+                    0xf200_0000 | (decoder::bits(raw, 28, 28) << 24) | (raw & 0x00ff_ffff)
+                };
+                self.neon(&decoder::decode_word(arm))?;
+                false
+            }
+            ThumbKind::Coprocessor => {
+                // the coprocessor encodings equal the ARM ones with cond = 1110
+                let decoded = decoder::decode_word(raw);
+                match decoded.kind {
+                    decoder::InstructionKind::Coprocessor => self.coprocessor(&decoded)?,
+                    decoder::InstructionKind::Coprocessor64 => self.coprocessor64(&decoded)?,
+                    _ => self.undefined(raw)?,
+                }
+                false
+            }
+            ThumbKind::Undefined => {
+                self.undefined(raw)?;
+                false
+            }
+            ThumbKind::Bkpt => {
+                self.breakpoint(raw);
+                false
+            }
+            ThumbKind::Unknown => return unknown(),
+        };
+
+        Ok(pc_changed)
+    }
+
+    /// The 32-bit data-processing opcodes in bits 24..21 differ from ARM's. Rd = pc with S set
+    /// selects the test instructions, Rn = pc the moves. Returns `None` for the encodings
+    /// without an ARM counterpart we do not model (PKHBT/PKHTB) and the undefined ones.
+    fn thumb_data_processing(
+        &mut self,
+        raw: u32,
+        imm: bool,
+        operand2: u32,
+        carry: bool,
+    ) -> Option<bool> {
+        let s = bit(raw, 20);
+        let rn = bits(raw, 19, 16);
+        let rd = bits(raw, 11, 8);
+        let test = rd == 15 && s;
+
+        let (op, operand2) = match bits(raw, 24, 21) {
+            0b0000 if test => (Op::Tst, operand2),
+            0b0000 => (Op::And, operand2),
+            0b0001 => (Op::Bic, operand2),
+            0b0010 if rn == 15 => (Op::Mov, operand2),
+            0b0010 => (Op::Orr, operand2),
+            0b0011 if rn == 15 => (Op::Mvn, operand2),
+            // ORN is ORR with an inverted operand
+            0b0011 => (Op::Orr, !operand2),
+            0b0100 if test => (Op::Teq, operand2),
+            0b0100 => (Op::Eor, operand2),
+            0b1000 if test => (Op::Cmn, operand2),
+            0b1000 => (Op::Add, operand2),
+            0b1010 => (Op::Adc, operand2),
+            0b1011 => (Op::Sbc, operand2),
+            0b1101 if test => (Op::Cmp, operand2),
+            0b1101 => (Op::Sub, operand2),
+            0b1110 => (Op::Rsb, operand2),
+            _ => return None,
+        };
+
+        let arm = if imm {
+            dp_imm(op, s, rn, rd)
+        } else {
+            dp(op, s, rn, rd)
+        };
+        Some(self.data_processing(&arm, operand2, carry))
+    }
+
+    /// LDR, STR and their byte, halfword and signed forms. Bits 22..21 hold the size, bit 24
+    /// sign extension. The offset is either a positive imm12 (bit 23 set or a literal), an imm8
+    /// with P/U/W in bits 10..8 or a register shifted left by bits 5..4. Returns `None` for
+    /// undefined encodings.
+    fn thumb_load_store_single(&mut self, raw: u32) -> Result<Option<bool>, err::Err> {
+        let signed = bit(raw, 24);
+        let size = bits(raw, 22, 21);
+        let l = bit(raw, 20);
+        let rn = bits(raw, 19, 16);
+        let rt = bits(raw, 15, 12);
+
+        let (p, u, w, offset) = if rn == 15 {
+            (true, bit(raw, 23), false, bits(raw, 11, 0))
+        } else if bit(raw, 23) {
+            (true, true, false, bits(raw, 11, 0))
+        } else if bit(raw, 11) {
+            // P = W = 0 is undefined, P = U = 1 with W = 0 is the unprivileged form
+            if !bit(raw, 10) && !bit(raw, 8) {
+                return Ok(None);
+            }
+            (bit(raw, 10), bit(raw, 9), bit(raw, 8), bits(raw, 7, 0))
+        } else if bits(raw, 11, 6) == 0 {
+            let rm = self.r[bits(raw, 3, 0) as usize];
+            (true, true, false, rm << bits(raw, 5, 4))
+        } else {
+            return Ok(None);
+        };
+
+        // PLD and PLI are byte and halfword loads into pc, we ignore the hint
+        if l && rt == 15 && size != 0b10 {
+            return Ok(Some(false));
+        }
+
+        let pc_changed = match (size, signed, l) {
+            (0b10, false, _) => {
+                self.load_store(&self.checked(raw, ls(p, u, false, w, l, rn, rt))?, offset)?
+            }
+            (0b00, false, _) => {
+                self.load_store(&self.checked(raw, ls(p, u, true, w, l, rn, rt))?, offset)?
+            }
+            (0b00, true, true) => self.extra_load_store(
+                &self.checked(raw, extra(p, u, w, true, rn, rt, 0b10))?,
+                offset,
+            )?,
+            (0b01, false, _) => {
+                self.extra_load_store(&self.checked(raw, extra(p, u, w, l, rn, rt, 0b01))?, offset)?
+            }
+            (0b01, true, true) => self.extra_load_store(
+                &self.checked(raw, extra(p, u, w, true, rn, rt, 0b11))?,
+                offset,
+            )?,
+            _ => return Ok(None),
+        };
+
+        Ok(Some(pc_changed))
+    }
+
+    /// The 32-bit multiplies and divides, op1 in bits 22..20 and op2 in bits 5..4 respectively
+    /// 7..4 select the instruction. The short forms hold Ra in bits 15..12 and Rd in 11..8, the
+    /// long ones RdLo and RdHi. Returns `None` for undefined encodings.
+    fn thumb_multiply(&mut self, kind: ThumbKind, raw: u32) -> Result<Option<bool>, err::Err> {
+        let op1 = bits(raw, 22, 20);
+        let rn = bits(raw, 19, 16);
+        let ra = bits(raw, 15, 12);
+        let rd = bits(raw, 11, 8);
+        let rm = bits(raw, 3, 0);
+        // N and M for the halfword forms, M and R for the dual and most significant word forms
+        let (n, m) = (bit(raw, 5), bit(raw, 4));
+
+        if kind == ThumbKind::Multiply {
+            let accumulate = ra != 15;
+            match (op1, bits(raw, 5, 4)) {
+                (0b000, 0b00) => self
+                    .multiply(&self.checked(raw, mul(accumulate as u32, false, rd, ra, rm, rn))?)?,
+                (0b000, 0b01) => {
+                    self.multiply(&self.checked(raw, mul(0b011, false, rd, ra, rm, rn))?)?
+                }
+                (0b001, _) => {
+                    let op = if accumulate { 0b00 } else { 0b11 };
+                    self.halfword_multiply(&self.checked(raw, mul_half(op, rd, ra, rm, rn, m, n))?)?
+                }
+                // SMLAD and SMUAD
+                (0b010, 0b00 | 0b01) => self.media_multiply(
+                    &self.checked(raw, mul_media(0b000, m as u32, rd, ra, rm, rn))?,
+                )?,
+                // SMLAW<y> and SMULW<y>
+                (0b011, 0b00 | 0b01) => self.halfword_multiply(
+                    &self.checked(raw, mul_half(0b01, rd, ra, rm, rn, m, !accumulate))?,
+                )?,
+                // SMLSD and SMUSD
+                (0b100, 0b00 | 0b01) => self.media_multiply(
+                    &self.checked(raw, mul_media(0b000, 0b010 | m as u32, rd, ra, rm, rn))?,
+                )?,
+                // SMMLA and SMMUL
+                (0b101, 0b00 | 0b01) => self.media_multiply(
+                    &self.checked(raw, mul_media(0b101, m as u32, rd, ra, rm, rn))?,
+                )?,
+                // SMMLS
+                (0b110, 0b00 | 0b01) => self.media_multiply(
+                    &self.checked(raw, mul_media(0b101, 0b110 | m as u32, rd, ra, rm, rn))?,
+                )?,
+                // USAD8 and USADA8
+                (0b111, 0b00) => self.sum_abs_diff(&self.checked(raw, usad8(rd, ra, rm, rn))?),
+                _ => return Ok(None),
+            }
+            return Ok(Some(false));
+        }
+
+        let (lo, hi) = (ra, rd);
+        match (op1, bits(raw, 7, 4)) {
+            (0b000, 0b0000) => {
+                self.multiply(&self.checked(raw, mul(0b110, false, hi, lo, rm, rn))?)?
+            }
+            (0b001, 0b1111) => self.divide(&self.checked(raw, div(false, rd, rm, rn))?)?,
+            (0b010, 0b0000) => {
+                self.multiply(&self.checked(raw, mul(0b100, false, hi, lo, rm, rn))?)?
+            }
+            (0b011, 0b1111) => self.divide(&self.checked(raw, div(true, rd, rm, rn))?)?,
+            (0b100, 0b0000) => {
+                self.multiply(&self.checked(raw, mul(0b111, false, hi, lo, rm, rn))?)?
+            }
+            // SMLAL<x><y>
+            (0b100, 0b1000..=0b1011) => {
+                self.halfword_multiply(&self.checked(raw, mul_half(0b10, hi, lo, rm, rn, m, n))?)?
+            }
+            // SMLALD and SMLSLD
+            (0b100 | 0b101, 0b1100 | 0b1101) => {
+                let op2 = ((op1 & 1) << 1) | m as u32;
+                self.media_multiply(&self.checked(raw, mul_media(0b100, op2, hi, lo, rm, rn))?)?
+            }
+            (0b110, 0b0000) => {
+                self.multiply(&self.checked(raw, mul(0b101, false, hi, lo, rm, rn))?)?
+            }
+            (0b110, 0b0110) => {
+                self.multiply(&self.checked(raw, mul(0b010, false, hi, lo, rm, rn))?)?
+            }
+            _ => return Ok(None),
+        }
+
+        Ok(Some(false))
+    }
+}
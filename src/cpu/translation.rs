@@ -11,8 +11,20 @@ pub enum ArmSyscall {
     write = 0x04,
     open = 0x05,
     close = 0x06,
+    uname = 0x7a,
+    openat = 0x142,
+    /// ARM private syscalls from `__ARM_NR_BASE` on, see [`syscall_private`]
+    breakpoint = 0xf0001,
+    cacheflush = 0xf0002,
+    usr26 = 0xf0003,
+    usr32 = 0xf0004,
+    set_tls = 0xf0005,
+    get_tls = 0xf0006,
 }
 
+/// `__ARM_NR_BASE`, the first of the ARM private syscalls
+pub const ARM_NR_BASE: u32 = 0xf0000;
+
 impl ArmSyscall {
     // TODO: rework this with some kind of writer
     pub fn print<const PRINT_INSTR: bool>(&self, cpu: &super::Cpu<'_, PRINT_INSTR>) -> String {
@@ -21,10 +33,23 @@ impl ArmSyscall {
         let args = match self {
             ArmSyscall::exit => format!("code={}", cpu.r[0]),
             ArmSyscall::fork => todo!(),
-            ArmSyscall::read => todo!(),
-            ArmSyscall::write => format!("fd={}, buf={:#x}, len={}", cpu.r[0], cpu.r[1], cpu.r[2]),
-            ArmSyscall::open => todo!(),
-            ArmSyscall::close => todo!(),
+            ArmSyscall::read | ArmSyscall::write => {
+                format!("fd={}, buf={:#x}, len={}", cpu.r[0], cpu.r[1], cpu.r[2])
+            }
+            ArmSyscall::open => format!("path={:#x}, flags={:#x}", cpu.r[0], cpu.r[1]),
+            ArmSyscall::openat => format!(
+                "dirfd={}, path={:#x}, flags={:#x}",
+                cpu.r[0] as i32, cpu.r[1], cpu.r[2]
+            ),
+            ArmSyscall::close => format!("fd={}", cpu.r[0]),
+            ArmSyscall::uname => format!("buf={:#x}", cpu.r[0]),
+            ArmSyscall::cacheflush => {
+                format!(
+                    "start={:#x}, end={:#x}, flags={}",
+                    cpu.r[0], cpu.r[1], cpu.r[2]
+                )
+            }
+            ArmSyscall::set_tls => format!("tls={:#x}", cpu.r[0]),
             _ => "unimplemented".into(),
         };
         buf.push_str(&args);
@@ -46,6 +71,14 @@ impl TryFrom<u32> for ArmSyscall {
             0x04 => Self::write,
             0x05 => Self::open,
             0x06 => Self::close,
+            0x7a => Self::uname,
+            0x142 => Self::openat,
+            0xf0001 => Self::breakpoint,
+            0xf0002 => Self::cacheflush,
+            0xf0003 => Self::usr26,
+            0xf0004 => Self::usr32,
+            0xf0005 => Self::set_tls,
+            0xf0006 => Self::get_tls,
             _ => return Err(err::Err::UnknownSyscall(value)),
         })
     }
@@ -61,7 +94,68 @@ pub fn syscall_forward<'cpu, const PRINT_INSTR: bool>(
             cpu.status = Some(cpu.r[0] as i32);
             0
         }
+        ArmSyscall::read => sys::read(cpu, cpu.r[0], cpu.r[1], cpu.r[2]),
         ArmSyscall::write => sys::write(cpu, cpu.r[0], cpu.r[1], cpu.r[2]),
+        ArmSyscall::open => sys::open_host(cpu, sys::AT_FDCWD, cpu.r[0], cpu.r[1], cpu.r[2]),
+        ArmSyscall::openat => sys::open_host(cpu, cpu.r[0] as i32, cpu.r[1], cpu.r[2], cpu.r[3]),
+        ArmSyscall::close => sys::close_host(cpu, cpu.r[0]),
+        ArmSyscall::uname => sys::uname(cpu, cpu.r[0]),
         c => todo!("{:?}", c),
     }
 }
+
+/// The ARM private syscall `nr`, issued by the svc instruction `raw`, mirroring the kernel's
+/// `arm_syscall`. Those not handled below return ENOSYS up to 0xf07ff and raise SIGILL above,
+/// 0xf0000 is a branch through zero and raises SIGSEGV.
+pub fn syscall_private<'cpu, const PRINT_INSTR: bool>(
+    cpu: &mut super::Cpu<'cpu, PRINT_INSTR>,
+    nr: u32,
+    raw: u32,
+) -> i32 {
+    let Ok(syscall) = ArmSyscall::try_from(nr) else {
+        return match nr - ARM_NR_BASE {
+            0 => {
+                cpu.raise(sys::Signal::SIGSEGV, raw);
+                0
+            }
+            0x800.. => {
+                cpu.raise(sys::Signal::SIGILL, raw);
+                0
+            }
+            _ => -(sys::Errno::ENOSYS as i32),
+        };
+    };
+
+    match syscall {
+        ArmSyscall::breakpoint => {
+            cpu.raise(sys::Signal::SIGTRAP, raw);
+            cpu.r[0] as i32
+        }
+        // the only cache to maintain holds the decoded blocks
+        ArmSyscall::cacheflush => {
+            let (start, end, flags) = (cpu.r[0], cpu.r[1], cpu.r[2]);
+            if end < start || flags != 0 {
+                -(sys::Errno::EINVAL as i32)
+            } else if cpu
+                .mem
+                .translate_range(start, (end - start) as usize)
+                .is_none()
+            {
+                -(sys::Errno::EFAULT as i32)
+            } else {
+                cpu.invalidate_blocks(start, end);
+                0
+            }
+        }
+        ArmSyscall::set_tls => {
+            cpu.cp15.tpidruro = cpu.r[0];
+            0
+        }
+        ArmSyscall::get_tls => cpu.cp15.tpidruro as i32,
+        // switching to 26-bit mode needs HWCAP_26BIT, which no ARMv7 has
+        _ => {
+            cpu.raise(sys::Signal::SIGILL, raw);
+            0
+        }
+    }
+}
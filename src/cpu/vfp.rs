@@ -0,0 +1,502 @@
+//! The VFPv3 floating-point extension used by armhf binaries, see ARMv7 ARM A7 "Advanced SIMD
+//! and Floating-point Instruction Encoding". The register file is 32 doubleword registers
+//! D0-D31, the single precision registers S0-S31 alias the halves of D0-D15. The arithmetic is
+//! done in [`crate::cpu::fpu`]. VFPv2 lacks D16-D31, VMOV (immediate) and the fixed-point
+//! conversions, VFPv4 adds the fused multiply accumulates.
+//!
+//! The Thumb-2 encodings equal the ARM ones with cond = 1110, so both instruction sets execute
+//! the same decoded word, whose [`Operands`] hold the registers, sz as `size` and the opcode
+//! fields selecting the operation inside each class as `op`. Short vectors (a
+//! nonzero FPSCR Len or Stride) are not implemented, like on the Cortex-A15, and every
+//! data-processing instruction is UNDEFINED with them.
+
+use crate::{
+    cpu::{
+        Cpu, alu,
+        decoder::{Decoded, InstructionKind, Operands, bits},
+        features::Features,
+        fpu::{self, DOUBLE, SINGLE},
+        neon,
+    },
+    err,
+};
+
+/// NZCV, QC, AHP, DN, FZ, RMode, Stride, Len and the cumulative exception bits, the trap enables
+/// read as zero since we do not support trapping
+const FPSCR_WRITABLE: u32 = 0xfff7_009f;
+
+/// VMRS and VMSR register number of the FPSCR, the ID registers are not accessible in User mode
+const FPSCR_REG: usize = 0b0001;
+
+/// Register number of a decoded `extra:field` operand, `field:extra` for singles
+fn reg_index(double: bool, reg: usize) -> usize {
+    if double {
+        reg
+    } else {
+        ((reg & 0xf) << 1) | (reg >> 4)
+    }
+}
+
+impl<'cpu, const PRINT_INSTR: bool> Cpu<'cpu, PRINT_INSTR> {
+    fn s(&self, n: usize) -> u32 {
+        (self.d[n / 2] >> (32 * (n % 2))) as u32
+    }
+
+    fn set_s(&mut self, n: usize, value: u32) {
+        let shift = 32 * (n % 2);
+        self.d[n / 2] = (self.d[n / 2] & !(0xffff_ffff << shift)) | ((value as u64) << shift);
+    }
+
+    /// read S`n` or D`n`, singles are zero extended
+    fn fp_read(&self, double: bool, n: usize) -> u64 {
+        if double { self.d[n] } else { self.s(n) as u64 }
+    }
+
+    /// whether D`n` exists, VFPv2 only has D0-D15
+    fn has_d(&self, n: usize) -> bool {
+        n < 16 || self.features.contains(Features::VFPV3)
+    }
+
+    fn fp_write(&mut self, double: bool, n: usize, value: u64) {
+        if double {
+            self.d[n] = value;
+        } else {
+            self.set_s(n, value as u32);
+        }
+    }
+
+    /// Execute a floating-point instruction from the coprocessor 10 and 11 space.
+    pub(super) fn vfp(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        if !self.features.contains(Features::VFP) {
+            return self.undefined(decoded.raw);
+        }
+
+        match decoded.kind {
+            InstructionKind::VfpDataProcessing => self.vfp_data_processing(decoded),
+            InstructionKind::VfpOther => self.vfp_other(decoded),
+            InstructionKind::VfpTransfer => self.vfp_transfer(decoded),
+            InstructionKind::VfpTransfer64 => self.vfp_transfer64(decoded),
+            InstructionKind::VfpLoadStore => self.vfp_load_store(decoded),
+            _ => self.undefined(decoded.raw),
+        }
+    }
+
+    /// The three register instructions, selected by bits 23 and 21..20 of opc1 and op in bit 6,
+    /// sz selects double precision. VMLA and its variants round the product first, only the
+    /// VFPv4 VFMA and its variants are fused.
+    fn vfp_data_processing(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            vd, vn, vm, size, ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+        if bits(self.fpscr, 21, 16) != 0 {
+            return self.undefined(raw);
+        }
+
+        let double = size == 1;
+        let fmt = if double { DOUBLE } else { SINGLE };
+        let d = reg_index(double, vd);
+        let (opc1, op) = (decoded.operands.op >> 1, decoded.operands.op & 1 == 1);
+        // the low bit of opc1 is bit 20
+        let negate_acc = opc1 & 1 == 1;
+
+        let (n, m) = (reg_index(double, vn), reg_index(double, vm));
+        if double && !(self.has_d(d) && self.has_d(n) && self.has_d(m)) {
+            return self.undefined(raw);
+        }
+        let n = self.fp_read(double, n);
+        let m = self.fp_read(double, m);
+        let mut fpscr = self.fpscr;
+        let result = match opc1 {
+            // VMLA, VMLS, VNMLS and VNMLA, bit 20 negates the accumulator, op the product
+            0b000 | 0b001 => {
+                let product = fpu::mul(fmt, n, m, &mut fpscr);
+                let product = if op { fpu::neg(fmt, product) } else { product };
+                let acc = self.fp_read(double, d);
+                let acc = if negate_acc { fpu::neg(fmt, acc) } else { acc };
+                fpu::add(fmt, acc, product, false, &mut fpscr)
+            }
+            // VMUL and VNMUL
+            0b010 => {
+                let product = fpu::mul(fmt, n, m, &mut fpscr);
+                if op { fpu::neg(fmt, product) } else { product }
+            }
+            // VADD and VSUB
+            0b011 => fpu::add(fmt, n, m, op, &mut fpscr),
+            0b100 if !op => fpu::div(fmt, n, m, &mut fpscr),
+            // VFMA and VFMS, VFNMS and VFNMA (bit 20) negate the accumulator, op the first operand
+            0b101 | 0b110 if self.features.contains(Features::VFPV4) => {
+                let n = if op { fpu::neg(fmt, n) } else { n };
+                let acc = self.fp_read(double, d);
+                let acc = if negate_acc { fpu::neg(fmt, acc) } else { acc };
+                fpu::mul_add(fmt, acc, n, m, &mut fpscr)
+            }
+            _ => return self.undefined(raw),
+        };
+
+        self.fpscr = fpscr;
+        self.fp_write(double, d, result);
+        Ok(())
+    }
+
+    /// The two register instructions and VMOV (immediate), selected by opc2 in bits 19..16 and
+    /// bit 7 of opc3, VMOV (immediate) has bit 6 clear. The conversions read or write a register
+    /// of the other width.
+    fn vfp_other(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            vd,
+            vm,
+            op,
+            imm: imm8,
+            size,
+            ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+        if bits(self.fpscr, 21, 16) != 0 {
+            return self.undefined(raw);
+        }
+
+        let double = size == 1;
+        let fmt = if double { DOUBLE } else { SINGLE };
+        let d = reg_index(double, vd);
+        let (opc2, b7, b6) = (op >> 2, op & 0b10 != 0, op & 1 == 1);
+        if !self.features.contains(Features::VFPV3) {
+            // the conversions read or write a register of the other width
+            let d_double = match opc2 {
+                0b0111 => !double,
+                0b1100 | 0b1101 => false,
+                _ => double,
+            };
+            let m_double = double && opc2 != 0b1000;
+            let fixed = matches!(opc2, 0b1010 | 0b1011 | 0b1110 | 0b1111);
+            if !b6 || fixed || (d_double && vd >> 4 == 1) || (m_double && vm >> 4 == 1) {
+                return self.undefined(raw);
+            }
+        }
+        if !b6 {
+            self.fp_write(double, d, fpu::expand_imm(fmt, imm8));
+            return Ok(());
+        }
+
+        let m = self.fp_read(double, reg_index(double, vm));
+        let mut fpscr = self.fpscr;
+        match (opc2, b7) {
+            (0b0000, false) => self.fp_write(double, d, m),
+            (0b0000, true) => self.fp_write(double, d, fpu::abs(fmt, m)),
+            (0b0001, false) => self.fp_write(double, d, fpu::neg(fmt, m)),
+            (0b0001, true) => self.fp_write(double, d, fpu::sqrt(fmt, m, &mut fpscr)),
+            // VCMP and VCMPE (bit 7), opc2 = 0101 compares with zero
+            (0b0100 | 0b0101, signal_nans) => {
+                let operand = if opc2 == 0b0101 {
+                    if vm != 0 {
+                        return Err(err::Err::UnpredictableInstruction(raw));
+                    }
+                    0
+                } else {
+                    m
+                };
+                let flags = fpu::compare(
+                    fmt,
+                    self.fp_read(double, d),
+                    operand,
+                    signal_nans,
+                    &mut fpscr,
+                );
+                fpscr = (fpscr & !alu::NZCV) | flags;
+            }
+            // VCVT between double and single precision, the destination has the other width
+            (0b0111, true) => {
+                let (from, to) = if double {
+                    (DOUBLE, SINGLE)
+                } else {
+                    (SINGLE, DOUBLE)
+                };
+                let result = fpu::convert(from, to, m, &mut fpscr);
+                self.fp_write(!double, reg_index(!double, vd), result);
+            }
+            // VCVT from a signed (bit 7) or unsigned integer in a single precision register
+            (0b1000, signed) => {
+                let value = self.s(reg_index(false, vm));
+                let result = fpu::from_fixed(fmt, value, 0, !signed, 32, &mut fpscr);
+                self.fp_write(double, d, result);
+            }
+            // VCVT between floating and fixed point in place, bit 18 converts to fixed, bit 16
+            // selects unsigned and bit 7 32 instead of 16 bits
+            (0b1010 | 0b1011 | 0b1110 | 0b1111, sx) => {
+                let size = if sx { 32 } else { 16 };
+                // imm4:i sits where a single precision Vm does
+                let imm = reg_index(false, vm) as u32;
+                if imm > size {
+                    return Err(err::Err::UnpredictableInstruction(raw));
+                }
+                let frac_bits = size - imm;
+                let unsigned = opc2 & 1 == 1;
+                let value = self.fp_read(double, d);
+                let result = if opc2 & 0b100 != 0 {
+                    let fixed = fpu::to_fixed(
+                        fmt,
+                        value,
+                        frac_bits,
+                        unsigned,
+                        size,
+                        fpu::Rounding::Zero,
+                        &mut fpscr,
+                    );
+                    if unsigned {
+                        fixed as u64
+                    } else {
+                        fixed as i32 as i64 as u64
+                    }
+                } else {
+                    fpu::from_fixed(fmt, value as u32, frac_bits, unsigned, size, &mut fpscr)
+                };
+                self.fp_write(double, d, result);
+            }
+            // VCVT (bit 7, towards zero) and VCVTR (FPSCR rounding) to a signed (opc2 = 1101) or
+            // unsigned integer in a single precision register
+            (0b1100 | 0b1101, round_zero) => {
+                let mode = if round_zero {
+                    fpu::Rounding::Zero
+                } else {
+                    fpu::Rounding::from_fpscr(fpscr)
+                };
+                let result = fpu::to_fixed(fmt, m, 0, opc2 == 0b1100, 32, mode, &mut fpscr);
+                self.set_s(reg_index(false, vd), result);
+            }
+            // VCVTB and VCVTT need the half-precision extension
+            _ => return self.undefined(raw),
+        }
+
+        self.fpscr = fpscr;
+        Ok(())
+    }
+
+    /// VMOV between a core and a single precision register, VMOV between a core register and
+    /// the 32-bit half of a double precision register (C, the scalar form) and VMRS/VMSR, with L
+    /// reading the FP side. `vmrs APSR_nzcv, fpscr` (Rt = pc) copies the comparison flags.
+    fn vfp_transfer(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            l: to_core,
+            vn,
+            rt,
+            op,
+            ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+        // op is C:A:B, A being bits 23..21 and B bits 6..5
+        let (a, b) = ((op >> 2) & 0b111, op & 0b11);
+
+        if op >> 5 == 1 {
+            if rt == 15 {
+                return Err(err::Err::UnpredictableInstruction(raw));
+            }
+            let d = reg_index(true, vn);
+            if !self.has_d(d) {
+                return self.undefined(raw);
+            }
+            if !to_core && a >> 2 == 1 {
+                return self.vdup_core(decoded, d, self.r[rt]);
+            }
+            // opc1:opc2 in bits 22..21 and 6..5 select the scalar size and index, the high bit
+            // of A is U
+            let opc = ((a & 0b11) << 2) | b;
+            let (esize, index) = if opc & 0b1000 != 0 {
+                (8, opc & 0b111)
+            } else if opc & 0b0001 != 0 {
+                (16, (opc >> 1) & 0b11)
+            } else if opc & 0b0010 == 0 {
+                (32, opc >> 2)
+            } else {
+                return self.undefined(raw);
+            };
+            // the byte and halfword scalars are Advanced SIMD
+            let unsigned = a >> 2 == 1;
+            if (esize != 32 && !self.features.contains(Features::NEON)) || (esize == 32 && unsigned)
+            {
+                return self.undefined(raw);
+            }
+            let (index, esize) = (index as usize, esize as u32);
+            if to_core {
+                let value = neon::elem(self.d[d] as u128, index, esize);
+                self.r[rt] = if unsigned {
+                    value as u32
+                } else {
+                    neon::sext(value, esize) as u32
+                };
+            } else {
+                self.d[d] =
+                    neon::set_elem(self.d[d] as u128, index, esize, self.r[rt] as u64) as u64;
+            }
+            return Ok(());
+        }
+
+        match a {
+            0b000 => {
+                if rt == 15 {
+                    return Err(err::Err::UnpredictableInstruction(raw));
+                }
+                let n = reg_index(false, vn);
+                if to_core {
+                    self.r[rt] = self.s(n);
+                } else {
+                    self.set_s(n, self.r[rt]);
+                }
+            }
+            0b111 if vn & 0xf == FPSCR_REG => {
+                if to_core && rt == 15 {
+                    self.cpsr = (self.cpsr & !alu::NZCV) | (self.fpscr & alu::NZCV);
+                } else if to_core {
+                    self.r[rt] = self.fpscr;
+                } else if rt == 15 {
+                    return Err(err::Err::UnpredictableInstruction(raw));
+                } else {
+                    self.fpscr = self.r[rt] & FPSCR_WRITABLE;
+                }
+            }
+            _ => return self.undefined(raw),
+        }
+
+        Ok(())
+    }
+
+    /// VMOV between two core registers and either two consecutive single precision registers or
+    /// a double precision register (sz). L moves to the core registers.
+    fn vfp_transfer64(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            l: to_core,
+            rt2,
+            rt,
+            vm,
+            size,
+            ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+        let double = size == 1;
+        let m = reg_index(double, vm);
+        if double && !self.has_d(m) {
+            return self.undefined(raw);
+        }
+        if rt == 15 || rt2 == 15 || (to_core && rt == rt2) || (!double && m == 31) {
+            return Err(err::Err::UnpredictableInstruction(raw));
+        }
+
+        let (low, high) = if double {
+            (2 * m, 2 * m + 1)
+        } else {
+            (m, m + 1)
+        };
+        if to_core {
+            self.r[rt] = self.s(low);
+            self.r[rt2] = self.s(high);
+        } else {
+            self.set_s(low, self.r[rt]);
+            self.set_s(high, self.r[rt2]);
+        }
+        Ok(())
+    }
+
+    /// VLDR and VSTR with an offset of imm8 words, VLDM and VSTM (VPUSH is `vstmdb sp!`, VPOP
+    /// `vldmia sp!`) transferring imm8 words. sz selects doubles. An odd imm8 with doubles is
+    /// the deprecated FLDMX/FSTMX, which transfers the same registers and skips the extra word.
+    fn vfp_load_store(&mut self, decoded: &Decoded) -> Result<(), err::Err> {
+        let Operands {
+            p,
+            u,
+            w,
+            l,
+            rn,
+            vd,
+            imm,
+            size,
+            ..
+        } = decoded.operands;
+        let raw = decoded.raw;
+        let double = size == 1;
+        let d = reg_index(double, vd);
+        let offset = imm << 2;
+
+        if p && !w {
+            // literal loads read the word aligned pc
+            let base = if rn == 15 {
+                self.reg(15) & !3
+            } else {
+                self.r[rn]
+            };
+            let addr = if u {
+                base.wrapping_add(offset)
+            } else {
+                base.wrapping_sub(offset)
+            };
+            if double && !self.has_d(d) {
+                return self.undefined(raw);
+            }
+            return self.vfp_access(raw, l, double, d, addr);
+        }
+        // P = U is a 64-bit transfer or undefined
+        if p == u {
+            return self.undefined(raw);
+        }
+
+        let count = if double {
+            imm as usize / 2
+        } else {
+            imm as usize
+        };
+        if count == 0 || d + count > 32 || (double && count > 16) || (w && rn == 15) {
+            return Err(err::Err::UnpredictableInstruction(raw));
+        }
+        if double && !self.has_d(d + count - 1) {
+            return self.undefined(raw);
+        }
+
+        let base = self.reg(rn);
+        let start = if u { base } else { base.wrapping_sub(offset) };
+        let size = if double { 8 } else { 4 };
+        for i in 0..count {
+            self.vfp_access(raw, l, double, d + i, start.wrapping_add((i * size) as u32))?;
+        }
+        if w {
+            self.r[rn] = if u {
+                base.wrapping_add(offset)
+            } else {
+                base.wrapping_sub(offset)
+            };
+        }
+        Ok(())
+    }
+
+    /// Load or store S`n` or D`n` at the word aligned `addr`
+    fn vfp_access(
+        &mut self,
+        raw: u32,
+        load: bool,
+        double: bool,
+        n: usize,
+        addr: u32,
+    ) -> Result<(), err::Err> {
+        let fault = err::Err::MemoryAccessViolation {
+            guest: addr,
+            instr: raw,
+        };
+        if !addr.is_multiple_of(4) {
+            return Err(fault);
+        }
+
+        match (load, double) {
+            (true, true) => self.d[n] = self.mem.read_u64(addr).ok_or(fault)?,
+            (true, false) => {
+                let value = self.mem.read_u32(addr).ok_or(fault)?;
+                self.set_s(n, value);
+            }
+            (false, true) => {
+                self.monitor.store(addr, 8);
+                self.mem.write_u64(addr, self.d[n]).map_err(|_| fault)?;
+            }
+            (false, false) => {
+                self.monitor.store(addr, 4);
+                self.mem.write_u32(addr, self.s(n)).map_err(|_| fault)?;
+            }
+        }
+        Ok(())
+    }
+}
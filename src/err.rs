@@ -3,5 +3,22 @@ pub enum Err {
     ElfConstraintViolation(String),
     UnknownSyscall(u32),
     UnknownOrUnsupportedInstruction(u32),
-    MemoryAccessViolation { guest: u32, instr: u32 },
+    /// An encoding the architecture defines as UNPREDICTABLE, for instance in User mode
+    UnpredictableInstruction(u32),
+    MemoryAccessViolation {
+        guest: u32,
+        instr: u32,
+    },
+}
+
+impl Err {
+    /// The exit status of an emulation stopped by self, outside both the statuses a guest exits
+    /// with on its own and the 128 + signal of a guest killed by a signal. An UNPREDICTABLE
+    /// instruction gets its own status, so `--strict` runs tell a violation from a gap.
+    pub fn exit_status(&self) -> i32 {
+        match self {
+            Self::UnpredictableInstruction(_) => 126,
+            _ => 125,
+        }
+    }
 }
@@ -42,6 +42,10 @@ fn main() {
     }
 
     let mut mem = mem::Mem::new();
+    // bare-metal images may be linked at 0, where their vector table lives
+    if conf.bare_metal {
+        mem.map_null_page();
+    }
 
     // The emulator's memory arena is much larger than the program image.  Keep
     // the file-backed extent of the segment containing the entry point so the
@@ -110,16 +114,23 @@ fn run<const PRINT_INSTR: bool>(
         stinkln!("starting the emulator");
     }
 
+    let mut fault = None;
     loop {
-        if cpu.instr_addr() >= instr_end {
+        // the kernel user helpers live far above the program image, a bare-metal image runs until
+        // it exits through semihosting
+        if !conf.bare_metal
+            && cpu.instr_addr() >= instr_end
+            && !cpu.mem.is_vectors(cpu.instr_addr())
+        {
             break;
         }
 
-        match cpu.step() {
+        match cpu.step_block(instr_end) {
             // EOI - end of instructions :^)
             Ok(false) => break,
             Err(err) => {
                 println!("err: `{:?}`, exiting emulation", err);
+                fault = Some(err.exit_status());
                 break;
             }
             Ok(true) => {}
@@ -130,7 +141,7 @@ fn run<const PRINT_INSTR: bool>(
         }
     }
 
-    let status = cpu.status.unwrap_or(0);
+    let status = fault.or(cpu.status).unwrap_or(0);
     if conf.verbose {
         stinkln!("exiting with `{}`", status);
     }
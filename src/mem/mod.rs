@@ -4,10 +4,36 @@ use std::ptr::NonNull;
 
 pub const DEFAULT_GUEST_MEMORY_SIZE: usize = 128 * 1024 * 1024;
 const NULL_PAGE_SIZE: u32 = 0x1000;
+/// the granularity code is tracked in, see [`Mem::mark_code`]
+pub const CODE_PAGE_SIZE: u32 = 0x1000;
+/// the high vector page, Linux puts the kernel user helpers at its end
+pub const VECTORS: u32 = 0xffff_0000;
+pub const VECTORS_SIZE: usize = 0x1000;
 
 pub struct Mem {
     ptr: NonNull<u8>,
     len: usize,
+    /// the page at [`VECTORS`], far above the arena, see [`Mem::map_vectors`]
+    vectors: Option<Box<[u8; VECTORS_SIZE]>>,
+    /// whether the guest may write to the vector page
+    vectors_writable: bool,
+    /// whether accesses to the first page fault, see [`Mem::map_null_page`]
+    null_guard: bool,
+    /// a bit per arena page holding decoded instructions, see [`Mem::mark_code`]
+    code_pages: Vec<u64>,
+    /// the ranges written to code pages since [`Mem::take_code_writes`]
+    code_writes: Vec<(u32, u32)>,
+}
+
+/// The arena as translated code accesses it, see [`Mem::arena`]
+#[repr(C)]
+pub struct Arena {
+    pub base: *mut u8,
+    pub len: u64,
+    /// the lowest accessible address, the first page is guarded unless it was mapped
+    pub low: u64,
+    /// the bitset of [`Mem::mark_code`], a bit per [`CODE_PAGE_SIZE`] bytes
+    pub code_pages: *const u64,
 }
 
 impl Default for Mem {
@@ -32,7 +58,93 @@ impl Mem {
         )
         .expect("failed to allocate guest memory");
 
-        Self { ptr, len: size }
+        Self {
+            ptr,
+            len: size,
+            vectors: None,
+            vectors_writable: false,
+            null_guard: true,
+            code_pages: vec![0; (size / CODE_PAGE_SIZE as usize).div_ceil(64)],
+            code_writes: Vec::new(),
+        }
+    }
+
+    /// Map `page` at [`VECTORS`]. The guest can read and execute it, but not write to it.
+    pub fn map_vectors(&mut self, page: [u8; VECTORS_SIZE]) {
+        self.vectors = Some(Box::new(page));
+        self.vectors_writable = false;
+    }
+
+    /// Map a zeroed page at [`VECTORS`] the guest can also write, bare-metal images install
+    /// their high exception vectors there.
+    pub fn map_writable_vectors(&mut self) {
+        self.vectors = Some(Box::new([0; VECTORS_SIZE]));
+        self.vectors_writable = true;
+    }
+
+    /// Make the first page accessible, bare-metal images place their low exception vectors at
+    /// address 0. Linux never maps it, so by default accesses to it fault.
+    pub fn map_null_page(&mut self) {
+        self.null_guard = false;
+    }
+
+    /// Whether `guest_addr` lies in the mapped vector page.
+    pub fn is_vectors(&self, guest_addr: u32) -> bool {
+        self.vectors.is_some() && (VECTORS..VECTORS + VECTORS_SIZE as u32).contains(&guest_addr)
+    }
+
+    /// Track writes to the pages of `len` bytes at `guest_addr`, because the CPU cached the
+    /// instructions there decoded. See [`Mem::take_code_writes`].
+    pub fn mark_code(&mut self, guest_addr: u32, len: u32) {
+        for page in Self::pages(guest_addr, len as usize) {
+            self.code_pages[page / 64] |= 1 << (page % 64);
+        }
+    }
+
+    /// Stop tracking writes to the page containing `guest_addr`.
+    pub fn unmark_code(&mut self, guest_addr: u32) {
+        let page = Self::pages(guest_addr, 1).start;
+        self.code_pages[page / 64] &= !(1 << (page % 64));
+    }
+
+    /// Whether a write hit a page marked by [`Mem::mark_code`] since the last
+    /// [`Mem::take_code_writes`].
+    #[inline(always)]
+    pub fn code_written(&self) -> bool {
+        !self.code_writes.is_empty()
+    }
+
+    /// The `start..end` ranges written to marked pages since the last call.
+    pub fn take_code_writes(&mut self) -> Vec<(u32, u32)> {
+        std::mem::take(&mut self.code_writes)
+    }
+
+    /// The arena pages `len` bytes at `guest_addr` touch
+    #[inline(always)]
+    fn pages(guest_addr: u32, len: usize) -> std::ops::Range<usize> {
+        let start = guest_addr as usize;
+        let page = CODE_PAGE_SIZE as usize;
+        start / page..(start + len).div_ceil(page)
+    }
+
+    /// The host view of the arena. The vector page isn't part of it, the pointers stay valid as
+    /// long as `self` lives.
+    pub fn arena(&self) -> Arena {
+        Arena {
+            base: self.ptr.as_ptr(),
+            len: self.len as u64,
+            low: if self.null_guard {
+                NULL_PAGE_SIZE as u64
+            } else {
+                0
+            },
+            code_pages: self.code_pages.as_ptr(),
+        }
+    }
+
+    /// Initial stack pointer, the guest stack grows down from the end of the arena.
+    pub fn stack_top(&self) -> u32 {
+        self.len as u32
     }
 
     /// Copy bytes into guest memory at `guest_addr`.
@@ -58,8 +170,13 @@ impl Mem {
         self.translate_range(guest_addr, 1)
     }
 
-    /// Translate a guest address range to a host pointer to the first byte.
+    /// Translate a guest address range to a host pointer to the first byte. Ranges in the vector
+    /// page must only be read through it, see [`Mem::translate_range_mut`] for writes.
     pub fn translate_range(&self, guest_addr: u32, len: usize) -> Option<*mut u8> {
+        if let Some(bytes) = self.vectors_slice(guest_addr, len) {
+            return Some(bytes.as_ptr() as *mut u8);
+        }
+
         if !self.in_bounds(guest_addr, len) {
             return None;
         }
@@ -67,20 +184,69 @@ impl Mem {
         Some(self.ptr.as_ptr().wrapping_add(guest_addr as usize))
     }
 
+    /// Translate a guest address range the host writes to, recording a write to code pages like
+    /// the other writes do. The read-only vector page doesn't translate.
+    pub fn translate_range_mut(&mut self, guest_addr: u32, len: usize) -> Option<*mut u8> {
+        self.get_slice_mut(guest_addr, len)
+            .map(|bytes| bytes.as_mut_ptr())
+    }
+
+    pub fn read_u8(&self, guest_addr: u32) -> Option<u8> {
+        self.get_slice(guest_addr, 1).map(|bytes| bytes[0])
+    }
+
+    pub fn write_u8(&mut self, guest_addr: u32, value: u8) -> Result<(), &'static str> {
+        let byte = self
+            .get_slice_mut(guest_addr, 1)
+            .ok_or("Failed compute host addr to write to")?;
+        byte[0] = value;
+        Ok(())
+    }
+
+    pub fn read_u16(&self, guest_addr: u32) -> Option<u16> {
+        let bytes = self.get_slice(guest_addr, 2)?;
+        Some(u16::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub fn write_u16(&mut self, guest_addr: u32, value: u16) -> Result<(), &'static str> {
+        let bytes = self
+            .get_slice_mut(guest_addr, 2)
+            .ok_or("Failed compute host addr to write to")?;
+        bytes.copy_from_slice(&value.to_le_bytes());
+        Ok(())
+    }
+
     pub fn read_u32(&self, guest_addr: u32) -> Option<u32> {
         let bytes = self.get_slice(guest_addr, 4)?;
         Some(u32::from_le_bytes(bytes.try_into().unwrap()))
     }
 
     pub fn write_u32(&mut self, guest_addr: u32, value: u32) -> Result<(), &'static str> {
-        let ptr = self
-            .translate_range(guest_addr, 4)
+        let bytes = self
+            .get_slice_mut(guest_addr, 4)
+            .ok_or("Failed compute host addr to write to")?;
+        bytes.copy_from_slice(&value.to_le_bytes());
+        Ok(())
+    }
+
+    pub fn read_u64(&self, guest_addr: u32) -> Option<u64> {
+        let bytes = self.get_slice(guest_addr, 8)?;
+        Some(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub fn write_u64(&mut self, guest_addr: u32, value: u64) -> Result<(), &'static str> {
+        let bytes = self
+            .get_slice_mut(guest_addr, 8)
             .ok_or("Failed compute host addr to write to")?;
-        unsafe { (ptr as *mut u32).write_unaligned(value.to_le()) };
+        bytes.copy_from_slice(&value.to_le_bytes());
         Ok(())
     }
 
     fn get_slice(&self, guest_addr: u32, len: usize) -> Option<&[u8]> {
+        if let Some(bytes) = self.vectors_slice(guest_addr, len) {
+            return Some(bytes);
+        }
+
         if !self.in_bounds(guest_addr, len) {
             return None;
         }
@@ -89,17 +255,38 @@ impl Mem {
     }
 
     fn get_slice_mut(&mut self, guest_addr: u32, len: usize) -> Option<&mut [u8]> {
+        if self.vectors_writable && self.is_vectors(guest_addr) {
+            let start = (guest_addr - VECTORS) as usize;
+            return self
+                .vectors
+                .as_mut()?
+                .get_mut(start..start.checked_add(len)?);
+        }
+
         if !self.in_bounds(guest_addr, len) {
             return None;
         }
 
+        if Self::pages(guest_addr, len)
+            .any(|page| self.code_pages[page / 64] >> (page % 64) & 1 == 1)
+        {
+            self.code_writes
+                .push((guest_addr, guest_addr.wrapping_add(len as u32)));
+        }
+
         Some(unsafe {
             std::slice::from_raw_parts_mut(self.ptr.as_ptr().add(guest_addr as usize), len)
         })
     }
 
+    fn vectors_slice(&self, guest_addr: u32, len: usize) -> Option<&[u8]> {
+        let page = self.vectors.as_ref()?;
+        let start = guest_addr.checked_sub(VECTORS)? as usize;
+        page.get(start..start.checked_add(len)?)
+    }
+
     fn in_bounds(&self, guest_addr: u32, len: usize) -> bool {
-        if guest_addr < NULL_PAGE_SIZE {
+        if self.null_guard && guest_addr < NULL_PAGE_SIZE {
             return false;
         }
 
@@ -152,4 +339,89 @@ mod tests {
 
         assert_eq!(mem.read_u32(0x1000), Some(0x1234_abcd));
     }
+
+    #[test]
+    fn byte_accesses_address_single_bytes() {
+        let mut mem = Mem::with_size(0x2000);
+
+        mem.write_u32(0x1000, 0x1234_abcd)
+            .expect("write should fit");
+        mem.write_u8(0x1001, 0xff).expect("write should fit");
+
+        assert_eq!(mem.read_u8(0x1000), Some(0xcd));
+        assert_eq!(mem.read_u32(0x1000), Some(0x1234_ffcd));
+        assert_eq!(mem.read_u8(0x2000), None);
+        assert!(mem.write_u8(0xfff, 0).is_err());
+    }
+
+    #[test]
+    fn halfword_and_doubleword_accesses_are_little_endian() {
+        let mut mem = Mem::with_size(0x2000);
+
+        mem.write_u64(0x1000, 0x0102_0304_0506_0708)
+            .expect("write should fit");
+        mem.write_u16(0x1003, 0xaabb).expect("write should fit");
+
+        assert_eq!(mem.read_u16(0x1000), Some(0x0708));
+        assert_eq!(mem.read_u32(0x1004), Some(0x0102_03aa));
+        assert_eq!(mem.read_u64(0x1000), Some(0x0102_03aa_bb06_0708));
+        assert_eq!(mem.read_u64(0x1ff9), None);
+        assert!(mem.write_u16(0x1fff, 0).is_err());
+    }
+
+    #[test]
+    fn vector_page_is_read_only_and_only_mapped_on_request() {
+        let mut mem = Mem::with_size(0x2000);
+        assert_eq!(mem.read_u32(0xffff_0ffc), None);
+        assert!(!mem.is_vectors(0xffff_0ffc));
+
+        let mut page = [0; super::VECTORS_SIZE];
+        page[0xffc..].copy_from_slice(&5u32.to_le_bytes());
+        mem.map_vectors(page);
+
+        assert!(mem.is_vectors(0xffff_0ffc));
+        assert_eq!(mem.read_u32(0xffff_0ffc), Some(5));
+        assert_eq!(mem.read_u64(0xffff_0ffc), None);
+        assert!(mem.write_u32(0xffff_0ffc, 0).is_err());
+        assert!(mem.translate_range(0xffff_0000, 0x1000).is_some());
+        assert!(mem.translate_range_mut(0xffff_0f60, 16).is_none());
+        assert_eq!(mem.read_u32(0xfffe_fffc), None);
+    }
+
+    #[test]
+    fn bare_metal_maps_the_null_page_and_writable_vectors() {
+        let mut mem = Mem::with_size(0x2000);
+        mem.map_null_page();
+        mem.map_writable_vectors();
+
+        mem.write_u32(0, 0xe59f_f018)
+            .expect("the null page is mapped");
+        mem.write_u32(0xffff_0004, 0xe59f_f018)
+            .expect("the vector page is writable");
+
+        assert_eq!(mem.read_u32(0), Some(0xe59f_f018));
+        assert_eq!(mem.read_u32(0xffff_0004), Some(0xe59f_f018));
+        assert!(mem.write_u32(0xffff_0ffe, 0).is_err());
+    }
+
+    #[test]
+    fn writes_to_code_pages_are_recorded() {
+        let mut mem = Mem::with_size(0x4000);
+        mem.mark_code(0x1ffc, 8);
+
+        mem.write_u32(0x3000, 1).expect("write should fit");
+        assert!(!mem.code_written());
+        mem.write_u16(0x1ffe, 1).expect("write should fit");
+        mem.write_u64(0x2ffc, 1).expect("write should fit");
+        assert!(mem.code_written());
+        assert_eq!(mem.take_code_writes(), [(0x1ffe, 0x2000), (0x2ffc, 0x3004)]);
+        assert!(!mem.code_written());
+        mem.translate_range_mut(0x1f00, 0x200)
+            .expect("range should fit");
+        assert_eq!(mem.take_code_writes(), [(0x1f00, 0x2100)]);
+
+        mem.unmark_code(0x1000);
+        mem.write_u8(0x1000, 1).expect("write should fit");
+        assert!(!mem.code_written());
+    }
 }
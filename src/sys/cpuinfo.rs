@@ -0,0 +1,55 @@
+use crate::{config::CpuModel, cpu::features::Features};
+
+/// Names of the AT_HWCAP bits in the Features line, indexed by bit, see `hwcap_str` in
+/// arch/arm/kernel/setup.c
+const HWCAP: [&str; 22] = [
+    "swp", "half", "thumb", "26bit", "fastmult", "fpa", "vfp", "edsp", "java", "iwmmxt", "crunch",
+    "thumbee", "neon", "vfpv3", "vfpv3d16", "tls", "vfpv4", "idiva", "idivt", "vfpd32", "lpae",
+    "evtstrm",
+];
+
+/// Names of the AT_HWCAP2 bits, following those of AT_HWCAP
+const HWCAP2: [&str; 5] = ["aes", "pmull", "sha1", "sha2", "crc32"];
+
+/// The `names` of the bits set in `hwcap`
+fn names(hwcap: u32, names: &'static [&'static str]) -> impl Iterator<Item = &'static str> {
+    names
+        .iter()
+        .enumerate()
+        .filter(move |(bit, _)| hwcap & (1 << bit) != 0)
+        .map(|(_, name)| *name)
+}
+
+/// The contents of /proc/cpuinfo for a single `model` processor with `features`, in the format
+/// Linux prints for AArch32 tasks
+pub fn cpuinfo(model: CpuModel, features: Features) -> String {
+    let flags: Vec<_> = names(features.hwcap(), &HWCAP)
+        .chain(names(features.hwcap2(), &HWCAP2))
+        .collect();
+
+    let arch = model.architecture();
+    let midr = model.midr();
+    let name = if arch < 7 {
+        format!("ARMv{arch}-compatible processor")
+    } else {
+        format!("ARMv{arch} Processor")
+    };
+
+    format!(
+        "processor\t: 0\n\
+         model name\t: {name} rev {revision} (v{arch}l)\n\
+         Features\t: {flags}\n\
+         CPU implementer\t: {implementer:#x}\n\
+         CPU architecture: {cpuid_arch}\n\
+         CPU variant\t: {variant:#x}\n\
+         CPU part\t: {part:#x}\n\
+         CPU revision\t: {revision}\n\n",
+        // Linux derives it from the memory model, the ARM1176 already has the ARMv7 one
+        cpuid_arch = arch.max(7),
+        revision = midr & 0xf,
+        flags = flags.join(" "),
+        implementer = midr >> 24,
+        variant = (midr >> 20) & 0xf,
+        part = (midr >> 4) & 0xfff,
+    )
+}
@@ -0,0 +1,252 @@
+use crate::{cpu, sys};
+
+/// longest path the guest may pass, including its NUL
+const PATH_MAX: u32 = 4096;
+/// the access mode bits of the open flags, O_RDONLY is 0
+const O_ACCMODE: u32 = 0b11;
+/// the guest shares stdin, stdout and stderr with the host, synthesized files come after them
+const FIRST_FD: u32 = 3;
+/// `dirfd` of a path relative to the working directory
+pub const AT_FDCWD: i32 = -100;
+/// The open flags whose values differ between ARM and x86-64, as (ARM, x86-64): O_DIRECTORY,
+/// O_NOFOLLOW, O_DIRECT and O_LARGEFILE
+const MOVED_FLAGS: [(u32, u32); 4] = [
+    (0o40000, 0o200000),
+    (0o100000, 0o400000),
+    (0o200000, 0o40000),
+    (0o400000, 0o100000),
+];
+
+/// A file the emulator generates instead of opening it on the host
+struct File {
+    contents: Vec<u8>,
+    offset: usize,
+}
+
+/// The synthesized files the guest has open, indexed by fd
+#[derive(Default)]
+pub struct Files(Vec<Option<File>>);
+
+impl Files {
+    /// whether `fd` is an open synthesized file
+    pub fn is_open(&self, fd: u32) -> bool {
+        self.0.get(fd as usize).is_some_and(Option::is_some)
+    }
+
+    fn get_mut(&mut self, fd: u32) -> Option<&mut File> {
+        self.0.get_mut(fd as usize)?.as_mut()
+    }
+
+    /// Closes `fd`, None if it isn't an open synthesized file
+    fn remove(&mut self, fd: u32) -> Option<File> {
+        self.0.get_mut(fd as usize)?.take()
+    }
+
+    /// Opens `file` on the lowest free fd, like the kernel picks it
+    fn insert(&mut self, file: File) -> u32 {
+        let fd = match self
+            .0
+            .iter()
+            .skip(FIRST_FD as usize)
+            .position(Option::is_none)
+        {
+            Some(slot) => FIRST_FD as usize + slot,
+            None => self.0.len().max(FIRST_FD as usize),
+        };
+        self.insert_at(fd as u32, file);
+        fd as u32
+    }
+
+    /// Opens `file` on `fd`
+    fn insert_at(&mut self, fd: u32, file: File) {
+        let fd = fd as usize;
+        if fd >= self.0.len() {
+            self.0.resize_with(fd + 1, || None);
+        }
+        self.0[fd] = Some(file);
+    }
+}
+
+/// The NUL terminated guest string at `addr`
+fn read_path<const PRINT_INSTR: bool>(
+    cpu: &cpu::Cpu<'_, PRINT_INSTR>,
+    addr: u32,
+) -> Result<Vec<u8>, sys::Errno> {
+    let mut path = Vec::new();
+    for i in 0..PATH_MAX {
+        match cpu.mem.read_u8(addr.wrapping_add(i)) {
+            Some(0) => return Ok(path),
+            Some(byte) => path.push(byte),
+            None => return Err(sys::Errno::EFAULT),
+        }
+    }
+    Err(sys::Errno::ENAMETOOLONG)
+}
+
+/// The contents of the file the emulator generates for `path`
+fn synthesized<const PRINT_INSTR: bool>(
+    cpu: &cpu::Cpu<'_, PRINT_INSTR>,
+    path: &[u8],
+) -> Option<Vec<u8>> {
+    match path {
+        b"/proc/cpuinfo" => Some(sys::cpuinfo(cpu.model, cpu.features).into_bytes()),
+        _ => None,
+    }
+}
+
+/// Opens the guest path at `path`. Only /proc/cpuinfo exists, generated from the emulated
+/// processor and read-only, there is no access to the host's files.
+pub fn open<const PRINT_INSTR: bool>(
+    cpu: &mut cpu::Cpu<'_, PRINT_INSTR>,
+    path: u32,
+    flags: u32,
+) -> i32 {
+    let path = match read_path(cpu, path) {
+        Ok(path) => path,
+        Err(errno) => return -(errno as i32),
+    };
+    let Some(contents) = synthesized(cpu, &path) else {
+        return -(sys::Errno::ENOENT as i32);
+    };
+    if flags & O_ACCMODE != 0 {
+        return -(sys::Errno::EACCES as i32);
+    }
+
+    cpu.files.insert(File {
+        contents,
+        offset: 0,
+    }) as i32
+}
+
+/// Opens the guest path at `path` relative to `dirfd` on the host, except for the files
+/// [`open`] synthesizes. A synthesized file holds an fd of /dev/null on the host, so the host
+/// doesn't hand out its number for another file.
+pub fn open_host<const PRINT_INSTR: bool>(
+    cpu: &mut cpu::Cpu<'_, PRINT_INSTR>,
+    dirfd: i32,
+    path: u32,
+    flags: u32,
+    mode: u32,
+) -> i32 {
+    let path = match read_path(cpu, path) {
+        Ok(path) => path,
+        Err(errno) => return -(errno as i32),
+    };
+    let Some(contents) = synthesized(cpu, &path) else {
+        return host_openat(dirfd, path, host_flags(flags), mode);
+    };
+    if flags & O_ACCMODE != 0 {
+        return -(sys::Errno::EACCES as i32);
+    }
+
+    let fd = host_openat(AT_FDCWD, b"/dev/null".to_vec(), 0, 0);
+    if fd >= 0 {
+        cpu.files.insert_at(
+            fd as u32,
+            File {
+                contents,
+                offset: 0,
+            },
+        );
+    }
+    fd
+}
+
+/// The ARM open `flags` as the host expects them
+fn host_flags(flags: u32) -> u32 {
+    let moved = MOVED_FLAGS.iter().fold(0, |mask, (arm, _)| mask | arm);
+    MOVED_FLAGS
+        .iter()
+        .filter(|(arm, _)| flags & arm != 0)
+        .fold(flags & !moved, |host, (_, x86)| host | x86)
+}
+
+/// The host's `openat`, `path` without its NUL
+fn host_openat(dirfd: i32, mut path: Vec<u8>, flags: u32, mode: u32) -> i32 {
+    path.push(0);
+    let ret: i64;
+    unsafe {
+        core::arch::asm!(
+            "syscall",
+            in("rax") 257_u64,
+            in("rdi") dirfd as i64 as u64,
+            in("rsi") path.as_ptr() as u64,
+            in("rdx") flags as u64,
+            in("r10") mode as u64,
+            lateout("rax") ret,
+            out("rcx") _,
+            out("r11") _,
+            options(nostack),
+        );
+    }
+    ret as i32
+}
+
+/// Reads up to `len` bytes from a synthesized file, or forwards the read of any other `fd` to
+/// the host
+pub fn read<const PRINT_INSTR: bool>(
+    cpu: &mut cpu::Cpu<'_, PRINT_INSTR>,
+    fd: u32,
+    buf: u32,
+    len: u32,
+) -> i32 {
+    if len == 0 {
+        return 0;
+    }
+
+    let Some(file) = cpu.files.get_mut(fd) else {
+        let Some(buf_ptr) = cpu.mem.translate_range_mut(buf, len as usize) else {
+            return -(sys::Errno::EFAULT as i32);
+        };
+
+        let ret: i64;
+        unsafe {
+            core::arch::asm!(
+                "syscall",
+                in("rax") 0_u64,
+                in("rdi") fd as u64,
+                in("rsi") buf_ptr as u64,
+                in("rdx") len as u64,
+                lateout("rax") ret,
+                out("rcx") _,
+                out("r11") _,
+                options(nostack),
+            );
+        }
+        return ret.try_into().unwrap_or(i32::MAX);
+    };
+
+    let bytes = &file.contents[file.offset..];
+    let n = bytes.len().min(len as usize);
+    if cpu.mem.map_region(buf, &bytes[..n]).is_err() {
+        return -(sys::Errno::EFAULT as i32);
+    }
+    file.offset += n;
+    n as i32
+}
+
+/// Closes a synthesized file. The guest can't close the host's stdin, stdout and stderr.
+pub fn close<const PRINT_INSTR: bool>(cpu: &mut cpu::Cpu<'_, PRINT_INSTR>, fd: u32) -> i32 {
+    match cpu.files.remove(fd) {
+        Some(_) => 0,
+        None => -(sys::Errno::EBADF as i32),
+    }
+}
+
+/// Closes `fd` on the host, for a synthesized file the fd [`open_host`] holds for it
+pub fn close_host<const PRINT_INSTR: bool>(cpu: &mut cpu::Cpu<'_, PRINT_INSTR>, fd: u32) -> i32 {
+    cpu.files.remove(fd);
+    let ret: i64;
+    unsafe {
+        core::arch::asm!(
+            "syscall",
+            in("rax") 3_u64,
+            in("rdi") fd as u64,
+            lateout("rax") ret,
+            out("rcx") _,
+            out("r11") _,
+            options(nostack),
+        );
+    }
+    ret as i32
+}
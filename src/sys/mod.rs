@@ -1,5 +1,11 @@
+mod cpuinfo;
+mod files;
+mod uname;
 mod write;
 
+pub use cpuinfo::cpuinfo;
+pub use files::{AT_FDCWD, Files, close, close_host, open, open_host, read};
+pub use uname::uname;
 pub use write::write;
 
 #[repr(i32)]
@@ -25,8 +31,22 @@ pub enum Errno {
     EACCES = 13,
     /// Bad address
     EFAULT = 14,
+    /// File exists
+    EEXIST = 17,
+    /// Not a directory
+    ENOTDIR = 20,
+    /// Is a directory
+    EISDIR = 21,
+    /// Invalid argument
+    EINVAL = 22,
+    /// Too many open files
+    EMFILE = 24,
+    /// File name too long
+    ENAMETOOLONG = 36,
     /// System call unimplemented
     ENOSYS = 38,
+    /// Too many symbolic links encountered
+    ELOOP = 40,
 }
 
 impl Errno {
@@ -36,9 +56,12 @@ impl Errno {
     }
 }
 
-impl From<i32> for Errno {
-    fn from(value: i32) -> Self {
-        match -value {
+/// The errno a syscall returned negated, the value itself if it isn't one of the above
+impl TryFrom<i32> for Errno {
+    type Error = i32;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        Ok(match -value {
             1 => Self::EPERM,
             2 => Self::ENOENT,
             3 => Self::ESRCH,
@@ -49,8 +72,35 @@ impl From<i32> for Errno {
             12 => Self::ENOMEM,
             13 => Self::EACCES,
             14 => Self::EFAULT,
+            17 => Self::EEXIST,
+            20 => Self::ENOTDIR,
+            21 => Self::EISDIR,
+            22 => Self::EINVAL,
+            24 => Self::EMFILE,
+            36 => Self::ENAMETOOLONG,
             38 => Self::ENOSYS,
-            _ => panic!("Programming error, errno `{}` is not mapped yet!", value),
-        }
+            40 => Self::ELOOP,
+            _ => return Err(value),
+        })
+    }
+}
+
+/// Signals raised by the emulated processor. The guest can't install handlers, so each of them
+/// takes the default action and terminates it.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// Illegal instruction: UDF, undefined encodings and unknown private syscalls
+    SIGILL = 4,
+    /// Trace/breakpoint trap: BKPT and the breakpoint private syscall
+    SIGTRAP = 5,
+    /// Segmentation fault: the private syscall 0, a branch through zero
+    SIGSEGV = 11,
+}
+
+impl Signal {
+    /// The exit status a shell reports for a process killed by self
+    pub fn exit_status(self) -> i32 {
+        128 + self as i32
     }
 }
@@ -0,0 +1,41 @@
+use crate::cpu;
+
+/// length of each NUL padded field of `struct new_utsname`
+const FIELD: usize = 65;
+/// sysname, nodename, release, version, machine and domainname
+const FIELDS: usize = 6;
+/// index of the machine field
+const MACHINE: usize = 4;
+
+/// The host's `uname`, with the machine field naming the emulated architecture, like `armv7l`
+/// for a Cortex-A15.
+pub fn uname<const PRINT_INSTR: bool>(cpu: &mut cpu::Cpu<'_, PRINT_INSTR>, buf: u32) -> i32 {
+    let mut uts = [0u8; FIELD * FIELDS];
+    let ret: i64;
+    unsafe {
+        core::arch::asm!(
+            "syscall",
+            in("rax") 63_u64,
+            in("rdi") uts.as_mut_ptr() as u64,
+            lateout("rax") ret,
+            out("rcx") _,
+            out("r11") _,
+            options(nostack),
+        );
+    }
+    if ret < 0 {
+        return ret as i32;
+    }
+
+    let machine = &mut uts[MACHINE * FIELD..(MACHINE + 1) * FIELD];
+    let name = format!("armv{}l", cpu.model.architecture());
+    machine.fill(0);
+    machine[..name.len()].copy_from_slice(name.as_bytes());
+
+    for (i, byte) in uts.iter().enumerate() {
+        if cpu.mem.write_u8(buf.wrapping_add(i as u32), *byte).is_err() {
+            return -(crate::sys::Errno::EFAULT as i32);
+        }
+    }
+    0
+}
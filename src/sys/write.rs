@@ -6,6 +6,10 @@ pub fn write<const PRINT_INSTR: bool>(
     buf: u32,
     len: u32,
 ) -> i32 {
+    // synthesized files are read-only
+    if cpu.files.is_open(fd) {
+        return -(sys::Errno::EBADF as i32);
+    }
     if len == 0 {
         return 0;
     }
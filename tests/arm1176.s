@@ -0,0 +1,78 @@
+@ stinkarm-test: address=0x8000; args=--cpu arm1176; exit=132; stdout-contains=guest killed by SIGILL
+@ Checks that the ARMv6 model reports the v6l platform and VFP without VFPv3,
+@ NEON or hardware divide, that VFPv2 arithmetic and Thumb BL still work, then
+@ that MOVW, which came with Thumb-2, raises SIGILL. A failing check exits with
+@ its index instead.
+
+    .syntax unified
+    .arch armv6kz
+    .fpu vfpv2
+    .text
+    .global _start
+_start:
+    mov r0, #1
+    adr r4, doubles
+    mov r5, #0
+    ldr r1, [sp]                @ argc
+    add r2, sp, #8
+    add r2, r2, r1, lsl #2      @ past the argv terminator
+1:  ldr r3, [r2], #4            @ skip envp
+    cmp r3, #0
+    bne 1b
+2:  ldr r3, [r2], #8
+    cmp r3, #0
+    beq 3f
+    cmp r3, #15                 @ AT_PLATFORM
+    ldreq r4, [r2, #-4]
+    cmp r3, #16                 @ AT_HWCAP
+    ldreq r5, [r2, #-4]
+    b 2b
+3:  ldr r3, [r4]
+    ldr r1, =0x006c3676         @ "v6l"
+    cmp r3, r1
+    bne fail
+
+    mov r0, #2
+    tst r5, #(1 << 6)           @ HWCAP_VFP
+    beq fail
+    ldr r1, =(1 << 12) | (1 << 13) | (1 << 17)
+    tst r5, r1                  @ HWCAP_NEON, HWCAP_VFPv3 and HWCAP_IDIVA
+    bne fail
+
+    mov r0, #3
+    adr r1, doubles
+    vldr d15, [r1]              @ 1.5
+    vadd.f64 d14, d15, d15
+    vcvt.s32.f64 s0, d14
+    vmov r2, s0
+    cmp r2, #3
+    bne fail
+
+    mov r0, #4
+    adr r1, thumb + 1
+    blx r1
+    cmp r2, #5
+    bne fail
+
+    .inst 0xe300002a            @ movw r0, #42
+fail:
+    mov r7, #1
+    svc #0
+
+    .thumb
+    .thumb_func
+thumb:
+    push {lr}
+    movs r2, #4
+    bl add_one
+    pop {pc}
+
+    .thumb_func
+add_one:
+    adds r2, #1
+    bx lr
+
+    .arm
+    .align 3
+doubles:
+    .double 1.5
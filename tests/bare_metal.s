@@ -0,0 +1,205 @@
+@ stinkarm-test: address=0x8000; args=--bare-metal; exit=42; stdout=ok\n
+@ Runs as a bare-metal image: installs a vector table at 0, checks the reset
+@ state and the banked sp of each mode, takes supervisor calls from System,
+@ User and Thumb state, an undefined instruction, a data abort and a timer
+@ IRQ and returns from them with movs pc, lr, subs pc, lr and rfe. Exits
+@ through semihosting with the index of the first failing check or 42.
+
+    .syntax unified
+    .global _start
+_start:
+    mov r6, #1
+    mrs r0, cpsr
+    ubfx r1, r0, #0, #9
+    ldr r2, =0x1d3              @ Supervisor mode, A, I and F masked
+    cmp r1, r2
+    bne fail
+
+    @ copy the vectors and their literal table to 0
+    mov r0, #0
+    adr r1, vectors
+    mov r2, #16
+1:  ldr r3, [r1], #4
+    str r3, [r0], #4
+    subs r2, r2, #1
+    bne 1b
+
+    mov r6, #2
+    ldr sp, =0x100000           @ Supervisor stack
+    cps #0x1b
+    ldr sp, =0xf0000            @ Undefined stack
+    cps #0x17
+    ldr sp, =0xe0000            @ Abort stack
+    cps #0x12
+    ldr sp, =0xd0000            @ IRQ stack
+    cps #0x1f
+    ldr sp, =0xc0000            @ System and User stack
+    ldr lr, =0x1200
+    cps #0x13
+    ldr r0, =0x100000
+    cmp sp, r0
+    bne fail
+    cmp lr, #0x1200             @ lr is banked too
+    beq fail
+    mov r0, sp
+    stmia r0, {sp, lr}^         @ the User mode registers
+    ldr r1, [r0]
+    ldr r2, =0xc0000
+    cmp r1, r2
+    bne fail
+    ldr r1, [r0, #4]
+    cmp r1, #0x1200
+    bne fail
+
+    @ System mode shares the User registers and has no SPSR
+    mov r6, #3
+    msr cpsr_c, #0xdf
+    cmp lr, #0x1200
+    bne fail
+    mov r8, #0
+    svc #5
+    cmp r0, #5                  @ the handler returns the immediate
+    bne fail
+    cmp r8, #0x1f               @ and the mode it was called from
+    bne fail
+
+    @ the undefined instruction exception leaves lr past the instruction
+    mov r6, #4
+    mov r9, #0
+undefined:
+    udf #0
+    adr r0, undefined + 4
+    cmp r9, r0
+    bne fail
+
+    @ a data abort records the address in DFAR, the handler skips the load
+    mov r6, #5
+    mov r10, #0
+    ldr r1, =0x20000000
+aborted:
+    ldr r0, [r1]
+    adr r0, aborted + 8
+    cmp r10, r0
+    bne fail
+    cmp r11, r1
+    bne fail
+
+    @ the virtual timer fires at once with TVAL 0, taken when IRQs are unmasked
+    mov r6, #6
+    mov r12, #0
+    mov r0, #0
+    mcr p15, 0, r0, c14, c3, 0  @ CNTV_TVAL
+    mov r0, #1
+    mcr p15, 0, r0, c14, c3, 1  @ CNTV_CTL, enabled
+    cmp r12, #0
+    bne fail
+    cpsie i
+    nop
+    cmp r12, #1
+    bne fail
+    mrs r0, cpsr
+    ubfx r0, r0, #0, #9
+    ldr r1, =0x15f              @ back in System mode with I clear
+    cmp r0, r1
+    bne fail
+
+    @ User mode can't change the mode, but an SVC handler can
+    mov r6, #7
+    cps #0x10
+    msr cpsr_c, #0xdf
+    mrs r0, cpsr
+    and r0, r0, #0x1f
+    cmp r0, #0x10
+    bne fail
+    svc #1                      @ returns to System mode
+    mrs r0, cpsr
+    and r0, r0, #0x1f
+    cmp r0, #0x1f
+    bne fail
+
+    @ svc in Thumb state returns past the 16-bit instruction
+    mov r6, #8
+    adr r0, thumb + 1
+    blx r0
+    cmp r0, #7
+    bne fail
+    cmp r8, #0x3f               @ the SPSR had T set
+    bne fail
+
+    adr r1, message
+    mov r0, #4                  @ SYS_WRITE0
+    svc 0x123456
+    mov r6, #42
+
+fail:
+    adr r1, exit_block
+    str r6, [r1, #4]
+    mov r0, #0x20               @ SYS_EXIT_EXTENDED
+    svc 0x123456
+
+vectors:
+    ldr pc, [pc, #24]
+    ldr pc, [pc, #24]
+    ldr pc, [pc, #24]
+    ldr pc, [pc, #24]
+    ldr pc, [pc, #24]
+    ldr pc, [pc, #24]
+    ldr pc, [pc, #24]
+    ldr pc, [pc, #24]
+    .word _start
+    .word und_handler
+    .word svc_handler
+    .word _start
+    .word abt_handler
+    .word _start
+    .word irq_handler
+    .word _start
+
+@ r0 = the immediate of the svc, r8 = the mode and T of the caller
+svc_handler:
+    mrs r8, spsr
+    and r8, r8, #0x3f
+    tst r8, #0x20
+    ldrhne r0, [lr, #-2]
+    andne r0, r0, #0xff
+    ldreq r0, [lr, #-4]
+    biceq r0, r0, #0xff000000
+    cmp r0, #1
+    bne 1f
+    mrs r1, spsr
+    orr r1, r1, #0x1f
+    msr spsr_c, r1
+1:  movs pc, lr
+
+und_handler:
+    mov r9, lr
+    movs pc, lr
+
+abt_handler:
+    mov r10, lr
+    mrc p15, 0, r11, c6, c0, 0  @ DFAR
+    subs pc, lr, #4
+
+irq_handler:
+    sub lr, lr, #4
+    srsdb sp!, #0x13
+    cps #0x13
+    push {r0}
+    mov r0, #0
+    mcr p15, 0, r0, c14, c3, 1  @ CNTV_CTL, disabled
+    add r12, r12, #1
+    pop {r0}
+    rfeia sp!
+
+message:
+    .asciz "ok\n"
+    .align 2
+exit_block:
+    .word 0x20026               @ ADP_Stopped_ApplicationExit
+    .word 0
+
+    .thumb
+thumb:
+    mrs r1, apsr
+    svc #7
+    bx lr
@@ -0,0 +1,19 @@
+@ stinkarm-test: address=0x8000; exit=133; stdout-contains=guest killed by SIGTRAP at 0x800c: 0xbe07
+@ BKPT in Thumb state raises SIGTRAP even inside an IT block whose condition
+@ fails, terminating the guest with 128 + 5.
+
+    .syntax unified
+    .global _start
+    .arm
+_start:
+    adr r0, thumb + 1
+    bx r0
+
+    .thumb
+thumb:
+    movs r0, #1                 @ clears Z
+    it eq
+    bkpt #7
+    movs r0, #42
+    movs r7, #1
+    svc #0
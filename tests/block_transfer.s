@@ -0,0 +1,68 @@
+@ stinkarm-test: address=0x8000; exit=42; stdout=
+@ Calls a function that saves and restores registers with push/pop and
+@ returns by popping pc, then walks a buffer with every LDM/STM addressing
+@ mode, exiting with the index of the first failing check or 42.
+
+    .bss
+    .align 2
+buf:
+    .space 32
+
+    .text
+    .global _start
+_start:
+    mov r4, #4
+    mov r0, #1
+    bl double_r1
+    cmp r4, #4                  @ callee-saved r4 restored
+    bne fail
+    cmp r1, #8
+    bne fail
+
+    mov r0, #2
+    ldr r5, =buf
+    mov r1, #1
+    mov r2, #2
+    mov r3, #3
+    stmia r5!, {r1-r3}          @ buf[0..3] = 1, 2, 3
+    ldr r6, =buf + 12
+    cmp r5, r6
+    bne fail
+
+    mov r0, #3
+    ldmdb r5, {r7, r8}          @ r7 = 2, r8 = 3
+    cmp r7, #2
+    bne fail
+    cmp r8, #3
+    bne fail
+
+    mov r0, #4
+    ldr r5, =buf
+    ldmib r5, {r7, r8}          @ r7 = 2, r8 = 3
+    add r7, r7, r8
+    cmp r7, #5
+    bne fail
+
+    mov r0, #5
+    add r5, r5, #16
+    stmda r5, {r1, r2}          @ buf[3] = 1, buf[4] = 2
+    ldr r7, =buf
+    ldr r8, [r7, #12]
+    cmp r8, #1
+    bne fail
+
+    mov r0, #6
+    ldmia r7, {r7, r8}          @ base in the list
+    cmp r7, #1
+    bne fail
+
+    mov r0, #42
+fail:
+    mov r7, #1
+    svc #0
+
+double_r1:
+    push {r4, lr}
+    mov r4, #0                  @ clobber the callee-saved register
+    mov r1, r0, lsl #3
+    pop {r4, pc}
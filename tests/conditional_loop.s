@@ -0,0 +1,32 @@
+@ stinkarm-test: address=0x8000; exit=55; stdout=
+@ Sums 1..10 in a `subs`/`bne` counted loop, then uses signed, unsigned and
+@ test/compare conditions to select the exit code.
+
+    .global _start
+_start:
+    mov r0, #0
+    mov r1, #10
+loop:
+    add r0, r0, r1
+    subs r1, r1, #1
+    bne loop                    @ r0 = 55
+
+    mvn r2, #0                  @ r2 = -1
+    cmp r2, #1
+    movge r0, #1                @ must NOT execute: -1 < 1 signed
+    movls r0, #2                @ must NOT execute: 0xffffffff > 1 unsigned
+    cmn r2, #1                  @ -1 + 1 = 0
+    movne r0, #3                @ must NOT execute
+    tst r2, #0x80000000
+    movpl r0, #4                @ must NOT execute
+    teq r2, r2
+    movmi r0, #5                @ must NOT execute
+    mov r3, #0x80000000
+    subs r3, r3, #1             @ signed overflow
+    movvc r0, #6                @ must NOT execute
+    cmp r0, #50
+    bgt done
+    mov r0, #7                  @ must NOT execute
+done:
+    mov r7, #1
+    svc #0
@@ -0,0 +1,12 @@
+@ stinkarm-test: address=0x8000; args=--counters none; exit=132; stdout-contains=guest killed by SIGILL at 0x8004: 0xec510f1e
+@ With --counters none reading the virtual count is undefined like on a
+@ kernel denying user access to the generic timer, while the thread ID
+@ registers stay accessible.
+
+    .global _start
+_start:
+    mrc p15, 0, r0, c13, c0, 3
+    mrrc p15, 1, r0, r1, c14
+    mov r0, #42
+    mov r7, #1
+    svc #0
@@ -0,0 +1,69 @@
+@ stinkarm-test: address=0x8000; args=--cpu cortex-a53-aarch32; exit=42; stdout-contains=: ARMv8 Processor rev 4 (v8l)\n; stdout-contains=: half thumb fastmult vfp edsp neon vfpv3 tls vfpv4 idiva idivt vfpd32 lpae aes pmull sha1 sha2 crc32\n; stdout-contains=: 0xd03\n
+@ Reads /proc/cpuinfo in small chunks and copies it to stdout, the emulator
+@ generates it from the --cpu model. The file can't be written and is closed
+@ only once, exiting with the index of the first failing check or 42.
+
+    .data
+path:
+    .asciz "/proc/cpuinfo"
+
+    .bss
+    .align 2
+buf:
+    .space 64
+
+    .text
+    .global _start
+_start:
+    ldr r0, =path
+    mov r1, #0                  @ O_RDONLY
+    mov r7, #5                  @ open
+    svc #0
+    cmp r0, #3                  @ the lowest fd after stdin, stdout and stderr
+    mov r0, #1
+    bne fail
+    mov r4, #3
+
+copy:
+    mov r0, r4
+    ldr r1, =buf
+    mov r2, #64
+    mov r7, #3                  @ read
+    svc #0
+    movs r2, r0
+    beq copied
+    mov r0, #2
+    blt fail
+    mov r0, #1
+    ldr r1, =buf
+    mov r7, #4                  @ write
+    svc #0
+    b copy
+
+copied:
+    mov r0, r4
+    ldr r1, =buf
+    mov r2, #1
+    mov r7, #4                  @ write
+    svc #0
+    cmn r0, #9                  @ EBADF
+    mov r0, #3
+    bne fail
+
+    mov r0, r4
+    mov r7, #6                  @ close
+    svc #0
+    cmp r0, #0
+    mov r0, #4
+    bne fail
+    mov r0, r4
+    mov r7, #6                  @ close
+    svc #0
+    cmn r0, #9                  @ EBADF
+    mov r0, #5
+    bne fail
+
+    mov r0, #42
+fail:
+    mov r7, #1
+    svc #0
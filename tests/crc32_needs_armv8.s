@@ -0,0 +1,11 @@
+@ stinkarm-test: address=0x8000; exit=132; stdout-contains=guest killed by SIGILL at 0x8000: 0xe1000040
+@ CRC32 only exists from ARMv8, so the default ARMv7 processor model must
+@ raise SIGILL rather than compute a checksum.
+
+    .arch armv8-a
+    .global _start
+_start:
+    crc32b r0, r0, r0
+    mov r0, #42
+    mov r7, #1
+    svc #0
@@ -0,0 +1,217 @@
+@ stinkarm-test: address=0x8000; args=--cpu cortex-a53-aarch32; exit=42; stdout=
+@ Checks that AT_HWCAP2 reports the ARMv8 extensions, then computes CRC32 and
+@ CRC32C of "123456789", runs an AES round forwards and backwards, hashes
+@ "abc" with the SHA-256 instructions and multiplies with VMULL.P64, in ARM and
+@ Thumb state, exiting with the index of the first failing check or 42.
+
+    .syntax unified
+    .arch armv8-a
+    .fpu crypto-neon-fp-armv8
+    .text
+    .global _start
+_start:
+    mov r0, #1
+    ldr r1, [sp]                @ argc
+    add r2, sp, #8
+    add r2, r2, r1, lsl #2      @ past the argv terminator
+1:  ldr r3, [r2], #4            @ skip envp
+    cmp r3, #0
+    bne 1b
+2:  ldr r3, [r2], #8
+    cmp r3, #0
+    beq fail
+    cmp r3, #26                 @ AT_HWCAP2
+    bne 2b
+    ldr r3, [r2, #-4]
+    cmp r3, #0x1f               @ AES, PMULL, SHA1, SHA2 and CRC32
+    bne fail
+
+    mov r0, #2
+    adr r1, digits
+    mov r2, #9
+    mvn r3, #0
+3:  ldrb r4, [r1], #1
+    crc32b r3, r3, r4
+    subs r2, r2, #1
+    bne 3b
+    mvn r3, r3
+    ldr r4, =0xcbf43926
+    cmp r3, r4
+    bne fail
+
+    mov r0, #3
+    adr r1, digits
+    mvn r3, #0
+    ldr r4, [r1], #4
+    crc32cw r3, r3, r4
+    ldrh r4, [r1], #2
+    crc32ch r3, r3, r4
+    ldrh r4, [r1], #2
+    crc32ch r3, r3, r4
+    ldrb r4, [r1]
+    crc32cb r3, r3, r4
+    mvn r3, r3
+    ldr r4, =0xe3069283
+    cmp r3, r4
+    bne fail
+
+    mov r0, #4
+    adr r1, aes_state
+    vld1.8 {q0}, [r1]
+    vmov.i8 q1, #0
+    aese.8 q0, q1               @ ShiftRows and SubBytes of round 1
+    vmov r2, r3, d0
+    ldr r4, =0x305dbfd4
+    cmp r2, r4
+    bne fail
+    aesmc.8 q2, q0
+    vmov r2, r3, d4
+    ldr r4, =0xe5816604
+    cmp r2, r4
+    bne fail
+    vmov r2, r3, d5
+    ldr r4, =0x4c260628
+    cmp r3, r4
+    bne fail
+    aesimc.8 q2, q2
+    aesd.8 q2, q1
+    vld1.8 {q3}, [r1]
+    veor q2, q2, q3
+    vorr d4, d4, d5
+    vmov r2, r3, d4
+    orrs r2, r2, r3
+    bne fail
+
+    mov r0, #5
+    adr r1, sha256_h
+    vld1.32 {q0, q1}, [r1]
+    vmov q14, q0
+    vmov q15, q1
+    adr r1, abc
+    vld1.8 {q8, q9}, [r1]!
+    vld1.8 {q10, q11}, [r1]
+    vrev32.8 q8, q8
+    vrev32.8 q9, q9
+    vrev32.8 q10, q10
+    vrev32.8 q11, q11
+    adr r1, sha256_k
+
+    .macro rounds w0, w1, w2, w3, update
+    vld1.32 {q12}, [r1]!
+    vadd.i32 q12, q12, \w0
+    .if \update
+    sha256su0.32 \w0, \w1
+    .endif
+    vmov q2, q0
+    sha256h.32 q0, q1, q12
+    sha256h2.32 q1, q2, q12
+    .if \update
+    sha256su1.32 \w0, \w2, \w3
+    .endif
+    .endm
+
+    .rept 3
+    rounds q8, q9, q10, q11, 1
+    rounds q9, q10, q11, q8, 1
+    rounds q10, q11, q8, q9, 1
+    rounds q11, q8, q9, q10, 1
+    .endr
+    rounds q8, q9, q10, q11, 0
+    rounds q9, q10, q11, q8, 0
+    rounds q10, q11, q8, q9, 0
+    rounds q11, q8, q9, q10, 0
+
+    vadd.i32 q0, q0, q14
+    vadd.i32 q1, q1, q15
+    vmov.32 r2, d0[0]
+    ldr r4, =0xba7816bf
+    cmp r2, r4
+    bne fail
+    vmov.32 r2, d3[1]
+    ldr r4, =0xf20015ad
+    cmp r2, r4
+    bne fail
+
+    mov r0, #6
+    mov r2, #1
+    mov r3, #0x80000000
+    vmov d2, r2, r3
+    mov r2, #3
+    mov r3, #0
+    vmov d3, r2, r3
+    vmull.p64 q2, d2, d3        @ (x^63 + 1)(x + 1) = x^64 + x^63 + x + 1
+    vmov r2, r3, d4
+    cmp r2, #3
+    bne fail
+    cmp r3, #0x80000000
+    bne fail
+    vmov r2, r3, d5
+    cmp r2, #1
+    bne fail
+    cmp r3, #0
+    bne fail
+
+    mov r0, #7
+    adr r3, thumb + 1
+    bx r3
+
+    .thumb
+thumb:
+    movs r3, #0
+    movs r4, #0x31
+    crc32b r3, r3, r4
+    ldr r4, =0x51de003a         @ the CRC of "1" from an accumulator of zero
+    cmp r3, r4
+    bne fail_thumb
+    mov r2, #1
+    vdup.32 q0, r2
+    sha1h.32 q1, q0
+    vmov r2, r3, d2
+    cmp r2, #0x40000000
+    bne fail_thumb
+    cmp r3, #0
+    bne fail_thumb
+
+    movs r0, #42
+fail_thumb:
+    movs r7, #1
+    svc #0
+
+    .arm
+    .align 2
+fail:
+    mov r7, #1
+    svc #0
+
+    .align 4
+digits:
+    .ascii "123456789"
+    .align 4
+aes_state:                      @ the state at the start of round 1, FIPS 197 appendix B
+    .byte 0x19, 0x3d, 0xe3, 0xbe, 0xa0, 0xf4, 0xe2, 0x2b
+    .byte 0x9a, 0xc6, 0x8d, 0x2a, 0xe9, 0xf8, 0x48, 0x08
+sha256_h:
+    .word 0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a
+    .word 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19
+abc:
+    .ascii "abc"
+    .byte 0x80
+    .space 59
+    .byte 24
+sha256_k:
+    .word 0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5
+    .word 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5
+    .word 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3
+    .word 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174
+    .word 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc
+    .word 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da
+    .word 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7
+    .word 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967
+    .word 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13
+    .word 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85
+    .word 0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3
+    .word 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070
+    .word 0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5
+    .word 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3
+    .word 0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208
+    .word 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2
@@ -0,0 +1,32 @@
+@ stinkarm-test: address=0x8000; exit=42; stdout=
+@ Chains immediate, register-shifted and register-shifted-register operands
+@ through the data-processing opcodes, including a write to pc, and exits
+@ with the accumulated result.
+
+    .global _start
+_start:
+    mov r1, #3
+    mov r0, r1, lsl #4          @ r0 = 48
+    sub r0, r0, #8              @ r0 = 40
+    mov r2, #1
+    add r0, r0, r2, lsl r2      @ r0 = 42
+    orr r3, r0, #0xff00         @ r3 = 0xff2a
+    bic r3, r3, #0xff00         @ r3 = 42
+    mvn r4, #0                  @ r4 = 0xffffffff
+    eor r3, r3, r4              @ r3 = ~42
+    mvn r3, r3                  @ r3 = 42
+    rsb r5, r3, #50             @ r5 = 8
+    mov r5, r5, lsr #3          @ r5 = 1
+    mov r6, r4, asr #31         @ r6 = -1
+    add r5, r5, r6              @ r5 = 0
+    add pc, pc, #0              @ skips the next instruction
+    mov r0, #1                  @ must NOT execute
+    adr r6, value
+    ldr r8, =value
+    sub r6, r6, r8              @ r6 = 0
+    add r0, r0, r5              @ r0 = 42
+    add r0, r0, r6
+    mov r7, #1
+    svc #0
+value:
+    .word 0
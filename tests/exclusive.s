@@ -0,0 +1,136 @@
+@ stinkarm-test: address=0x8000; exit=42; stdout=
+@ Runs LDREX/STREX retry loops of every size and checks that CLREX, an
+@ intervening store and a syscall make the store exclusive fail, in ARM and
+@ Thumb state, exiting with the index of the first failing check or 42.
+
+    .syntax unified
+    .bss
+    .align 3
+counter:
+    .space 16
+
+    .text
+    .global _start
+_start:
+    ldr r1, =counter
+    mov r0, #1
+    mov r3, #10
+increment:
+    ldrex r4, [r1]
+    add r4, r4, #1
+    strex r5, r4, [r1]
+    cmp r5, #0
+    bne increment
+    dmb ish
+    subs r3, r3, #1
+    bne increment
+    ldr r4, [r1]
+    cmp r4, #10
+    bne fail
+
+    mov r0, #2
+    ldrex r4, [r1]
+    clrex
+    strex r5, r4, [r1]
+    cmp r5, #1
+    bne fail
+
+    mov r0, #3
+    ldrex r4, [r1]
+    str r4, [r1]                @ intervening store to the tagged word
+    strex r5, r4, [r1]
+    cmp r5, #1
+    bne fail
+    ldrex r4, [r1]
+    str r4, [r1, #4]            @ a store elsewhere keeps the tag
+    strex r5, r4, [r1]
+    cmp r5, #0
+    bne fail
+
+    mov r0, #4
+    add r2, r1, #4
+    ldrex r4, [r1]
+    strex r5, r4, [r2]          @ different address
+    cmp r5, #1
+    bne fail
+    ldrex r4, [r1]
+    mov r6, r0
+    mov r7, #20                 @ any syscall is a context switch
+    svc #0
+    mov r0, r6
+    strex r5, r4, [r1]
+    cmp r5, #1
+    bne fail
+
+    mov r0, #5
+    ldrexb r4, [r1]
+    add r4, r4, #1
+    strexb r5, r4, [r1]
+    cmp r5, #0
+    bne fail
+    ldrexh r4, [r1]
+    add r4, r4, #1
+    strexh r5, r4, [r1]
+    cmp r5, #0
+    bne fail
+    ldr r4, [r1]
+    cmp r4, #12
+    bne fail
+
+    mov r0, #6
+    ldr r8, =0x11111111
+    ldr r9, =0x22222222
+    add r2, r1, #8
+    ldrexd r4, r5, [r2]
+    strexd r6, r8, r9, [r1]     @ tag is on counter + 8
+    cmp r6, #1
+    bne fail
+    ldrexd r4, r5, [r1]
+    strexd r6, r8, r9, [r1]
+    cmp r6, #0
+    bne fail
+    ldr r4, [r1, #4]
+    cmp r4, r9
+    bne fail
+    isb sy
+    dsb sy
+
+    mov r0, #7
+    adr r3, thumb + 1
+    bx r3
+
+    .thumb
+thumb:
+    ldrex r4, [r1, #8]
+    adds r4, #1
+    strex r5, r4, [r1, #8]
+    cmp r5, #0
+    bne fail_thumb
+    ldr r4, [r1, #8]
+    cmp r4, #1
+    bne fail_thumb
+
+    movs r0, #8
+    ldrexh r4, [r1]
+    clrex
+    strexh r5, r4, [r1]
+    cmp r5, #1
+    bne fail_thumb
+    ldrexd r4, r6, [r1]
+    strexd r5, r6, r4, [r1]     @ swap the words
+    cmp r5, #0
+    bne fail_thumb
+    ldr r4, [r1]
+    cmp r4, r9
+    bne fail_thumb
+    dmb ish
+
+    movs r0, #42
+fail_thumb:
+    movs r7, #1
+    svc #0
+
+    .arm
+fail:
+    mov r7, #1
+    svc #0
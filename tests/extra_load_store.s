@@ -0,0 +1,69 @@
+@ stinkarm-test: address=0x8000; exit=42; stdout=
+@ Round-trips halfwords, sign-extended bytes and halfwords and doublewords
+@ through memory with immediate, register, pre- and post-indexed offsets,
+@ exiting with the index of the first failing check or 42.
+
+    .bss
+    .align 3
+buf:
+    .space 32
+
+    .text
+    .global _start
+_start:
+    ldr r4, =buf
+    mov r0, #1
+
+    ldr r1, =0x1234abcd
+    strh r1, [r4, #2]
+    ldrh r2, [r4, #2]
+    mov r3, #0xab00
+    orr r3, r3, #0xcd
+    cmp r2, r3
+    bne fail
+
+    mov r0, #2
+    mov r5, #2
+    ldrsh r2, [r4, r5]          @ 0xabcd sign extends
+    ldr r3, =0xffffabcd
+    cmp r2, r3
+    bne fail
+
+    mov r0, #3
+    ldrsb r2, [r4, #3]          @ 0xab sign extends
+    mvn r3, #0x54               @ 0xffffffab
+    cmp r2, r3
+    bne fail
+
+    mov r0, #4
+    mov r6, r4
+    ldrh r2, [r6], #2           @ post-indexed, low halfword is still zero
+    cmp r2, #0
+    bne fail
+    sub r2, r6, r4
+    cmp r2, #2
+    bne fail
+
+    mov r0, #5
+    ldr r2, =0x55667788
+    ldr r3, =0x99aabbcc
+    strd r2, r3, [r4, #8]!      @ pre-indexed writeback, r4 = buf + 8
+    ldr r8, [r4, #4]
+    cmp r8, r3
+    bne fail
+    mov r5, #8
+    ldrd r8, r9, [r4, -r5]      @ buf[0..8]
+    ldr r3, =0xabcd0000
+    cmp r8, r3
+    bne fail
+    cmp r9, #0
+    bne fail
+
+    mov r0, #6
+    ldrd r8, r9, [r4], #8       @ post-indexed
+    cmp r8, r2
+    bne fail
+    mov r0, #42
+fail:
+    mov r7, #1
+    svc #0
@@ -0,0 +1,101 @@
+@ stinkarm-test: address=0x8000; args=-C forward; exit=42; stdout=
+@ Forwards open, read and close of host files: reads zeros from /dev/zero over
+@ an instruction that already ran, which must not run again, and closes the
+@ file once. /proc/cpuinfo is still synthesized, a missing file is ENOENT.
+@ Exits with the index of the first failing check or 42.
+
+    .data
+zero:
+    .asciz "/dev/zero"
+cpuinfo:
+    .asciz "/proc/cpuinfo"
+missing:
+    .asciz "/nonexistent/stinkarm"
+
+    .bss
+    .align 2
+buf:
+    .space 4
+
+    .text
+    .global _start
+_start:
+    ldr r0, =zero
+    mov r1, #0                  @ O_RDONLY
+    mov r7, #5                  @ open
+    svc #0
+    movs r4, r0
+    mov r0, #1
+    bmi fail
+
+    mov r5, #0
+    mov r6, #2
+    b again                     @ both passes run the block starting here
+again:
+    add r5, r5, #1              @ andeq r0, r0, r0 after the read
+    subs r6, r6, #1
+    beq patched
+    mov r0, r4
+    adr r1, again
+    mov r2, #4
+    mov r7, #3                  @ read
+    svc #0
+    cmp r0, #4
+    mov r0, #2
+    bne fail
+    b again
+patched:
+    cmp r5, #1
+    mov r0, #3
+    bne fail
+
+    mov r0, r4
+    mov r7, #6                  @ close
+    svc #0
+    cmp r0, #0
+    mov r0, #4
+    bne fail
+    mov r0, r4
+    mov r7, #6                  @ close
+    svc #0
+    cmn r0, #9                  @ EBADF
+    mov r0, #5
+    bne fail
+
+    ldr r0, =cpuinfo
+    mov r1, #0                  @ O_RDONLY
+    mov r7, #5                  @ open
+    svc #0
+    movs r4, r0
+    mov r0, #6
+    bmi fail
+    mov r0, r4
+    ldr r1, =buf
+    mov r2, #4
+    mov r7, #3                  @ read
+    svc #0
+    ldr r1, =buf
+    ldr r1, [r1]
+    ldr r2, =0x636f7270         @ "proc"
+    cmp r1, r2
+    mov r0, #7
+    bne fail
+    mov r0, r4
+    mov r7, #6                  @ close
+    svc #0
+    cmp r0, #0
+    mov r0, #8
+    bne fail
+
+    ldr r0, =missing
+    mov r1, #0                  @ O_RDONLY
+    mov r7, #5                  @ open
+    svc #0
+    cmn r0, #2                  @ ENOENT
+    mov r0, #9
+    bne fail
+
+    mov r0, #42
+fail:
+    mov r7, #1
+    svc #0
@@ -0,0 +1,84 @@
+@ stinkarm-test: address=0x8000; args=--bare-metal --high-vectors; exit=42; stdout=
+@ Installs the vector table at 0xffff0000 with SCTLR.V set and takes prefetch
+@ aborts for BKPT, with a debug event in IFSR, and for a branch outside of
+@ memory, with the address in IFAR. Exits through semihosting with the index
+@ of the first failing check or 42.
+
+    .syntax unified
+    .global _start
+_start:
+    mov r6, #1
+    mrc p15, 0, r0, c1, c0, 0   @ SCTLR
+    tst r0, #0x2000             @ V
+    beq fail
+
+    ldr r0, =0xffff0000
+    adr r1, vectors
+    mov r2, #16
+1:  ldr r3, [r1], #4
+    str r3, [r0], #4
+    subs r2, r2, #1
+    bne 1b
+
+    mov r6, #2
+    mov r9, #0
+breakpoint:
+    bkpt #0
+    adr r0, breakpoint + 4
+    cmp r9, r0
+    bne fail
+    cmp r10, #2                 @ debug event
+    bne fail
+
+    mov r6, #3
+    adr r4, returned
+    ldr r0, =0x30000000
+    bx r0
+returned:
+    ldr r0, =0x30000004
+    cmp r9, r0
+    bne fail
+    mrc p15, 0, r1, c6, c0, 2   @ IFAR
+    ldr r0, =0x30000000
+    cmp r1, r0
+    bne fail
+    cmp r10, #5                 @ translation fault
+    bne fail
+
+    mov r6, #42
+fail:
+    adr r1, exit_block
+    str r6, [r1, #4]
+    mov r0, #0x20               @ SYS_EXIT_EXTENDED
+    svc 0x123456
+
+vectors:
+    ldr pc, [pc, #24]
+    ldr pc, [pc, #24]
+    ldr pc, [pc, #24]
+    ldr pc, [pc, #24]
+    ldr pc, [pc, #24]
+    ldr pc, [pc, #24]
+    ldr pc, [pc, #24]
+    ldr pc, [pc, #24]
+    .word _start
+    .word _start
+    .word _start
+    .word pabt_handler
+    .word _start
+    .word _start
+    .word _start
+    .word _start
+
+@ r9 = lr, r10 = IFSR, returns past a BKPT or to r4 after a failed fetch
+pabt_handler:
+    mov r9, lr
+    mrc p15, 0, r10, c5, c0, 1  @ IFSR
+    cmp r10, #2
+    movne lr, r4
+    movs pc, lr
+
+    .align 2
+exit_block:
+    .word 0x20026               @ ADP_Stopped_ApplicationExit
+    .word 0
@@ -0,0 +1,29 @@
+@ stinkarm-test: address=0x8000; exit=42; stdout=
+@ Returns from functions with `bx lr`, calls through a register with
+@ `blx`, returns via `mov pc, lr` and a `ldr pc` load, exiting with 42 if every
+@ call came back to the right place.
+
+    .global _start
+_start:
+    mov r0, #0
+    bl add_ten                  @ r0 = 10
+    ldr r3, =add_ten
+    blx r3                      @ r0 = 20
+    adr r3, add_one
+    blx r3                      @ r0 = 21
+    bl add_via_load             @ r0 = 42
+    mov r7, #1
+    svc #0
+
+add_ten:
+    add r0, r0, #10
+    bx lr
+
+add_one:
+    add r0, r0, #1
+    mov pc, lr
+
+add_via_load:
+    push {lr}
+    add r0, r0, r0
+    ldr pc, [sp], #4
@@ -0,0 +1,199 @@
+@ stinkarm-test: address=0x8000; args=--engine jit; exit=42; stdout=
+@ Runs the instructions the JIT translates from compiled blocks: flags of the
+@ arithmetic and logical operations read back through MRS, the shifter carry,
+@ conditions, MUL and MLA, MOVW and MOVT, every LDR/STR addressing mode, BL, a
+@ loop that branches to its own block and a store to the code of the running
+@ block, exiting with the index of the first failing check or 42.
+
+    .syntax unified
+    .bss
+    .align 2
+buf:
+    .space 32
+
+    .text
+    .global _start
+_start:
+    @ adds overflows into N and V without a carry
+    mov r0, #1
+    ldr r1, =0x7fffffff
+    adds r2, r1, #1
+    mrs r3, apsr
+    and r3, r3, #0xf0000000
+    cmp r3, #0x90000000
+    bne fail
+    cmp r2, #0x80000000
+    bne fail
+
+    @ a 64-bit subtraction and addition through the carry
+    mov r0, #2
+    mov r4, #0
+    mov r5, #1
+    subs r6, r4, #1
+    sbcs r7, r5, #0             @ 1 - 0 - borrow, neither zero nor borrowing
+    bne fail
+    bcc fail
+    cmp r6, #0xffffffff
+    cmpeq r7, #0
+    bne fail
+    adds r6, r6, #1
+    adc r7, r7, #0
+    cmp r6, #0
+    cmpeq r7, #1
+    bne fail
+
+    @ reverse subtractions borrow when Rn is larger
+    mov r0, #3
+    rsbs r2, r1, #0
+    rsc r3, r1, #0
+    ldr r4, =0x80000001
+    cmp r2, r4
+    cmpeq r3, #0x80000000
+    bne fail
+
+    @ logical operations take C from the shifter and keep V
+    mov r0, #4
+    adds r2, r1, #1             @ sets V
+    movs r2, r1, lsl #1
+    mrs r3, apsr
+    and r3, r3, #0xf0000000
+    cmp r3, #0x90000000         @ N and V, bit 31 shifted out of 0x7fffffff is 0
+    bne fail
+    adds r2, r1, #1             @ the cmp cleared V
+    movs r2, r1, lsr #1
+    bcc fail
+    ands r2, r1, #0x80000000    @ a rotated immediate sets C from its bit 31
+    mrs r3, apsr
+    and r3, r3, #0xf0000000
+    cmp r3, #0x70000000         @ Z, C and V
+    bne fail
+
+    @ shifts by 32 and RRX
+    mov r0, #5
+    mov r1, #0x80000000
+    movs r2, r1, lsr #32
+    bne fail
+    bcc fail
+    movs r2, r1, asr #32
+    bcc fail
+    cmp r2, #0xffffffff
+    bne fail
+    mov r1, #3
+    cmp r1, r1                  @ sets C
+    movs r2, r1, rrx
+    bcc fail
+    ldr r4, =0x80000001
+    cmp r2, r4
+    bne fail
+    mov r2, r1, ror #1
+    cmp r2, r4
+    bne fail
+
+    @ signed and unsigned conditions
+    mov r0, #6
+    mov r1, #-1
+    mov r2, #1
+    mov r3, #0
+    cmp r1, r2
+    addlt r3, r3, #1
+    addge r3, r3, #0x10
+    addhi r3, r3, #2
+    addls r3, r3, #0x20
+    addne r3, r3, #4
+    addmi r3, r3, #8
+    addvs r3, r3, #0x40
+    cmp r3, #15
+    bne fail
+
+    @ multiplies
+    mov r0, #7
+    mov r1, #7
+    mov r2, #6
+    mov r3, #100
+    mul r4, r1, r2
+    mla r5, r1, r2, r3
+    cmp r4, #42
+    cmpeq r5, #142
+    bne fail
+    mov r2, #0
+    cmp r1, r1                  @ sets C, which MULS keeps
+    muls r4, r1, r2
+    bne fail
+    bcc fail
+
+    @ MOVW and MOVT
+    mov r0, #8
+    movw r2, #0x5678
+    movt r2, #0x1234
+    ldr r3, =0x12345678
+    cmp r2, r3
+    bne fail
+
+    @ loads and stores with every addressing mode
+    mov r0, #9
+    ldr r8, =buf
+    ldr r1, =0xa1b2c3d4
+    str r1, [r8, #4]
+    ldr r2, [r8, #4]
+    cmp r2, r1
+    bne fail
+    str r1, [r8, #8]!           @ r8 = buf + 8
+    ldr r2, [r8], #-8           @ r8 = buf
+    cmp r2, r1
+    bne fail
+    ldr r3, =buf
+    cmp r8, r3
+    bne fail
+    mov r9, #2
+    ldr r2, [r8, r9, lsl #2]    @ buf + 8
+    cmp r2, r1
+    bne fail
+    add r10, r8, #12
+    ldr r2, [r10, -r9, lsl #2]  @ buf + 4
+    cmp r2, r1
+    bne fail
+    strb r1, [r8, #16]
+    ldrb r2, [r8, #16]
+    cmp r2, #0xd4
+    bne fail
+    ldrb r2, [r8, #7]
+    cmp r2, #0xa1
+    bne fail
+    strb r9, [r8, r9]!          @ r8 = buf + 2
+    ldrb r2, [r3, #2]
+    cmp r2, #2
+    bne fail
+    sub r8, r8, r3
+    cmp r8, #2
+    bne fail
+
+    @ calls and a loop that branches to its own block
+    mov r0, #10
+    mov r1, #100
+    mov r2, #0
+    bl sum
+    ldr r3, =5050
+    cmp r2, r3
+    bne fail
+
+    @ a store to the running block patches the instruction after next
+    mov r0, #11
+    ldr r2, =0xe3a06007         @ mov r6, #7
+    mov r6, #0
+    str r2, [pc]
+    nop
+    mov r6, #1
+    cmp r6, #7
+    bne fail
+
+    mov r0, #42
+fail:
+    mov r7, #1
+    svc #0
+
+@ r2 += r1 + (r1 - 1) + ... + 1
+sum:
+    add r2, r2, r1
+    subs r1, r1, #1
+    bne sum
+    bx lr
@@ -0,0 +1,106 @@
+@ stinkarm-test: address=0x8000; args=--kuser-helpers; exit=42; stdout=
+@ Calls the kernel user helpers like older libcs do: checks the helper
+@ version, reads the thread pointer, swaps words and doublewords with
+@ __kuser_cmpxchg and __kuser_cmpxchg64 including the failing cases and
+@ calls the memory barrier from Thumb state, exiting with the index of the
+@ first failing check or 42.
+
+    .syntax unified
+    .bss
+    .align 3
+value:
+    .space 8
+old:
+    .space 8
+new:
+    .space 8
+
+    .text
+    .global _start
+_start:
+    mov r6, #1
+    ldr r0, =0xffff0ffc         @ __kuser_helper_version
+    ldr r0, [r0]
+    cmp r0, #5
+    blt fail
+
+    mov r6, #2
+    ldr r0, =0x1234
+    ldr r7, =0xf0005            @ __ARM_NR_set_tls
+    svc #0
+    ldr r3, =0xffff0fe0         @ __kuser_get_tls
+    blx r3
+    ldr r1, =0x1234
+    cmp r0, r1
+    bne fail
+
+    mov r6, #3
+    ldr r4, =value
+    mov r0, #5
+    str r0, [r4]
+    mov r1, #9
+    mov r2, r4
+    ldr r3, =0xffff0fc0         @ __kuser_cmpxchg
+    blx r3
+    bcc fail
+    cmp r0, #0
+    bne fail
+    ldr r0, [r4]
+    cmp r0, #9
+    bne fail
+
+    mov r6, #4
+    mov r0, #5                  @ stale oldval
+    mov r1, #7
+    mov r2, r4
+    ldr r3, =0xffff0fc0
+    blx r3
+    bcs fail
+    cmp r0, #0
+    beq fail
+    ldr r0, [r4]
+    cmp r0, #9
+    bne fail
+
+    mov r6, #5
+    ldr r0, =old
+    mov r2, #9
+    mov r3, #0
+    strd r2, r3, [r0]
+    ldr r1, =new
+    mov r2, #1
+    mov r3, #2
+    strd r2, r3, [r1]
+    mov r2, r4
+    ldr r3, =0xffff0f60         @ __kuser_cmpxchg64
+    blx r3
+    bcc fail
+    ldrd r0, r1, [r4]
+    cmp r0, #1
+    cmpeq r1, #2
+    bne fail
+
+    mov r6, #6
+    ldr r0, =old                @ still 9:0, the target is 1:2
+    ldr r1, =new
+    mov r2, r4
+    ldr r3, =0xffff0f60
+    blx r3
+    bcs fail
+
+    mov r6, #7
+    adr r0, thumb + 1
+    bx r0
+
+fail:
+    mov r0, r6
+    mov r7, #1
+    svc #0
+
+    .thumb
+thumb:
+    ldr r3, =0xffff0fa0         @ __kuser_memory_barrier
+    blx r3
+    movs r0, #42
+    movs r7, #1
+    svc #0
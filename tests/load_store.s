@@ -0,0 +1,54 @@
+@ stinkarm-test: address=0x8000; exit=42; stdout=ok\n
+@ Exercises LDR/STR/LDRB/STRB with immediate, negative, register and scaled
+@ register offsets, pre-indexed writeback and post-indexed addressing, then
+@ writes the bytes it stored to stdout.
+
+    .bss
+    .align 2
+buf:
+    .space 64
+
+    .text
+    .global _start
+_start:
+    ldr r4, =buf
+    mov r0, #'o'
+    strb r0, [r4], #1           @ post-indexed: buf[0] = 'o', r4 = buf + 1
+    mov r1, #1
+    ldrb r3, [r4, -r1]!         @ pre-indexed writeback: r4 = buf
+    cmp r3, #'o'
+    bne fail
+    mov r0, #'k'
+    strb r0, [r4, r1]           @ buf[1] = 'k'
+    mov r0, #'\n'
+    strb r0, [r4, #2]
+
+    @ word round trip through a scaled register offset
+    ldr r0, =0x11223344
+    mov r2, #4
+    str r0, [r4, r2, lsl #2]    @ buf[16..20]
+    add r5, r4, #20
+    ldr r3, [r5, #-4]
+    cmp r3, r0
+    bne fail
+    ldrb r3, [r5, #-1]
+    cmp r3, #0x11
+    bne fail
+    ldr r3, [r4], #16           @ post-indexed load, r4 = buf + 16
+    ldr r3, [r4]
+    cmp r3, r0
+    bne fail
+
+    mov r0, #1
+    sub r1, r4, #16
+    mov r2, #3
+    mov r7, #4
+    svc #0
+
+    mov r0, #42
+    mov r7, #1
+    svc #0
+fail:
+    mov r0, #1
+    mov r7, #1
+    svc #0
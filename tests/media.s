@@ -0,0 +1,185 @@
+@ stinkarm-test: address=0x8000; exit=42; stdout=
+@ Checks MOVW/MOVT, the sign and zero extensions, byte reversal, CLZ, RBIT,
+@ the bitfield instructions and SSAT/USAT in ARM and Thumb state, exiting with
+@ the index of the first failing check or 42.
+
+    .syntax unified
+    .text
+    .global _start
+_start:
+    mov r0, #1
+    movw r1, #0xabcd
+    movt r1, #0x1234
+    ldr r2, =0x1234abcd
+    cmp r1, r2
+    bne fail
+    movw r1, #0x8000            @ clears the top halfword
+    cmp r1, #0x8000
+    bne fail
+
+    mov r0, #2
+    ldr r1, =0x12348281
+    uxtb r2, r1
+    cmp r2, #0x81
+    bne fail
+    sxtb r2, r1
+    mvn r3, #0x7e               @ 0xffffff81
+    cmp r2, r3
+    bne fail
+    sxth r2, r1
+    ldr r3, =0xffff8281
+    cmp r2, r3
+    bne fail
+    uxth r2, r1, ror #16
+    ldr r3, =0x1234
+    cmp r2, r3
+    bne fail
+    mov r4, #0x10
+    uxtab r2, r4, r1, ror #8    @ 0x10 + 0x82
+    cmp r2, #0x92
+    bne fail
+    sxtb16 r2, r1               @ 0x0034 and 0xff81
+    ldr r3, =0x0034ff81
+    cmp r2, r3
+    bne fail
+
+    mov r0, #3
+    ldr r1, =0x11223344
+    rev r2, r1
+    ldr r3, =0x44332211
+    cmp r2, r3
+    bne fail
+    rev16 r2, r1
+    ldr r3, =0x22114433
+    cmp r2, r3
+    bne fail
+    revsh r2, r1                @ 0x4433, positive
+    ldr r3, =0x4433
+    cmp r2, r3
+    bne fail
+    rbit r2, r1
+    ldr r3, =0x22cc4488
+    cmp r2, r3
+    bne fail
+
+    mov r0, #4
+    clz r2, r1
+    cmp r2, #3
+    bne fail
+    mov r1, #0
+    clz r2, r1
+    cmp r2, #32
+    bne fail
+
+    mov r0, #5
+    ldr r1, =0x12345f78
+    ubfx r2, r1, #4, #8
+    cmp r2, #0xf7
+    bne fail
+    sbfx r2, r1, #4, #8
+    mvn r3, #8                  @ 0xfffffff7
+    cmp r2, r3
+    bne fail
+    mov r2, #0
+    bfi r2, r1, #8, #4          @ low nibble 8 into bits 11..8
+    cmp r2, #0x800
+    bne fail
+    mvn r2, #0
+    bfc r2, #4, #24
+    ldr r3, =0xf000000f
+    cmp r2, r3
+    bne fail
+
+    mov r0, #6
+    mov r1, #300
+    ssat r2, #8, r1
+    cmp r2, #127
+    bne fail
+    ldr r1, =-300
+    ssat r2, #8, r1
+    mvn r3, #127                @ -128
+    cmp r2, r3
+    bne fail
+    usat r2, #8, r1
+    cmp r2, #0
+    bne fail
+    mov r1, #300
+    usat r2, #8, r1, asr #1
+    cmp r2, #150
+    bne fail
+
+    mov r0, #7
+    adr r3, thumb + 1
+    bx r3
+
+    .thumb
+thumb:
+    movw r1, #0xbeef
+    movt r1, #0xdead
+    ldr r2, =0xdeadbeef
+    cmp r1, r2
+    bne fail_thumb
+
+    movs r0, #8
+    uxtb r2, r1
+    cmp r2, #0xef
+    bne fail_thumb
+    sxth r2, r1
+    ldr r3, =0xffffbeef
+    cmp r2, r3
+    bne fail_thumb
+    uxtb.w r8, r1, ror #8
+    cmp r8, #0xbe
+    bne fail_thumb
+    movs r4, #1
+    uxtah r2, r4, r1, ror #16
+    ldr r3, =0xdeae
+    cmp r2, r3
+    bne fail_thumb
+
+    movs r0, #9
+    rev r2, r1
+    ldr r3, =0xefbeadde
+    cmp r2, r3
+    bne fail_thumb
+    rev16.w r9, r1
+    ldr r3, =0xaddeefbe
+    cmp r9, r3
+    bne fail_thumb
+    rbit r2, r1
+    ldr r3, =0xf77db57b
+    cmp r2, r3
+    bne fail_thumb
+    clz r2, r4
+    cmp r2, #31
+    bne fail_thumb
+
+    movs r0, #10
+    ubfx r2, r1, #16, #16
+    ldr r3, =0xdead
+    cmp r2, r3
+    bne fail_thumb
+    sbfx r2, r1, #28, #4
+    cmn r2, #3                  @ 0xd sign extends to -3
+    bne fail_thumb
+    bfc r1, #8, #16
+    ldr r3, =0xde0000ef
+    cmp r1, r3
+    bne fail_thumb
+    mov r2, #0xff
+    ssat r3, #4, r2, lsl #1
+    cmp r3, #7
+    bne fail_thumb
+    usat r3, #4, r2, asr #4
+    cmp r3, #15
+    bne fail_thumb
+
+    movs r0, #42
+fail_thumb:
+    movs r7, #1
+    svc #0
+
+    .arm
+fail:
+    mov r7, #1
+    svc #0
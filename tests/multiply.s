@@ -0,0 +1,194 @@
+@ stinkarm-test: address=0x8000; exit=42; stdout=
+@ Checks the 32 and 64-bit multiplies, the halfword and most significant word
+@ multiplies and SDIV/UDIV, including division by zero, in ARM and Thumb state,
+@ exiting with the index of the first failing check or 42.
+
+    .syntax unified
+    .arch_extension idiv
+    .text
+    .global _start
+_start:
+    mov r0, #1
+    mov r1, #6
+    mov r2, #7
+    mul r3, r1, r2
+    cmp r3, #42
+    bne fail
+
+    mov r0, #2
+    mov r4, #100
+    mla r3, r1, r2, r4          @ 6 * 7 + 100
+    cmp r3, #142
+    bne fail
+    mls r3, r1, r2, r4          @ 100 - 6 * 7
+    cmp r3, #58
+    bne fail
+
+    mov r0, #3
+    mvn r1, #0                  @ -1
+    muls r3, r1, r2             @ -7 sets N, leaves C alone
+    bpl fail
+    bcc fail
+    muls r3, r3, r4             @ nonzero clears Z
+    beq fail
+
+    mov r0, #4
+    mvn r1, #0
+    mov r2, #2
+    umull r5, r6, r1, r2        @ 0x1_ffff_fffe
+    mvn r3, #1
+    cmp r5, r3
+    bne fail
+    cmp r6, #1
+    bne fail
+    smull r5, r6, r1, r2        @ -2
+    cmp r5, r3
+    bne fail
+    cmn r6, #1
+    bne fail
+
+    mov r0, #5
+    mov r5, #4
+    mov r6, #0
+    smlal r5, r6, r1, r2        @ 4 + -2
+    cmp r5, #2
+    bne fail
+    cmp r6, #0
+    bne fail
+    umlal r5, r6, r1, r2        @ 2 + 0x1_ffff_fffe
+    cmp r5, #0
+    bne fail
+    cmp r6, #2
+    bne fail
+    mvn r5, #0
+    mvn r6, #0
+    umaal r5, r6, r1, r1        @ 0xffffffff^2 + 2 * 0xffffffff = 0xffffffff_ffffffff
+    cmn r5, #1
+    bne fail
+    cmn r6, #1
+    bne fail
+
+    mov r0, #6
+    umulls r5, r6, r1, r2       @ N from bit 63 of the result
+    bmi fail
+    smulls r5, r6, r1, r2
+    bpl fail
+    mov r3, #0
+    umulls r5, r6, r3, r1
+    bne fail
+
+    mov r0, #7
+    ldr r1, =0x0003fffe         @ top 3, bottom -2
+    ldr r2, =0x00050007         @ top 5, bottom 7
+    smulbb r3, r1, r2           @ -2 * 7
+    cmn r3, #14
+    bne fail
+    smultb r3, r1, r2           @ 3 * 7
+    cmp r3, #21
+    bne fail
+    smlabt r3, r1, r2, r4       @ -2 * 5 + 100
+    cmp r3, #90
+    bne fail
+    smulwt r3, r1, r2           @ (0x3fffe * 5) >> 16
+    cmp r3, #19
+    bne fail
+    smuad r3, r1, r2            @ -2 * 7 + 3 * 5
+    cmp r3, #1
+    bne fail
+    smusdx r3, r1, r2           @ -2 * 5 - 3 * 7
+    cmn r3, #31
+    bne fail
+
+    mov r0, #8
+    ldr r1, =0x7fff7fff
+    ldr r3, =0x80000000
+    smlad r3, r1, r1, r3        @ 2 * 0x3fff0001 - 2^31, fits
+    ldr r5, =0xfffe0002
+    cmp r3, r5
+    bne fail
+    ldr r1, =0x40000000
+    mov r2, #1 << 16
+    smmul r3, r1, r2            @ (2^30 * 2^16) >> 32
+    cmp r3, #1 << 14
+    bne fail
+    ldr r2, =0x80000001
+    smmulr r3, r1, r2           @ rounds -2^61 + 2^30 up
+    ldr r5, =0xe0000000
+    cmp r3, r5
+    bne fail
+
+    mov r0, #9
+    mvn r1, #99                 @ -100
+    mov r2, #7
+    sdiv r3, r1, r2             @ rounds towards zero
+    cmn r3, #14
+    bne fail
+    udiv r3, r1, r2
+    ldr r5, =0x24924916
+    cmp r3, r5
+    bne fail
+    mov r2, #0
+    sdiv r3, r1, r2
+    cmp r3, #0
+    bne fail
+    udiv r3, r1, r2
+    cmp r3, #0
+    bne fail
+    ldr r1, =0x80000000
+    mvn r2, #0
+    sdiv r3, r1, r2             @ overflows to i32::MIN
+    cmp r3, r1
+    bne fail
+
+    mov r0, #10
+    adr r3, thumb + 1
+    bx r3
+
+    .thumb
+thumb:
+    movs r1, #6
+    movs r2, #7
+    muls r2, r1, r2
+    cmp r2, #42
+    bne fail_thumb
+
+    movs r0, #11
+    mov r8, #6
+    mov r9, #7
+    mul r10, r8, r9
+    cmp r10, #42
+    bne fail_thumb
+    mls r10, r8, r9, r10
+    cmp r10, #0
+    bne fail_thumb
+
+    movs r0, #12
+    mvn r1, #0
+    movs r2, #2
+    smull r3, r4, r1, r2
+    cmn r4, #1
+    bne fail_thumb
+    umull r3, r4, r1, r2
+    cmp r4, #1
+    bne fail_thumb
+
+    movs r0, #13
+    mvn r1, #99
+    movs r2, #7
+    sdiv r3, r1, r2
+    cmn r3, #14
+    bne fail_thumb
+    movs r2, #0
+    udiv r3, r1, r2
+    cmp r3, #0
+    bne fail_thumb
+
+    movs r0, #42
+fail_thumb:
+    movs r7, #1
+    svc #0
+
+    .arm
+fail:
+    mov r7, #1
+    svc #0
@@ -0,0 +1,332 @@
+@ stinkarm-test: address=0x8000; exit=42; stdout=
+@ Checks that AT_HWCAP reports NEON, then Advanced SIMD loads and stores, integer,
+@ logical, permute, shift, long and narrowing, saturating and floating-point
+@ operations in ARM and Thumb state, exiting with the index of the first failing
+@ check or 42.
+
+    .syntax unified
+    .fpu neon
+    .text
+    .global _start
+_start:
+    mov r0, #1
+    ldr r1, [sp]                @ argc
+    add r2, sp, #8
+    add r2, r2, r1, lsl #2      @ past the argv terminator
+1:  ldr r3, [r2], #4            @ skip envp
+    cmp r3, #0
+    bne 1b
+2:  ldr r3, [r2], #8
+    cmp r3, #0
+    beq fail
+    cmp r3, #16                 @ AT_HWCAP
+    bne 2b
+    ldr r3, [r2, #-4]
+    tst r3, #(1 << 12)          @ HWCAP_NEON
+    beq fail
+    tst r3, #(1 << 13)          @ HWCAP_VFPv3
+    beq fail
+
+    mov r0, #2
+    adr r1, bytes
+    mov r4, r1
+    vld1.8 {d0-d1}, [r1]!
+    sub r2, r1, r4
+    cmp r2, #16
+    bne fail
+    vadd.i8 q1, q0, q0
+    vmov r2, r3, d2
+    ldr r4, =0x06040200
+    cmp r2, r4
+    bne fail
+    ldr r4, =0x0e0c0a08
+    cmp r3, r4
+    bne fail
+    vsub.i16 q1, q1, q0         @ back to the bytes
+    vceq.i32 q2, q1, q0
+    vmov r2, r3, d5
+    and r2, r2, r3
+    cmn r2, #1
+    bne fail
+
+    mov r0, #3
+    vmov.i32 q2, #1
+    vmov.i32 q3, #3
+    vmla.i32 q2, q3, q3
+    vmov.32 r2, d4[0]
+    cmp r2, #10
+    bne fail
+    vmov.32 r2, d5[1]
+    cmp r2, #10
+    bne fail
+    vmul.i16 d6, d6, d6         @ halfwords of 3 and 0
+    vmov.u16 r2, d6[0]
+    cmp r2, #9
+    bne fail
+
+    mov r0, #4
+    vmov.i8 d0, #0xf0
+    vmov.i8 d1, #0x11
+    vmov.i8 d2, #0x22
+    vbsl d0, d1, d2
+    vmov r2, r3, d0
+    ldr r4, =0x12121212
+    cmp r2, r4
+    bne fail
+    vorr.i32 d0, #0xff000000
+    vbic.i16 d0, #0x12
+    vmvn d1, d0
+    vmov r2, r3, d1
+    ldr r4, =0x00ffedff
+    cmp r3, r4
+    bne fail
+
+    mov r0, #5
+    mov r1, #0x80
+    vdup.8 d0, r1
+    vmov.s8 r2, d0[3]
+    cmn r2, #128
+    bne fail
+    mov r1, #5
+    vmov.8 d0[2], r1
+    vmov.u8 r2, d0[2]
+    cmp r2, #5
+    bne fail
+    vmov.u8 r2, d0[5]
+    cmp r2, #0x80
+    bne fail
+    vdup.16 q1, d0[1]
+    vmov.u16 r2, d3[3]
+    ldr r4, =0x8005
+    cmp r2, r4
+    bne fail
+
+    mov r0, #6
+    adr r1, bytes
+    vld1.8 {d0-d1}, [r1:128]
+    adr r1, indices
+    vld1.8 {d3}, [r1]
+    vtbl.8 d4, {d0, d1}, d3
+    vmov r2, r3, d4
+    ldr r4, =0x0300000f
+    cmp r2, r4
+    bne fail
+    ldr r4, =0x07010800
+    cmp r3, r4
+    bne fail
+    vmov.i8 d5, #0xff
+    vtbx.8 d5, {d0, d1}, d3
+    vmov r2, r3, d5
+    ldr r4, =0x03ff000f
+    cmp r2, r4
+    bne fail
+
+    mov r0, #7
+    vzip.8 d0, d1
+    vmov r2, r3, d0
+    ldr r4, =0x09010800
+    cmp r2, r4
+    bne fail
+    vmov r2, r3, d1
+    ldr r4, =0x0f070e06
+    cmp r3, r4
+    bne fail
+    vuzp.8 d0, d1
+    vtrn.32 d0, d1
+    vmov r2, r3, d0
+    ldr r4, =0x03020100
+    cmp r2, r4
+    bne fail
+    ldr r4, =0x0b0a0908
+    cmp r3, r4
+    bne fail
+    vtrn.32 d0, d1
+
+    mov r0, #8
+    vext.8 d2, d0, d1, #3
+    vmov r2, r3, d2
+    ldr r4, =0x06050403
+    cmp r2, r4
+    bne fail
+    ldr r4, =0x0a090807
+    cmp r3, r4
+    bne fail
+    vrev32.8 d2, d0
+    vmov r2, r3, d2
+    ldr r4, =0x00010203
+    cmp r2, r4
+    bne fail
+
+    mov r0, #9
+    mov r1, #0
+    vmsr fpscr, r1
+    vmov.i16 d0, #0x8000
+    vshr.s16 d1, d0, #4
+    vmov.u16 r2, d1[0]
+    ldr r4, =0xf800
+    cmp r2, r4
+    bne fail
+    vshr.u16 d2, d0, #4
+    vmov.u16 r2, d2[1]
+    cmp r2, #0x800
+    bne fail
+    vrshr.u16 d2, d0, #16
+    vmov.u16 r2, d2[2]
+    cmp r2, #1
+    bne fail
+    vmrs r2, fpscr
+    tst r2, #(1 << 27)          @ QC still clear
+    bne fail
+    vqshl.s16 d3, d1, #5
+    vmov.u16 r2, d3[3]
+    cmp r2, #0x8000
+    bne fail
+    vmrs r2, fpscr
+    tst r2, #(1 << 27)
+    beq fail
+    vsli.16 d1, d2, #8
+    vmov.u16 r2, d1[0]
+    ldr r4, =0x0100
+    cmp r2, r4
+    bne fail
+
+    mov r0, #10
+    adr r1, bytes
+    vld1.8 {d0}, [r1]
+    vmull.u8 q2, d0, d0
+    vmov.u16 r2, d5[3]
+    cmp r2, #49
+    bne fail
+    vmovl.u8 q3, d0
+    vmov.u16 r2, d7[2]
+    cmp r2, #6
+    bne fail
+    vmov.i16 q4, #0x100
+    vqmovn.u16 d10, q4
+    vmov r2, r3, d10
+    cmn r3, #1
+    bne fail
+    vpaddl.u8 d1, d0
+    vmov.u16 r2, d1[3]
+    cmp r2, #13
+    bne fail
+    vaddl.u8 q6, d0, d10
+    vmov.u16 r2, d13[3]
+    ldr r4, =0x106
+    cmp r2, r4
+    bne fail
+
+    mov r0, #11
+    vmov.f32 q0, #1.5
+    vadd.f32 q1, q0, q0
+    vmul.f32 q1, q1, q0         @ 4.5
+    vcvt.s32.f32 q2, q1         @ towards zero
+    vmov.32 r2, d5[1]
+    cmp r2, #4
+    bne fail
+    vcgt.f32 d6, d2, d0
+    vmov r2, r3, d6
+    cmn r2, #1
+    bne fail
+    vmov.f32 d7, #1.0
+    vrecpe.f32 d7, d7
+    vmov r2, r3, d7
+    ldr r4, =0x3f7f8000
+    cmp r2, r4
+    bne fail
+    vcvt.s32.f32 d7, d2, #4
+    vmov r2, r3, d7
+    cmp r2, #72
+    bne fail
+    vmla.f32 d2, d0, d0[0]      @ 4.5 + 2.25
+    vmov r2, r3, d2
+    ldr r4, =0x40d80000
+    cmp r2, r4
+    bne fail
+
+    mov r0, #12
+    adr r1, halfwords
+    vld2.16 {d0, d1}, [r1]
+    vmov.u16 r2, d1[2]
+    cmp r2, #5
+    bne fail
+    vld1.32 {d2[]}, [r1]
+    vmov r2, r3, d2
+    cmp r2, r3
+    bne fail
+    ldr r4, =0x00010000
+    cmp r2, r4
+    bne fail
+    vld4.8 {d4[], d5[], d6[], d7[]}, [r1]
+    vmov.u8 r2, d5[7]
+    cmp r2, #0
+    bne fail
+    vmov.u8 r2, d6[3]
+    cmp r2, #1
+    bne fail
+    sub sp, sp, #16
+    vst1.16 {d0[1]}, [sp]
+    ldrh r2, [sp]
+    cmp r2, #2
+    bne fail
+    vst2.16 {d0, d1}, [sp]
+    ldr r2, [sp, #12]
+    ldr r4, =0x00070006
+    cmp r2, r4
+    bne fail
+    add sp, sp, #16
+
+    mov r0, #13
+    adr r3, thumb + 1
+    bx r3
+
+    .thumb
+thumb:
+    adr r1, bytes
+    movs r2, #8
+    vld1.8 {d0}, [r1], r2
+    vld1.8 {d1}, [r1]
+    vadd.i32 d2, d0, d1
+    vpadd.i32 d3, d2, d2
+    vmov r2, s6
+    ldr r4, =0x24201c18         @ 0x03020100 + 0x07060504 + 0x0b0a0908 + 0x0f0e0d0c
+    cmp r2, r4
+    bne fail_thumb
+    it eq
+    vmoveq.u8 r2, d0[7]
+    cmp r2, #7
+    bne fail_thumb
+
+    movs r0, #14
+    vcnt.8 d4, d1
+    vmov r2, r3, d4
+    ldr r4, =0x03020201         @ popcounts of 8, 9, 10 and 11
+    cmp r2, r4
+    bne fail_thumb
+    vclz.i32 d4, d1
+    vmov r2, r3, d4
+    cmp r2, #4
+    bne fail_thumb
+    vmax.u16 d5, d0, d1
+    vmov r2, r3, d5
+    ldr r4, =0x0f0e0d0c
+    cmp r3, r4
+    bne fail_thumb
+
+    movs r0, #42
+fail_thumb:
+    movs r7, #1
+    svc #0
+
+    .arm
+fail:
+    mov r7, #1
+    svc #0
+
+    .align 4
+bytes:
+    .byte 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15
+indices:
+    .byte 15, 0, 16, 3, 200, 8, 1, 7
+halfwords:
+    .hword 0, 1, 2, 3, 4, 5, 6, 7
@@ -0,0 +1,226 @@
+@ stinkarm-test: address=0x8000; exit=42; stdout=
+@ Checks the parallel additions and subtractions with the GE flags they set,
+@ SEL, USAD8, MSR to APSR_g and the saturating QADD, QSUB, QDADD and QDSUB
+@ with the Q flag in ARM and Thumb state, exiting with the index of the first
+@ failing check or 42.
+
+    .syntax unified
+    .text
+    .global _start
+_start:
+    ldr r4, =0x11223344
+    ldr r5, =0xaabbccdd
+
+    mov r0, #1
+    ldr r1, =0x7fff0001
+    ldr r2, =0x0001fffe
+    sadd16 r3, r1, r2           @ 1 + -2 and a wrapping 0x7fff + 1
+    ldr r6, =0x8000ffff
+    cmp r3, r6
+    bne fail
+    mrs r6, apsr
+    ubfx r6, r6, #16, #4
+    cmp r6, #0b1100
+    bne fail
+    sel r3, r4, r5
+    ldr r6, =0x1122ccdd
+    cmp r3, r6
+    bne fail
+
+    mov r0, #2
+    ldr r1, =0x80ff0102
+    ldr r2, =0x80010203
+    uadd8 r3, r1, r2            @ the top two bytes carry out
+    ldr r6, =0x00000305
+    cmp r3, r6
+    bne fail
+    mrs r6, apsr
+    ubfx r6, r6, #16, #4
+    cmp r6, #0b1100
+    bne fail
+    ldr r1, =0x01020304
+    ldr r2, =0x02020202
+    usub8 r3, r1, r2            @ only the top byte borrows
+    ldr r6, =0xff000102
+    cmp r3, r6
+    bne fail
+    sel r3, r4, r5
+    ldr r6, =0xaa223344
+    cmp r3, r6
+    bne fail
+
+    mov r0, #3
+    ldr r1, =0x00050003
+    ldr r2, =0x00040001
+    sasx r3, r1, r2             @ 5 + 1 and 3 - 4
+    ldr r6, =0x0006ffff
+    cmp r3, r6
+    bne fail
+    sel r3, r4, r5
+    ldr r6, =0x1122ccdd
+    cmp r3, r6
+    bne fail
+    ssax r3, r1, r2             @ 5 - 1 and 3 + 4
+    ldr r6, =0x00040007
+    cmp r3, r6
+    bne fail
+    sel r3, r4, r5
+    cmp r3, r4
+    bne fail
+
+    @ the saturating and halving forms leave GE and Q alone
+    mov r0, #4
+    msr APSR_nzcvq, #0
+    ldr r1, =0x70009000
+    ldr r2, =0x2000a000
+    qadd16 r3, r1, r2
+    ldr r6, =0x7fff8000
+    cmp r3, r6
+    bne fail
+    ldr r1, =0x10203040
+    ldr r2, =0x20102050
+    uqsub8 r3, r1, r2
+    ldr r6, =0x00101000
+    cmp r3, r6
+    bne fail
+    ldr r1, =0x807f02fe
+    ldr r2, =0x807f04fe
+    shadd8 r3, r1, r2
+    ldr r6, =0x807f03fe
+    cmp r3, r6
+    bne fail
+    ldr r1, =0x00010010
+    ldr r2, =0x00030004
+    uhsub16 r3, r1, r2
+    ldr r6, =0xffff0006
+    cmp r3, r6
+    bne fail
+    mrs r6, apsr
+    tst r6, #0x08000000         @ Q
+    bne fail
+    sel r3, r4, r5              @ GE is still 0b1111 from ssax
+    cmp r3, r4
+    bne fail
+
+    mov r0, #5
+    ldr r1, =0x01ff1005
+    ldr r2, =0x02001510
+    usad8 r3, r1, r2            @ 1 + 255 + 5 + 11
+    ldr r6, =272
+    cmp r3, r6
+    bne fail
+    mov r6, #1000
+    usada8 r3, r1, r2, r6
+    ldr r6, =1272
+    cmp r3, r6
+    bne fail
+
+    mov r0, #6
+    mov r6, #0x50000            @ GE = 0b0101
+    msr APSR_g, r6
+    sel r3, r4, r5
+    ldr r6, =0xaa22cc44
+    cmp r3, r6
+    bne fail
+
+    mov r0, #7
+    mov r1, #10
+    mov r2, #3
+    qdsub r3, r1, r2            @ 10 - 2 * 3
+    cmp r3, #4
+    bne fail
+    mrs r6, apsr
+    tst r6, #0x08000000
+    bne fail
+    ldr r1, =0x7fffffff
+    mov r2, #1
+    qadd r3, r1, r2
+    cmp r3, r1
+    bne fail
+    mrs r6, apsr
+    tst r6, #0x08000000
+    beq fail
+    msr APSR_nzcvq, #0
+    mov r1, #0x80000000
+    qsub r3, r1, r2
+    cmp r3, r1
+    bne fail
+    msr APSR_nzcvq, #0
+    mov r1, #1
+    mov r2, #0x40000000
+    qdadd r3, r1, r2            @ 2 * r2 saturates first
+    ldr r6, =0x7fffffff
+    cmp r3, r6
+    bne fail
+    mrs r6, apsr
+    tst r6, #0x08000000
+    beq fail
+
+    mov r0, #8
+    adr r3, thumb + 1
+    bx r3
+
+    .thumb
+thumb:
+    ldr r1, =0x7fff0001
+    ldr r2, =0x0001fffe
+    sadd16 r3, r1, r2
+    ldr r6, =0x8000ffff
+    cmp r3, r6
+    bne fail_thumb
+    sel r3, r4, r5
+    ldr r6, =0x1122ccdd
+    cmp r3, r6
+    bne fail_thumb
+
+    movs r0, #9
+    ldr r1, =0xffff0002
+    ldr r2, =0x00010004
+    uhadd16 r3, r1, r2
+    ldr r6, =0x80000003
+    cmp r3, r6
+    bne fail_thumb
+    ldr r1, =0x00040000
+    ldr r2, =0x00080002
+    shasx r3, r1, r2            @ (4 + 2) / 2 and (0 - 8) / 2
+    ldr r6, =0x0003fffc
+    cmp r3, r6
+    bne fail_thumb
+    ldr r1, =0x10203040
+    ldr r2, =0x20102050
+    uqsub8 r3, r1, r2
+    ldr r6, =0x00101000
+    cmp r3, r6
+    bne fail_thumb
+
+    movs r0, #10
+    ldr r1, =0x01ff1005
+    ldr r2, =0x02001510
+    usad8 r3, r1, r2
+    cmp r3, #272
+    bne fail_thumb
+    msr APSR_nzcvq, r0          @ clears Q
+    movs r1, #0
+    mov r2, #0x40000000
+    qdsub r3, r1, r2            @ 0 - 0x7fffffff
+    ldr r6, =0x80000001
+    cmp r3, r6
+    bne fail_thumb
+    mrs r6, apsr
+    tst r6, #0x08000000
+    beq fail_thumb
+    movs r1, #5
+    movs r2, #7
+    qsub r3, r1, r2
+    cmn r3, #2
+    bne fail_thumb
+
+    movs r0, #42
+fail_thumb:
+    movs r7, #1
+    svc #0
+
+    .arm
+fail:
+    mov r7, #1
+    svc #0
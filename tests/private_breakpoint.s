@@ -0,0 +1,11 @@
+@ stinkarm-test: address=0x8000; exit=133; stdout-contains=guest killed by SIGTRAP at 0x8008: 0xef000000
+@ The breakpoint private syscall raises SIGTRAP at the svc, 128 + 5.
+
+    .global _start
+_start:
+    mov r7, #1
+    orr r7, r7, #0xf0000       @ __ARM_NR_breakpoint
+    svc #0
+    mov r0, #42
+    mov r7, #1
+    svc #0
@@ -0,0 +1,45 @@
+@ stinkarm-test: address=0x8000; args=--kuser-helpers; exit=42; stdout=
+@ Reads into the read-only vector page fail with EFAULT, whether they go to
+@ the host or come from a synthesized file, and leave the kernel user helpers
+@ intact. Exits with the index of the first failing check or 42.
+
+    .data
+path:
+    .asciz "/proc/cpuinfo"
+
+    .text
+    .global _start
+_start:
+    ldr r4, =0xffff0f60         @ __kuser_cmpxchg
+    ldr r5, [r4]
+
+    mov r0, #0                  @ stdin
+    mov r1, r4
+    mov r2, #16
+    mov r7, #3                  @ read
+    svc #0
+    cmn r0, #14                 @ EFAULT
+    mov r0, #1
+    bne fail
+
+    ldr r0, =path
+    mov r1, #0                  @ O_RDONLY
+    mov r7, #5                  @ open
+    svc #0
+    mov r1, r4
+    mov r2, #16
+    mov r7, #3                  @ read
+    svc #0
+    cmn r0, #14                 @ EFAULT
+    mov r0, #2
+    bne fail
+
+    ldr r0, [r4]
+    cmp r0, r5
+    mov r0, #3
+    bne fail
+
+    mov r0, #42
+fail:
+    mov r7, #1
+    svc #0
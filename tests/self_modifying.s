@@ -0,0 +1,63 @@
+@ stinkarm-test: address=0x8000; exit=42; stdout=
+@ Patches instructions that already ran from the decoded block cache: one in
+@ a loop body on its second pass, the first running it from its own block, the
+@ instruction right after the store in the same block and a function flushed
+@ with cacheflush before calling it again, exiting with the index of the first
+@ failing check or 42.
+
+    .syntax unified
+    .global _start
+_start:
+    mov r0, #1
+    mov r4, #0
+    mov r5, #0
+loop:
+    add r5, r5, #1              @ becomes add r5, r5, #10 after the second pass
+    add r4, r4, #1
+    cmp r4, #2
+    bne 1f
+    adr r1, loop
+    ldr r2, =0xe285500a         @ add r5, r5, #10
+    str r2, [r1]
+1:  cmp r4, #4
+    blt loop
+    cmp r5, #22
+    bne fail
+
+    mov r0, #2
+    ldr r2, =0xe3a06007         @ mov r6, #7
+    mov r6, #0
+    str r2, [pc]                @ pc reads as the store + 8
+    nop
+    mov r6, #1
+    cmp r6, #7
+    bne fail
+
+    mov r0, #3
+    bl five
+    cmp r3, #5
+    bne fail
+    adr r1, five
+    ldr r2, =0xe3a03009         @ mov r3, #9
+    str r2, [r1]
+    mov r8, r0
+    adr r0, five
+    add r1, r0, #8
+    mov r2, #0
+    ldr r7, =0xf0002            @ __ARM_NR_cacheflush
+    svc #0
+    cmp r0, #0
+    bne fail
+    mov r0, r8
+    bl five
+    cmp r3, #9
+    bne fail
+
+    mov r0, #42
+fail:
+    mov r7, #1
+    svc #0
+
+five:
+    mov r3, #5
+    bx lr
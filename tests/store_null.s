@@ -0,0 +1,12 @@
+@ stinkarm-test: address=0x8000; exit=125; stdout-contains=MemoryAccessViolation { guest: 4
+@ Stores through a null pointer with an offset, the emulator should fault
+@ instead of writing into the null page.
+
+    .global _start
+_start:
+    mov r0, #0
+    mov r1, #42
+    str r1, [r0, #4]
+    mov r0, #1
+    mov r7, #1
+    svc #0
@@ -0,0 +1,11 @@
+@ stinkarm-test: address=0x8000; args=--strict; exit=126; stdout-contains=UNPREDICTABLE BlockTransfer at 0x8004 writes back to a base register in its register list: 0xe8b10003
+@ An LDM that writes back to a base register it also loads is UNPREDICTABLE,
+@ --strict stops the emulation naming the instruction and the rule.
+
+    .global _start
+_start:
+    mov r1, sp
+    .inst 0xe8b10003            @ ldm r1!, {r0, r1}
+    mov r0, #1
+    mov r7, #1
+    svc #0
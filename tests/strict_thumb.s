@@ -0,0 +1,17 @@
+@ stinkarm-test: address=0x8000; args=--strict; exit=126; stdout-contains=UNPREDICTABLE LoadStoreImm at 0x800a writes back to a base register it also transfers: 0xf8500b04
+@ --strict checks Thumb instructions as the ARM instruction they execute as, a
+@ post-indexed LDR.W loading its own base register stops the emulation.
+
+    .syntax unified
+    .global _start
+_start:
+    adr r0, thumb + 1
+    bx r0
+
+    .thumb
+thumb:
+    mov r0, sp
+    .inst.w 0xf8500b04          @ ldr.w r0, [r0], #4
+    movs r0, #1
+    movs r7, #1
+    svc #0
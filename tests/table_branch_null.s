@@ -0,0 +1,15 @@
+@ stinkarm-test: address=0x8000; exit=125; stdout-contains=MemoryAccessViolation { guest: 16,
+@ TBH through a table at the null page, the fault should report the address of
+@ the halfword read (Rn + 2 * Rm) rather than the table base.
+
+    .syntax unified
+    .thumb
+    .global _start
+    .thumb_func
+_start:
+    mov.w r0, #0
+    mov.w r1, #8
+    tbh [r0, r1, lsl #1]
+    mov.w r0, #1
+    mov.w r7, #1
+    svc #0
@@ -0,0 +1,107 @@
+@ stinkarm-test: address=0x8000; exit=42; stdout=ok\n
+@ Starts in ARM, switches to Thumb with `blx`, runs 16-bit and 32-bit Thumb-2
+@ code including IT blocks, CBZ/CBNZ and TBB, calls back into ARM and returns
+@ to ARM with `bx lr`. Exits with the index of the first failing check or 42.
+
+    .syntax unified
+
+    .bss
+    .align 2
+buf:
+    .space 16
+
+    .text
+    .arm
+    .global _start
+_start:
+    blx thumb_main
+    mov r7, #1
+    svc #0
+
+    .type arm_double, %function
+arm_double:
+    add r0, r0, r0
+    bx lr
+
+    .thumb
+    .thumb_func
+thumb_main:
+    push {r4-r7, lr}
+    movs r0, #1
+    movs r1, #20
+    adds r1, r1, #1             @ r1 = 21
+    lsls r2, r1, #1             @ r2 = 42
+    cmp r2, #42
+    bne fail
+
+    movs r0, #2
+    mov.w r3, #0x00ff00ff       @ Thumb-2 replicated immediate
+    ldr r4, =0x00ff00ff
+    cmp r3, r4
+    bne fail
+    orn r3, r3, #0xff000000     @ r3 = 0x00ffffff ... | ~0xff000000
+    ldr r4, =0x00ffffff
+    cmp r3, r4
+    bne fail
+
+    movs r0, #3
+    movs r5, #0
+    cmp r2, #42
+    ite eq
+    moveq r5, #1
+    movne r5, #2
+    cmp r5, #1
+    bne fail
+    itt ne                      @ neither instruction executes
+    movne r0, #99
+    bne fail
+
+    movs r0, #4
+    movs r3, #0
+    cbnz r3, fail
+    cbz r3, 1f
+    b fail
+1:
+    movs r0, #5
+    movs r3, #2
+    tbb [pc, r3]
+table:
+    .byte (case0 - table) / 2
+    .byte (case1 - table) / 2
+    .byte (case2 - table) / 2
+    .byte 0
+case0:
+case1:
+    b fail
+case2:
+    movs r0, #6
+    ldr r4, =buf
+    movs r3, #'o'
+    strb r3, [r4]
+    movs r3, #'k'
+    strb.w r3, [r4, #1]
+    movs r3, #'\n'
+    strh r3, [r4, #2]
+    ldrh.w r6, [r4, #2]
+    cmp r6, #'\n'
+    bne fail
+    ldrd r6, r7, [r4]
+    strd r6, r7, [r4, #8]!
+    ldr r6, [r4, #-8]
+    cmp r6, r7
+    beq fail                    @ first word is "ok\n\0", second is zero
+
+    movs r0, #21
+    bl arm_double
+    cmp r0, #42
+    bne fail
+
+    movs r0, #1
+    sub r1, r4, #8
+    movs r2, #3
+    movs r7, #4
+    svc #0
+
+    movs r0, #42
+fail:
+    pop {r4-r7, pc}
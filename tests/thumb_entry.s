@@ -0,0 +1,42 @@
+@ stinkarm-test: address=0x8000; exit=42; stdout=
+@ Thumb entry point (bit 0 of e_entry set) running only 32-bit Thumb-2
+@ encodings: wide branches, register shifts, shifted register operands,
+@ ADDW/SUBW, indexed loads, PUSH.W/POP.W and TBH.
+
+    .syntax unified
+    .thumb
+    .global _start
+    .thumb_func
+_start:
+    mov.w r0, #1
+    mov.w r1, #3
+    mov.w r2, #2
+    lsl.w r3, r1, r2            @ r3 = 12
+    add.w r3, r3, r1, lsl #1    @ r3 = 18
+    addw r3, r3, #1000          @ r3 = 1018
+    subw r3, r3, #994           @ r3 = 24
+    cmp.w r3, #24
+    bne.w done
+
+    mov.w r0, #2
+    push.w {r1-r3, lr}
+    ldr.w r4, [sp], #4          @ post-indexed, r4 = r1
+    ldr.w r5, [sp, #4]!         @ pre-indexed writeback, r5 = r3
+    add.w sp, sp, #8
+    add.w r4, r4, r5            @ r4 = 27
+    cmp.w r4, #27
+    bne.w done
+
+    mov.w r0, #3
+    mov.w r6, #1
+    tbh [pc, r6, lsl #1]
+table:
+    .hword (case0 - table) / 2
+    .hword (case1 - table) / 2
+case0:
+    b.w done
+case1:
+    mov.w r0, #42
+done:
+    mov.w r7, #1
+    svc #0
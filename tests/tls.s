@@ -0,0 +1,89 @@
+@ stinkarm-test: address=0x8000; exit=42; stdout=
+@ Sets the thread pointer with the set_tls private syscall and reads it back
+@ through TPIDRURO and get_tls, round trips TPIDRURW, checks cacheflush and
+@ an unimplemented private syscall, reads the generic timer and does the same
+@ in Thumb state, exiting with the index of the first failing check or 42.
+
+    .syntax unified
+    .global _start
+_start:
+    mov r6, #1
+    ldr r4, =0x12345678
+    mov r0, r4
+    ldr r7, =0xf0005            @ __ARM_NR_set_tls
+    svc #0
+    cmp r0, #0
+    bne fail
+    mrc p15, 0, r1, c13, c0, 3  @ TPIDRURO
+    cmp r1, r4
+    bne fail
+
+    mov r6, #2
+    ldr r7, =0xf0006            @ __ARM_NR_get_tls
+    svc #0
+    cmp r0, r4
+    bne fail
+
+    mov r6, #3
+    ldr r5, =0xcafe
+    mcr p15, 0, r5, c13, c0, 2  @ TPIDRURW
+    mrc p15, 0, r1, c13, c0, 2
+    cmp r1, r5
+    bne fail
+
+    mov r6, #4
+    adr r0, _start
+    add r1, r0, #64
+    mov r2, #0
+    ldr r7, =0xf0002            @ __ARM_NR_cacheflush
+    svc #0
+    cmp r0, #0
+    bne fail
+    mov r0, #64
+    mov r1, #0                  @ end before start
+    svc #0
+    cmn r0, #22                 @ -EINVAL
+    bne fail
+
+    mov r6, #5
+    ldr r7, =0xf07ff            @ unimplemented, but below 0xf0800
+    svc #0
+    cmn r0, #38                 @ -ENOSYS
+    bne fail
+
+    mov r6, #6
+    mrc p15, 0, r1, c14, c0, 0  @ CNTFRQ
+    cmp r1, #0
+    beq fail
+    mrrc p15, 1, r2, r3, c14    @ CNTVCT
+    mrrc p15, 1, r4, r5, c14
+    subs r4, r4, r2
+    sbcs r5, r5, r3
+    bcc fail                    @ the count never goes backwards
+    mcr p15, 0, r0, c7, c10, 5  @ CP15DMB
+
+    mov r6, #7
+    adr r1, thumb + 1
+    bx r1
+
+fail:
+    mov r0, r6
+    mov r7, #1
+    svc #0
+
+    .thumb
+thumb:
+    movw r4, #0xbeef
+    mov r0, r4
+    movw r7, #5
+    movt r7, #0xf               @ __ARM_NR_set_tls
+    svc #0
+    mrc p15, 0, r1, c13, c0, 3
+    movs r0, #7
+    cmp r1, r4
+    bne thumb_exit
+
+    movs r0, #42
+thumb_exit:
+    movs r7, #1
+    svc #0
@@ -0,0 +1,11 @@
+@ stinkarm-test: address=0x8000; exit=132; stdout-contains=guest killed by SIGILL at 0x8004: 0x7f000f0; stdout-not-contains=unimplemented
+@ UDF raises SIGILL even with a condition that fails, terminating the guest
+@ with 128 + 4 like a shell reports a process killed by the signal.
+
+    .global _start
+_start:
+    movs r0, #1                 @ clears Z
+    .inst 0x07f000f0            @ udf with cond = eq, which fails
+    mov r0, #42
+    mov r7, #1
+    svc #0
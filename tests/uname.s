@@ -0,0 +1,22 @@
+@ stinkarm-test: address=0x8000; args=--cpu cortex-a53-aarch32; exit=42; stdout=armv8l
+@ uname names the machine after the emulated processor, not the host, so the
+@ AArch32 Cortex-A53 prints armv8l.
+
+    .global _start
+_start:
+    sub sp, sp, #392            @ struct new_utsname, six fields of 65 bytes
+    mov r0, sp
+    mov r7, #122                @ uname
+    svc #0
+    cmp r0, #0
+    bne fail
+
+    mov r0, #1
+    add r1, sp, #(4 * 65)       @ machine
+    mov r2, #6
+    mov r7, #4                  @ write
+    svc #0
+    mov r0, #42
+fail:
+    mov r7, #1
+    svc #0
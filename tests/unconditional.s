@@ -0,0 +1,49 @@
+@ stinkarm-test: address=0x8000; exit=132; stdout-contains=guest killed by SIGILL; stdout-not-contains=unimplemented
+@ Runs the unconditional instructions, cond = 1111: BLX (immediate) into
+@ Thumb, the preload hints on an unmapped address, CLREX, the barriers and
+@ SETEND LE. A word of that space without an unconditional encoding is not
+@ skipped as if its condition failed but raises SIGILL. Exits with the index of
+@ the first failing check instead.
+
+    .syntax unified
+    .global _start
+_start:
+    mov r0, #1
+    mov r1, #20
+    blx thumb_double
+    cmp r1, #40
+    bne fail
+
+    mov r0, #2
+    ldr r1, =0x30000000
+    mov r2, #4
+    pld [r1]
+    pld [r1, #-4]
+    pli [r1, r2]
+    pld [r1, r2, lsl #2]
+
+    mov r0, #3
+    clrex
+    dmb ish
+    dsb sy
+    isb sy
+
+    mov r0, #4
+    setend le
+    mrs r1, apsr
+    tst r1, #0x200              @ E
+    bne fail
+
+    mov r0, #5
+    .inst 0xff000000            @ svc with cond = 1111 is undefined
+    mov r0, #6
+
+fail:
+    mov r7, #1
+    svc #0
+
+    .thumb
+    .thumb_func
+thumb_double:
+    adds r1, r1, r1
+    bx lr
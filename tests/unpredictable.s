@@ -0,0 +1,59 @@
+@ stinkarm-test: address=0x8000; exit=42; stdout=
+@ Without --strict the UNPREDICTABLE encodings run as documented on
+@ decoder::unpredictable::Rule: loads keep the loaded value over the writeback,
+@ stores store the base before it, a long multiply into one register keeps
+@ the high word and encodings using pc are NOPs. Exits with the index of the
+@ first failing check or 42.
+
+    .syntax unified
+    .data
+    .align 2
+words:
+    .word 0x11111111, 0x22222222, 0x33333333
+
+    .text
+    .global _start
+_start:
+    mov r0, #1
+    ldr r1, =words
+    .inst 0xe4911004            @ ldr r1, [r1], #4 writes back, then loads
+    ldr r2, =0x11111111
+    cmp r1, r2
+    bne fail
+
+    mov r0, #2
+    ldr r1, =words + 4
+    .inst 0xe5211004            @ str r1, [r1, #-4]! stores words + 4
+    ldr r2, =words
+    cmp r1, r2
+    bne fail
+    ldr r3, [r2]
+    add r2, r2, #4
+    cmp r3, r2
+    bne fail
+
+    mov r0, #3
+    ldr r1, =words + 4
+    .inst 0xe8b10003            @ ldm r1!, {r0, r1}
+    ldr r2, =0x33333333
+    cmp r1, r2
+    mov r0, #3
+    bne fail
+
+    mov r0, #4
+    ldr r1, =0x10000
+    .inst 0xe0822191            @ umull r2, r2, r1, r1, 0x1_0000_0000
+    cmp r2, #1
+    bne fail
+
+    mov r0, #5
+    mov r2, #7
+    .inst 0xe16fff12            @ clz pc, r2
+    .inst 0xe5bf2004            @ ldr r2, [pc, #4]!
+    cmp r2, #7
+    bne fail
+
+    mov r0, #42
+fail:
+    mov r7, #1
+    svc #0
@@ -0,0 +1,10 @@
+@ stinkarm-test: address=0x8000; exit=125; stdout-contains=UnknownOrUnsupportedInstruction
+@ SETEND BE is valid ARMv6, but big-endian data accesses aren't emulated. The
+@ emulator should stop with its own exit status instead of passing for the guest.
+
+    .global _start
+_start:
+    setend be
+    mov r0, #0
+    mov r7, #1
+    svc #0
@@ -0,0 +1,10 @@
+@ stinkarm-test: address=0x8000; exit=126; stdout-contains=UnpredictableInstruction
+@ The user bank form of LDM is UNPREDICTABLE in User mode and must not be
+@ executed as a plain LDM.
+
+    .global _start
+_start:
+    ldmia sp, {r0, r1}^
+    mov r0, #1
+    mov r7, #1
+    svc #0
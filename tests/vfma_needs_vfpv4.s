@@ -0,0 +1,11 @@
+@ stinkarm-test: address=0x8000; args=--cpu cortex-a9; exit=132; stdout-contains=guest killed by SIGILL at 0x8000: 0xeea00b00
+@ The Cortex-A9 implements VFPv3 without the fused multiply accumulates, so
+@ VFMA must raise SIGILL instead of computing anything.
+
+    .fpu vfpv4
+    .global _start
+_start:
+    vfma.f64 d0, d0, d0
+    mov r0, #42
+    mov r7, #1
+    svc #0
@@ -0,0 +1,242 @@
+@ stinkarm-test: address=0x8000; exit=42; stdout=
+@ Checks VFP arithmetic, conversions, comparisons through VMRS APSR_nzcv, the
+@ FPSCR rounding modes and default NaN, loads, stores and VPUSH/VPOP in ARM and
+@ Thumb state, exiting with the index of the first failing check or 42.
+
+    .syntax unified
+    .fpu vfpv3
+    .text
+    .global _start
+_start:
+    mov r0, #1
+    ldr r1, =0x3fc00000         @ 1.5
+    vmov s0, r1
+    vadd.f32 s1, s0, s0
+    vmov r2, s1
+    ldr r3, =0x40400000         @ 3.0
+    cmp r2, r3
+    bne fail
+    vmul.f32 s2, s1, s0         @ 4.5
+    vsub.f32 s2, s2, s0         @ 3.0
+    vmov r2, s2
+    cmp r2, r3
+    bne fail
+
+    mov r0, #2
+    adr r1, doubles
+    vldr d0, [r1]               @ 2.0
+    vldr d1, [r1, #8]           @ 9.0
+    vsqrt.f64 d2, d1
+    vmul.f64 d3, d2, d0
+    vmov r2, r3, d3             @ 6.0
+    cmp r2, #0
+    bne fail
+    ldr r4, =0x40180000
+    cmp r3, r4
+    bne fail
+    vmov.32 r2, d3[1]
+    cmp r2, r4
+    bne fail
+    vmla.f64 d3, d2, d0         @ 6 + 3 * 2
+    vdiv.f64 d4, d3, d1         @ 12 / 9
+    vldr d5, [r1, #16]
+    vcmp.f64 d4, d5
+    vmrs APSR_nzcv, fpscr
+    bne fail
+
+    mov r0, #3
+    vcmp.f64 d2, d0             @ 3 > 2
+    vmrs APSR_nzcv, fpscr
+    ble fail
+    vcmpe.f64 d0, d2
+    vmrs APSR_nzcv, fpscr
+    bge fail
+    vcmp.f64 d0, #0
+    vmrs APSR_nzcv, fpscr
+    ble fail
+    vdiv.f64 d6, d0, d0         @ 1.0
+    vsub.f64 d6, d6, d6
+    vcmp.f64 d6, #0
+    vmrs APSR_nzcv, fpscr
+    bne fail
+
+    mov r0, #4
+    vmov.f64 d5, #-2.5
+    vcvt.s32.f64 s12, d5        @ towards zero
+    vmov r2, s12
+    cmn r2, #2
+    bne fail
+    vcvtr.s32.f64 s12, d5       @ to nearest even
+    vmov r2, s12
+    cmn r2, #2
+    bne fail
+    ldr r1, =0x00800000         @ round towards minus infinity
+    vmsr fpscr, r1
+    vcvtr.s32.f64 s12, d5
+    vmov r2, s12
+    cmn r2, #3
+    bne fail
+    vcvt.f64.s32 d6, s12
+    vmov.f64 d7, #-3.0
+    vcmp.f64 d6, d7
+    vmrs APSR_nzcv, fpscr
+    bne fail
+
+    mov r0, #5
+    vmov.f32 s0, #1.0
+    vmov.f32 s1, #3.0
+    vdiv.f32 s2, s0, s1         @ still rounding down
+    vmov r2, s2
+    ldr r3, =0x3eaaaaaa
+    cmp r2, r3
+    bne fail
+    mov r1, #0
+    vmsr fpscr, r1
+    vdiv.f32 s2, s0, s1
+    vmov r2, s2
+    ldr r3, =0x3eaaaaab
+    cmp r2, r3
+    bne fail
+    vmrs r2, fpscr              @ inexact
+    cmp r2, #0x10
+    bne fail
+
+    mov r0, #6
+    ldr r1, =0x7f800001         @ signaling NaN
+    vmov s3, r1
+    vadd.f32 s4, s0, s3
+    vmov r2, s4
+    ldr r3, =0x7fc00001
+    cmp r2, r3
+    bne fail
+    vmrs r2, fpscr
+    tst r2, #1                  @ invalid operation
+    beq fail
+    ldr r1, =0x02000000         @ default NaN
+    vmsr fpscr, r1
+    vadd.f32 s4, s0, s3
+    vmov r2, s4
+    ldr r3, =0x7fc00000
+    cmp r2, r3
+    bne fail
+    vcvt.f64.f32 d3, s3
+    vmov r2, r3, d3
+    ldr r4, =0x7ff80000
+    cmp r3, r4
+    bne fail
+
+    mov r0, #7
+    ldr r1, =0x01000000         @ flush to zero
+    vmsr fpscr, r1
+    ldr r1, =0x00000001         @ smallest subnormal
+    vmov s3, r1
+    vadd.f32 s4, s0, s3
+    vmov r2, s4
+    ldr r3, =0x3f800000
+    cmp r2, r3
+    bne fail
+    vmrs r2, fpscr
+    tst r2, #0x80               @ input denormal
+    beq fail
+    mov r1, #0
+    vmsr fpscr, r1
+
+    mov r0, #8
+    vmov.f32 s5, #-1.5
+    vcvt.s16.f32 s5, s5, #8
+    vmov r2, s5
+    ldr r3, =0xfffffe80
+    cmp r2, r3
+    bne fail
+    vcvt.f32.s16 s5, s5, #8
+    vmov r2, s5
+    ldr r3, =0xbfc00000
+    cmp r2, r3
+    bne fail
+    vcvt.u32.f32 s6, s5         @ negative saturates to 0
+    vmov r2, s6
+    cmp r2, #0
+    bne fail
+
+    mov r0, #9
+    mov r5, sp
+    vmov.f64 d8, #1.0
+    vmov.f64 d9, #2.0
+    vpush {d8-d9}
+    vmov.f64 d8, #4.0
+    vmov.f64 d9, #4.0
+    ldr r2, [sp, #12]           @ high word of d9
+    cmp r2, #0x40000000
+    bne fail
+    vpop {d8-d9}
+    cmp sp, r5
+    bne fail
+    vmov.f64 d10, #2.0
+    vcmp.f64 d9, d10
+    vmrs APSR_nzcv, fpscr
+    bne fail
+    sub sp, sp, #8
+    vstr s0, [sp, #4]
+    vldr s31, [sp, #4]
+    vmov r2, s31
+    ldr r3, =0x3f800000
+    cmp r2, r3
+    bne fail
+    vstmia sp, {s30-s31}
+    vldmia sp!, {d15}
+    vmov r2, r3, d15
+    ldr r4, =0x3f800000
+    cmp r3, r4
+    bne fail
+    cmp sp, r5
+    bne fail
+
+    mov r0, #10
+    adr r3, thumb + 1
+    bx r3
+
+    .thumb
+thumb:
+    adr r1, doubles
+    vldr d0, [r1]
+    vldr d1, [r1, #8]
+    vadd.f64 d2, d0, d1         @ 11.0
+    vmov.f64 d3, #11.0
+    vcmp.f64 d2, d3
+    vmrs APSR_nzcv, fpscr
+    bne fail_thumb
+    ite eq
+    vnegeq.f64 d4, d2
+    vmovne.f64 d4, d2
+    vabs.f64 d5, d4
+    vcmp.f64 d5, d2
+    vmrs APSR_nzcv, fpscr
+    bne fail_thumb
+    vcvt.f32.f64 s12, d5
+    vmov r2, s12
+    ldr r3, =0x41300000
+    cmp r2, r3
+    bne fail_thumb
+
+    movs r0, #11
+    vmov s13, r2
+    vcvt.u32.f32 s14, s13
+    vmov r2, s14
+    cmp r2, #11
+    bne fail_thumb
+
+    movs r0, #42
+fail_thumb:
+    movs r7, #1
+    svc #0
+
+    .arm
+fail:
+    mov r7, #1
+    svc #0
+
+    .align 3
+doubles:
+    .double 2.0
+    .double 9.0
+    .double 1.3333333333333333
@@ -0,0 +1,88 @@
+@ stinkarm-test: address=0x8000; exit=42; stdout=
+@ Checks that AT_HWCAP reports VFPv4 and LPAE on the default Cortex-A15, then
+@ that VFMA rounds only once and the other fused multiply accumulates in VFP and
+@ Advanced SIMD, exiting with the index of the first failing check or 42.
+
+    .syntax unified
+    .fpu neon-vfpv4
+    .text
+    .global _start
+_start:
+    mov r0, #1
+    ldr r1, [sp]                @ argc
+    add r2, sp, #8
+    add r2, r2, r1, lsl #2      @ past the argv terminator
+1:  ldr r3, [r2], #4            @ skip envp
+    cmp r3, #0
+    bne 1b
+2:  ldr r3, [r2], #8
+    cmp r3, #0
+    beq fail
+    cmp r3, #16                 @ AT_HWCAP
+    bne 2b
+    ldr r3, [r2, #-4]
+    tst r3, #(1 << 16)          @ HWCAP_VFPv4
+    beq fail
+    tst r3, #(1 << 20)          @ HWCAP_LPAE
+    beq fail
+
+    mov r0, #2
+    adr r1, doubles
+    vldr d0, [r1]               @ 1 + 2^-30
+    vldr d1, [r1, #8]           @ -1.0
+    vfma.f64 d1, d0, d0         @ 2^-29 + 2^-60, VMLA would lose the 2^-60
+    vmov r2, r3, d1
+    ldr r4, =0x00200000
+    cmp r2, r4
+    bne fail
+    ldr r4, =0x3e200000
+    cmp r3, r4
+    bne fail
+
+    mov r0, #3
+    vmov.f32 s0, #2.0
+    vmov.f32 s1, #3.0
+    vmov.f32 s2, #10.0
+    vmov.f32 s3, #10.0
+    vmov.f32 s4, #10.0
+    vfms.f32 s2, s0, s1         @ 10 - 6
+    vfnma.f32 s3, s0, s1        @ -10 - 6
+    vfnms.f32 s4, s0, s1        @ -10 + 6
+    vmov r2, s2
+    ldr r4, =0x40800000         @ 4.0
+    cmp r2, r4
+    bne fail
+    vmov r2, s3
+    ldr r4, =0xc1800000         @ -16.0
+    cmp r2, r4
+    bne fail
+    vmov r2, s4
+    ldr r4, =0xc0800000         @ -4.0
+    cmp r2, r4
+    bne fail
+
+    mov r0, #4
+    vmov.f32 q0, #2.0
+    vmov.f32 q1, #3.0
+    vmov.f32 q2, #1.0
+    vmov.f32 q3, #1.0
+    vfma.f32 q2, q0, q1         @ 1 + 6
+    vfms.f32 q3, q0, q1         @ 1 - 6
+    vmov.32 r2, d5[1]
+    ldr r4, =0x40e00000         @ 7.0
+    cmp r2, r4
+    bne fail
+    vmov.32 r2, d6[0]
+    ldr r4, =0xc0a00000         @ -5.0
+    cmp r2, r4
+    bne fail
+
+    mov r0, #42
+fail:
+    mov r7, #1
+    svc #0
+
+    .align 3
+doubles:
+    .word 0x00400000, 0x3ff00000
+    .double -1.0
@@ -42,7 +42,7 @@ fn assemble(input: &Path, object: &Path) -> Result<(), Box<dyn std::error::Error
         Command::new("arm-none-eabi-as")
             .arg("-march=armv7-a")
             .arg("-o")
-            .arg(&object)
+            .arg(object)
             .arg(input)
             .output()?,
         "assembler",